@@ -0,0 +1,118 @@
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use std::{
+    sync::mpsc,
+    thread,
+    time::{Duration, Instant},
+};
+use tux_lockfree::channel::{spsc, RecvErr};
+
+/// A channel of `u64`s. Receiving blocks (or spins) until a message arrives.
+trait BenchChannel: 'static {
+    /// The name of the contender, shown in the reports.
+    const NAME: &'static str;
+
+    type Sender: Send;
+
+    type Receiver: Send;
+
+    fn create() -> (Self::Sender, Self::Receiver);
+
+    fn send(sender: &mut Self::Sender, msg: u64);
+
+    fn recv(receiver: &mut Self::Receiver) -> u64;
+}
+
+struct TuxSpsc;
+
+impl BenchChannel for TuxSpsc {
+    const NAME: &'static str = "tux_lockfree spsc";
+
+    type Sender = spsc::Sender<u64>;
+
+    type Receiver = spsc::Receiver<u64>;
+
+    fn create() -> (Self::Sender, Self::Receiver) {
+        spsc::create()
+    }
+
+    fn send(sender: &mut Self::Sender, msg: u64) {
+        sender.send(msg).expect("receiver disconnected");
+    }
+
+    fn recv(receiver: &mut Self::Receiver) -> u64 {
+        loop {
+            match receiver.recv() {
+                Ok(msg) => break msg,
+                Err(RecvErr::NoMessage) => std::hint::spin_loop(),
+                Err(RecvErr::NoSender) => panic!("sender disconnected"),
+            }
+        }
+    }
+}
+
+struct StdMpsc;
+
+impl BenchChannel for StdMpsc {
+    const NAME: &'static str = "std mpsc";
+
+    type Sender = mpsc::Sender<u64>;
+
+    type Receiver = mpsc::Receiver<u64>;
+
+    fn create() -> (Self::Sender, Self::Receiver) {
+        mpsc::channel()
+    }
+
+    fn send(sender: &mut Self::Sender, msg: u64) {
+        sender.send(msg).expect("receiver disconnected");
+    }
+
+    fn recv(receiver: &mut Self::Receiver) -> u64 {
+        receiver.recv().expect("sender disconnected")
+    }
+}
+
+// Two threads bounce a message back and forth. Every iteration is a round
+// trip.
+fn ping_pong<C>(iters: u64) -> Duration
+where
+    C: BenchChannel,
+{
+    let (mut ping_tx, mut ping_rx) = C::create();
+    let (mut pong_tx, mut pong_rx) = C::create();
+
+    let echo = thread::spawn(move || {
+        for _ in 0..iters {
+            let msg = C::recv(&mut ping_rx);
+            C::send(&mut pong_tx, msg);
+        }
+    });
+
+    let start = Instant::now();
+    for i in 0..iters {
+        C::send(&mut ping_tx, i);
+        assert_eq!(C::recv(&mut pong_rx), i);
+    }
+    let elapsed = start.elapsed();
+    echo.join().expect("echo thread panicked");
+    elapsed
+}
+
+fn bench_ping_pong<C>(c: &mut Criterion)
+where
+    C: BenchChannel,
+{
+    c.bench_with_input(
+        BenchmarkId::new("channel ping-pong", C::NAME),
+        &(),
+        |b, _| b.iter_custom(ping_pong::<C>),
+    );
+}
+
+fn channel(c: &mut Criterion) {
+    bench_ping_pong::<TuxSpsc>(c);
+    bench_ping_pong::<StdMpsc>(c);
+}
+
+criterion_group!(benches, channel);
+criterion_main!(benches);
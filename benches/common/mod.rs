@@ -0,0 +1,201 @@
+// Harness shared by the benchmarks. Every structure is benchmarked through a
+// small trait, so adding a contender is just implementing the trait and adding
+// it to the list of the benchmark file.
+#![allow(dead_code)]
+
+use criterion::{black_box, measurement::WallTime, BenchmarkGroup, BenchmarkId};
+use std::{
+    collections::VecDeque,
+    sync::{Arc, Barrier, Mutex},
+    thread,
+    time::{Duration, Instant},
+};
+use tux_lockfree::{queue::Queue, stack::Stack};
+
+/// Thread counts every concurrent case is run with.
+pub const THREADS: [usize; 3] = [1, 4, 16];
+
+/// Runs `op` in `threads` threads over the shared state, each one performing
+/// `iters` operations. Only the time between all threads being ready and all
+/// threads finishing is measured.
+pub fn run_parallel<S, F>(shared: Arc<S>, threads: usize, iters: u64, op: F) -> Duration
+where
+    S: Send + Sync + 'static,
+    F: Fn(&S, usize, u64) + Send + Sync + 'static,
+{
+    let op = Arc::new(op);
+    let barrier = Arc::new(Barrier::new(threads + 1));
+    let handles = (0..threads)
+        .map(|thread_index| {
+            let shared = shared.clone();
+            let op = op.clone();
+            let barrier = barrier.clone();
+            thread::spawn(move || {
+                barrier.wait();
+                for i in 0..iters {
+                    op(&shared, thread_index, i);
+                }
+            })
+        })
+        .collect::<Vec<_>>();
+
+    barrier.wait();
+    let start = Instant::now();
+    for handle in handles {
+        handle.join().expect("benchmark thread panicked");
+    }
+    start.elapsed()
+}
+
+/// Benchmarks `op` with every thread count in [`THREADS`], creating a new
+/// shared state with `setup` for every batch of iterations. The throughput is
+/// reported per operation of a single thread.
+pub fn bench_parallel<S, G, F>(group: &mut BenchmarkGroup<WallTime>, name: &str, setup: G, op: F)
+where
+    S: Send + Sync + 'static,
+    G: Fn(usize, u64) -> S,
+    F: Fn(&S, usize, u64) + Send + Sync + Clone + 'static,
+{
+    for &threads in &THREADS {
+        group.bench_with_input(BenchmarkId::new(name, threads), &threads, |b, &threads| {
+            b.iter_custom(|iters| {
+                let shared = Arc::new(setup(threads, iters));
+                run_parallel(shared, threads, iters, op.clone())
+            })
+        });
+    }
+}
+
+/// A concurrent container of `u64`s with push and pop operations, such as a
+/// queue or a stack.
+pub trait BenchContainer: Send + Sync + 'static {
+    /// The name of the contender, shown in the reports.
+    const NAME: &'static str;
+
+    /// Creates an empty container.
+    fn new() -> Self;
+
+    /// Pushes a value into the container.
+    fn push(&self, val: u64);
+
+    /// Pops a value from the container, if any.
+    fn pop(&self) -> Option<u64>;
+}
+
+impl BenchContainer for Queue<u64> {
+    const NAME: &'static str = "tux_lockfree Queue";
+
+    fn new() -> Self {
+        Queue::new()
+    }
+
+    fn push(&self, val: u64) {
+        Queue::push(self, val)
+    }
+
+    fn pop(&self) -> Option<u64> {
+        Queue::pop(self)
+    }
+}
+
+impl BenchContainer for Stack<u64> {
+    const NAME: &'static str = "tux_lockfree Stack";
+
+    fn new() -> Self {
+        Stack::new()
+    }
+
+    fn push(&self, val: u64) {
+        Stack::push(self, val)
+    }
+
+    fn pop(&self) -> Option<u64> {
+        Stack::pop(self)
+    }
+}
+
+impl BenchContainer for Mutex<VecDeque<u64>> {
+    const NAME: &'static str = "Mutex<VecDeque>";
+
+    fn new() -> Self {
+        Mutex::new(VecDeque::new())
+    }
+
+    fn push(&self, val: u64) {
+        self.lock().unwrap().push_back(val)
+    }
+
+    fn pop(&self) -> Option<u64> {
+        self.lock().unwrap().pop_front()
+    }
+}
+
+impl BenchContainer for Mutex<Vec<u64>> {
+    const NAME: &'static str = "Mutex<Vec>";
+
+    fn new() -> Self {
+        Mutex::new(Vec::new())
+    }
+
+    fn push(&self, val: u64) {
+        self.lock().unwrap().push(val)
+    }
+
+    fn pop(&self) -> Option<u64> {
+        self.lock().unwrap().pop()
+    }
+}
+
+#[cfg(feature = "bench-comparisons")]
+impl BenchContainer for crossbeam_queue::SegQueue<u64> {
+    const NAME: &'static str = "crossbeam SegQueue";
+
+    fn new() -> Self {
+        crossbeam_queue::SegQueue::new()
+    }
+
+    fn push(&self, val: u64) {
+        crossbeam_queue::SegQueue::push(self, val)
+    }
+
+    fn pop(&self) -> Option<u64> {
+        crossbeam_queue::SegQueue::pop(self)
+    }
+}
+
+/// Every thread alternates pushes and pops.
+pub fn push_pop<C>(group: &mut BenchmarkGroup<WallTime>)
+where
+    C: BenchContainer,
+{
+    bench_parallel(
+        group,
+        C::NAME,
+        |_, _| C::new(),
+        |container: &C, _, i| {
+            container.push(i);
+            black_box(container.pop());
+        },
+    );
+}
+
+/// Every thread pops from a prefilled container.
+pub fn pop_prefilled<C>(group: &mut BenchmarkGroup<WallTime>)
+where
+    C: BenchContainer,
+{
+    bench_parallel(
+        group,
+        C::NAME,
+        |threads, iters| {
+            let container = C::new();
+            for i in 0..threads as u64 * iters {
+                container.push(i);
+            }
+            container
+        },
+        |container: &C, _, _| {
+            black_box(container.pop());
+        },
+    );
+}
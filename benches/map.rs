@@ -1,8 +1,173 @@
-use criterion::{black_box, criterion_group, criterion_main, Criterion};
+mod common;
+
+use common::bench_parallel;
+use criterion::{
+    black_box, criterion_group, criterion_main, measurement::WallTime, BatchSize, BenchmarkGroup,
+    Criterion,
+};
+use std::{collections::HashMap, sync::RwLock};
 use tux_lockfree::map::Map;
 
+/// Number of entries of prefilled maps.
+const ENTRIES: u64 = 1 << 14;
+
+/// A concurrent map from `u64` to `u64`.
+trait BenchMap: Send + Sync + 'static {
+    /// The name of the contender, shown in the reports.
+    const NAME: &'static str;
+
+    fn new() -> Self;
+
+    fn insert(&self, key: u64, val: u64);
+
+    fn get(&self, key: u64) -> Option<u64>;
+
+    fn remove(&self, key: u64) -> Option<u64>;
+}
+
+impl BenchMap for Map<u64, u64> {
+    const NAME: &'static str = "tux_lockfree Map";
+
+    fn new() -> Self {
+        Map::new()
+    }
+
+    fn insert(&self, key: u64, val: u64) {
+        Map::insert(self, key, val);
+    }
+
+    fn get(&self, key: u64) -> Option<u64> {
+        Map::get(self, &key).map(|guard| *guard.val())
+    }
+
+    fn remove(&self, key: u64) -> Option<u64> {
+        Map::remove(self, &key).map(|removed| *removed.val())
+    }
+}
+
+impl BenchMap for RwLock<HashMap<u64, u64>> {
+    const NAME: &'static str = "RwLock<HashMap>";
+
+    fn new() -> Self {
+        RwLock::new(HashMap::new())
+    }
+
+    fn insert(&self, key: u64, val: u64) {
+        self.write().unwrap().insert(key, val);
+    }
+
+    fn get(&self, key: u64) -> Option<u64> {
+        self.read().unwrap().get(&key).copied()
+    }
+
+    fn remove(&self, key: u64) -> Option<u64> {
+        self.write().unwrap().remove(&key)
+    }
+}
+
+#[cfg(feature = "bench-comparisons")]
+impl BenchMap for dashmap::DashMap<u64, u64> {
+    const NAME: &'static str = "DashMap";
+
+    fn new() -> Self {
+        dashmap::DashMap::new()
+    }
+
+    fn insert(&self, key: u64, val: u64) {
+        dashmap::DashMap::insert(self, key, val);
+    }
+
+    fn get(&self, key: u64) -> Option<u64> {
+        dashmap::DashMap::get(self, &key).map(|entry| *entry)
+    }
+
+    fn remove(&self, key: u64) -> Option<u64> {
+        dashmap::DashMap::remove(self, &key).map(|(_, val)| val)
+    }
+}
+
+fn prefilled<M>(entries: u64) -> M
+where
+    M: BenchMap,
+{
+    let map = M::new();
+    for i in 0..entries {
+        map.insert(i, i);
+    }
+    map
+}
+
+// The key a thread uses in its own range of keys.
+fn key(thread: usize, i: u64) -> u64 {
+    (thread as u64) << 32 | i
+}
+
+// Every thread inserts its own range of keys into an empty map.
+fn insert<M>(group: &mut BenchmarkGroup<WallTime>)
+where
+    M: BenchMap,
+{
+    bench_parallel(
+        group,
+        M::NAME,
+        |_, _| M::new(),
+        |map: &M, thread, i| map.insert(key(thread, i), i),
+    );
+}
+
+// Every thread reads keys spread over a prefilled map.
+fn get<M>(group: &mut BenchmarkGroup<WallTime>)
+where
+    M: BenchMap,
+{
+    bench_parallel(
+        group,
+        M::NAME,
+        |_, _| prefilled::<M>(ENTRIES),
+        |map: &M, thread, i| {
+            let key = (i.wrapping_mul(7919) + thread as u64) % ENTRIES;
+            black_box(map.get(key));
+        },
+    );
+}
+
+// Every thread removes its own range of keys from a prefilled map.
+fn remove<M>(group: &mut BenchmarkGroup<WallTime>)
+where
+    M: BenchMap,
+{
+    bench_parallel(
+        group,
+        M::NAME,
+        |threads, iters| {
+            let map = M::new();
+            for thread in 0..threads {
+                for i in 0..iters {
+                    map.insert(key(thread, i), i);
+                }
+            }
+            map
+        },
+        |map: &M, thread, i| {
+            black_box(map.remove(key(thread, i)));
+        },
+    );
+}
+
+// Runs a case for every contender. Adding a contender is adding a line here.
+macro_rules! contenders {
+    ($c:expr, $name:expr, $case:ident) => {{
+        let mut group = $c.benchmark_group($name);
+        $case::<Map<u64, u64>>(&mut group);
+        $case::<RwLock<HashMap<u64, u64>>>(&mut group);
+        #[cfg(feature = "bench-comparisons")]
+        $case::<dashmap::DashMap<u64, u64>>(&mut group);
+        group.finish();
+    }};
+}
+
 fn random_read_write(n: u64) {
-    let mut map = Map::new();
+    let map = Map::new();
     for i in 0..n {
         map.insert(i, i);
     }
@@ -11,11 +176,84 @@ fn random_read_write(n: u64) {
     }
 }
 
-fn criterion_benchmark(c: &mut Criterion) {
+// Collects every key of a prefilled map, with and without knowing the length
+// up front.
+fn collect(c: &mut Criterion) {
+    let mut group = c.benchmark_group("map collect");
+    let mut map = prefilled::<Map<u64, u64>>(ENTRIES);
+    group.bench_function("iter", |b| {
+        b.iter(|| black_box(map.iter().map(|guard| *guard.key()).collect::<Vec<_>>()))
+    });
+    group.bench_function("iter_exact", |b| {
+        b.iter(|| {
+            black_box(
+                map.iter_exact()
+                    .map(|guard| *guard.key())
+                    .collect::<Vec<_>>(),
+            )
+        })
+    });
+    group.finish();
+}
+
+// Looks up every key of a prefilled map, one `get` at a time and in a single
+// batch.
+fn lookup_batch(c: &mut Criterion) {
+    let mut group = c.benchmark_group("map lookup batch");
+    let map = prefilled::<Map<u64, u64>>(ENTRIES);
+    let keys = (0..ENTRIES)
+        .map(|i| i.wrapping_mul(7919) % ENTRIES)
+        .collect::<Vec<_>>();
+    group.bench_function("get", |b| {
+        b.iter(|| {
+            black_box(keys.iter().map(|key| map.get(key)).collect::<Vec<_>>());
+        })
+    });
+    group.bench_function("get_many", |b| b.iter(|| black_box(map.get_many(&keys))));
+    group.finish();
+}
+
+// Removes every key of a prefilled map, one `remove` at a time and in a single
+// batch.
+fn remove_batch(c: &mut Criterion) {
+    let mut group = c.benchmark_group("map remove batch");
+    let keys = (0..ENTRIES)
+        .map(|i| i.wrapping_mul(7919) % ENTRIES)
+        .collect::<Vec<_>>();
+    group.bench_function("remove", |b| {
+        b.iter_batched(
+            || prefilled::<Map<u64, u64>>(ENTRIES),
+            |map| {
+                black_box(keys.iter().map(|key| map.remove(key)).collect::<Vec<_>>());
+                map
+            },
+            BatchSize::LargeInput,
+        )
+    });
+    group.bench_function("remove_all", |b| {
+        b.iter_batched(
+            || prefilled::<Map<u64, u64>>(ENTRIES),
+            |map| {
+                black_box(map.remove_all(&keys));
+                map
+            },
+            BatchSize::LargeInput,
+        )
+    });
+    group.finish();
+}
+
+fn map(c: &mut Criterion) {
     c.bench_function("read write 20", |b| {
         b.iter(|| random_read_write(black_box(20)))
     });
+    contenders!(c, "map insert", insert);
+    contenders!(c, "map get", get);
+    contenders!(c, "map remove", remove);
+    collect(c);
+    lookup_batch(c);
+    remove_batch(c);
 }
 
-criterion_group!(benches, criterion_benchmark);
+criterion_group!(benches, map);
 criterion_main!(benches);
@@ -0,0 +1,25 @@
+mod common;
+
+use common::{pop_prefilled, push_pop};
+use criterion::{criterion_group, criterion_main, Criterion};
+use std::{collections::VecDeque, sync::Mutex};
+use tux_lockfree::queue::Queue;
+
+fn queue(c: &mut Criterion) {
+    let mut group = c.benchmark_group("queue push/pop");
+    push_pop::<Queue<u64>>(&mut group);
+    push_pop::<Mutex<VecDeque<u64>>>(&mut group);
+    #[cfg(feature = "bench-comparisons")]
+    push_pop::<crossbeam_queue::SegQueue<u64>>(&mut group);
+    group.finish();
+
+    let mut group = c.benchmark_group("queue pop");
+    pop_prefilled::<Queue<u64>>(&mut group);
+    pop_prefilled::<Mutex<VecDeque<u64>>>(&mut group);
+    #[cfg(feature = "bench-comparisons")]
+    pop_prefilled::<crossbeam_queue::SegQueue<u64>>(&mut group);
+    group.finish();
+}
+
+criterion_group!(benches, queue);
+criterion_main!(benches);
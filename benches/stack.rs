@@ -0,0 +1,21 @@
+mod common;
+
+use common::{pop_prefilled, push_pop};
+use criterion::{criterion_group, criterion_main, Criterion};
+use std::sync::Mutex;
+use tux_lockfree::stack::Stack;
+
+fn stack(c: &mut Criterion) {
+    let mut group = c.benchmark_group("stack push/pop");
+    push_pop::<Stack<u64>>(&mut group);
+    push_pop::<Mutex<Vec<u64>>>(&mut group);
+    group.finish();
+
+    let mut group = c.benchmark_group("stack pop");
+    pop_prefilled::<Stack<u64>>(&mut group);
+    pop_prefilled::<Mutex<Vec<u64>>>(&mut group);
+    group.finish();
+}
+
+criterion_group!(benches, stack);
+criterion_main!(benches);
@@ -0,0 +1,201 @@
+//! A sharded cache with expiration. Every shard is a `Map`, and all the shards
+//! share one incinerator, so the garbage of all of them is kept in one place
+//! instead of one per shard. Clients read through the cache, computing missing
+//! values with `Map::get_or_init`, while a janitor thread advances a logical
+//! clock and removes the entries which expired.
+//!
+//! Run with `cargo run --release --example cache`.
+
+use std::{
+    sync::{
+        atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering::*},
+        Arc,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+use tux_lockfree::map::{Map, SharedIncin};
+
+struct Config {
+    shards: usize,
+    clients: usize,
+    lookups_per_client: usize,
+    keys: u64,
+    // Number of clock ticks an entry lives.
+    ttl: u64,
+}
+
+struct Cached {
+    val: u64,
+    expires: u64,
+}
+
+struct Cache {
+    shards: Vec<Map<u64, Cached>>,
+    clock: AtomicU64,
+    ttl: u64,
+    computed: AtomicUsize,
+}
+
+impl Cache {
+    fn new(shards: usize, ttl: u64) -> Self {
+        let incin = SharedIncin::new();
+        Self {
+            shards: (0..shards)
+                .map(|_| Map::with_incin(incin.clone()))
+                .collect(),
+            clock: AtomicU64::new(0),
+            ttl,
+            computed: AtomicUsize::new(0),
+        }
+    }
+
+    fn shard(&self, key: u64) -> &Map<u64, Cached> {
+        &self.shards[key as usize % self.shards.len()]
+    }
+
+    fn get(&self, key: u64) -> u64 {
+        let guard = self.shard(key).get_or_init(key, || {
+            self.computed.fetch_add(1, Relaxed);
+            Cached {
+                val: expensive(key),
+                expires: self.clock.load(Acquire) + self.ttl,
+            }
+        });
+        guard.val().val
+    }
+
+    fn tick(&self) {
+        self.clock.fetch_add(1, AcqRel);
+    }
+
+    // Removes the expired entries, keeping the others. Entries refreshed
+    // between finding and removing them are kept.
+    fn sweep(&self) -> usize {
+        let now = self.clock.load(Acquire);
+        let mut expired = 0;
+        for shard in &self.shards {
+            let keys = shard
+                .iter()
+                .filter(|guard| guard.val().expires <= now)
+                .map(|guard| *guard.key())
+                .collect::<Vec<_>>();
+            for key in keys {
+                if shard
+                    .remove_with(&key, |(_, cached)| cached.expires <= now)
+                    .is_some()
+                {
+                    expired += 1;
+                }
+            }
+        }
+        expired
+    }
+
+    fn len(&self) -> usize {
+        self.shards.iter().map(|shard| shard.iter().count()).sum()
+    }
+}
+
+fn expensive(key: u64) -> u64 {
+    (0..256).fold(key, |acc, i| {
+        acc.wrapping_mul(6364136223846793005)
+            .wrapping_add(1442695040888963407 ^ i)
+    })
+}
+
+// A skewed key distribution, so some keys are hot and hit the cache often.
+fn next_key(state: &mut u64, keys: u64) -> u64 {
+    *state = state.wrapping_mul(6364136223846793005).wrapping_add(1);
+    let uniform = (*state >> 33) % keys;
+    uniform * uniform / keys
+}
+
+fn run(config: &Config) {
+    let start = Instant::now();
+    let cache = Arc::new(Cache::new(config.shards, config.ttl));
+    let done = Arc::new(AtomicBool::new(false));
+
+    let janitor = {
+        let cache = cache.clone();
+        let done = done.clone();
+        thread::spawn(move || {
+            let mut expired = 0;
+            while !done.load(Acquire) {
+                cache.tick();
+                expired += cache.sweep();
+                thread::sleep(Duration::from_millis(1));
+            }
+            expired
+        })
+    };
+
+    let clients = (0..config.clients)
+        .map(|client| {
+            let cache = cache.clone();
+            let keys = config.keys;
+            let lookups = config.lookups_per_client;
+            thread::spawn(move || {
+                let mut state = client as u64;
+                for _ in 0..lookups {
+                    let key = next_key(&mut state, keys);
+                    assert_eq!(cache.get(key), expensive(key));
+                }
+            })
+        })
+        .collect::<Vec<_>>();
+    for client in clients {
+        client.join().expect("client panicked");
+    }
+    done.store(true, Release);
+    let mut expired = janitor.join().expect("janitor panicked");
+
+    let left = cache.len();
+    let lookups = config.clients * config.lookups_per_client;
+    let computed = cache.computed.load(Relaxed);
+    // Every computed value was either expired or is still cached.
+    assert_eq!(computed, expired + left);
+
+    // Once everything expires, a sweep leaves the cache empty.
+    for _ in 0..=config.ttl {
+        cache.tick();
+    }
+    expired += cache.sweep();
+    assert_eq!(cache.len(), 0);
+    assert_eq!(computed, expired);
+
+    println!(
+        "{} lookups over {} shards, {} hits, {} computed, took {:?}",
+        lookups,
+        config.shards,
+        lookups - computed,
+        computed,
+        start.elapsed()
+    );
+}
+
+fn main() {
+    run(&Config {
+        shards: 16,
+        clients: 8,
+        lookups_per_client: 100_000,
+        keys: 10_000,
+        ttl: 20,
+    });
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn runs_briefly() {
+        run(&Config {
+            shards: 4,
+            clients: 4,
+            lookups_per_client: 2_000,
+            keys: 500,
+            ttl: 2,
+        });
+    }
+}
@@ -0,0 +1,172 @@
+//! A metrics aggregator. Worker threads record the latencies of simulated
+//! requests into histograms kept in a `ThreadLocal`, so recording never
+//! contends with other workers. A reporter thread periodically folds the
+//! histograms of all threads into one snapshot.
+//!
+//! Run with `cargo run --release --example metrics`.
+
+use std::{
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering::*},
+        Arc,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+use tux_lockfree::tls::ThreadLocal;
+
+const BUCKETS: usize = 8;
+
+struct Config {
+    workers: usize,
+    requests_per_worker: u64,
+}
+
+// Latencies in microseconds, in power-of-two buckets: the first one is below
+// 2µs, the last one is 128µs and above.
+#[derive(Default)]
+struct Histogram {
+    buckets: [AtomicU64; BUCKETS],
+    count: AtomicU64,
+    sum: AtomicU64,
+}
+
+impl Histogram {
+    fn record(&self, micros: u64) {
+        let bucket = (64 - micros.leading_zeros() as usize).min(BUCKETS - 1);
+        // Only the owner thread writes, so relaxed updates are enough. The
+        // reporter may see a snapshot in the middle of a recording.
+        self.buckets[bucket].fetch_add(1, Relaxed);
+        self.sum.fetch_add(micros, Relaxed);
+        self.count.fetch_add(1, Release);
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+struct Snapshot {
+    buckets: [u64; BUCKETS],
+    count: u64,
+    sum: u64,
+}
+
+impl Snapshot {
+    fn mean(&self) -> f64 {
+        if self.count == 0 {
+            0.0
+        } else {
+            self.sum as f64 / self.count as f64
+        }
+    }
+}
+
+fn fold(histograms: &ThreadLocal<Histogram>) -> Snapshot {
+    histograms
+        .iter()
+        .fold(Snapshot::default(), |mut snapshot, histogram| {
+            snapshot.count += histogram.count.load(Acquire);
+            snapshot.sum += histogram.sum.load(Relaxed);
+            for (total, bucket) in snapshot.buckets.iter_mut().zip(&histogram.buckets) {
+                *total += bucket.load(Relaxed);
+            }
+            snapshot
+        })
+}
+
+// A deterministic latency for the given request, mostly low with a long tail.
+fn latency(worker: usize, request: u64) -> u64 {
+    let mut x = (worker as u64) << 32 | request;
+    x ^= x >> 33;
+    x = x.wrapping_mul(0xff51afd7ed558ccd);
+    x ^= x >> 33;
+    match x % 100 {
+        0 => 100 + x % 900,
+        1..=9 => 10 + x % 90,
+        _ => x % 10,
+    }
+}
+
+fn run(config: &Config) {
+    let start = Instant::now();
+    let histograms = Arc::new(ThreadLocal::<Histogram>::new());
+    let done = Arc::new(AtomicBool::new(false));
+
+    let reporter = {
+        let histograms = histograms.clone();
+        let done = done.clone();
+        thread::spawn(move || {
+            let mut reports = 0;
+            let mut last = Snapshot::default();
+            while !done.load(Acquire) {
+                let snapshot = fold(&histograms);
+                // Counts never go back, even though the snapshot is taken
+                // while the workers record.
+                assert!(snapshot.count >= last.count);
+                last = snapshot;
+                reports += 1;
+                thread::sleep(Duration::from_millis(1));
+            }
+            reports
+        })
+    };
+
+    let workers = (0..config.workers)
+        .map(|worker| {
+            let histograms = histograms.clone();
+            let requests = config.requests_per_worker;
+            thread::spawn(move || {
+                let histogram = histograms.with_default();
+                let mut sum = 0;
+                for request in 0..requests {
+                    let micros = latency(worker, request);
+                    histogram.record(micros);
+                    sum += micros;
+                }
+                sum
+            })
+        })
+        .collect::<Vec<_>>();
+    let expected_sum = workers
+        .into_iter()
+        .map(|worker| worker.join().expect("worker panicked"))
+        .sum::<u64>();
+    done.store(true, Release);
+    let reports = reporter.join().expect("reporter panicked");
+
+    let snapshot = fold(&histograms);
+    assert_eq!(
+        snapshot.count,
+        config.workers as u64 * config.requests_per_worker
+    );
+    assert_eq!(snapshot.buckets.iter().sum::<u64>(), snapshot.count);
+    assert_eq!(snapshot.sum, expected_sum);
+
+    println!(
+        "{} requests from {} workers, mean {:.2}µs, buckets {:?}, {} reports, took {:?}",
+        snapshot.count,
+        config.workers,
+        snapshot.mean(),
+        snapshot.buckets,
+        reports,
+        start.elapsed()
+    );
+}
+
+fn main() {
+    run(&Config {
+        workers: 8,
+        requests_per_worker: 1_000_000,
+    });
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn runs_briefly() {
+        run(&Config {
+            workers: 4,
+            requests_per_worker: 10_000,
+        });
+    }
+}
@@ -0,0 +1,219 @@
+//! A work-stealing job scheduler. Jobs are submitted through an MPMC channel
+//! shared by all workers. A worker splits big jobs and keeps the halves in its
+//! own `Queue`, and idle workers steal from the queues of the others. Results
+//! go back to the submitter through an MPSC channel.
+//!
+//! Every job sums the lengths of the Collatz sequences of a range of numbers,
+//! which is checked against a sequential computation at the end.
+//!
+//! Run with `cargo run --release --example scheduler`.
+
+use std::{
+    sync::{
+        atomic::{AtomicUsize, Ordering::*},
+        Arc,
+    },
+    thread,
+    time::Instant,
+};
+use tux_lockfree::{
+    channel::{mpmc, mpsc, RecvErr},
+    queue::Queue,
+};
+
+struct Config {
+    workers: usize,
+    jobs: usize,
+    // Every job covers this many numbers.
+    job_len: u64,
+    // Jobs covering fewer numbers than this are not split anymore.
+    grain: u64,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Job {
+    id: usize,
+    start: u64,
+    end: u64,
+}
+
+struct Shared {
+    locals: Vec<Queue<Job>>,
+    // Jobs submitted or split off which have not finished yet. A job is
+    // counted before it becomes visible and discounted after its result is
+    // sent, so this only reaches zero when all the work is done.
+    pending: AtomicUsize,
+    steals: AtomicUsize,
+}
+
+impl Shared {
+    fn steal(&self, thief: usize) -> Option<Job> {
+        let workers = self.locals.len();
+        (1..workers)
+            .map(|offset| &self.locals[(thief + offset) % workers])
+            .find_map(Queue::pop)
+    }
+}
+
+fn collatz_len(mut n: u64) -> u64 {
+    let mut len = 1;
+    while n > 1 {
+        n = if n % 2 == 0 { n / 2 } else { 3 * n + 1 };
+        len += 1;
+    }
+    len
+}
+
+fn sum_range(start: u64, end: u64) -> u64 {
+    (start..end).map(collatz_len).sum()
+}
+
+fn worker(
+    index: usize,
+    shared: Arc<Shared>,
+    injector: mpmc::Receiver<Job>,
+    results: mpsc::Sender<(usize, u64)>,
+    grain: u64,
+) {
+    loop {
+        let job = match shared.locals[index].pop() {
+            Some(job) => job,
+            None => match injector.recv() {
+                Ok(job) => job,
+                Err(err) => match shared.steal(index) {
+                    Some(job) => {
+                        shared.steals.fetch_add(1, Relaxed);
+                        job
+                    }
+                    None if err == RecvErr::NoSender && shared.pending.load(Acquire) == 0 => {
+                        break;
+                    }
+                    None => {
+                        thread::yield_now();
+                        continue;
+                    }
+                },
+            },
+        };
+
+        // Keep the first half, and leave the second half for ourselves or for
+        // some thief.
+        let mut end = job.end;
+        while end - job.start > grain {
+            let mid = job.start + (end - job.start) / 2;
+            shared.pending.fetch_add(1, AcqRel);
+            shared.locals[index].push(Job {
+                id: job.id,
+                start: mid,
+                end,
+            });
+            end = mid;
+        }
+
+        let sum = sum_range(job.start, end);
+        if results.send((job.id, sum)).is_err() {
+            panic!("the submitter stopped listening");
+        }
+        shared.pending.fetch_sub(1, AcqRel);
+    }
+}
+
+fn run(config: &Config) {
+    let start = Instant::now();
+    let shared = Arc::new(Shared {
+        locals: (0..config.workers).map(|_| Queue::new()).collect(),
+        pending: AtomicUsize::new(0),
+        steals: AtomicUsize::new(0),
+    });
+    let (injector_tx, injector_rx) = mpmc::create();
+    let (results_tx, mut results_rx) = mpsc::create();
+
+    let workers = (0..config.workers)
+        .map(|index| {
+            let shared = shared.clone();
+            let injector = injector_rx.clone();
+            let results = results_tx.clone();
+            let grain = config.grain;
+            thread::spawn(move || worker(index, shared, injector, results, grain))
+        })
+        .collect::<Vec<_>>();
+    drop((injector_rx, results_tx));
+
+    let jobs = (0..config.jobs)
+        .map(|id| {
+            let start = 1 + id as u64 * config.job_len;
+            Job {
+                id,
+                start,
+                end: start + config.job_len,
+            }
+        })
+        .collect::<Vec<_>>();
+    for &job in &jobs {
+        shared.pending.fetch_add(1, AcqRel);
+        if injector_tx.send(job).is_err() {
+            panic!("every worker stopped");
+        }
+    }
+    drop(injector_tx);
+
+    // The channel disconnects once every worker has finished and dropped its
+    // sender, and only then every result is in.
+    let mut sums = vec![0; config.jobs];
+    let mut parts = 0;
+    loop {
+        match results_rx.recv() {
+            Ok((id, sum)) => {
+                sums[id] += sum;
+                parts += 1;
+            }
+            Err(RecvErr::NoMessage) => thread::yield_now(),
+            Err(RecvErr::NoSender) => break,
+        }
+    }
+    for worker in workers {
+        worker.join().expect("worker panicked");
+    }
+
+    for job in &jobs {
+        assert_eq!(
+            sums[job.id],
+            sum_range(job.start, job.end),
+            "job {}",
+            job.id
+        );
+    }
+    assert_eq!(shared.pending.load(Acquire), 0);
+    println!(
+        "{} jobs done in {} parts by {} workers, {} steals, took {:?}",
+        config.jobs,
+        parts,
+        config.workers,
+        shared.steals.load(Relaxed),
+        start.elapsed()
+    );
+}
+
+fn main() {
+    run(&Config {
+        workers: 8,
+        jobs: 256,
+        job_len: 1 << 14,
+        grain: 1 << 8,
+    });
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn runs_briefly() {
+        run(&Config {
+            workers: 4,
+            jobs: 16,
+            job_len: 1 << 10,
+            grain: 1 << 6,
+        });
+    }
+}
@@ -0,0 +1,76 @@
+//! Soak test which runs the same load against the map, the set, the queue and
+//! the stack, written once against the traits of `tux_lockfree::traits`.
+//!
+//! Run with `cargo run --release --example soak`.
+
+use std::{sync::Arc, thread, time::Instant};
+use tux_lockfree::{
+    map::Map,
+    queue::Queue,
+    set::Set,
+    stack::Stack,
+    traits::{ApproxLen, ConcurrentInsert, ConcurrentPop},
+};
+
+const THREADS: usize = 8;
+const OPS_PER_THREAD: usize = 10_000;
+// Every thread pops once each this many insertions.
+const POP_EVERY: usize = 3;
+
+fn soak<C, T, F>(name: &str, collection: C, make: F)
+where
+    C: ConcurrentInsert<T> + ConcurrentPop<T> + ApproxLen + Send + Sync + 'static,
+    F: Fn(usize) -> T + Send + Sync + 'static,
+{
+    let collection = Arc::new(collection);
+    let make = Arc::new(make);
+    let start = Instant::now();
+
+    let threads = (0..THREADS)
+        .map(|thread_index| {
+            let collection = collection.clone();
+            let make = make.clone();
+            thread::spawn(move || {
+                let mut inserted = 0;
+                let mut popped = 0;
+                for i in 0..OPS_PER_THREAD {
+                    // Elements are unique, so maps and sets accept all of them.
+                    let elem = make(thread_index * OPS_PER_THREAD + i);
+                    if collection.try_insert(elem).is_ok() {
+                        inserted += 1;
+                    }
+                    if i % POP_EVERY == 0 && collection.pop().is_some() {
+                        popped += 1;
+                    }
+                }
+                (inserted, popped)
+            })
+        })
+        .collect::<Vec<_>>();
+
+    let (inserted, popped) = threads
+        .into_iter()
+        .map(|thread| thread.join().expect("soak thread panicked"))
+        .fold((0, 0), |(ins, pop), (thread_ins, thread_pop)| {
+            (ins + thread_ins, pop + thread_pop)
+        });
+
+    let left = collection.len();
+    assert_eq!(inserted, THREADS * OPS_PER_THREAD);
+    assert_eq!(left, inserted - popped);
+    println!(
+        "{:>5}: {} inserted, {} popped, {} left, took {:?}",
+        name,
+        inserted,
+        popped,
+        left,
+        start.elapsed()
+    );
+}
+
+fn main() {
+    soak("map", Map::new(), |i| (i, i.to_string()));
+    soak("set", Set::new(), |i| i);
+    soak("queue", Queue::new(), |i| i);
+    soak("stack", Stack::new(), |i| i);
+}
@@ -0,0 +1,75 @@
+#[cfg(feature = "std")]
+use std::thread;
+
+/// The decision of a retry policy after a failed attempt. See
+/// [`map::Removed::try_into_with`](crate::map::Removed::try_into_with) for an
+/// example of use.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RetryDecision {
+    /// Busy-waits for the given number of spin-loop hints and then retries.
+    Spin(u32),
+    /// Yields the current thread to the scheduler and then retries. Without
+    /// the `std` feature there is no scheduler to yield to, so a single
+    /// spin-loop hint is issued instead.
+    Yield,
+    /// Stops retrying. The operation fails and ownership is handed back to
+    /// the caller, which may retry later, e.g. after yielding to an async
+    /// runtime.
+    GiveUp,
+}
+
+impl RetryDecision {
+    // Executes the waiting part of the decision. Returns whether the caller
+    // should retry.
+    pub(crate) fn wait(self) -> bool {
+        match self {
+            RetryDecision::Spin(count) => {
+                for _ in 0..count {
+                    core::hint::spin_loop();
+                }
+                true
+            }
+
+            RetryDecision::Yield => {
+                #[cfg(feature = "std")]
+                thread::yield_now();
+                #[cfg(not(feature = "std"))]
+                core::hint::spin_loop();
+                true
+            }
+
+            RetryDecision::GiveUp => false,
+        }
+    }
+}
+
+/// The default retry policy. It spins for exponentially more iterations on
+/// each of the first attempts, then yields the thread for a while, and finally
+/// gives up. The argument is the number of the attempt that just failed,
+/// starting from `0`.
+pub fn exponential_backoff(attempt: u32) -> RetryDecision {
+    const SPIN_ATTEMPTS: u32 = 7;
+    const YIELD_ATTEMPTS: u32 = 10;
+
+    if attempt < SPIN_ATTEMPTS {
+        RetryDecision::Spin(1 << attempt)
+    } else if attempt < SPIN_ATTEMPTS + YIELD_ATTEMPTS {
+        RetryDecision::Yield
+    } else {
+        RetryDecision::GiveUp
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn exponential_backoff_eventually_gives_up() {
+        assert_eq!(exponential_backoff(0), RetryDecision::Spin(1));
+        assert_eq!(exponential_backoff(3), RetryDecision::Spin(8));
+        assert_eq!(exponential_backoff(7), RetryDecision::Yield);
+        assert_eq!(exponential_backoff(17), RetryDecision::GiveUp);
+        assert_eq!(exponential_backoff(u32::MAX), RetryDecision::GiveUp);
+    }
+}
@@ -4,8 +4,9 @@ pub use super::{
 };
 use crate::{
     incin::Pause,
-    ptr::{bypass_null, check_null_align},
+    ptr::bypass_null,
     removable::Removable,
+    tagged_ptr::{check_align, is_tagged, tag, untag},
 };
 use alloc::sync::Arc;
 use core::{
@@ -25,7 +26,7 @@ pub fn create<T>() -> (Sender<T>, Receiver<T>) {
 /// Same as [`create`], but use a passed incinerator instead of creating a new
 /// one.
 pub fn with_incin<T>(incin: SharedIncin<T>) -> (Sender<T>, Receiver<T>) {
-    check_null_align::<Node<T>>();
+    check_align::<Node<T>>();
 
     // First we allocate this single node.
     let alloc = OwnedAlloc::new(Node {
@@ -83,7 +84,7 @@ impl<T> Sender<T> {
         loop {
             // When the receiver disconnect, it will bit-mark the back. Let's
             // check it.
-            if loaded as usize & 1 == 1 {
+            if is_tagged(loaded) {
                 // Safe because we are deallocating the node we just created
                 // without sharing it.
                 let mut alloc = unsafe { OwnedAlloc::from_raw(node) };
@@ -147,6 +148,71 @@ impl<T> Sender<T> {
         }
     }
 
+    /// Sends the messages of the given iterable as a single batch. The
+    /// messages are contiguous in the channel and keep the order of the
+    /// iterable: nothing sent concurrently, either alone or in another batch,
+    /// lands among them. Nothing is sent for an empty iterable. If the
+    /// receivers disconnected before the batch was sent, all of its messages
+    /// are returned in the error.
+    pub fn send_many<I>(&self, messages: I) -> Result<(), NoRecv<Vec<T>>>
+    where
+        I: IntoIterator<Item = T>,
+    {
+        let (first, last) = match chain(messages) {
+            Some(ends) => ends,
+            None => return Ok(()),
+        };
+
+        // Just like a single send, but the whole chain goes in between the
+        // previous back and the new one. This is safe because the shared back
+        // is only deallocated when both sides disconnect.
+        let mut loaded = unsafe { self.inner.back.as_ref().ptr.load(Relaxed) };
+
+        loop {
+            if is_tagged(loaded) {
+                // Safe because the chain was not shared.
+                let message = unsafe { unchain(first) };
+                break Err(NoRecv { message });
+            }
+
+            let res = unsafe {
+                self.inner.back.as_ref().ptr.compare_exchange(
+                    loaded,
+                    last.as_ptr(),
+                    AcqRel,
+                    Relaxed,
+                )
+            };
+
+            match res {
+                Ok(_) => {
+                    // Safe because we never store null on the back.
+                    let prev = unsafe { bypass_null(loaded) };
+                    let res = unsafe {
+                        prev.as_ref().next.compare_exchange(
+                            null_mut(),
+                            first.as_ptr(),
+                            Release,
+                            Relaxed,
+                        )
+                    };
+
+                    // The receivers disconnected meanwhile, so the previous
+                    // back and the whole chain are thrown away, as in `send`.
+                    if res.is_err() {
+                        unsafe {
+                            OwnedAlloc::from_raw(prev);
+                            delete_before_last(first, None);
+                        }
+                    }
+                    break Ok(());
+                }
+
+                Err(new) => loaded = new,
+            }
+        }
+    }
+
     /// Tests if there are any [`Receiver`]s still connected. There are no
     /// guarantees that [`send`](Sender::send) will succeed if this method
     /// returns `true` because the [`Receiver`] may disconnect meanwhile.
@@ -156,7 +222,7 @@ impl<T> Sender<T> {
         // when both sides disconnected. We load it to check for bit
         // marking (since it means sender disconnected).
         let back = unsafe { self.inner.back.as_ref() };
-        back.ptr.load(Relaxed) as usize & 1 == 0
+        !is_tagged(back.ptr.load(Relaxed))
     }
 }
 
@@ -239,7 +305,7 @@ impl<T> Receiver<T> {
         // when both sides disconnected. We load it to check for bit
         // marking (since it means sender disconnected).
         let back = unsafe { self.inner.back.as_ref() };
-        back.ptr.load(Relaxed) as usize & 1 == 0
+        !is_tagged(back.ptr.load(Relaxed))
             || front.message.is_present(Relaxed)
             || !front.next.load(Relaxed).is_null()
     }
@@ -277,7 +343,7 @@ impl<T> Receiver<T> {
                 // pointers on the front.
                 Err(found) => Ok(bypass_null(found)),
             }
-        } else if self.inner.back.as_ref().ptr.load(Relaxed) as usize & 1 == 1 {
+        } else if is_tagged(self.inner.back.as_ref().ptr.load(Relaxed)) {
             // If the back is bit flagged, sender disconnected, no more messages
             // ever.
             Err(RecvErr::NoSender)
@@ -311,6 +377,7 @@ struct SenderInner<T> {
 
 impl<T> Drop for SenderInner<T> {
     fn drop(&mut self) {
+        trace_event!(debug, "mpmc sender disconnected");
         // This is safe because we only store nodes allocated via
         // `OwnedAlloc`. Also, the shared back is only deallocated when both
         // sides disconnected.
@@ -318,7 +385,7 @@ impl<T> Drop for SenderInner<T> {
 
         // Let's check for bit marking. If 1 the receiver is already
         // disconnected. If 0, nobody disconnected yet.
-        if ptr as usize & 1 == 0 {
+        if !is_tagged(ptr) {
             // This is safe because we only store nodes allocated via
             // `OwnedAlloc`. Also, the shared back is only deallocated when both
             // sides disconnected.
@@ -329,10 +396,7 @@ impl<T> Drop for SenderInner<T> {
                 // Safe to be a swap since we are the only ones which store
                 // something different from ptr and ptr | 1 and we are not doing
                 // so.
-                self.back
-                    .as_ref()
-                    .ptr
-                    .swap((ptr as usize | 1) as *mut _, Release)
+                self.back.as_ref().ptr.swap(tag(ptr), Release)
             };
 
             if res == ptr {
@@ -342,7 +406,7 @@ impl<T> Drop for SenderInner<T> {
             }
         }
 
-        let ptr = (ptr as usize & !1) as *mut Node<T>;
+        let ptr = untag(ptr);
         // This is safe because the pointer stored in the back will
         // never be null. Also, the sender disconnected and we are the
         // only sender left.
@@ -375,6 +439,7 @@ impl<T> ReceiverInner<T> {
 
 impl<T> Drop for ReceiverInner<T> {
     fn drop(&mut self) {
+        trace_event!(debug, "mpmc receiver disconnected");
         // This is safe because when senders disconnect, they won't drop the
         // back. And we are the only receiver.
         //
@@ -383,7 +448,7 @@ impl<T> Drop for ReceiverInner<T> {
 
         loop {
             // Bit is marked, sender disconnected.
-            if ptr as usize & 1 == 1 {
+            if is_tagged(ptr) {
                 // Safe to delete all nodes because sender disconnected and we
                 // are the only receiver.
                 //
@@ -403,12 +468,10 @@ impl<T> Drop for ReceiverInner<T> {
                 // Let's try to mark the back. Needs to be a CAS because the
                 // back might change the back to some other pointer it
                 // meanwhile.
-                self.back.as_ref().ptr.compare_exchange(
-                    ptr,
-                    (ptr as usize | 1) as *mut _,
-                    Relaxed,
-                    Relaxed,
-                )
+                self.back
+                    .as_ref()
+                    .ptr
+                    .compare_exchange(ptr, tag(ptr), Relaxed, Relaxed)
             };
 
             match res {
@@ -464,10 +527,7 @@ unsafe fn delete_before_last<T>(mut curr: NonNull<Node<T>>, last: Option<NonNull
         // Let's try to mark the next field so other threads can see this node
         // needs to be thrown away. It is ok to swap since we are the
         // only ones accessing this node if the update fails.
-        let next = curr
-            .as_ref()
-            .next
-            .swap((null_mut::<Node<T>>() as usize | 1) as *mut _, Acquire);
+        let next = curr.as_ref().next.swap(tag(null_mut::<Node<T>>()), Acquire);
 
         match NonNull::new(next) {
             // Failure. The node was not null. It was a plain node. We need to
@@ -484,9 +544,51 @@ unsafe fn delete_before_last<T>(mut curr: NonNull<Node<T>>, last: Option<NonNull
     }
 }
 
+// The first and the last nodes of a chain of messages.
+type Chain<T> = (NonNull<Node<T>>, NonNull<Node<T>>);
+
+// Links a node for each message, returning the first and the last ones, or
+// `None` if there are no messages. If the iterator panics, the nodes linked so
+// far are leaked.
+fn chain<T, I>(messages: I) -> Option<Chain<T>>
+where
+    I: IntoIterator<Item = T>,
+{
+    let mut nodes = messages.into_iter().map(|message| {
+        OwnedAlloc::new(Node {
+            message: Removable::new(message),
+            next: AtomicPtr::new(null_mut()),
+        })
+        .into_raw()
+    });
+    let first = nodes.next()?;
+    let last = nodes.fold(first, |prev, node| {
+        // Safe because the nodes are only shared once the chain is complete.
+        unsafe { prev.as_ref().next.store(node.as_ptr(), Relaxed) };
+        node
+    });
+    Some((first, last))
+}
+
+// Deallocates a chain made by `chain` and returns its messages. Unsafe because
+// the chain must not have been shared.
+unsafe fn unchain<T>(first: NonNull<Node<T>>) -> Vec<T> {
+    let mut messages = Vec::new();
+    let mut node_ptr = Some(first);
+    while let Some(node) = node_ptr {
+        let mut alloc = OwnedAlloc::from_raw(node);
+        node_ptr = NonNull::new(alloc.next.load(Relaxed));
+        messages.extend(alloc.message.replace(None));
+    }
+    messages
+}
+
 #[cfg(test)]
 mod test {
-    use crate::channel::mpmc;
+    use crate::{
+        channel::mpmc,
+        test_util::{produce_batches, with_writers, BatchChecker, Produced},
+    };
     use alloc::sync::Arc;
     use alloc::vec::Vec;
     use core::sync::atomic::AtomicBool;
@@ -540,4 +642,38 @@ mod test {
             assert!(status.load(Relaxed));
         }
     }
+
+    #[test]
+    fn send_many_batches_are_contiguous() {
+        const NPRODUCER: usize = 4;
+
+        let (sender, receiver) = mpmc::create();
+        let mut checker = BatchChecker::new();
+        with_writers(
+            &Arc::new(sender),
+            NPRODUCER,
+            |sender, id| {
+                for produced in produce_batches(id) {
+                    match produced {
+                        Produced::Single(item) => sender.send(item).unwrap(),
+                        Produced::Batch(batch) => sender.send_many(batch).unwrap(),
+                    }
+                }
+            },
+            |_| {
+                while let Ok(item) = receiver.recv() {
+                    checker.consume(item);
+                }
+            },
+        );
+        checker.finish(NPRODUCER);
+    }
+
+    #[test]
+    fn send_many_after_disconnect_returns_messages() {
+        let (sender, receiver) = mpmc::create();
+        sender.send_many(Vec::new()).unwrap();
+        drop(receiver);
+        assert_eq!(sender.send_many(0..3).unwrap_err().message, [0, 1, 2]);
+    }
 }
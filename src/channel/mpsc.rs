@@ -2,7 +2,10 @@ pub use super::{
     NoRecv,
     RecvErr::{self, *},
 };
-use crate::ptr::{bypass_null, check_null_align};
+use crate::{
+    ptr::bypass_null,
+    tagged_ptr::{check_align, is_tagged, tag, untag},
+};
 use alloc::sync::Arc;
 use core::{
     fmt,
@@ -15,7 +18,7 @@ use owned_alloc::OwnedAlloc;
 /// channel. In order to allow multiple producers, [`Sender`] is clonable and
 /// does not require mutability.
 pub fn create<T>() -> (Sender<T>, Receiver<T>) {
-    check_null_align::<Node<T>>();
+    check_align::<Node<T>>();
 
     // A single empty node shared between two ends.
     let alloc = OwnedAlloc::new(Node {
@@ -67,7 +70,7 @@ impl<T> Sender<T> {
 
         loop {
             // If the lower bit is marked, it means the receiver disconnected.
-            if loaded as usize & 1 == 1 {
+            if is_tagged(loaded) {
                 // This is safe because we are only recreating the owned
                 // allocation for the node we just created. We did not share the
                 // node.
@@ -134,6 +137,64 @@ impl<T> Sender<T> {
         }
     }
 
+    /// Sends the messages of the given iterable as a single batch. The
+    /// messages are contiguous in the channel and keep the order of the
+    /// iterable: nothing sent concurrently, either alone or in another batch,
+    /// lands among them. Nothing is sent for an empty iterable. If the
+    /// receiver disconnected before the batch was sent, all of its messages
+    /// are returned in the error.
+    pub fn send_many<I>(&self, messages: I) -> Result<(), NoRecv<Vec<T>>>
+    where
+        I: IntoIterator<Item = T>,
+    {
+        let (first, last) = match chain(messages) {
+            Some(ends) => ends,
+            None => return Ok(()),
+        };
+
+        // Just like a single send, but the whole chain goes in between the
+        // previous back and the new one. This is safe because the shared back
+        // is only deallocated when both sides disconnect.
+        let mut loaded = unsafe { self.inner.back.as_ref().ptr.load(Relaxed) };
+
+        loop {
+            if is_tagged(loaded) {
+                // Safe because the chain was not shared.
+                let message = unsafe { unchain(first) };
+                break Err(NoRecv { message });
+            }
+
+            let res = unsafe {
+                self.inner.back.as_ref().ptr.compare_exchange(
+                    loaded,
+                    last.as_ptr(),
+                    AcqRel,
+                    Relaxed,
+                )
+            };
+
+            match res {
+                Ok(_) => {
+                    // Safe because we never store null on the back.
+                    let prev = unsafe { bypass_null(loaded) };
+                    let res = unsafe { prev.as_ref().next.swap(first.as_ptr(), Release) };
+
+                    // The receiver disconnected meanwhile, so the previous back
+                    // and the whole chain are thrown away, as in `send`.
+                    if !res.is_null() {
+                        unsafe {
+                            OwnedAlloc::from_raw(prev);
+                            delete_before_last(first, None);
+                        }
+                    }
+                    break Ok(());
+                }
+
+                Err(new) => loaded = new,
+            }
+        }
+    }
+
     /// Tests if the [`Receiver`] is still connected. There are no guarantees
     /// that [`send`](Sender::send) will succeed if this method returns `true`
     /// because the [`Receiver`] may disconnect meanwhile.
@@ -143,7 +204,7 @@ impl<T> Sender<T> {
         // when both sides disconnected. We load it to check for bit
         // marking (since it means sender disconnected).
         let back = unsafe { self.inner.back.as_ref() };
-        back.ptr.load(Relaxed) as usize & 1 == 0
+        !is_tagged(back.ptr.load(Relaxed))
     }
 }
 
@@ -215,9 +276,9 @@ impl<T> Receiver<T> {
                             // when both sides disconnected. We load it to check
                             // for bit
                             // marking (since it means sender disconnected).
-                            let back = unsafe { self.back.as_ref().ptr.load(Relaxed) as usize };
+                            let back = unsafe { self.back.as_ref().ptr.load(Relaxed) };
 
-                            break if back & 1 == 0 || (back & !1) as *mut _ != self.front.as_ptr() {
+                            break if !is_tagged(back) || untag(back) != self.front.as_ptr() {
                                 // If back is not marked, we just don't have
                                 // messages.
                                 Err(RecvErr::NoMessage)
@@ -265,7 +326,7 @@ impl<T> Receiver<T> {
         // when both sides disconnected. We load it to check for bit
         // marking (since it means sender disconnected).
         let back = unsafe { self.back.as_ref() };
-        back.ptr.load(Acquire) as usize & 1 == 0
+        !is_tagged(back.ptr.load(Acquire))
             || front.message.is_some()
             || !front.next.load(Acquire).is_null()
     }
@@ -284,6 +345,7 @@ impl<T> Receiver<T> {
 
 impl<T> Drop for Receiver<T> {
     fn drop(&mut self) {
+        trace_event!(debug, "mpsc receiver disconnected");
         // This is safe because when senders disconnect, they won't drop the
         // back. The shared back is only deleted when both sides disconnect.
         // And we are the only receiver.
@@ -292,7 +354,7 @@ impl<T> Drop for Receiver<T> {
         let mut ptr = unsafe { self.back.as_ref().ptr.load(Relaxed) };
         loop {
             // Bit is marked, sender disconnected.
-            if ptr as usize & 1 == 1 {
+            if is_tagged(ptr) {
                 // Safe to delete all nodes because sender disconnected and we
                 // are the only receiver.
                 //
@@ -312,12 +374,10 @@ impl<T> Drop for Receiver<T> {
                 // Let's try to mark the back. Needs to be a CAS because the
                 // back might change the back to some other pointer it
                 // meanwhile.
-                self.back.as_ref().ptr.compare_exchange(
-                    ptr,
-                    (ptr as usize | 1) as *mut _,
-                    Relaxed,
-                    Relaxed,
-                )
+                self.back
+                    .as_ref()
+                    .ptr
+                    .compare_exchange(ptr, tag(ptr), Relaxed, Relaxed)
             };
 
             match res {
@@ -353,6 +413,7 @@ struct SenderInner<T> {
 
 impl<T> Drop for SenderInner<T> {
     fn drop(&mut self) {
+        trace_event!(debug, "mpsc sender disconnected");
         // This is safe because we only store nodes allocated via
         // `OwnedAlloc`. Also, the shared back is only deallocated when both
         // sides disconnected.
@@ -360,7 +421,7 @@ impl<T> Drop for SenderInner<T> {
 
         // Let's check for bit marking. If 1 the receiver is already
         // disconnected. If 0, nobody disconnected yet.
-        if ptr as usize & 1 == 0 {
+        if !is_tagged(ptr) {
             // This is safe because we only store nodes allocated via
             // `OwnedAlloc`. Also, the shared back is only deallocated when both
             // sides disconnected.
@@ -371,10 +432,7 @@ impl<T> Drop for SenderInner<T> {
                 // Safe to be a swap since we are the only ones which store
                 // something different from ptr and ptr | 1 and we are not doing
                 // so.
-                self.back
-                    .as_ref()
-                    .ptr
-                    .swap((ptr as usize | 1) as *mut _, Relaxed)
+                self.back.as_ref().ptr.swap(tag(ptr), Relaxed)
             };
 
             if res == ptr {
@@ -385,7 +443,7 @@ impl<T> Drop for SenderInner<T> {
         }
 
         // Falling here means sender disconnected.
-        let ptr = (ptr as usize & !1) as *mut Node<T>;
+        let ptr = untag(ptr);
         // This is safe because the pointer stored in the back will
         // never be null. Also, the sender disconnected and we are the
         // only sender left.
@@ -420,10 +478,7 @@ unsafe fn delete_before_last<T>(mut curr: NonNull<Node<T>>, last: Option<NonNull
         // Let's try to mark the next field so other threads can see this node
         // needs to be thrown away. It is ok to swap since we are the
         // only ones accessing this node if the update fails.
-        let next = curr
-            .as_ref()
-            .next
-            .swap((null_mut::<Node<T>>() as usize | 1) as *mut _, Acquire);
+        let next = curr.as_ref().next.swap(tag(null_mut::<Node<T>>()), Acquire);
 
         match NonNull::new(next) {
             // Failure. The node was not null. It was a plain node. We need to
@@ -440,9 +495,52 @@ unsafe fn delete_before_last<T>(mut curr: NonNull<Node<T>>, last: Option<NonNull
     }
 }
 
+// The first and the last nodes of a chain of messages.
+type Chain<T> = (NonNull<Node<T>>, NonNull<Node<T>>);
+
+// Links a node for each message, returning the first and the last ones, or
+// `None` if there are no messages. If the iterator panics, the nodes linked so
+// far are leaked.
+fn chain<T, I>(messages: I) -> Option<Chain<T>>
+where
+    I: IntoIterator<Item = T>,
+{
+    let mut nodes = messages.into_iter().map(|message| {
+        OwnedAlloc::new(Node {
+            message: Some(message),
+            next: AtomicPtr::new(null_mut()),
+        })
+        .into_raw()
+    });
+    let first = nodes.next()?;
+    let last = nodes.fold(first, |prev, node| {
+        // Safe because the nodes are only shared once the chain is complete.
+        unsafe { prev.as_ref().next.store(node.as_ptr(), Relaxed) };
+        node
+    });
+    Some((first, last))
+}
+
+// Deallocates a chain made by `chain` and returns its messages. Unsafe because
+// the chain must not have been shared.
+unsafe fn unchain<T>(first: NonNull<Node<T>>) -> Vec<T> {
+    let mut messages = Vec::new();
+    let mut node_ptr = Some(first);
+    while let Some(node) = node_ptr {
+        let mut alloc = OwnedAlloc::from_raw(node);
+        node_ptr = NonNull::new(alloc.next.load(Relaxed));
+        messages.extend(alloc.message.take());
+    }
+    messages
+}
+
 #[cfg(test)]
 mod test {
-    use crate::channel::mpsc;
+    use crate::{
+        channel::mpsc,
+        test_util::{produce_batches, with_writers, BatchChecker, Produced},
+    };
+    use alloc::sync::Arc;
     use std::thread;
 
     #[test]
@@ -488,4 +586,38 @@ mod test {
             assert!(*status);
         }
     }
+
+    #[test]
+    fn send_many_batches_are_contiguous() {
+        const NPRODUCER: usize = 4;
+
+        let (sender, mut receiver) = mpsc::create();
+        let mut checker = BatchChecker::new();
+        with_writers(
+            &Arc::new(sender),
+            NPRODUCER,
+            |sender, id| {
+                for produced in produce_batches(id) {
+                    match produced {
+                        Produced::Single(item) => sender.send(item).unwrap(),
+                        Produced::Batch(batch) => sender.send_many(batch).unwrap(),
+                    }
+                }
+            },
+            |_| {
+                while let Ok(item) = receiver.recv() {
+                    checker.consume(item);
+                }
+            },
+        );
+        checker.finish(NPRODUCER);
+    }
+
+    #[test]
+    fn send_many_after_disconnect_returns_messages() {
+        let (sender, receiver) = mpsc::create();
+        sender.send_many(Vec::new()).unwrap();
+        drop(receiver);
+        assert_eq!(sender.send_many(0..3).unwrap_err().message, [0, 1, 2]);
+    }
 }
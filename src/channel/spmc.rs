@@ -4,8 +4,9 @@ pub use super::{
 };
 use crate::{
     incin::Pause,
-    ptr::{bypass_null, check_null_align},
+    ptr::bypass_null,
     removable::Removable,
+    tagged_ptr::{check_align, is_tagged, tag},
 };
 use alloc::sync::Arc;
 use core::{
@@ -25,7 +26,7 @@ pub fn create<T>() -> (Sender<T>, Receiver<T>) {
 /// Same as [`create`], but use a passed incinerator instead of creating a new
 /// one.
 pub fn with_incin<T>(incin: SharedIncin<T>) -> (Sender<T>, Receiver<T>) {
-    check_null_align::<Node<T>>();
+    check_align::<Node<T>>();
 
     // First we create a single node shared between two ends.
     let alloc = OwnedAlloc::new(Node {
@@ -91,6 +92,39 @@ impl<T> Sender<T> {
         }
     }
 
+    /// Sends the messages of the given iterable as a single batch. The
+    /// messages are contiguous in the channel and keep the order of the
+    /// iterable. Nothing is sent for an empty iterable. If the receivers
+    /// disconnected, none of the messages is sent and all of them are returned
+    /// in the error.
+    pub fn send_many<I>(&mut self, messages: I) -> Result<(), NoRecv<Vec<T>>>
+    where
+        I: IntoIterator<Item = T>,
+    {
+        let (first, last) = match chain(messages) {
+            Some(ends) => ends,
+            None => return Ok(()),
+        };
+
+        // Just like a single send, but the whole chain is published at once.
+        // This dereferral is safe because the queue has at least one node.
+        let res = unsafe {
+            self.back
+                .as_ref()
+                .next
+                .compare_exchange(null_mut(), first.as_ptr(), Release, Relaxed)
+        };
+
+        if res.is_ok() {
+            self.back = last;
+            Ok(())
+        } else {
+            // Safe because the chain was not shared, since the CAS failed.
+            let message = unsafe { unchain(first) };
+            Err(NoRecv { message })
+        }
+    }
+
     /// Tests if there are any [`Receiver`]s still connected. There are no
     /// guarantees that [`send`](Sender::send) will succeed if this method
     /// returns `true` because the [`Receiver`] may disconnect meanwhile.
@@ -104,6 +138,7 @@ impl<T> Sender<T> {
 
 impl<T> Drop for Sender<T> {
     fn drop(&mut self) {
+        trace_event!(debug, "spmc sender disconnected");
         // This dereferral is safe because the queue always have at least one
         // node. This single node is only dropped when the last side to
         // disconnect drops.
@@ -116,7 +151,7 @@ impl<T> Drop for Sender<T> {
             self.back
                 .as_ref()
                 .next
-                .swap((null_mut::<Node<T>>() as usize | 1) as *mut _, Relaxed)
+                .swap(tag(null_mut::<Node<T>>()), Relaxed)
         };
 
         // If the previously stored value was not null, receiver has already
@@ -195,7 +230,7 @@ impl<T> Receiver<T> {
         // Safe to derefer this pointer because we paused the incinerator and we
         // only delete nodes via incinerator.
         let front = unsafe { &*self.inner.front.load(Relaxed) };
-        front.message.is_present(Relaxed) || front.next.load(Relaxed) as usize & 1 == 0
+        front.message.is_present(Relaxed) || !is_tagged(front.next.load(Relaxed))
     }
 
     /// The shared incinerator used by this [`Receiver`].
@@ -213,7 +248,7 @@ impl<T> Receiver<T> {
     ) -> Result<NonNull<Node<T>>, RecvErr> {
         let next = expected.as_ref().next.load(Acquire);
 
-        if next as usize & 1 == 1 {
+        if is_tagged(next) {
             // If the next is bit flagged, sender disconnected, no more messages
             // ever.
             Err(RecvErr::NoSender)
@@ -272,6 +307,7 @@ struct ReceiverInner<T> {
 
 impl<T> Drop for ReceiverInner<T> {
     fn drop(&mut self) {
+        trace_event!(debug, "spmc receiver disconnected");
         let front = self.front.get_mut();
         loop {
             // This null-check-by-pass is safe because we never store null in
@@ -286,7 +322,7 @@ impl<T> Drop for ReceiverInner<T> {
                 // sender already disconnected and marked this pointer.
                 front_nnptr.as_ref().next.compare_exchange(
                     null_mut(),
-                    (null_mut::<Node<T>>() as usize | 1) as *mut _,
+                    tag(null_mut::<Node<T>>()),
                     AcqRel,
                     Acquire,
                 )
@@ -306,7 +342,7 @@ impl<T> Drop for ReceiverInner<T> {
 
                     // This means the sender disconnected we reached the end of
                     // the queue.
-                    if next as usize & 1 == 1 {
+                    if is_tagged(next) {
                         break;
                     }
 
@@ -330,9 +366,51 @@ make_shared_incin! {
     pub SharedIncin<T> of OwnedAlloc<Node<T>>
 }
 
+// The first and the last nodes of a chain of messages.
+type Chain<T> = (NonNull<Node<T>>, NonNull<Node<T>>);
+
+// Links a node for each message, returning the first and the last ones, or
+// `None` if there are no messages. If the iterator panics, the nodes linked so
+// far are leaked.
+fn chain<T, I>(messages: I) -> Option<Chain<T>>
+where
+    I: IntoIterator<Item = T>,
+{
+    let mut nodes = messages.into_iter().map(|message| {
+        OwnedAlloc::new(Node {
+            message: Removable::new(message),
+            next: AtomicPtr::new(null_mut()),
+        })
+        .into_raw()
+    });
+    let first = nodes.next()?;
+    let last = nodes.fold(first, |prev, node| {
+        // Safe because the nodes are only shared once the chain is complete.
+        unsafe { prev.as_ref().next.store(node.as_ptr(), Relaxed) };
+        node
+    });
+    Some((first, last))
+}
+
+// Deallocates a chain made by `chain` and returns its messages. Unsafe because
+// the chain must not have been shared.
+unsafe fn unchain<T>(first: NonNull<Node<T>>) -> Vec<T> {
+    let mut messages = Vec::new();
+    let mut node_ptr = Some(first);
+    while let Some(node) = node_ptr {
+        let mut alloc = OwnedAlloc::from_raw(node);
+        node_ptr = NonNull::new(alloc.next.load(Relaxed));
+        messages.extend(alloc.message.replace(None));
+    }
+    messages
+}
+
 #[cfg(test)]
 mod test {
-    use crate::channel::spmc;
+    use crate::{
+        channel::spmc,
+        test_util::{produce_batches, BatchChecker, Produced},
+    };
     use alloc::sync::Arc;
     use alloc::vec::Vec;
     use core::sync::atomic::AtomicBool;
@@ -378,4 +456,36 @@ mod test {
             assert!(status.load(Relaxed));
         }
     }
+
+    #[test]
+    fn send_many_batches_are_contiguous() {
+        let (mut sender, receiver) = spmc::create();
+        let producer = thread::spawn(move || {
+            for produced in produce_batches(0) {
+                match produced {
+                    Produced::Single(item) => sender.send(item).unwrap(),
+                    Produced::Batch(batch) => sender.send_many(batch).unwrap(),
+                }
+            }
+        });
+
+        let mut checker = BatchChecker::new();
+        loop {
+            match receiver.recv() {
+                Ok(item) => checker.consume(item),
+                Err(spmc::NoMessage) => (),
+                Err(spmc::NoSender) => break,
+            }
+        }
+        producer.join().unwrap();
+        checker.finish(1);
+    }
+
+    #[test]
+    fn send_many_after_disconnect_returns_messages() {
+        let (mut sender, receiver) = spmc::create();
+        sender.send_many(Vec::new()).unwrap();
+        drop(receiver);
+        assert_eq!(sender.send_many(0..3).unwrap_err().message, [0, 1, 2]);
+    }
 }
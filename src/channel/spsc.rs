@@ -2,7 +2,7 @@ pub use super::{
     NoRecv,
     RecvErr::{self, *},
 };
-use crate::ptr::check_null_align;
+use crate::tagged_ptr::{check_align, is_tagged, tag, untag};
 use core::{
     fmt,
     ptr::{null_mut, NonNull},
@@ -13,7 +13,7 @@ use owned_alloc::OwnedAlloc;
 /// Creates an asynchronous lock-free Single-Producer-Single-Consumer (SPSC)
 /// channel.
 pub fn create<T>() -> (Sender<T>, Receiver<T>) {
-    check_null_align::<Node<T>>();
+    check_align::<Node<T>>();
 
     // A single empty node shared between two ends.
     let alloc = OwnedAlloc::new(Node {
@@ -71,6 +71,40 @@ impl<T> Sender<T> {
         }
     }
 
+    /// Sends the messages of the given iterable as a single batch. The
+    /// [`Receiver`] gets them contiguously and in the order of the iterable.
+    /// Nothing is sent for an empty iterable. If the receiver disconnected,
+    /// none of the messages is sent and all of them are returned in the
+    /// error.
+    pub fn send_many<I>(&mut self, messages: I) -> Result<(), NoRecv<Vec<T>>>
+    where
+        I: IntoIterator<Item = T>,
+    {
+        let (first, last) = match chain(messages) {
+            Some(ends) => ends,
+            None => return Ok(()),
+        };
+
+        // Just like a single send, but the whole chain is published at once.
+        // This dereferral is safe because the queue will always have at least
+        // one node.
+        let res = unsafe {
+            self.back
+                .as_ref()
+                .next
+                .compare_exchange(null_mut(), first.as_ptr(), Release, Relaxed)
+        };
+
+        if res.is_ok() {
+            self.back = last;
+            Ok(())
+        } else {
+            // Safe because the chain was not shared, since the CAS failed.
+            let message = unsafe { unchain(first) };
+            Err(NoRecv { message })
+        }
+    }
+
     /// Tests if the [`Receiver`] is still connected. There are no guarantees
     /// that [`send`](Sender::send) will succeed if this method returns `true`
     /// because the [`Receiver`] may disconnect meanwhile.
@@ -84,6 +118,7 @@ impl<T> Sender<T> {
 
 impl<T> Drop for Sender<T> {
     fn drop(&mut self) {
+        trace_event!(debug, "spsc sender disconnected");
         // This dereferral is safe because the queue will always have at least
         // one node. Also, we only put nodes allocated from `OwnedAlloc`.
         let res = unsafe {
@@ -95,7 +130,7 @@ impl<T> Drop for Sender<T> {
             self.back
                 .as_ref()
                 .next
-                .swap((null_mut::<Node<T>>() as usize | 1) as *mut _, Relaxed)
+                .swap(tag(null_mut::<Node<T>>()), Relaxed)
         };
 
         // If the previously stored value was not null, receiver has already
@@ -137,7 +172,7 @@ impl<T> Receiver<T> {
             // First we remove a node logically.
             match node.message.take() {
                 Some(message) => {
-                    let cleared = (next as usize & !1) as *mut _;
+                    let cleared = untag(next);
                     // But only if we have a new node. Otherwise we will not
                     // remove the only node of the queue. Also, let's clear the
                     // bit flag so null pointers are not misused.
@@ -153,7 +188,7 @@ impl<T> Receiver<T> {
                 }
 
                 None => {
-                    if next as usize & 1 == 0 {
+                    if !is_tagged(next) {
                         // Lower bit clean. Let's try to remove the next.
                         match NonNull::new(next) {
                             Some(nnptr) => {
@@ -188,12 +223,13 @@ impl<T> Receiver<T> {
         // Safe because we always have at least one node, which is only dropped
         // in the last side to disconnect's drop.
         let front = unsafe { self.front.as_ref() };
-        front.message.is_some() || front.next.load(Relaxed) as usize & 1 == 0
+        front.message.is_some() || !is_tagged(front.next.load(Relaxed))
     }
 }
 
 impl<T> Drop for Receiver<T> {
     fn drop(&mut self) {
+        trace_event!(debug, "spsc receiver disconnected");
         loop {
             // This dereferral is safe because we only put nodes allocated from
             // `OwnedAlloc`.
@@ -206,7 +242,7 @@ impl<T> Drop for Receiver<T> {
                 self.front
                     .as_ref()
                     .next
-                    .swap((null_mut::<Node<T>>() as usize | 1) as *mut _, Acquire)
+                    .swap(tag(null_mut::<Node<T>>()), Acquire)
             };
 
             // Then we check for null (success of our swap).
@@ -223,7 +259,7 @@ impl<T> Drop for Receiver<T> {
 
             // if next is marked, it is actually null | 1, but we can deallocate
             // it because the sender already disconnected.
-            if next as usize & 1 == 1 {
+            if is_tagged(next) {
                 break;
             }
 
@@ -249,11 +285,85 @@ struct Node<T> {
     next: AtomicPtr<Node<T>>,
 }
 
+// The first and the last nodes of a chain of messages.
+type Chain<T> = (NonNull<Node<T>>, NonNull<Node<T>>);
+
+// Links a node for each message, returning the first and the last ones, or
+// `None` if there are no messages. If the iterator panics, the nodes linked so
+// far are leaked.
+fn chain<T, I>(messages: I) -> Option<Chain<T>>
+where
+    I: IntoIterator<Item = T>,
+{
+    let mut nodes = messages.into_iter().map(|message| {
+        OwnedAlloc::new(Node {
+            message: Some(message),
+            next: AtomicPtr::new(null_mut()),
+        })
+        .into_raw()
+    });
+    let first = nodes.next()?;
+    let last = nodes.fold(first, |prev, node| {
+        // Safe because the nodes are only shared once the chain is complete.
+        unsafe { prev.as_ref().next.store(node.as_ptr(), Relaxed) };
+        node
+    });
+    Some((first, last))
+}
+
+// Deallocates a chain made by `chain` and returns its messages. Unsafe because
+// the chain must not have been shared.
+unsafe fn unchain<T>(first: NonNull<Node<T>>) -> Vec<T> {
+    let mut messages = Vec::new();
+    let mut node_ptr = Some(first);
+    while let Some(node) = node_ptr {
+        let mut alloc = OwnedAlloc::from_raw(node);
+        node_ptr = NonNull::new(alloc.next.load(Relaxed));
+        messages.extend(alloc.message.take());
+    }
+    messages
+}
+
 #[cfg(test)]
 mod test {
-    use crate::channel::spsc;
+    use crate::{
+        channel::spsc,
+        test_util::{produce_batches, BatchChecker, Produced},
+    };
     use std::thread;
 
+    #[test]
+    fn send_many_batches_are_contiguous() {
+        let (mut sender, mut receiver) = spsc::create();
+        let producer = thread::spawn(move || {
+            for produced in produce_batches(0) {
+                match produced {
+                    Produced::Single(item) => sender.send(item).unwrap(),
+                    Produced::Batch(batch) => sender.send_many(batch).unwrap(),
+                }
+            }
+        });
+
+        let mut checker = BatchChecker::new();
+        loop {
+            match receiver.recv() {
+                Ok(item) => checker.consume(item),
+                Err(spsc::NoMessage) => (),
+                Err(spsc::NoSender) => break,
+            }
+        }
+        producer.join().unwrap();
+        checker.finish(1);
+    }
+
+    #[test]
+    fn send_many_after_disconnect_returns_messages() {
+        let (mut sender, receiver) = spsc::create();
+        sender.send_many(Vec::new()).unwrap();
+        drop(receiver);
+        assert_eq!(sender.send_many(0..3).unwrap_err().message, [0, 1, 2]);
+    }
+
     #[test]
     fn correct_sequence() {
         const MSGS: usize = 512;
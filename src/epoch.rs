@@ -0,0 +1,109 @@
+use crate::incin::Reclaimer;
+use alloc::boxed::Box;
+
+/// A [`Reclaimer`] which defers the garbage of an incinerator to the global
+/// collector of crossbeam-epoch, so it is dropped alongside the garbage of the
+/// rest of the process.
+///
+/// Pauses of the incinerator still protect the structure, since its guards
+/// may be sent to other threads while crossbeam's guards may not. Garbage
+/// handed to crossbeam is only dropped once the epoch advanced past it and the
+/// incinerator has no active pause. Otherwise, it is deferred again. In these
+/// terms, asking whether no sensitive reads are active, as
+/// [`Removed::try_into`](crate::map::Removed::try_into) does, first flushes
+/// crossbeam and then checks the pauses.
+///
+/// # Example
+/// ```rust
+/// use tux_lockfree::{epoch::EpochReclaim, map::Map};
+///
+/// let map = Map::with_reclaimer(EpochReclaim::default());
+/// map.insert(1, "one");
+/// let guard = map.get(&1).unwrap();
+/// map.remove(&1);
+/// // The guard keeps the removed entry alive.
+/// assert_eq!(*guard.val(), "one");
+/// ```
+#[derive(Debug, Clone, Copy, Default)]
+pub struct EpochReclaim {
+    _priv: (),
+}
+
+impl Reclaimer for EpochReclaim {
+    fn defer(&self, job: Box<dyn FnOnce() + Send>) {
+        crossbeam_epoch::pin().defer(job);
+    }
+
+    fn flush(&self) {
+        crossbeam_epoch::pin().flush();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{map::Removed, prelude::*};
+    use alloc::sync::Arc;
+
+    // Flushes crossbeam until the given value is only referenced once, giving
+    // up after a while.
+    fn reclaimed<T>(val: &Arc<T>) -> bool {
+        for _ in 0..10_000 {
+            if Arc::strong_count(val) == 1 {
+                return true;
+            }
+            EpochReclaim::default().flush();
+        }
+        false
+    }
+
+    #[test]
+    fn guards_outlive_removal() {
+        let map = Map::with_reclaimer(EpochReclaim::default());
+        let val = Arc::new(5);
+        map.insert("key", val.clone());
+
+        let guard = map.get("key").unwrap();
+        drop(map.remove("key"));
+        assert!(!reclaimed(&val));
+        assert_eq!(**guard.val(), 5);
+
+        drop(guard);
+        assert!(reclaimed(&val));
+    }
+
+    #[test]
+    fn remove_and_reinsert() {
+        let map = Map::with_reclaimer(EpochReclaim::default());
+        map.insert(3, Arc::new(3));
+        map.insert(4, Arc::new(4));
+
+        let removed = map.remove(&3).unwrap();
+        assert!(map.get(&3).is_none());
+        assert!(map.reinsert(removed).created());
+        assert_eq!(**map.get(&3).unwrap().val(), 3);
+
+        let guard = map.get(&4).unwrap();
+        let removed = map.remove(&3).unwrap();
+        let removed = Removed::try_into(removed).unwrap_err();
+        drop(guard);
+        let (key, val) = Removed::try_into(removed).unwrap();
+        assert_eq!((key, *val), (3, 3));
+    }
+
+    #[test]
+    fn queue_and_set() {
+        let queue = Queue::with_reclaimer(EpochReclaim::default());
+        let val = Arc::new(());
+        queue.push(val.clone());
+        queue.push(Arc::new(()));
+        drop(queue.pop());
+        assert!(reclaimed(&val));
+
+        let set = Set::with_reclaimer(EpochReclaim::default());
+        set.insert(7).unwrap();
+        let guard = set.get(&7).unwrap();
+        assert!(set.remove(&7).is_some());
+        assert_eq!(*guard, 7);
+    }
+}
@@ -0,0 +1,34 @@
+/// Introspection of the memory held by a concurrent structure. The numbers
+/// count the bookkeeping allocations of the structure (tables, nodes, entries
+/// and the storage of the elements themselves), but not memory owned by the
+/// elements, such as the buffer of a `String`.
+///
+/// Since other threads may be changing the structure at the same time, the
+/// numbers are estimates. They do grow as elements are added and shrink as
+/// elements are removed and garbage is dropped.
+///
+/// # Example
+/// ```rust
+/// use tux_lockfree::{footprint::MemoryFootprint, map::Map};
+///
+/// let map = Map::new();
+/// let empty = map.bytes_allocated();
+/// map.insert(3u32, "three");
+/// assert!(map.bytes_allocated() > empty);
+/// ```
+pub trait MemoryFootprint {
+    /// Returns an estimate of how many bytes the structure currently has
+    /// allocated for its live elements and its internal layout.
+    fn bytes_allocated(&self) -> usize;
+
+    /// Returns an estimate of how many bytes are held by removed elements and
+    /// nodes waiting for deferred reclamation. Structures sharing an
+    /// incinerator report the garbage of the whole incinerator.
+    fn garbage_bytes(&self) -> usize;
+
+    /// Returns the sum of [`MemoryFootprint::bytes_allocated`] and
+    /// [`MemoryFootprint::garbage_bytes`].
+    fn memory_footprint(&self) -> usize {
+        self.bytes_allocated() + self.garbage_bytes()
+    }
+}
@@ -0,0 +1,216 @@
+use core::hash::{BuildHasher, Hasher};
+
+/// The hasher builder used by default by [`Map`](crate::map::Map) and
+/// [`Set`](crate::set::Set). With the `std` feature, this is the randomly
+/// seeded builder of the standard library.
+#[cfg(feature = "std")]
+pub type DefaultState = std::collections::hash_map::RandomState;
+
+/// The hasher builder used by default by [`Map`](crate::map::Map) and
+/// [`Set`](crate::set::Set). Without the `std` feature there is no source of
+/// randomness, so this is the [`DeterministicState`]. Consider supplying your
+/// own builder if keys may be chosen by an adversary.
+#[cfg(not(feature = "std"))]
+pub type DefaultState = DeterministicState;
+
+/// A hasher builder which always builds hashers with the same initial state.
+/// Hashes are the same in every run of the program, which makes it vulnerable
+/// to collision attacks, but it needs no source of randomness.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DeterministicState;
+
+impl BuildHasher for DeterministicState {
+    type Hasher = DeterministicHasher;
+
+    fn build_hasher(&self) -> Self::Hasher {
+        DeterministicHasher::default()
+    }
+}
+
+/// The hasher built by [`DeterministicState`]. It is an implementation of the
+/// 64-bit FNV-1a hash.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DeterministicHasher {
+    state: u64,
+}
+
+const FNV_OFFSET: u64 = 0xcbf29ce484222325;
+
+const FNV_PRIME: u64 = 0x100000001b3;
+
+impl Default for DeterministicHasher {
+    fn default() -> Self {
+        Self { state: FNV_OFFSET }
+    }
+}
+
+impl Hasher for DeterministicHasher {
+    fn finish(&self) -> u64 {
+        self.state
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.state ^= u64::from(byte);
+            self.state = self.state.wrapping_mul(FNV_PRIME);
+        }
+    }
+}
+
+/// A hasher builder with an explicit 128-bit seed. Hashers are SipHash-2-4
+/// hashers keyed with the seed, so two maps or sets built with the same seed
+/// and the same operations have the same shape and iteration order, in every
+/// run of the program.
+///
+/// This is meant for reproducible tests. It is not resistant to collision
+/// attacks unless the seed is kept secret and chosen randomly, in which case
+/// the default builder should just be used.
+///
+/// # Example
+/// ```rust
+/// use tux_lockfree::{hash::SeededState, map::Map};
+///
+/// let seed = *b"reproducible map";
+/// let first = Map::with_hasher(SeededState::new(seed));
+/// let second = Map::with_seed(seed);
+/// for i in 0..32 {
+///     first.insert(i, i);
+///     second.insert(i, i);
+/// }
+/// assert!(first.iter().map(|guard| *guard.key()).eq(second.iter().map(|guard| *guard.key())));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SeededState {
+    k0: u64,
+    k1: u64,
+}
+
+impl SeededState {
+    /// Creates a hasher builder from the given seed.
+    pub fn new(seed: [u8; 16]) -> Self {
+        let mut k0 = [0; 8];
+        let mut k1 = [0; 8];
+        k0.copy_from_slice(&seed[..8]);
+        k1.copy_from_slice(&seed[8..]);
+        Self {
+            k0: u64::from_le_bytes(k0),
+            k1: u64::from_le_bytes(k1),
+        }
+    }
+}
+
+impl BuildHasher for SeededState {
+    type Hasher = SeededHasher;
+
+    fn build_hasher(&self) -> Self::Hasher {
+        SeededHasher::new(self.k0, self.k1)
+    }
+}
+
+/// The hasher built by [`SeededState`]. It is an implementation of
+/// SipHash-2-4.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SeededHasher {
+    v0: u64,
+    v1: u64,
+    v2: u64,
+    v3: u64,
+    tail: u64,
+    ntail: usize,
+    length: usize,
+}
+
+impl SeededHasher {
+    fn new(k0: u64, k1: u64) -> Self {
+        Self {
+            v0: k0 ^ 0x736f6d6570736575,
+            v1: k1 ^ 0x646f72616e646f6d,
+            v2: k0 ^ 0x6c7967656e657261,
+            v3: k1 ^ 0x7465646279746573,
+            tail: 0,
+            ntail: 0,
+            length: 0,
+        }
+    }
+
+    fn round(&mut self) {
+        self.v0 = self.v0.wrapping_add(self.v1);
+        self.v1 = self.v1.rotate_left(13) ^ self.v0;
+        self.v0 = self.v0.rotate_left(32);
+        self.v2 = self.v2.wrapping_add(self.v3);
+        self.v3 = self.v3.rotate_left(16) ^ self.v2;
+        self.v0 = self.v0.wrapping_add(self.v3);
+        self.v3 = self.v3.rotate_left(21) ^ self.v0;
+        self.v2 = self.v2.wrapping_add(self.v1);
+        self.v1 = self.v1.rotate_left(17) ^ self.v2;
+        self.v2 = self.v2.rotate_left(32);
+    }
+
+    fn compress(&mut self, word: u64) {
+        self.v3 ^= word;
+        self.round();
+        self.round();
+        self.v0 ^= word;
+    }
+}
+
+impl Hasher for SeededHasher {
+    fn finish(&self) -> u64 {
+        // Finishing must not change the state, so we work on a copy.
+        let mut this = *self;
+        let last = (self.length as u64 & 0xff) << 56 | self.tail;
+        this.compress(last);
+        this.v2 ^= 0xff;
+        for _ in 0..4 {
+            this.round();
+        }
+        this.v0 ^ this.v1 ^ this.v2 ^ this.v3
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        self.length = self.length.wrapping_add(bytes.len());
+        for &byte in bytes {
+            self.tail |= u64::from(byte) << (8 * self.ntail);
+            self.ntail += 1;
+            if self.ntail == 8 {
+                let word = self.tail;
+                self.compress(word);
+                self.tail = 0;
+                self.ntail = 0;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn deterministic() {
+        let mut first = DeterministicState.build_hasher();
+        first.write(b"foobar");
+        let mut second = DeterministicState.build_hasher();
+        second.write(b"foobar");
+        assert_eq!(first.finish(), second.finish());
+        assert_eq!(first.finish(), 0x85944171f73967e8);
+    }
+
+    #[test]
+    fn siphash_reference_vectors() {
+        let mut seed = [0; 16];
+        for (i, byte) in seed.iter_mut().enumerate() {
+            *byte = i as u8;
+        }
+        let state = SeededState::new(seed);
+
+        let hasher = state.build_hasher();
+        assert_eq!(hasher.finish(), 0x726fdb47dd0e0e31);
+
+        let message = (0..15).collect::<alloc::vec::Vec<u8>>();
+        let mut hasher = state.build_hasher();
+        hasher.write(&message[..7]);
+        hasher.write(&message[7..]);
+        assert_eq!(hasher.finish(), 0xa129ca6149be45e5);
+    }
+}
@@ -1,11 +1,21 @@
+#[cfg(any(not(feature = "std"), loom))]
+use crate::sync::AtomicPtr;
+use crate::sync::AtomicUsize;
+#[cfg(all(feature = "std", not(loom)))]
 use crate::tls::ThreadLocal;
+#[cfg(all(feature = "std", not(loom)))]
 use alloc::vec::Vec;
-use core::{
-    cell::Cell,
-    fmt,
-    marker::PhantomData,
-    sync::atomic::{AtomicUsize, Ordering::*},
+use alloc::{
+    boxed::Box,
+    sync::{Arc, Weak},
 };
+#[cfg(all(feature = "std", not(loom)))]
+use core::cell::Cell;
+#[cfg(any(not(feature = "std"), loom))]
+use core::ptr::{null_mut, NonNull};
+use core::{fmt, marker::PhantomData, mem, sync::atomic::Ordering::*};
+#[cfg(any(not(feature = "std"), loom))]
+use owned_alloc::OwnedAlloc;
 
 /// The incinerator. It is an API used to solve the infamous ABA problem. It
 /// basically consists of a counter and a list of garbage. Before a thread
@@ -20,6 +30,15 @@ use core::{
 /// When the incinerator is dropped, all the garbage is automatically dropped
 /// too.
 ///
+/// Without the `std` feature (or when model-checking with `--cfg loom`) there
+/// is no thread-local storage, so a single lock-free garbage list is shared by
+/// all threads. It is only cleared when
+/// the counter is observed to be zero after the list was taken, which means
+/// garbage may be held for longer than with the `std` feature.
+///
+/// An incinerator created with [`Incinerator::with_reclaimer`] keeps no garbage
+/// lists. It hands its garbage to an external [`Reclaimer`] instead.
+///
 /// C11 Implementation: <https://gitlab.com/bzim/c11-incinerator/>
 ///
 /// # Example
@@ -66,7 +85,11 @@ use core::{
 #[derive(Debug)]
 pub struct Incinerator<T> {
     counter: AtomicUsize,
+    #[cfg(all(feature = "std", not(loom)))]
     tls_list: ThreadLocal<GarbageList<T>>,
+    #[cfg(any(not(feature = "std"), loom))]
+    shared_list: SharedList<T>,
+    external: Option<External<T>>,
 }
 
 impl<T> Incinerator<T> {
@@ -74,38 +97,72 @@ impl<T> Incinerator<T> {
     pub fn new() -> Self {
         Self {
             counter: AtomicUsize::new(0),
+            #[cfg(all(feature = "std", not(loom)))]
             tls_list: ThreadLocal::new(),
+            #[cfg(any(not(feature = "std"), loom))]
+            shared_list: SharedList::new(),
+            external: None,
         }
     }
 
+    /// Creates a new incinerator which hands its garbage to the given
+    /// reclamation scheme instead of keeping it. Pauses still protect the
+    /// garbage: a value is only dropped once the reclaimer runs its job and
+    /// the pause counter is zero, otherwise the job is handed back to the
+    /// reclaimer.
+    pub fn with_reclaimer<R>(reclaimer: R) -> Arc<Self>
+    where
+        T: Send + 'static,
+        R: Reclaimer,
+    {
+        // Safe because the garbage may be sent to other threads.
+        unsafe { Self::with_reclaimer_unchecked(reclaimer) }
+    }
+
+    // Just like `with_reclaimer`, but the garbage does not need to be `Send`.
+    // The caller must make sure that dropping the garbage on another thread is
+    // fine, like structures which are only `Send` for `Send` elements.
+    pub(crate) unsafe fn with_reclaimer_unchecked<R>(reclaimer: R) -> Arc<Self>
+    where
+        T: 'static,
+        R: Reclaimer,
+    {
+        Arc::new_cyclic(|incin| Self {
+            counter: AtomicUsize::new(0),
+            #[cfg(all(feature = "std", not(loom)))]
+            tls_list: ThreadLocal::new(),
+            #[cfg(any(not(feature = "std"), loom))]
+            shared_list: SharedList::new(),
+            external: Some(External {
+                reclaimer: Arc::new(reclaimer),
+                incin: incin.clone(),
+                defer: defer_external::<T>,
+            }),
+        })
+    }
+
     /// Increments the pause counter and creates a pause associated with this
     /// incinerator. Only after creating the pause you should perform atomic
     /// operations such as `load` and any other operation affected by ABA
     /// problem. This operation performs [`AcqRel`] on the pause counter.
     pub fn pause(&self) -> Pause<T> {
-        let mut count = self.counter.load(Relaxed);
-        loop {
-            // Sanity check.
-            if count == usize::MAX {
-                panic!("Too many pauses");
-            }
-            // Simply try to increment it. This will be decremented at
-            // `Pause::drop`. Nobody will be able to drop stuff while this is
-            // not 0.
-            match self
-                .counter
-                .compare_exchange(count, count + 1, AcqRel, Relaxed)
-            {
-                Ok(_) => {
-                    break Pause {
-                        incin: self,
-                        had_list: self.tls_list.get().is_some(),
-                        _unsync: PhantomData,
-                    };
-                }
+        self.acquire_pause();
+        Pause {
+            incin: self,
+            #[cfg(all(feature = "std", not(loom)))]
+            had_list: self.tls_list.get().is_some(),
+            _unsync: PhantomData,
+        }
+    }
 
-                Err(new) => count = new,
-            }
+    /// Just like [`Incinerator::pause`], but the created pause owns a handle
+    /// to the incinerator instead of borrowing it. This is useful when the
+    /// pause needs to be stored somewhere that cannot carry the lifetime of
+    /// the incinerator.
+    pub fn owned_pause(self: &Arc<Self>) -> OwnedPause<T> {
+        self.acquire_pause();
+        OwnedPause {
+            incin: self.clone(),
         }
     }
 
@@ -135,11 +192,11 @@ impl<T> Incinerator<T> {
             // resource was removed from shared context. Since we use Thread
             // Local Storage, nobody can add something to the list meanwhile
             // besides us.
-            self.tls_list.get().map(GarbageList::clear);
+            self.clear_own_list();
             drop(val);
         } else {
             // Not safe to drop. We have to save the value in the garbage list.
-            self.tls_list.with_init(GarbageList::new).add(val);
+            self.store(val);
         }
     }
 
@@ -148,11 +205,16 @@ impl<T> Incinerator<T> {
     /// `true` is returned. This operation performs [`Acquire`] on the pause
     /// counter.
     pub fn try_clear(&self) -> bool {
+        if let Some(external) = &self.external {
+            // Lets the reclaimer run the jobs it can, which drop garbage if no
+            // pause is active.
+            external.reclaimer.flush();
+        }
         if self.counter.load(Acquire) == 0 {
             // It is only safe to drop if there are no active pauses. Remember
             // nobody can add something to this specific list besides us because
             // it is thread local.
-            self.tls_list.get().map(GarbageList::clear);
+            self.clear_own_list();
             true
         } else {
             false
@@ -162,7 +224,90 @@ impl<T> Incinerator<T> {
     /// Clears everything that is in the inicinerator regardless of pauses.
     /// Exclusive reference is required.
     pub fn clear(&mut self) {
+        let freed = self.garbage_len();
+        #[cfg(all(feature = "std", not(loom)))]
         self.tls_list.clear();
+        #[cfg(any(not(feature = "std"), loom))]
+        self.shared_list.clear_mut();
+        if freed > 0 {
+            trace_event!(trace, freed, "incinerator reclaimed garbage");
+        }
+    }
+
+    /// Returns how many values are waiting in the garbage lists of all threads
+    /// to be dropped. Other threads may be adding or dropping garbage at the
+    /// same time, so this is only an estimate. With the `std` feature, each
+    /// thread counts its own list, and this takes time proportional to the
+    /// number of threads which added garbage. Garbage handed to a
+    /// [`Reclaimer`] is not counted.
+    pub fn garbage_len(&self) -> usize {
+        #[cfg(all(feature = "std", not(loom)))]
+        let len = self.tls_list.iter().map(GarbageList::len).sum();
+        #[cfg(any(not(feature = "std"), loom))]
+        let len = self.shared_list.len.load(Relaxed);
+        len
+    }
+
+    // Clears the garbage list this thread is responsible for. Must only be
+    // called after the counter was observed to be zero.
+    #[cfg(all(feature = "std", not(loom)))]
+    fn clear_own_list(&self) {
+        if let Some(list) = self.tls_list.get() {
+            let freed = list.clear();
+            if freed > 0 {
+                trace_event!(trace, freed, "incinerator reclaimed garbage");
+            }
+        }
+    }
+
+    // Without thread-local lists, other threads may be adding garbage to the
+    // shared list right now, so the list has to be taken before the counter is
+    // checked again.
+    #[cfg(any(not(feature = "std"), loom))]
+    fn clear_own_list(&self) {
+        let freed = self.shared_list.try_clear(&self.counter);
+        if freed > 0 {
+            trace_event!(trace, freed, "incinerator reclaimed garbage");
+        }
+    }
+
+    fn store(&self, val: T) {
+        if let Some(external) = &self.external {
+            (external.defer)(external, val);
+            return;
+        }
+        #[cfg(all(feature = "std", not(loom)))]
+        self.tls_list.with_init(GarbageList::new).add(val);
+        #[cfg(any(not(feature = "std"), loom))]
+        self.shared_list.push(val);
+    }
+
+    fn acquire_pause(&self) {
+        let mut count = self.counter.load(Relaxed);
+        loop {
+            // Sanity check.
+            if count == usize::MAX {
+                panic!("Too many pauses");
+            }
+            // Simply try to increment it. This will be decremented at
+            // `release_pause`. Nobody will be able to drop stuff while this is
+            // not 0.
+            match self
+                .counter
+                .compare_exchange(count, count + 1, AcqRel, Relaxed)
+            {
+                Ok(_) => break,
+                Err(new) => count = new,
+            }
+        }
+    }
+
+    fn release_pause(&self) {
+        if self.counter.fetch_sub(1, AcqRel) == 1 {
+            // If the previous value was 1, this means now it is 0 and... we can
+            // delete our local list.
+            self.clear_own_list();
+        }
     }
 }
 
@@ -172,6 +317,64 @@ impl<T> Default for Incinerator<T> {
     }
 }
 
+/// A reclamation scheme which an [`Incinerator`] may hand its garbage to, so a
+/// process already running one does not need two of them. See
+/// [`Incinerator::with_reclaimer`].
+pub trait Reclaimer: Send + Sync + 'static {
+    /// Runs the given job once the scheme considers it safe, on any thread.
+    /// Jobs of an incinerator check its pause counter themselves and defer
+    /// themselves again if needed.
+    fn defer(&self, job: Box<dyn FnOnce() + Send>);
+
+    /// Tries to run the jobs which are ready now. Called when an incinerator
+    /// is asked to clear its garbage.
+    fn flush(&self);
+}
+
+// The reclaimer of an incinerator and what its jobs need.
+struct External<T> {
+    reclaimer: Arc<dyn Reclaimer>,
+    incin: Weak<Incinerator<T>>,
+    // Only created where the bounds of the garbage are known.
+    defer: fn(&External<T>, T),
+}
+
+impl<T> fmt::Debug for External<T> {
+    fn fmt(&self, fmtr: &mut fmt::Formatter) -> fmt::Result {
+        write!(fmtr, "External {{ incin: {:?} }}", self.incin.as_ptr())
+    }
+}
+
+// Garbage travelling through a reclaimer. Creating an external incinerator
+// requires it to be fine to drop the garbage on other threads.
+struct SendGarbage<T>(T, Weak<Incinerator<T>>);
+
+impl<T> SendGarbage<T> {
+    // Moves the whole wrapper into closures, rather than its fields.
+    fn into_parts(self) -> (T, Weak<Incinerator<T>>) {
+        (self.0, self.1)
+    }
+}
+
+unsafe impl<T> Send for SendGarbage<T> {}
+
+fn defer_external<T>(external: &External<T>, val: T)
+where
+    T: 'static,
+{
+    let garbage = SendGarbage(val, external.incin.clone());
+    external.reclaimer.defer(Box::new(move || {
+        let (val, incin) = garbage.into_parts();
+        match incin.upgrade() {
+            // Some pause may still be reading it, so we check again later.
+            Some(incin) if incin.counter.load(Acquire) != 0 => incin.store(val),
+            // Garbage was removed from shared context before being added, so
+            // no pause started afterwards can reach it.
+            _ => drop(val),
+        }
+    }));
+}
+
 /// An active incinerator pause. When a value of this type is alive, no
 /// sensitive data is dropped in the incinerator. When a value of this type is
 /// dropped, the incinerator counter is decremented.
@@ -181,6 +384,7 @@ where
     T: 'incin,
 {
     incin: &'incin Incinerator<T>,
+    #[cfg(all(feature = "std", not(loom)))]
     had_list: bool,
     _unsync: PhantomData<*mut ()>,
 }
@@ -202,14 +406,17 @@ impl<'incin, T> Pause<'incin, T> {
             // Safe to drop it all. Note that we check the counter after the
             // resource was removed from shared context. Since we use Thread
             // Local Storage, nobody can add something to the list meanwhile
-            // besides us.
+            // besides us. The shared list of `no_std` builds may contain
+            // garbage which our own pause is still reading, so it is left
+            // alone.
+            #[cfg(all(feature = "std", not(loom)))]
             if self.had_list {
-                self.incin.tls_list.get().map(GarbageList::clear);
+                self.incin.clear_own_list();
             }
             drop(val);
         } else {
             // Not safe to drop. We have to save the value in the garbage list.
-            self.incin.tls_list.with_init(GarbageList::new).add(val);
+            self.incin.store(val);
         }
     }
 
@@ -223,11 +430,7 @@ impl<'incin, T> Pause<'incin, T> {
 
 impl<'incin, T> Drop for Pause<'incin, T> {
     fn drop(&mut self) {
-        if self.incin.counter.fetch_sub(1, AcqRel) == 1 {
-            // If the previous value was 1, this means now it is 0 and... we can
-            // delete our local list.
-            self.incin.tls_list.get().map(GarbageList::clear);
-        }
+        self.incin.release_pause();
     }
 }
 
@@ -239,28 +442,114 @@ impl<'incin, T> Clone for Pause<'incin, T> {
 
 unsafe impl<'incin, T> Send for Pause<'incin, T> where T: Send {}
 
+impl<'incin, T> Pause<'incin, T> {
+    // Erases the garbage type of this pause, keeping it active.
+    pub(crate) fn erase(self) -> ErasedPause<'incin> {
+        let incin = self.incin;
+        // The counter is decremented by the erased pause instead.
+        mem::forget(self);
+        ErasedPause { incin }
+    }
+}
+
+// Allows a pause to be released without knowing the garbage type of its
+// incinerator.
+trait ReleasePause {
+    fn release(&self);
+}
+
+impl<T> ReleasePause for Incinerator<T> {
+    fn release(&self) {
+        self.release_pause();
+    }
+}
+
+// An active pause whose garbage type was erased. It cannot add garbage to the
+// incinerator, it only keeps the incinerator paused.
+pub(crate) struct ErasedPause<'incin> {
+    incin: &'incin (dyn ReleasePause + Sync + 'incin),
+}
+
+impl<'incin> Drop for ErasedPause<'incin> {
+    fn drop(&mut self) {
+        self.incin.release();
+    }
+}
+
+/// An active incinerator pause which owns a shared handle to its incinerator.
+/// Created by [`Incinerator::owned_pause`]. It behaves just like [`Pause`], but
+/// it is not bound to the lifetime of the incinerator.
+#[derive(Debug)]
+pub struct OwnedPause<T> {
+    incin: Arc<Incinerator<T>>,
+}
+
+impl<T> OwnedPause<T> {
+    /// Returns the incinerator on which this pause acts.
+    pub fn incin(&self) -> &Arc<Incinerator<T>> {
+        &self.incin
+    }
+
+    /// Forces drop and decrements the incinerator counter. See
+    /// [`Pause::resume`] for more details.
+    pub fn resume(self) {}
+}
+
+impl<T> Drop for OwnedPause<T> {
+    fn drop(&mut self) {
+        self.incin.release_pause();
+    }
+}
+
+impl<T> Clone for OwnedPause<T> {
+    fn clone(&self) -> Self {
+        self.incin.owned_pause()
+    }
+}
+
+#[cfg(all(feature = "std", not(loom)))]
 struct GarbageList<T> {
     list: Cell<Vec<T>>,
+    // Only written by the owning thread, so counting needs no shared
+    // read-modify-write.
+    len: AtomicUsize,
 }
 
+#[cfg(all(feature = "std", not(loom)))]
 impl<T> GarbageList<T> {
     fn new() -> Self {
         Self {
             list: Cell::new(Vec::new()),
+            len: AtomicUsize::new(0),
         }
     }
 
     fn add(&self, val: T) {
         let mut list = self.list.replace(Vec::new());
         list.push(val);
+        self.len.store(list.len(), Relaxed);
         self.list.replace(list);
     }
 
-    fn clear(&self) {
-        self.list.replace(Vec::new());
+    // Returns how many values were dropped.
+    fn clear(&self) -> usize {
+        let list = self.list.replace(Vec::new());
+        let len = list.len();
+        self.len.store(0, Relaxed);
+        drop(list);
+        len
+    }
+
+    fn len(&self) -> usize {
+        self.len.load(Relaxed)
     }
 }
 
+// Other threads only ever read the length of a list, never its values.
+#[cfg(all(feature = "std", not(loom)))]
+unsafe impl<T> Sync for GarbageList<T> {}
+
+#[cfg(all(feature = "std", not(loom)))]
 impl<T> fmt::Debug for GarbageList<T>
 where
     T: fmt::Debug,
@@ -281,6 +570,119 @@ where
     }
 }
 
+// A garbage list shared by all threads, used when there is no thread-local
+// storage. Nodes are only pushed one by one and taken all at once, so there
+// is no ABA problem here. Since the list is shared anyway, its length is
+// counted next to it.
+#[cfg(any(not(feature = "std"), loom))]
+struct SharedList<T> {
+    top: AtomicPtr<SharedNode<T>>,
+    len: AtomicUsize,
+}
+
+#[cfg(any(not(feature = "std"), loom))]
+struct SharedNode<T> {
+    // Never read, only dropped.
+    #[allow(dead_code)]
+    val: T,
+    next: *mut SharedNode<T>,
+}
+
+#[cfg(any(not(feature = "std"), loom))]
+impl<T> SharedList<T> {
+    fn new() -> Self {
+        Self {
+            top: AtomicPtr::new(null_mut()),
+            len: AtomicUsize::new(0),
+        }
+    }
+
+    fn push(&self, val: T) {
+        let node = OwnedAlloc::new(SharedNode {
+            val,
+            next: null_mut(),
+        })
+        .into_raw();
+        self.len.fetch_add(1, Relaxed);
+        self.push_chain(node, node);
+    }
+
+    fn push_chain(&self, first: NonNull<SharedNode<T>>, last: NonNull<SharedNode<T>>) {
+        let mut top = self.top.load(Relaxed);
+        loop {
+            // Safe because the chain is not shared until the exchange succeeds.
+            unsafe { (*last.as_ptr()).next = top }
+            match self
+                .top
+                .compare_exchange(top, first.as_ptr(), AcqRel, Relaxed)
+            {
+                Ok(_) => break,
+                Err(new) => top = new,
+            }
+        }
+    }
+
+    // Returns how many values were dropped.
+    fn try_clear(&self, counter: &AtomicUsize) -> usize {
+        let first = match NonNull::new(self.top.swap(null_mut(), AcqRel)) {
+            Some(first) => first,
+            None => return 0,
+        };
+
+        // Every node in the chain was removed from shared context before being
+        // pushed. If the counter is zero now, every pause which could have
+        // loaded them is gone.
+        if counter.load(Acquire) == 0 {
+            // Safe because we took the chain from the list and nobody else
+            // can reach it.
+            let freed = unsafe { Self::drop_chain(first.as_ptr()) };
+            self.len.fetch_sub(freed, Relaxed);
+            freed
+        } else {
+            let mut last = first;
+            // Safe because we own the chain.
+            while let Some(next) = NonNull::new(unsafe { (*last.as_ptr()).next }) {
+                last = next;
+            }
+            self.push_chain(first, last);
+            0
+        }
+    }
+
+    fn clear_mut(&mut self) {
+        // The access is exclusive, but loom's atomics have no `get_mut`.
+        let first = self.top.swap(null_mut(), Acquire);
+        // Safe because we have exclusive access to the list.
+        let freed = unsafe { Self::drop_chain(first) };
+        self.len.fetch_sub(freed, Relaxed);
+    }
+
+    unsafe fn drop_chain(mut node: *mut SharedNode<T>) -> usize {
+        let mut count = 0;
+        while let Some(nnptr) = NonNull::new(node) {
+            let alloc = OwnedAlloc::from_raw(nnptr);
+            node = alloc.next;
+            drop(alloc);
+            count += 1;
+        }
+        count
+    }
+}
+
+#[cfg(any(not(feature = "std"), loom))]
+impl<T> Drop for SharedList<T> {
+    fn drop(&mut self) {
+        self.clear_mut();
+    }
+}
+
+#[cfg(any(not(feature = "std"), loom))]
+impl<T> fmt::Debug for SharedList<T> {
+    fn fmt(&self, fmtr: &mut fmt::Formatter) -> fmt::Result {
+        write!(fmtr, "SharedList {{ top: {:?} }}", self.top.load(Relaxed))
+    }
+}
+
 macro_rules! doc {
     ($doc:expr ; $($target:tt)*) => {
         #[doc = $doc]
@@ -323,6 +725,26 @@ macro_rules! make_shared_incin {
                     }
                 }
             }
+            doc! {
+                concat!("Creates a new shared incinerator for ", $target, " \
+                         which hands its garbage to the given reclaimer. See \
+                         [`Incinerator::with_reclaimer`](crate::incin::Incinerator::with_reclaimer).");
+                $vis fn with_reclaimer<R>(reclaimer: R) -> Self
+                where
+                    R: crate::incin::Reclaimer,
+                    $($params: Send + 'static),*
+                {
+                    use crate::incin::Incinerator;
+                    use core::mem::MaybeUninit;
+
+                    // Safe because the elements may be sent to other threads,
+                    // and so may the garbage holding them.
+                    let incin = unsafe { Incinerator::with_reclaimer_unchecked(reclaimer) };
+                    Self {
+                        inner: MaybeUninit::new(incin),
+                    }
+                }
+            }
             doc! {
                 concat!("Tries to clear the incinerator garbage list in the \
                          best possible way given the runtime status of this \
@@ -0,0 +1,143 @@
+/// The result of an interactive insertion, shared by
+/// [`map::Insertion`](crate::map::Insertion) and
+/// [`set::Insertion`](crate::set::Insertion). `T` is the type of the old entry
+/// given back by an update, and `E` is the type given back on failure.
+#[derive(Debug, PartialEq, Eq)]
+pub enum Insertion<T, E> {
+    /// The entry was created.
+    Created,
+    /// The entry was updated and this was the old entry.
+    Updated(T),
+    /// The insertion failed and no operation was performed. Failure of an
+    /// insertion might happen because the closure rejected the conditions.
+    /// Another reason is that method-specific contract was not respected (such
+    /// as the one of [`Map::reinsert_with`](crate::map::Map::reinsert_with)).
+    Failed(E),
+}
+
+impl<T, E> Insertion<T, E> {
+    /// Returns whether the insertion created an entry.
+    pub fn created(&self) -> bool {
+        self.is_created()
+    }
+
+    /// Returns whether the insertion created an entry.
+    pub fn is_created(&self) -> bool {
+        matches!(self, Insertion::Created)
+    }
+
+    /// Returns whether the insertion updated an entry.
+    pub fn is_updated(&self) -> bool {
+        matches!(self, Insertion::Updated(_))
+    }
+
+    /// Returns whether the insertion failed.
+    pub fn is_failed(&self) -> bool {
+        matches!(self, Insertion::Failed(_))
+    }
+
+    /// Returns the old entry if the insertion updated an entry.
+    pub fn updated(&self) -> Option<&T> {
+        match self {
+            Insertion::Updated(old) => Some(old),
+            _ => None,
+        }
+    }
+
+    /// Tries to take the updated entry of this insertion and encodes it as a
+    /// [`Result`]. [`Ok`] is returned only if this insertion updated an entry.
+    pub fn take_updated(self) -> Result<T, Self> {
+        self.updated_or_else(|this| this)
+    }
+
+    /// Takes the updated entry of this insertion, or computes an error from
+    /// the whole insertion with the given closure if it did not update.
+    pub fn updated_or_else<X, F>(self, fallback: F) -> Result<T, X>
+    where
+        F: FnOnce(Self) -> X,
+    {
+        match self {
+            Insertion::Updated(old) => Ok(old),
+            this => Err(fallback(this)),
+        }
+    }
+
+    /// Returns the failure if the insertion failed.
+    pub fn failed(&self) -> Option<&E> {
+        match self {
+            Insertion::Failed(err) => Some(err),
+            _ => None,
+        }
+    }
+
+    /// Tries to take the failure of this insertion and encodes it as a
+    /// [`Result`]. [`Ok`] is returned only if this insertion has a failure.
+    pub fn take_failed(self) -> Result<E, Self> {
+        self.failed_or_else(|this| this)
+    }
+
+    /// Takes the failure of this insertion, or computes an error from the
+    /// whole insertion with the given closure if it did not fail.
+    pub fn failed_or_else<X, F>(self, fallback: F) -> Result<E, X>
+    where
+        F: FnOnce(Self) -> X,
+    {
+        match self {
+            Insertion::Failed(err) => Ok(err),
+            this => Err(fallback(this)),
+        }
+    }
+
+    /// Asserts the insertion created an entry.
+    ///
+    /// # Panics
+    /// Panics with the given message if the insertion updated an entry or
+    /// failed.
+    #[track_caller]
+    pub fn expect_created(self, msg: &str) {
+        match self {
+            Insertion::Created => (),
+            Insertion::Updated(_) => panic!("{}: the insertion updated an entry", msg),
+            Insertion::Failed(_) => panic!("{}: the insertion failed", msg),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn predicates() {
+        let created = Insertion::<u8, u8>::Created;
+        assert!(created.is_created() && !created.is_updated());
+        let updated = Insertion::<u8, u8>::Updated(3);
+        assert!(updated.is_updated() && !updated.is_failed());
+        let failed = Insertion::<u8, u8>::Failed(5);
+        assert!(failed.is_failed() && !failed.is_created());
+    }
+
+    #[test]
+    fn combinators() {
+        let updated = Insertion::<u8, u8>::Updated(3);
+        assert_eq!(updated.updated_or_else(|_| "no update"), Ok(3));
+        let failed = Insertion::<u8, u8>::Failed(5);
+        assert_eq!(
+            failed.updated_or_else(|this| this),
+            Err(Insertion::Failed(5))
+        );
+        assert_eq!(Insertion::<u8, u8>::Failed(5).failed_or_else(|_| ()), Ok(5));
+        assert_eq!(Insertion::<u8, u8>::Created.failed_or_else(|_| ()), Err(()));
+    }
+
+    #[test]
+    fn expect_created() {
+        Insertion::<u8, u8>::Created.expect_created("should create");
+    }
+
+    #[test]
+    #[should_panic(expected = "should create: the insertion failed")]
+    fn expect_created_panics() {
+        Insertion::<u8, u8>::Failed(5).expect_created("should create");
+    }
+}
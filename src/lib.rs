@@ -26,6 +26,47 @@
 //! - `[x]` [Queue](queue::Queue)
 //! - `[ ]` Deque
 //!
+//! # `no_std`
+//! The default `std` feature can be disabled in order to use the crate with
+//! only `core` and `alloc`. In this configuration, [`Map`](map::Map),
+//! [`Set`](set::Set), [`Stack`](stack::Stack) and [`Queue`](queue::Queue) are
+//! still available, but the thread-local storage and the channels are not.
+//! The incinerator falls back to a single shared garbage list and the default
+//! hasher builder becomes the deterministic
+//! [`DeterministicState`](hash::DeterministicState).
+//!
+//! # `tracing`
+//! With the `tracing` feature, structural changes are reported as
+//! [`tracing`](https://docs.rs/tracing) events: creation and removal of the
+//! nested tables of a [`Map`](map::Map), runs of
+//! [`Map::optimize_space`](map::Map::optimize_space), batches of garbage
+//! reclaimed by an incinerator and disconnections of channels. Per-element
+//! operations never emit events.
+//!
+//! # `instrument`
+//! With the `instrument` feature, a [`Map`](map::Map) counts the events of its
+//! internal algorithms, such as failed compare-and-swaps, nested tables
+//! created and collisions stepped over, and reports them through
+//! `Map::instrumentation`. The counters are relaxed atomics, and without the
+//! feature they are compiled out entirely.
+//!
+//! # `serde`
+//! With the `serde` feature, [`Map`](map::Map) implements `Serialize` and
+//! `Deserialize` as a map, and its guards and removed entries implement
+//! `Serialize` as pairs.
+//!
+//! # `rayon`
+//! With the `rayon` feature, [`Map::par_iter`](map::Map::par_iter) walks the
+//! map with rayon's parallel iterators, and `&Map` implements
+//! `IntoParallelIterator`.
+//!
+//! # `crossbeam-interop`
+//! With the `crossbeam-interop` feature,
+//! [`EpochReclaim`](epoch::EpochReclaim) hands the garbage of an incinerator
+//! to [`crossbeam-epoch`](https://docs.rs/crossbeam-epoch), for processes which
+//! already run it. Structures use it through constructors such as
+//! [`Map::with_reclaimer`](map::Map::with_reclaimer).
+//!
 //! # Performance Guide
 //! In order to achieve a better time performance with lockfree, it is
 //! recommended to avoid global locking stuff like heap allocation.
@@ -33,14 +74,37 @@
 extern crate alloc;
 extern crate owned_alloc;
 
-/// Provides convenient re-exports.
+/// Provides convenient re-exports. Types whose names collide between modules
+/// are re-exported with the name of their module as a prefix, such as
+/// `MapReadGuard` and `SetRemoved`.
+///
+/// # Example
+/// ```rust
+/// use tux_lockfree::prelude::*;
+///
+/// let map = Map::new();
+/// map.insert("five", 5);
+/// let guard: MapReadGuard<&str, i32> = map.get("five").unwrap();
+/// assert_eq!(*guard.val(), 5);
+///
+/// let set = Set::new();
+/// set.insert(5).unwrap();
+/// let removed: SetRemoved<i32> = set.remove(&5).unwrap();
+/// assert_eq!(*removed, 5);
+///
+/// let queue = Queue::new();
+/// queue.push(*guard.val());
+/// assert_eq!(queue.pop(), Some(5));
+/// ```
 pub mod prelude;
 
+#[macro_use]
+mod trace;
+
 /// Incinerator API. The purpouse of this module is to solve the "ABA problem"
 /// related to pointers while still being lock-free. See documentation of the
 /// inner type for more details.
 #[macro_use]
-#[cfg(feature = "std")]
 pub mod incin;
 
 /// A wait-free per-object Thread Local Storage (TLS).
@@ -48,21 +112,23 @@ pub mod incin;
 pub mod tls;
 
 /// A lock-free queue.
-#[cfg(feature = "std")]
 pub mod queue;
 
 /// A lock-free stack.
-#[cfg(feature = "std")]
 pub mod stack;
 
 /// A lock-free map.
-#[cfg(feature = "std")]
 pub mod map;
 
 /// A lock-free set.
-#[cfg(feature = "std")]
 pub mod set;
 
+/// Result types of insertions, shared by the map and the set.
+pub mod insertion;
+
+/// Hasher builders used by the map and the set.
+pub mod hash;
+
 /// Collection of lock-free FIFO channels. These channels are fully asynchronous
 /// and their receivers do not provide any sort of `wait-for-message` operation.
 /// It would be blocking otherwise, thus not lock-free. If you need such a
@@ -75,5 +141,27 @@ pub mod channel;
 /// A shared removable value. No extra allocation is necessary.
 pub mod removable;
 
+/// Traits shared by the collections of this crate, for code which is generic
+/// over them.
+pub mod traits;
+
+/// Introspection of the memory held by the structures of this crate.
+pub mod footprint;
+
+/// Retry policies for operations which may have to wait until no sensitive
+/// reads are active.
+pub mod backoff;
+
+/// Reclamation of incinerator garbage by crossbeam-epoch.
+#[cfg(feature = "crossbeam-interop")]
+pub mod epoch;
+
 #[allow(dead_code)]
 mod ptr;
+
+mod tagged_ptr;
+
+mod sync;
+
+#[cfg(all(test, feature = "std"))]
+mod test_util;
@@ -0,0 +1,381 @@
+use super::{Iter, Map, ReadGuard, Removed};
+use crate::{backoff::exponential_backoff, hash::DefaultState, queue::Queue, traits::ApproxLen};
+use alloc::boxed::Box;
+use core::{
+    borrow::Borrow,
+    fmt,
+    hash::{BuildHasher, Hash},
+    sync::atomic::{AtomicUsize, Ordering::*},
+};
+
+/// A [`Map`] bounded to a given capacity. When an insertion makes the map
+/// exceed its capacity, entries are evicted roughly in the order in which they
+/// were written (FIFO), which approximates least-recently-updated eviction.
+/// Every evicted entry is passed exactly once to the callback registered with
+/// [`BoundedMap::on_evict`].
+///
+/// Under concurrency, eviction is approximate: the length may exceed the
+/// capacity by about the number of threads inserting at the same time, and
+/// racing evictions may leave it slightly below the capacity. Since a queued
+/// key only becomes visible once its insertion finishes queueing it, a thread
+/// descheduled in the middle of an insertion may let the length grow further
+/// until it resumes. Once insertions stop, the capacity holds again.
+///
+/// # Example
+/// ```rust
+/// use std::sync::{Arc, Mutex};
+/// use tux_lockfree::map::BoundedMap;
+///
+/// let evicted = Arc::new(Mutex::new(Vec::new()));
+/// let mut cache = BoundedMap::new(2);
+/// let sink = evicted.clone();
+/// cache.on_evict(move |removed| sink.lock().unwrap().push(*removed.key()));
+///
+/// cache.insert(1, "one");
+/// cache.insert(2, "two");
+/// cache.insert(3, "three");
+///
+/// assert_eq!(cache.len(), 2);
+/// assert!(cache.get(&1).is_none());
+/// assert_eq!(*evicted.lock().unwrap(), [1]);
+/// ```
+pub struct BoundedMap<K, V, H = DefaultState> {
+    map: Map<K, V, H>,
+    // Keys in the order they were written, each paired with the address of
+    // the entry's allocation at that time. The address tells whether the
+    // entry is still the one which was queued, so entries removed or updated
+    // since then are never evicted because of a stale position.
+    order: Queue<(K, usize)>,
+    queued: AtomicUsize,
+    // Entries inserted minus entries removed. An eviction or removal may be
+    // counted before the insertion of its entry, so this may briefly wrap
+    // below zero.
+    len: AtomicUsize,
+    capacity: AtomicUsize,
+    on_evict: Option<EvictCallback<K, V>>,
+}
+
+type EvictCallback<K, V> = Box<dyn Fn(Removed<K, V>) + Send + Sync>;
+
+impl<K, V> BoundedMap<K, V> {
+    /// Creates a new [`BoundedMap`] with the given capacity and the default
+    /// hasher builder.
+    pub fn new(capacity: usize) -> Self {
+        Self::with_hasher(capacity, DefaultState::default())
+    }
+}
+
+impl<K, V, H> BoundedMap<K, V, H> {
+    /// Creates a new [`BoundedMap`] with the given capacity and hasher
+    /// builder.
+    pub fn with_hasher(capacity: usize, builder: H) -> Self
+    where
+        H: BuildHasher,
+    {
+        Self {
+            map: Map::with_hasher(builder),
+            order: Queue::new(),
+            queued: AtomicUsize::new(0),
+            len: AtomicUsize::new(0),
+            capacity: AtomicUsize::new(capacity),
+            on_evict: None,
+        }
+    }
+
+    /// Registers the callback which receives every evicted entry, replacing
+    /// any previous one. Entries removed through [`BoundedMap::remove`] or
+    /// replaced through [`BoundedMap::insert`] are not evicted, and so are not
+    /// passed to the callback.
+    pub fn on_evict<F>(&mut self, callback: F)
+    where
+        F: Fn(Removed<K, V>) + Send + Sync + 'static,
+    {
+        self.on_evict = Some(Box::new(callback));
+    }
+
+    /// The maximum number of entries kept by this map.
+    pub fn capacity(&self) -> usize {
+        self.capacity.load(Relaxed)
+    }
+
+    /// The number of entries in this map. It is exact when no operation runs
+    /// concurrently.
+    pub fn len(&self) -> usize {
+        let len = self.len.load(Acquire);
+        // A concurrent removal may have been counted before its insertion.
+        if len > isize::MAX as usize {
+            0
+        } else {
+            len
+        }
+    }
+
+    /// Returns whether this map has no entries.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Creates an iterator over guarded references to the key-value entries.
+    pub fn iter(&self) -> Iter<'_, K, V> {
+        self.map.iter()
+    }
+}
+
+impl<K, V, H> BoundedMap<K, V, H>
+where
+    H: BuildHasher,
+{
+    /// Searches for the entry identified by the given key, just like
+    /// [`Map::get`]. Reading an entry does not change its eviction order.
+    pub fn get<'map, Q>(&'map self, key: &Q) -> Option<ReadGuard<'map, K, V>>
+    where
+        Q: ?Sized + Hash + Eq,
+        K: Borrow<Q>,
+    {
+        self.map.get(key)
+    }
+
+    /// Inserts unconditionally the given key and value. If there was a
+    /// previously stored value, it is returned, and the entry moves to the end
+    /// of the eviction order. If the insertion makes the map exceed its
+    /// capacity, the oldest entries are evicted.
+    pub fn insert(&self, key: K, val: V) -> Option<Removed<K, V>>
+    where
+        K: Hash + Eq + Clone,
+    {
+        let old = self.map.insert(key.clone(), val);
+        if old.is_none() {
+            self.len.fetch_add(1, AcqRel);
+        }
+
+        // Someone might have removed or replaced the entry meanwhile, which
+        // only means there is nothing of ours to queue.
+        if let Some(addr) = self.map.get(&key).map(|guard| address_of(&guard)) {
+            self.order.push((key, addr));
+            self.queued.fetch_add(1, Relaxed);
+        }
+
+        self.evict_excess();
+        old
+    }
+
+    /// Removes unconditionally the entry identified by the given key, just
+    /// like [`Map::remove`]. The removed entry is not passed to the eviction
+    /// callback.
+    pub fn remove<Q>(&self, key: &Q) -> Option<Removed<K, V>>
+    where
+        Q: ?Sized + Hash + Eq,
+        K: Borrow<Q>,
+    {
+        let removed = self.map.remove(key)?;
+        self.len.fetch_sub(1, AcqRel);
+        Some(removed)
+    }
+
+    /// Changes the capacity of this map. If the new capacity is smaller than
+    /// the length, the oldest entries are evicted.
+    pub fn set_capacity(&self, capacity: usize)
+    where
+        K: Hash + Eq,
+    {
+        self.capacity.store(capacity, Relaxed);
+        self.evict_excess();
+    }
+
+    fn evict_excess(&self)
+    where
+        K: Hash + Eq,
+    {
+        let mut attempt = 0;
+        while self.len() > self.capacity() {
+            let (key, addr) = match self.order.pop() {
+                Some(queued) => queued,
+                // A concurrent insertion may have counted its entry but not
+                // queued it yet, or its push may not be visible yet.
+                None if exponential_backoff(attempt).wait() => {
+                    attempt += 1;
+                    continue;
+                }
+                None => break,
+            };
+            self.queued.fetch_sub(1, Relaxed);
+
+            let removed = self
+                .map
+                .remove_with(&key, |pair| pair as *const _ as usize == addr);
+            if let Some(removed) = removed {
+                self.len.fetch_sub(1, AcqRel);
+                if let Some(callback) = &self.on_evict {
+                    callback(removed);
+                }
+            }
+        }
+
+        // Removals and updates leave stale positions behind. Discard some of
+        // them so the queue does not grow without bound while the map stays
+        // below its capacity. Live positions go back to the end.
+        let limit = self.len() + self.capacity();
+        let excess = self.queued.load(Relaxed).saturating_sub(limit);
+        for _ in 0..excess {
+            let (key, addr) = match self.order.pop() {
+                Some(queued) => queued,
+                None => break,
+            };
+
+            let live = self
+                .map
+                .get(&key)
+                .map_or(false, |guard| address_of(&guard) == addr);
+            if live {
+                self.order.push((key, addr));
+            } else {
+                self.queued.fetch_sub(1, Relaxed);
+            }
+        }
+    }
+}
+
+impl<K, V, H> ApproxLen for BoundedMap<K, V, H> {
+    fn len(&self) -> usize {
+        BoundedMap::len(self)
+    }
+}
+
+impl<K, V, H> fmt::Debug for BoundedMap<K, V, H>
+where
+    H: fmt::Debug,
+{
+    fn fmt(&self, fmtr: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            fmtr,
+            "BoundedMap {{ map: {:?}, len: {}, capacity: {} }}",
+            self.map.debug_structure(),
+            self.len(),
+            self.capacity()
+        )
+    }
+}
+
+fn address_of<K, V>(guard: &ReadGuard<K, V>) -> usize {
+    &**guard as *const (K, V) as usize
+}
+
+#[cfg(all(test, feature = "std"))]
+mod test {
+    use super::*;
+    use alloc::{sync::Arc, vec::Vec};
+    use std::{sync::Mutex, thread};
+
+    #[test]
+    fn evicts_oldest() {
+        let mut map = BoundedMap::new(3);
+        let evicted = Arc::new(Mutex::new(Vec::new()));
+        let sink = evicted.clone();
+        map.on_evict(move |removed| sink.lock().unwrap().push(*removed.key()));
+
+        for i in 0..3 {
+            assert!(map.insert(i, i).is_none());
+        }
+        // Updating moves the entry to the end of the eviction order.
+        assert_eq!(map.insert(0, 10).map(|old| *old.val()), Some(0));
+        map.insert(3, 3);
+        map.insert(4, 4);
+
+        assert_eq!(*evicted.lock().unwrap(), [1, 2]);
+        assert_eq!(map.len(), 3);
+        assert_eq!(*map.get(&0).unwrap().val(), 10);
+
+        map.set_capacity(1);
+        assert_eq!(*evicted.lock().unwrap(), [1, 2, 0, 3]);
+        assert_eq!(map.len(), 1);
+        assert!(map.get(&4).is_some());
+    }
+
+    #[test]
+    fn removal_is_not_eviction() {
+        let mut map = BoundedMap::new(2);
+        let evicted = Arc::new(Mutex::new(Vec::new()));
+        let sink = evicted.clone();
+        map.on_evict(move |removed| sink.lock().unwrap().push(*removed.key()));
+
+        map.insert(1, ());
+        assert!(map.remove(&1).is_some());
+        map.insert(1, ());
+        map.insert(2, ());
+        assert!(evicted.lock().unwrap().is_empty());
+        assert_eq!(map.len(), 2);
+
+        // The stale position of the first insertion of 1 must not evict the
+        // second one before 2.
+        map.insert(3, ());
+        assert_eq!(*evicted.lock().unwrap(), [1]);
+    }
+
+    #[test]
+    fn removal_counted_first_evicts_nothing() {
+        let map = BoundedMap::new(4);
+        map.insert(1, ());
+        // Two insertions which have not counted their entries yet, as if they
+        // were racing the removals.
+        map.map.insert(2, ());
+        map.map.insert(3, ());
+        map.remove(&2);
+        map.remove(&3);
+        assert_eq!(map.len(), 0);
+
+        map.set_capacity(1);
+        map.len.fetch_add(2, AcqRel);
+        assert_eq!(map.len(), 1);
+        assert!(map.get(&1).is_some());
+    }
+
+    #[test]
+    fn stale_positions_are_discarded() {
+        let map = BoundedMap::new(4);
+        for i in 0..1000 {
+            map.insert(i, ());
+            map.remove(&i);
+        }
+        assert!(map.queued.load(Relaxed) <= 2 * map.capacity() + 1);
+        assert!(map.is_empty());
+    }
+
+    #[test]
+    fn bound_holds_under_concurrency() {
+        const CAPACITY: usize = 64;
+        const NTHREAD: usize = 8;
+        const NITER: usize = 2000;
+
+        let evicted = Arc::new(Mutex::new(Vec::new()));
+        let mut map = BoundedMap::new(CAPACITY);
+        let sink = evicted.clone();
+        map.on_evict(move |removed| sink.lock().unwrap().push(*removed.key()));
+        let map = Arc::new(map);
+
+        let threads = (0..NTHREAD)
+            .map(|t| {
+                let map = map.clone();
+                thread::spawn(move || {
+                    for i in 0..NITER {
+                        map.insert(t * NITER + i, i);
+                        assert!(map.len() <= CAPACITY + NTHREAD);
+                    }
+                })
+            })
+            .collect::<Vec<_>>();
+
+        for thread in threads {
+            thread.join().unwrap();
+        }
+
+        let mut evicted = evicted.lock().unwrap().clone();
+        let remaining = map.iter().map(|guard| *guard.key()).collect::<Vec<_>>();
+        assert_eq!(remaining.len(), map.len());
+        assert!(map.len() <= CAPACITY);
+
+        // Every key was either evicted exactly once or is still present.
+        evicted.extend(remaining);
+        evicted.sort_unstable();
+        assert_eq!(evicted, (0..NTHREAD * NITER).collect::<Vec<_>>());
+    }
+}
@@ -1,23 +1,28 @@
 use super::{
-    guard::{ReadGuard, Removed},
+    guard::{ReadGuard, Reclaim, Removed},
     insertion::Inserter,
+    instrument::Counters,
+    table::{Subtable, Table},
 };
 use crate::{
     incin::{Incinerator, Pause},
     ptr::non_zero_null,
+    sync::AtomicPtr,
+    tagged_ptr::{check_align, is_tagged, tag, untag},
 };
 use alloc::sync::Arc;
 use alloc::vec::Vec;
 use core::{
     borrow::Borrow,
-    cmp::Ordering,
     fmt, mem,
     ptr::{null_mut, NonNull},
-    sync::atomic::{AtomicPtr, Ordering::*},
+    sync::atomic::Ordering::*,
 };
 use owned_alloc::OwnedAlloc;
 
-#[repr(align(/* at least */ 2))]
+// Tables are told apart from buckets by the lower bit of pointers, and nodes
+// of tables being collapsed are frozen with the next one.
+#[repr(align(/* at least */ 4))]
 pub struct Bucket<K, V> {
     hash: u64,
     list: List<K, V>,
@@ -25,6 +30,12 @@ pub struct Bucket<K, V> {
 
 impl<K, V> Bucket<K, V> {
     pub fn new(hash: u64, pair: NonNull<(K, V)>) -> Self {
+        // Tables are told apart from buckets by tags, removed entries are
+        // marked by tagging their next list, and pinned entries by tagging
+        // them in their list.
+        check_align::<Self>();
+        check_align::<List<K, V>>();
+        check_align::<Entry<K, V>>();
         // We create a bucket with a single entry.
 
         // First we create an entry for the pair whose next node is null.
@@ -51,7 +62,7 @@ impl<K, V> Bucket<K, V> {
 
     // Unsafe because it might need incinerator's pause.
     pub unsafe fn is_empty(&self) -> bool {
-        (*self.list.atomic.load(Acquire)).is_empty()
+        self.list.load().as_ref().is_empty()
     }
 
     pub fn take_first(&mut self) -> Option<OwnedAlloc<Entry<K, V>>> {
@@ -76,26 +87,138 @@ impl<K, V> Bucket<K, V> {
         })
     }
 
+    // Removes the entries for which the predicate returns false, along with
+    // the entries already removed logically, and frees them right away, which
+    // the exclusive reference allows. Decrements `len` for every entry removed
+    // by the predicate. If the predicate panics, the bucket is left consistent
+    // with the entries visited so far already removed.
+    pub fn retain<F>(&mut self, pred: &mut F, len: &mut usize)
+    where
+        F: FnMut(&K, &V) -> bool,
+    {
+        // Safe because of exclusive reference. We also *do not* store null
+        // pointers in list's AtomicPtr!
+        let mut prev = unsafe { &mut *self.list.atomic.load(Relaxed) };
+
+        // The previous entry is never removed, so its next is never marked.
+        while let Some(list) = NonNull::new(prev.next) {
+            // Safe because we only store properly allocated nodes, and never
+            // null pointers in list's AtomicPtr.
+            let entry = unsafe { &mut *list.as_ref().atomic.load(Relaxed) };
+
+            let is_removed = is_tagged(entry.next);
+            if !is_removed {
+                // Safe because the pair is only dangling when the next field is
+                // marked, and we checked for the mark.
+                let (key, val) = unsafe { &*entry.pair.as_ptr() };
+                if pred(key, val) {
+                    prev = entry;
+                    continue;
+                }
+            }
+
+            prev.next = untag(entry.next);
+            let pair = entry.pair;
+            // Safe because we unlinked the nodes and nobody else has a
+            // reference to them.
+            unsafe {
+                OwnedAlloc::from_raw(NonNull::from(entry));
+                OwnedAlloc::from_raw(list);
+            }
+            if !is_removed {
+                *len -= 1;
+                // Safe because the entry was not removed, so the pair belongs
+                // to it, and we unlinked it. Dropped last, in case it panics.
+                unsafe { OwnedAlloc::from_raw(pair) };
+            }
+        }
+    }
+
+    // Moves the entries for which the predicate returns true, in order, into
+    // a new bucket with the same hash, which is returned unless it is empty.
+    // The entries already removed logically are freed right away, just like in
+    // `retain`. Decrements `len` for every entry moved. If the predicate
+    // panics, the entries moved so far are dropped with the new bucket.
+    pub fn partition<F>(&mut self, pred: &mut F, len: &mut usize) -> Option<Self>
+    where
+        F: FnMut(&K, &V) -> bool,
+    {
+        let moved = Self {
+            hash: self.hash,
+            list: List::new(Entry::root(null_mut())),
+        };
+        let mut is_empty = true;
+
+        // Safe because of exclusive references to both buckets, and we *do
+        // not* store null pointers in list's AtomicPtr!
+        let mut tail = unsafe { &mut *moved.list.atomic.load(Relaxed) };
+        let mut prev = unsafe { &mut *self.list.atomic.load(Relaxed) };
+
+        // The previous entry is never removed, so its next is never marked.
+        while let Some(list) = NonNull::new(prev.next) {
+            // Safe because we only store properly allocated nodes, and never
+            // null pointers in list's AtomicPtr.
+            let entry = unsafe { &mut *list.as_ref().atomic.load(Relaxed) };
+
+            if is_tagged(entry.next) {
+                prev.next = untag(entry.next);
+                // Safe because we unlinked the nodes and nobody else has a
+                // reference to them. The pair belongs to whoever removed it.
+                unsafe {
+                    OwnedAlloc::from_raw(NonNull::from(entry));
+                    OwnedAlloc::from_raw(list);
+                }
+                continue;
+            }
+
+            // Safe because the pair is only dangling when the next field is
+            // marked, and we checked for the mark.
+            let (key, val) = unsafe { &*entry.pair.as_ptr() };
+            if !pred(key, val) {
+                prev = entry;
+                continue;
+            }
+
+            // Unlinks the nodes from this bucket and links them at the end of
+            // the new one, so they are never owned by both.
+            prev.next = entry.next;
+            entry.next = null_mut();
+            tail.next = list.as_ptr();
+            tail = entry;
+            is_empty = false;
+            *len -= 1;
+        }
+
+        if is_empty {
+            None
+        } else {
+            Some(moved)
+        }
+    }
+
     // Unsafe because it might need incinerator's pause and there is no
     // guarantee the passed pause by this thread comes from the same incinerator
-    // from which other threads pass pauses.
+    // from which other threads pass pauses. The found entry is only valid
+    // while the pause is active.
     pub unsafe fn get<'map, Q>(
-        &self,
+        &'map self,
         key: &Q,
-        pause: Pause<'map, Garbage<K, V>>,
+        pause: &Pause<Garbage<K, V>>,
+        counters: &Counters,
     ) -> GetRes<'map, K, V>
     where
-        Q: ?Sized + Ord,
+        Q: ?Sized + Eq,
         K: Borrow<Q>,
     {
-        match self.find(key, &pause) {
+        match self.find(key, pause, counters) {
             // The table must delete the whole bucket.
-            FindRes::Delete => GetRes::Delete(pause),
+            FindRes::Delete => GetRes::Delete,
 
             // We found the entry.
-            FindRes::Exact { curr, .. } => {
-                GetRes::Found(ReadGuard::new(&*curr.as_ref().pair.as_ptr(), pause))
-            }
+            FindRes::Exact { curr_list, curr } => GetRes::Found(Found {
+                list: curr_list,
+                entry: curr,
+            }),
 
             // We found no entry.
             FindRes::After { .. } => GetRes::NotFound,
@@ -111,13 +234,14 @@ impl<K, V> Bucket<K, V> {
         mut inserter: I,
         pause: &Pause<Garbage<K, V>>,
         incin: &Arc<Incinerator<Garbage<K, V>>>,
+        counters: &Counters,
     ) -> InsertRes<I, K, V>
     where
         I: Inserter<K, V>,
-        K: Ord,
+        K: Eq,
     {
         loop {
-            match self.find(inserter.key(), pause) {
+            match self.find(inserter.key(), pause, counters) {
                 // The table must delete the whole bucket.
                 FindRes::Delete => break InsertRes::Delete(inserter),
 
@@ -145,6 +269,7 @@ impl<K, V> Bucket<K, V> {
                     // And now we try to update the place where the old entry
                     // was.
                     if curr_list.try_update(curr, new_ptr, pause) {
+                        counters.incin_deferrals(1);
                         // Remember to prevent the inserter from deallocating.
                         inserter.take_pointer();
                         // Create a removed entry from the old pair.
@@ -152,6 +277,7 @@ impl<K, V> Bucket<K, V> {
                         let removed = Removed::new(pair, incin);
                         break InsertRes::Updated(removed);
                     }
+                    counters.cas_retry();
                 }
 
                 // We found a spot to insert at.
@@ -185,12 +311,14 @@ impl<K, V> Bucket<K, V> {
 
                     // And try to update.
                     if prev_list.try_update(prev, new_ptr, pause) {
+                        counters.incin_deferrals(1);
                         // Remember to prevent the inserter from deallocating.
                         inserter.take_pointer();
                         break InsertRes::Created;
                     }
 
                     // Clean-up in case of failure.
+                    counters.cas_retry();
                     OwnedAlloc::from_raw(curr_nnptr.as_ref().load());
                     OwnedAlloc::from_raw(curr_nnptr);
                 }
@@ -207,14 +335,15 @@ impl<K, V> Bucket<K, V> {
         mut interactive: F,
         pause: &Pause<Garbage<K, V>>,
         incin: &Arc<Incinerator<Garbage<K, V>>>,
+        counters: &Counters,
     ) -> RemoveRes<K, V>
     where
-        Q: ?Sized + Ord,
+        Q: ?Sized + Eq,
         K: Borrow<Q>,
         F: FnMut(&(K, V)) -> bool,
     {
         loop {
-            match self.find(key, pause) {
+            match self.find(key, pause, counters) {
                 // The table must delete the whole bucket.
                 FindRes::Delete => {
                     break RemoveRes {
@@ -238,12 +367,13 @@ impl<K, V> Bucket<K, V> {
                     let pair_ptr = curr.as_ref().pair;
                     let new_entry = Entry {
                         pair: pair_ptr,
-                        next: (curr.as_ref().next as usize | 1) as *mut _,
+                        next: tag(curr.as_ref().next),
                     };
                     let new_ptr = OwnedAlloc::new(new_entry).into_raw();
 
                     // Then we try to update where it was before.
                     if curr_list.try_update(curr, new_ptr, pause) {
+                        counters.incin_deferrals(1);
                         let pair = OwnedAlloc::from_raw(pair_ptr);
                         break RemoveRes {
                             pair: Some(Removed::new(pair, incin)),
@@ -251,6 +381,7 @@ impl<K, V> Bucket<K, V> {
                             delete: self.try_clear_first(pause),
                         };
                     }
+                    counters.cas_retry();
                 }
 
                 // This means the entry was not found.
@@ -299,6 +430,209 @@ impl<K, V> Bucket<K, V> {
         }
     }
 
+    // Pushes references to the entries of this bucket which are not removed,
+    // just like `collect`, but without creating guards. The pause must
+    // outlive the use of the references. Unsafe for the same reasons as
+    // `collect`.
+    pub unsafe fn pairs<'map>(
+        &'map self,
+        pause: &Pause<Garbage<K, V>>,
+        out: &mut Vec<&'map (K, V)>,
+    ) {
+        // The length to which we will truncate the vector at each retry.
+        let trunc = out.len();
+
+        'retry: loop {
+            // Clean-up previous try.
+            out.truncate(trunc);
+            let mut prev_list = &self.list;
+            let mut prev = prev_list.load();
+
+            loop {
+                match prev_list.load_next(prev, pause) {
+                    LoadNextRes::Failed => continue 'retry,
+                    LoadNextRes::End => break 'retry,
+                    LoadNextRes::Cleared { new_prev } => prev = new_prev,
+                    LoadNextRes::Ok { list, entry } => {
+                        out.push(&*entry.as_ref().pair.as_ptr());
+                        prev_list = &*list.as_ptr();
+                        prev = entry;
+                    }
+                }
+            }
+        }
+    }
+
+    // Returns a guard to the first entry of this bucket which is not removed,
+    // or `None` if there is no such entry. Unsafe for the same reasons as
+    // `collect`.
+    pub unsafe fn first<'map>(
+        &'map self,
+        pause: &Pause<'map, Garbage<K, V>>,
+    ) -> Option<ReadGuard<'map, K, V>> {
+        'retry: loop {
+            let mut prev = self.list.load();
+
+            loop {
+                match self.list.load_next(prev, pause) {
+                    LoadNextRes::Failed => continue 'retry,
+                    LoadNextRes::End => break 'retry None,
+                    LoadNextRes::Cleared { new_prev } => prev = new_prev,
+                    LoadNextRes::Ok { entry, .. } => {
+                        break 'retry Some(ReadGuard::new(
+                            &*entry.as_ref().pair.as_ptr(),
+                            pause.clone(),
+                        ));
+                    }
+                }
+            }
+        }
+    }
+
+    // Creates a new bucket with clones of the entries of this one, or `None` if
+    // this one is empty. Returns the number of cloned entries too. Unsafe
+    // because it might need incinerator's pause and there is no guarantee the
+    // passed pause by this thread comes from the same incinerator from which
+    // other threads pass pauses.
+    pub unsafe fn clone_entries(&self, pause: &Pause<Garbage<K, V>>) -> Option<(Self, usize)>
+    where
+        K: Clone,
+        V: Clone,
+    {
+        // Clones are collected first, so nothing leaks if a clone panics.
+        let mut pairs = Vec::new();
+
+        'retry: loop {
+            // Clean-up previous try.
+            pairs.clear();
+            let mut prev_list = &self.list;
+            let mut prev = prev_list.load();
+
+            loop {
+                match prev_list.load_next(prev, pause) {
+                    LoadNextRes::Failed => continue 'retry,
+                    LoadNextRes::End => break 'retry,
+                    LoadNextRes::Cleared { new_prev } => prev = new_prev,
+                    LoadNextRes::Ok { list, entry } => {
+                        let (key, val) = &*entry.as_ref().pair.as_ptr();
+                        pairs.push((key.clone(), val.clone()));
+                        prev_list = &*list.as_ptr();
+                        prev = entry;
+                    }
+                }
+            }
+        }
+
+        if pairs.is_empty() {
+            return None;
+        }
+
+        let count = pairs.len();
+        Some((Self::from_pairs(self.hash, pairs), count))
+    }
+
+    // Makes a bucket with the given pairs, in the same order. The pairs must
+    // not be empty, and their keys must have the given hash and be distinct.
+    pub fn from_pairs(hash: u64, mut pairs: Vec<(K, V)>) -> Self {
+        debug_assert!(!pairs.is_empty());
+        check_align::<Self>();
+        check_align::<List<K, V>>();
+
+        // The order of the entries is kept, so we link them from the last.
+        let mut next = null_mut();
+        while let Some(pair) = pairs.pop() {
+            let entry = Entry {
+                pair: OwnedAlloc::new(pair).into_raw(),
+                next,
+            };
+            next = OwnedAlloc::new(List::new(entry)).into_raw().as_ptr();
+        }
+
+        Self {
+            hash,
+            list: List::new(Entry::root(next)),
+        }
+    }
+
+    // Returns how many heap bytes this bucket and its entries take. Unsafe
+    // because it might need incinerator's pause and there is no guarantee the
+    // passed pause by this thread comes from the same incinerator from which
+    // other threads pass pauses.
+    pub unsafe fn bytes_allocated(&self, pause: &Pause<Garbage<K, V>>) -> usize {
+        Self::bytes_with_len(self.len(pause))
+    }
+
+    // Returns how many heap bytes a bucket with the given number of entries
+    // takes.
+    pub fn bytes_with_len(len: usize) -> usize {
+        // The bucket itself and its root entry.
+        let base = mem::size_of::<Self>() + mem::size_of::<Entry<K, V>>();
+        // Each entry comes with its list node and its pair.
+        let per_entry =
+            mem::size_of::<Entry<K, V>>() + mem::size_of::<List<K, V>>() + mem::size_of::<(K, V)>();
+        base + len * per_entry
+    }
+
+    // Counts the entries of this bucket which are not removed. Unsafe for the
+    // same reasons as `bytes_allocated`.
+    pub unsafe fn len(&self, pause: &Pause<Garbage<K, V>>) -> usize {
+        'retry: loop {
+            let mut count = 0;
+            let mut prev_list = &self.list;
+            let mut prev = prev_list.load();
+
+            loop {
+                match prev_list.load_next(prev, pause) {
+                    LoadNextRes::Failed => continue 'retry,
+                    LoadNextRes::End => break 'retry count,
+                    LoadNextRes::Cleared { new_prev } => prev = new_prev,
+                    LoadNextRes::Ok { list, entry } => {
+                        count += 1;
+                        prev_list = &*list.as_ptr();
+                        prev = entry;
+                    }
+                }
+            }
+        }
+    }
+
+    // Removes every entry of this bucket, which was detached from its table,
+    // and returns how many were removed. Writers which loaded the bucket
+    // earlier either change it before an entry is removed, or find it empty
+    // and go back to the table. Unsafe for the same reasons as `remove`.
+    pub unsafe fn seal(&self, pause: &Pause<Garbage<K, V>>) -> usize {
+        let mut count = 0;
+
+        'retry: loop {
+            let mut prev = self.list.load();
+
+            loop {
+                match self.list.load_next(prev, pause) {
+                    LoadNextRes::Failed => continue 'retry,
+                    LoadNextRes::End => break 'retry count,
+                    LoadNextRes::Cleared { new_prev } => prev = new_prev,
+                    LoadNextRes::Ok { list, entry } => {
+                        // Removed just like `remove` does, but the pair is
+                        // not given to anybody.
+                        let pair_ptr = entry.as_ref().pair;
+                        let new_entry = Entry {
+                            pair: pair_ptr,
+                            next: tag(entry.as_ref().next),
+                        };
+                        let new_ptr = OwnedAlloc::new(new_entry).into_raw();
+
+                        if list.as_ref().try_update(entry, new_ptr, pause) {
+                            count += 1;
+                            let pair = OwnedAlloc::from_raw(pair_ptr);
+                            pause.add_to_incin(Garbage::Pair(pair));
+                        }
+                        continue 'retry;
+                    }
+                }
+            }
+        }
+    }
+
     // Returns whether the bucket is empty. Unsafe because it might need
     // incinerator's pause and there is no guarantee the passed pause by
     // this thread comes from the same incinerator from which other threads
@@ -322,9 +656,10 @@ impl<K, V> Bucket<K, V> {
         &'map self,
         key: &Q,
         pause: &Pause<Garbage<K, V>>,
+        counters: &Counters,
     ) -> FindRes<'map, K, V>
     where
-        Q: ?Sized + Ord,
+        Q: ?Sized + Eq,
         K: Borrow<Q>,
     {
         'retry: loop {
@@ -333,7 +668,10 @@ impl<K, V> Bucket<K, V> {
 
             loop {
                 match prev_list.load_next(prev, pause) {
-                    LoadNextRes::Failed => continue 'retry,
+                    LoadNextRes::Failed => {
+                        counters.cas_retry();
+                        continue 'retry;
+                    }
 
                     LoadNextRes::End => {
                         // If the previous is the root and we reached the end we
@@ -341,40 +679,34 @@ impl<K, V> Bucket<K, V> {
                         break 'retry if prev.as_ref().is_root() {
                             FindRes::Delete
                         } else {
-                            // Otherwise it just means the key would fit better
-                            // after the previous.
+                            // Otherwise the key is absent, and new entries go
+                            // after the last one.
                             FindRes::After { prev_list, prev }
                         };
                     }
 
-                    LoadNextRes::Cleared { new_prev } => prev = new_prev,
+                    LoadNextRes::Cleared { new_prev } => {
+                        // The old previous node, and the list node and
+                        // entry of the removed one.
+                        counters.incin_deferrals(3);
+                        prev = new_prev;
+                    }
 
                     LoadNextRes::Ok { list, entry } => {
-                        let comparison = {
-                            let (stored_key, _) = entry.as_ref().pair.as_ref();
-                            key.cmp(stored_key.borrow())
-                        };
-
-                        match comparison {
-                            // The exact key.
-                            Ordering::Equal => {
-                                break 'retry FindRes::Exact {
-                                    curr_list: &*list.as_ptr(),
-                                    curr: entry,
-                                };
-                            }
-
-                            // The previous is the point of insertion.
-                            Ordering::Less => {
-                                break 'retry FindRes::After { prev_list, prev };
-                            }
-
-                            // Let's keep looking.
-                            Ordering::Greater => {
-                                prev_list = &*list.as_ptr();
-                                prev = entry;
-                            }
+                        let (stored_key, _) = entry.as_ref().pair.as_ref();
+
+                        // The exact key.
+                        if key == stored_key.borrow() {
+                            break 'retry FindRes::Exact {
+                                curr_list: &*list.as_ptr(),
+                                curr: entry,
+                            };
                         }
+
+                        // Let's keep looking.
+                        counters.chain_traversal();
+                        prev_list = &*list.as_ptr();
+                        prev = entry;
                     }
                 }
             }
@@ -438,14 +770,14 @@ impl<K, V> Drop for Bucket<K, V> {
                 // access to the bucket.
                 OwnedAlloc::from_raw(list);
 
-                let next = if entry.as_ref().next as usize & 1 == 0 {
+                let next = if !is_tagged(entry.as_ref().next) {
                     // If the node is *not* marked, this entry was not removed
                     // and the pair needs to be deallocated. Ok to deallocate
                     // since we have exclusive reference.
                     OwnedAlloc::from_raw(entry.as_ref().pair);
                     entry.as_ref().next
                 } else {
-                    (entry.as_ref().next as usize & !1) as *mut _
+                    untag(entry.as_ref().next)
                 };
                 // Ok to deallocate it now since we already retrieved
                 // information. Note that we have exclusive
@@ -517,9 +849,10 @@ impl<K, V> List<K, V> {
         }
     }
 
-    // Unsafe because `Bucket` needs to store entries correctly.
+    // Unsafe because `Bucket` needs to store entries correctly. The entry
+    // may be pinned, which `try_update` fails on.
     unsafe fn load(&self) -> NonNull<Entry<K, V>> {
-        NonNull::new_unchecked(self.atomic.load(Acquire))
+        NonNull::new_unchecked(untag(self.atomic.load(Acquire)))
     }
 
     // Loads the next and do clean-up if necessary. Unsafe because it might need
@@ -539,17 +872,17 @@ impl<K, V> List<K, V> {
         };
 
         let entry = list.as_ref().load();
-        let next = entry.as_ref().next as usize;
+        let next = entry.as_ref().next;
 
         // If the next field was marked, this node was logically removed. Time
         // to remove it physically.
-        if next & 1 == 1 {
+        if is_tagged(next) {
             // Make a new previous node. A node with the same pair as the found
             // previous, but with next field pointing to current node's the
             // intermediate list.
             let new_entry = Entry {
                 pair: prev.as_ref().pair,
-                next: (next & !1) as *mut _,
+                next: untag(next),
             };
             let new_ptr = OwnedAlloc::new(new_entry).into_raw();
 
@@ -601,30 +934,89 @@ impl<K, V> List<K, V> {
 
 pub enum Garbage<K, V> {
     Pair(OwnedAlloc<(K, V)>),
+    Reclaim(Reclaim<K, V>),
     Entry(OwnedAlloc<Entry<K, V>>),
     List(OwnedAlloc<List<K, V>>),
     Bucket(OwnedAlloc<Bucket<K, V>>),
+    Table(Subtable<K, V>),
+    // A nested table whose bucket, if any, moved to its parent table. Only the
+    // table itself is freed.
+    Collapsed(OwnedAlloc<Table<K, V>>),
+}
+
+impl<K, V> Garbage<K, V> {
+    // An estimate of the heap bytes held by a garbage item: the largest
+    // allocation it may own.
+    pub const ESTIMATED_BYTES: usize = {
+        let pair = mem::size_of::<(K, V)>();
+        let bucket = mem::size_of::<Bucket<K, V>>();
+        if pair > bucket {
+            pair
+        } else {
+            bucket
+        }
+    };
 }
 
 impl<K, V> fmt::Debug for Garbage<K, V> {
     fn fmt(&self, fmtr: &mut fmt::Formatter) -> fmt::Result {
         match self {
             Garbage::Pair(ptr) => write!(fmtr, "Garbage::Pair({:?})", ptr),
+            Garbage::Reclaim(reclaim) => write!(fmtr, "Garbage::Reclaim({:?})", reclaim),
             Garbage::List(ptr) => write!(fmtr, "Garbage::List({:?})", ptr),
             Garbage::Bucket(ptr) => write!(fmtr, "Garbage::Bucket({:?})", ptr),
             Garbage::Entry(ptr) => write!(fmtr, "Garbage::Entry({:?})", ptr),
+            Garbage::Table(subtable) => write!(fmtr, "Garbage::Table({:?})", subtable),
+            Garbage::Collapsed(ptr) => write!(fmtr, "Garbage::Collapsed({:?})", ptr),
         }
     }
 }
 
-pub enum GetRes<'map, K, V>
-where
-    K: 'map,
-    V: 'map,
-{
-    Found(ReadGuard<'map, K, V>),
+pub enum GetRes<'map, K, V> {
+    Found(Found<'map, K, V>),
     NotFound,
-    Delete(Pause<'map, Garbage<K, V>>),
+    Delete,
+}
+
+// An entry found in a bucket, which is only valid while the pause under which
+// it was found is active.
+pub struct Found<'map, K, V> {
+    list: &'map List<K, V>,
+    entry: NonNull<Entry<K, V>>,
+}
+
+impl<'map, K, V> Found<'map, K, V> {
+    pub fn pair(&self) -> NonNull<(K, V)> {
+        // Safe because the entry is valid while the pause is active.
+        unsafe { self.entry.as_ref().pair }
+    }
+
+    // Keeps the entry from being replaced or removed until the pin is
+    // dropped. Fails if the entry was already replaced, removed, or changed
+    // to point to another list. Other writers of the list retry while it is
+    // pinned, so the pin must only be held for a few atomic operations.
+    pub fn pin(&self) -> Option<EntryPin<'_, 'map, K, V>> {
+        let ptr = self.entry.as_ptr();
+        self.list
+            .atomic
+            .compare_exchange(ptr, tag(ptr), Acquire, Relaxed)
+            .ok()
+            .map(|_| EntryPin { found: self })
+    }
+}
+
+pub struct EntryPin<'found, 'map, K, V> {
+    found: &'found Found<'map, K, V>,
+}
+
+impl<'found, 'map, K, V> Drop for EntryPin<'found, 'map, K, V> {
+    fn drop(&mut self) {
+        // Only we change the list while it is pinned.
+        self.found
+            .list
+            .atomic
+            .store(self.found.entry.as_ptr(), Release);
+    }
 }
 
 pub enum InsertRes<I, K, V> {
@@ -694,11 +1086,11 @@ impl<K, V> Iterator for IntoIter<K, V> {
             let entry = unsafe { OwnedAlloc::from_raw(entry_nnptr) };
             // Safe because we have ownership over the nodes *and* we clear the
             // bit that may be set.
-            self.curr = NonNull::new((entry.next as usize & !1) as *mut _)
-                .map(|nnptr| unsafe { OwnedAlloc::from_raw(nnptr) });
+            self.curr =
+                NonNull::new(untag(entry.next)).map(|nnptr| unsafe { OwnedAlloc::from_raw(nnptr) });
 
             // Safe because, again, we have ownership over the nodes.
-            if entry.next as usize & 1 == 0 {
+            if !is_tagged(entry.next) {
                 break Some(unsafe { OwnedAlloc::from_raw(entry.pair) });
             }
         }
@@ -743,12 +1135,9 @@ impl<'map, K, V> Iterator for IterMut<'map, K, V> {
 
             // Safe because we clear the only bit we mark. Also, we only store
             // properly allocated nodes.
-            self.curr = unsafe {
-                let cleared = entry.next as usize & !1;
-                (cleared as *mut List<K, V>).as_mut()
-            };
+            self.curr = unsafe { untag(entry.next).as_mut() };
 
-            if entry.next as usize & 1 == 0 {
+            if !is_tagged(entry.next) {
                 // Safe because the only case in which entry.pair is dangling is
                 // when entry.next is marked. We checked for the mark.
                 let (key, val) = unsafe { &mut *entry.pair.as_ptr() };
@@ -0,0 +1,253 @@
+use crate::sync::{
+    AtomicI16, AtomicI32, AtomicI8, AtomicIsize, AtomicU16, AtomicU32, AtomicU8, AtomicUsize,
+};
+#[cfg(target_has_atomic = "64")]
+use crate::sync::{AtomicI64, AtomicU64};
+use core::{
+    fmt,
+    sync::atomic::Ordering::{self, *},
+};
+
+/// A value which can be updated in place, through a shared reference. Stored
+/// as the values of a [`Map`](super::Map), it lets
+/// [`Map::fetch_update_copy`](super::Map::fetch_update_copy) and
+/// [`Map::increment`](super::Map::increment) change a value without replacing
+/// its entry, and thus without allocating.
+///
+/// Guards to the entry see the changes, since the entry itself is not
+/// replaced. Values are loaded with [`Acquire`] and stored with [`Release`].
+///
+/// # Example
+/// ```rust
+/// use tux_lockfree::map::AtomicCell;
+///
+/// let cell = AtomicCell::new(5u64);
+/// assert_eq!(cell.fetch_update(|val| val * 2), 5);
+/// assert_eq!(cell.load(), 10);
+/// ```
+pub struct AtomicCell<V>
+where
+    V: AtomicValue,
+{
+    atomic: V::Atomic,
+}
+
+impl<V> AtomicCell<V>
+where
+    V: AtomicValue,
+{
+    /// Creates a cell with the given value.
+    pub fn new(val: V) -> Self {
+        Self {
+            atomic: val.into_atomic(),
+        }
+    }
+
+    /// Loads the current value.
+    pub fn load(&self) -> V {
+        V::load(&self.atomic, Acquire)
+    }
+
+    /// Stores the given value.
+    pub fn store(&self, val: V) {
+        V::store(&self.atomic, val, Release)
+    }
+
+    /// Stores the given value and returns the previous one.
+    pub fn swap(&self, val: V) -> V {
+        V::swap(&self.atomic, val, AcqRel)
+    }
+
+    /// Replaces the current value with the one computed from it by the given
+    /// closure, and returns the previous value. The closure may be called many
+    /// times if other threads change the value meanwhile, each time with the
+    /// latest value.
+    pub fn fetch_update<F>(&self, mut update: F) -> V
+    where
+        F: FnMut(V) -> V,
+    {
+        let mut current = self.load();
+        loop {
+            let new = update(current);
+            match V::compare_exchange_weak(&self.atomic, current, new, AcqRel, Acquire) {
+                Ok(_) => break current,
+                Err(found) => current = found,
+            }
+        }
+    }
+
+    // Stores the new value if the current one is the given one. Returns the
+    // previous value, whether the value was stored or not.
+    pub(crate) fn compare_exchange(&self, current: V, new: V) -> Result<V, V> {
+        V::compare_exchange(&self.atomic, current, new, AcqRel, Acquire)
+    }
+
+    /// Adds the given value, wrapping around on overflow, and returns the
+    /// previous value.
+    pub fn fetch_add(&self, val: V) -> V {
+        V::fetch_add(&self.atomic, val, AcqRel)
+    }
+
+    /// Consumes the cell and returns its value.
+    pub fn into_inner(self) -> V {
+        V::into_inner(self.atomic)
+    }
+}
+
+impl<V> Default for AtomicCell<V>
+where
+    V: AtomicValue + Default,
+{
+    fn default() -> Self {
+        Self::new(V::default())
+    }
+}
+
+impl<V> From<V> for AtomicCell<V>
+where
+    V: AtomicValue,
+{
+    fn from(val: V) -> Self {
+        Self::new(val)
+    }
+}
+
+impl<V> Clone for AtomicCell<V>
+where
+    V: AtomicValue,
+{
+    fn clone(&self) -> Self {
+        Self::new(self.load())
+    }
+}
+
+impl<V> PartialEq for AtomicCell<V>
+where
+    V: AtomicValue + PartialEq,
+{
+    fn eq(&self, other: &Self) -> bool {
+        self.load() == other.load()
+    }
+}
+
+impl<V> fmt::Debug for AtomicCell<V>
+where
+    V: AtomicValue + fmt::Debug,
+{
+    fn fmt(&self, fmtr: &mut fmt::Formatter) -> fmt::Result {
+        write!(fmtr, "AtomicCell {{ val: {:?} }}", self.load())
+    }
+}
+
+/// The types which an [`AtomicCell`] can hold: the primitive integers, each
+/// backed by its atomic counterpart in `core`. This trait is sealed.
+pub trait AtomicValue: Copy + sealed::Sealed {}
+
+mod sealed {
+    use core::sync::atomic::Ordering;
+
+    pub trait Sealed: Sized {
+        type Atomic;
+
+        fn into_atomic(self) -> Self::Atomic;
+
+        fn into_inner(atomic: Self::Atomic) -> Self;
+
+        fn load(atomic: &Self::Atomic, ordering: Ordering) -> Self;
+
+        fn store(atomic: &Self::Atomic, val: Self, ordering: Ordering);
+
+        fn swap(atomic: &Self::Atomic, val: Self, ordering: Ordering) -> Self;
+
+        fn compare_exchange(
+            atomic: &Self::Atomic,
+            current: Self,
+            new: Self,
+            success: Ordering,
+            failure: Ordering,
+        ) -> Result<Self, Self>;
+
+        fn compare_exchange_weak(
+            atomic: &Self::Atomic,
+            current: Self,
+            new: Self,
+            success: Ordering,
+            failure: Ordering,
+        ) -> Result<Self, Self>;
+
+        fn fetch_add(atomic: &Self::Atomic, val: Self, ordering: Ordering) -> Self;
+    }
+}
+
+macro_rules! atomic_value {
+    ($($(#[$attr:meta])* $int:ty => $atomic:ident,)*) => {$(
+        $(#[$attr])*
+        impl sealed::Sealed for $int {
+            type Atomic = $atomic;
+
+            fn into_atomic(self) -> $atomic {
+                $atomic::new(self)
+            }
+
+            fn into_inner(atomic: $atomic) -> Self {
+                // Owned, so nobody else accesses it. Loading also works with
+                // the atomics of loom.
+                atomic.load(Relaxed)
+            }
+
+            fn load(atomic: &$atomic, ordering: Ordering) -> Self {
+                atomic.load(ordering)
+            }
+
+            fn store(atomic: &$atomic, val: Self, ordering: Ordering) {
+                atomic.store(val, ordering)
+            }
+
+            fn swap(atomic: &$atomic, val: Self, ordering: Ordering) -> Self {
+                atomic.swap(val, ordering)
+            }
+
+            fn compare_exchange(
+                atomic: &$atomic,
+                current: Self,
+                new: Self,
+                success: Ordering,
+                failure: Ordering,
+            ) -> Result<Self, Self> {
+                atomic.compare_exchange(current, new, success, failure)
+            }
+
+            fn compare_exchange_weak(
+                atomic: &$atomic,
+                current: Self,
+                new: Self,
+                success: Ordering,
+                failure: Ordering,
+            ) -> Result<Self, Self> {
+                atomic.compare_exchange_weak(current, new, success, failure)
+            }
+
+            fn fetch_add(atomic: &$atomic, val: Self, ordering: Ordering) -> Self {
+                atomic.fetch_add(val, ordering)
+            }
+        }
+
+        $(#[$attr])*
+        impl AtomicValue for $int {}
+    )*};
+}
+
+atomic_value! {
+    u8 => AtomicU8,
+    u16 => AtomicU16,
+    u32 => AtomicU32,
+    #[cfg(target_has_atomic = "64")]
+    u64 => AtomicU64,
+    usize => AtomicUsize,
+    i8 => AtomicI8,
+    i16 => AtomicI16,
+    i32 => AtomicI32,
+    #[cfg(target_has_atomic = "64")]
+    i64 => AtomicI64,
+    isize => AtomicIsize,
+}
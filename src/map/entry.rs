@@ -0,0 +1,195 @@
+use super::{
+    insertion::{InsertNew, Insertion, Preview},
+    Map, ReadGuard,
+};
+use core::{
+    fmt,
+    hash::{BuildHasher, Hash},
+    mem,
+    sync::atomic::Ordering::*,
+};
+
+/// An entry of a [`Map`] which may be present or absent, created by
+/// [`Map::entry`]. The terminal methods such as [`Entry::or_insert`] insert
+/// or update the entry in a single atomic operation and return a guard to the
+/// resulting entry.
+///
+/// Unlike with `HashMap`, nothing is looked up or locked when the [`Entry`]
+/// is created: other threads may insert, change or remove the entry until a
+/// terminal method is called. Then, whether the key is present is decided
+/// atomically by that method, and if some other thread changes the entry
+/// meanwhile, the method starts over according to the latest state. This
+/// means the closure given to [`Entry::and_modify`] may be called many times,
+/// each time with the latest stored value, and only the last value it
+/// computes is stored. The value to insert is made at most once, though.
+///
+/// # Example
+/// ```rust
+/// use tux_lockfree::map::Map;
+///
+/// let counts = Map::new();
+/// for word in "a b a c a".split(' ') {
+///     counts.entry(word).and_modify(|count| count + 1).or_insert(1);
+/// }
+/// assert_eq!(*counts.get("a").unwrap().val(), 3);
+/// assert_eq!(*counts.get("c").unwrap().val(), 1);
+/// ```
+pub struct Entry<'map, K, V, H, F = fn(&V) -> V> {
+    map: &'map Map<K, V, H>,
+    key: K,
+    modify: Option<F>,
+}
+
+impl<'map, K, V, H> Entry<'map, K, V, H> {
+    pub(super) fn new(map: &'map Map<K, V, H>, key: K) -> Self {
+        Self {
+            map,
+            key,
+            modify: None,
+        }
+    }
+
+    /// Makes the terminal method replace the stored value with the one
+    /// computed by the given closure if the key is present. The closure may
+    /// be called many times due to concurrent modifications, each time with
+    /// the latest stored value. The stored value is only borrowed, since
+    /// other threads may still be reading it.
+    pub fn and_modify<F>(self, modify: F) -> Entry<'map, K, V, H, F>
+    where
+        F: FnMut(&V) -> V,
+    {
+        Entry {
+            map: self.map,
+            key: self.key,
+            modify: Some(modify),
+        }
+    }
+}
+
+impl<'map, K, V, H, F> Entry<'map, K, V, H, F> {
+    /// The key of this entry.
+    pub fn key(&self) -> &K {
+        &self.key
+    }
+}
+
+impl<'map, K, V, H, F> Entry<'map, K, V, H, F>
+where
+    K: Hash + Eq,
+    H: BuildHasher,
+    F: FnMut(&V) -> V,
+{
+    /// Inserts the given value if the key is absent. Otherwise, the stored
+    /// value is kept, or replaced according to [`Entry::and_modify`]. Returns
+    /// a guard to the resulting entry.
+    pub fn or_insert(self, val: V) -> ReadGuard<'map, K, V> {
+        self.resolve(Some(val), None::<fn() -> V>)
+    }
+
+    /// Inserts the value made by the given closure if the key is absent.
+    /// Otherwise, the stored value is kept, or replaced according to
+    /// [`Entry::and_modify`]. The closure is called at most once. Returns a
+    /// guard to the resulting entry.
+    pub fn or_insert_with<M>(self, make: M) -> ReadGuard<'map, K, V>
+    where
+        M: FnOnce() -> V,
+    {
+        self.resolve(None, Some(make))
+    }
+
+    /// Inserts the default value if the key is absent. Otherwise, the stored
+    /// value is kept, or replaced according to [`Entry::and_modify`]. Returns
+    /// a guard to the resulting entry.
+    pub fn or_default(self) -> ReadGuard<'map, K, V>
+    where
+        V: Default,
+    {
+        self.or_insert_with(V::default)
+    }
+
+    // Inserts either `spare` or the value made by `make`, exactly one of which
+    // must be given, or modifies the stored value.
+    fn resolve<M>(self, mut spare: Option<V>, mut make: Option<M>) -> ReadGuard<'map, K, V>
+    where
+        M: FnOnce() -> V,
+    {
+        let Entry { map, key, modify } = self;
+        let mut take_spare = move || match spare.take() {
+            Some(val) => val,
+            None => (make.take().expect("the value to insert was already made"))(),
+        };
+
+        let mut modify = match modify {
+            Some(modify) => modify,
+            None => return map.get_or_insert_with(key, take_spare),
+        };
+
+        let hash = map.hash_key(&key);
+        // Whether the generated value, if any, is the value to insert, rather
+        // than a modified one. The value to insert is moved out of the
+        // generated value before a modified one replaces it, so it is never
+        // made twice.
+        let mut is_spare = false;
+        let mut put_back = None;
+        let inserter = InsertNew::with_key(
+            |_, generated: Option<&mut V>, found: Option<&(K, V)>| match (found, generated) {
+                (Some((_, stored)), Some(generated)) => {
+                    let old = mem::replace(generated, modify(stored));
+                    if mem::replace(&mut is_spare, false) {
+                        put_back = Some(old);
+                    }
+                    Preview::Keep
+                }
+                (Some((_, stored)), None) => Preview::New(modify(stored)),
+                (None, Some(_)) if is_spare => Preview::Keep,
+                (None, _) => {
+                    is_spare = true;
+                    Preview::New(put_back.take().unwrap_or_else(&mut take_spare))
+                }
+            },
+            key,
+        );
+        let nnptr = inserter.raw();
+
+        let pause = map.incin.get_unchecked().pause();
+        let writing = map.writes.begin();
+        // Safe because we paused properly.
+        let insertion = unsafe {
+            map.top.insert(
+                inserter,
+                hash,
+                &pause,
+                map.incin.get_unchecked(),
+                &map.counters,
+            )
+        };
+        drop(writing);
+
+        let old = match insertion {
+            Insertion::Created => {
+                map.len.fetch_add(1, AcqRel);
+                None
+            }
+            Insertion::Updated(old) => Some(old),
+            Insertion::Failed(_) => unreachable!(),
+        };
+        // Safe because the pair is now in the map, and the pause keeps it from
+        // being deallocated even if it is removed.
+        unsafe { map.notify_insert(nnptr, old.as_ref()) };
+        ReadGuard::new(unsafe { &*nnptr.as_ptr() }, pause)
+    }
+}
+
+impl<'map, K, V, H, F> fmt::Debug for Entry<'map, K, V, H, F>
+where
+    K: fmt::Debug,
+{
+    fn fmt(&self, fmtr: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            fmtr,
+            "Entry {{ key: {:?}, modify: {} }}",
+            self.key,
+            self.modify.is_some()
+        )
+    }
+}
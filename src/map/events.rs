@@ -0,0 +1,52 @@
+use alloc::boxed::Box;
+
+/// A change of a [`Map`](super::Map), passed to the hook registered with
+/// [`Map::on_change`](super::Map::on_change) once the change is published.
+/// The references are only valid during the call of the hook.
+#[derive(Debug, PartialEq, Eq)]
+pub enum MapEvent<'entry, K, V> {
+    /// An entry was inserted for a key which was absent.
+    Created {
+        /// The key of the new entry.
+        key: &'entry K,
+        /// The value of the new entry.
+        val: &'entry V,
+    },
+    /// The entry of a key was replaced by a new one.
+    Updated {
+        /// The key of the new entry.
+        key: &'entry K,
+        /// The value of the replaced entry.
+        old: &'entry V,
+        /// The value of the new entry.
+        new: &'entry V,
+    },
+    /// The entry of a key was removed.
+    Removed {
+        /// The key of the removed entry.
+        key: &'entry K,
+        /// The value of the removed entry.
+        val: &'entry V,
+    },
+}
+
+impl<'entry, K, V> MapEvent<'entry, K, V> {
+    /// The key whose entry changed.
+    pub fn key(&self) -> &'entry K {
+        match self {
+            MapEvent::Created { key, .. } => key,
+            MapEvent::Updated { key, .. } => key,
+            MapEvent::Removed { key, .. } => key,
+        }
+    }
+}
+
+impl<'entry, K, V> Clone for MapEvent<'entry, K, V> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<'entry, K, V> Copy for MapEvent<'entry, K, V> {}
+
+pub type ChangeHook<K, V> = Box<dyn Fn(MapEvent<K, V>) + Send + Sync>;
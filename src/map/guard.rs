@@ -1,6 +1,13 @@
-use super::bucket::Garbage;
-use crate::incin::{Incinerator, Pause};
-use alloc::sync::{Arc, Weak};
+use super::{bucket::Garbage, Map};
+use crate::{
+    backoff::RetryDecision,
+    hash::DefaultState,
+    incin::{ErasedPause, Incinerator, OwnedPause, Pause},
+};
+use alloc::{
+    boxed::Box,
+    sync::{Arc, Weak},
+};
 use core::{
     borrow::Borrow,
     cmp::Ordering,
@@ -14,6 +21,21 @@ use owned_alloc::OwnedAlloc;
 
 /// A read-operation guard. This ensures no entry allocation is
 /// mutated or freed while potential reads are performed.
+///
+/// Since the guard gives shared access to the entry, it is only [`Send`] and
+/// [`Sync`] if both `K` and `V` are [`Sync`]:
+///
+/// ```rust,compile_fail
+/// use std::cell::Cell;
+/// use tux_lockfree::map::Map;
+///
+/// fn assert_send<T: Send>(_: &T) {}
+///
+/// let map = Map::new();
+/// map.insert(0, Cell::new(0));
+/// let guard = map.get(&0).unwrap();
+/// assert_send(&guard);
+/// ```
 #[derive(Debug)]
 #[allow(dead_code)]
 pub struct ReadGuard<'map, K, V>
@@ -43,6 +65,143 @@ impl<'map, K, V> ReadGuard<'map, K, V> {
         let (_, v) = &**self;
         v
     }
+
+    /// Projects the guard into a part of the entry, such as a field of the
+    /// value. The returned guard keeps the same protection as this one, but
+    /// only exposes the projected part.
+    ///
+    /// # Example
+    /// ```rust
+    /// use tux_lockfree::map::{Map, MappedGuard, ReadGuard};
+    ///
+    /// struct User {
+    ///     name: String,
+    ///     age: u32,
+    /// }
+    ///
+    /// fn name<'map>(users: &'map Map<u64, User>, id: u64) -> Option<MappedGuard<'map, str>> {
+    ///     users
+    ///         .get(&id)
+    ///         .map(|guard| ReadGuard::map(guard, |(_, user)| user.name.as_str()))
+    /// }
+    ///
+    /// let users = Map::new();
+    /// users.insert(7, User { name: "ana".to_owned(), age: 31 });
+    /// assert_eq!(name(&users, 7).as_deref(), Some("ana"));
+    /// assert!(name(&users, 8).is_none());
+    /// ```
+    pub fn map<U, F>(this: Self, proj: F) -> MappedGuard<'map, U>
+    where
+        U: ?Sized,
+        F: FnOnce(&(K, V)) -> &U,
+    {
+        MappedGuard {
+            val: proj(this.pair),
+            _pause: this.pause.erase(),
+        }
+    }
+
+    /// Projects the guard into the key of the entry. See [`ReadGuard::map`].
+    pub fn key_guard(this: Self) -> MappedGuard<'map, K> {
+        Self::map(this, |(key, _)| key)
+    }
+
+    /// Projects the guard into the value of the entry. See [`ReadGuard::map`].
+    pub fn val_guard(this: Self) -> MappedGuard<'map, V> {
+        Self::map(this, |(_, val)| val)
+    }
+
+    /// Clones the key of this borrowed entry. This is the intended way of
+    /// getting owned data out of the guard, since the guard (and its pause)
+    /// cannot escape the lifetime of the [`Map`](super::Map).
+    ///
+    /// # Example
+    /// ```rust
+    /// use tux_lockfree::map::Map;
+    ///
+    /// fn first_key(map: &Map<String, u32>) -> Option<String> {
+    ///     map.iter().next().map(|guard| guard.key_cloned())
+    /// }
+    ///
+    /// let map = Map::new();
+    /// map.insert("five".to_owned(), 5);
+    /// assert_eq!(first_key(&map).as_deref(), Some("five"));
+    /// ```
+    pub fn key_cloned(&self) -> K
+    where
+        K: Clone,
+    {
+        self.key().clone()
+    }
+
+    /// Clones the value of this borrowed entry. This is the intended way of
+    /// getting owned data out of the guard, since the guard (and its pause)
+    /// cannot escape the lifetime of the [`Map`](super::Map).
+    ///
+    /// # Example
+    /// ```rust
+    /// use tux_lockfree::map::Map;
+    ///
+    /// fn lookup(map: &Map<&'static str, Vec<u32>>, key: &str) -> Option<Vec<u32>> {
+    ///     map.get(key).map(|guard| guard.val_cloned())
+    /// }
+    ///
+    /// let map = Map::new();
+    /// map.insert("primes", vec![2, 3, 5]);
+    /// assert_eq!(lookup(&map, "primes"), Some(vec![2, 3, 5]));
+    /// assert_eq!(lookup(&map, "evens"), None);
+    /// ```
+    pub fn val_cloned(&self) -> V
+    where
+        V: Clone,
+    {
+        self.val().clone()
+    }
+
+    /// Clones both the key and the value of this borrowed entry. This is the
+    /// intended way of getting owned data out of the guard, since the guard
+    /// (and its pause) cannot escape the lifetime of the [`Map`](super::Map).
+    ///
+    /// # Example
+    /// ```rust
+    /// use tux_lockfree::map::Map;
+    ///
+    /// fn entry(map: &Map<String, u32>, key: &str) -> Option<(String, u32)> {
+    ///     map.get(key).map(|guard| guard.pair_cloned())
+    /// }
+    ///
+    /// let map = Map::new();
+    /// map.insert("five".to_owned(), 5);
+    /// assert_eq!(entry(&map, "five"), Some(("five".to_owned(), 5)));
+    /// ```
+    pub fn pair_cloned(&self) -> (K, V)
+    where
+        K: Clone,
+        V: Clone,
+    {
+        (self.key_cloned(), self.val_cloned())
+    }
+
+    /// Clones both the key and the value of this borrowed entry, just like
+    /// [`ReadGuard::pair_cloned`]. Named after the `cloned` method of the
+    /// guards of [`Set`](crate::set::Set), which clones the element.
+    ///
+    /// # Example
+    /// ```rust
+    /// use tux_lockfree::map::Map;
+    ///
+    /// let map = Map::new();
+    /// map.insert("five".to_owned(), 5);
+    /// let (key, val) = map.get("five").unwrap().cloned();
+    /// assert_eq!((key.as_str(), val), ("five", 5));
+    /// ```
+    pub fn cloned(&self) -> (K, V)
+    where
+        K: Clone,
+        V: Clone,
+    {
+        self.pair_cloned()
+    }
 }
 
 impl<'map, K, V> Deref for ReadGuard<'map, K, V> {
@@ -124,10 +283,24 @@ impl<'map, K, V> Borrow<(K, V)> for ReadGuard<'map, K, V> {
     }
 }
 
+#[cfg(feature = "serde")]
+impl<'map, K, V> serde::Serialize for ReadGuard<'map, K, V>
+where
+    K: serde::Serialize,
+    V: serde::Serialize,
+{
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        (**self).serialize(serializer)
+    }
+}
+
 unsafe impl<'map, K, V> Send for ReadGuard<'map, K, V>
 where
-    K: Send,
-    V: Send,
+    K: Sync,
+    V: Sync,
 {
 }
 
@@ -138,14 +311,247 @@ where
 {
 }
 
+/// A read-operation guard projected into a part of the guarded data. Just like
+/// the guard it was created from, it ensures no allocation is mutated or freed
+/// while potential reads are performed.
+pub struct MappedGuard<'origin, U>
+where
+    U: ?Sized + 'origin,
+{
+    val: &'origin U,
+    _pause: ErasedPause<'origin>,
+}
+
+impl<'origin, U> MappedGuard<'origin, U>
+where
+    U: ?Sized,
+{
+    /// Projects this guard further into a part of the guarded data.
+    pub fn map<W, F>(this: Self, proj: F) -> MappedGuard<'origin, W>
+    where
+        W: ?Sized,
+        F: FnOnce(&U) -> &W,
+    {
+        MappedGuard {
+            val: proj(this.val),
+            _pause: this._pause,
+        }
+    }
+}
+
+impl<'origin, U> Deref for MappedGuard<'origin, U>
+where
+    U: ?Sized,
+{
+    type Target = U;
+
+    fn deref(&self) -> &U {
+        self.val
+    }
+}
+
+impl<'origin, U> fmt::Debug for MappedGuard<'origin, U>
+where
+    U: ?Sized + fmt::Debug,
+{
+    fn fmt(&self, fmtr: &mut fmt::Formatter) -> fmt::Result {
+        (**self).fmt(fmtr)
+    }
+}
+
+impl<'origin, U> fmt::Display for MappedGuard<'origin, U>
+where
+    U: ?Sized + fmt::Display,
+{
+    fn fmt(&self, fmtr: &mut fmt::Formatter) -> fmt::Result {
+        (**self).fmt(fmtr)
+    }
+}
+
+impl<'origin, U> AsRef<U> for MappedGuard<'origin, U>
+where
+    U: ?Sized,
+{
+    fn as_ref(&self) -> &U {
+        self
+    }
+}
+
+impl<'origin, U> Borrow<U> for MappedGuard<'origin, U>
+where
+    U: ?Sized,
+{
+    fn borrow(&self) -> &U {
+        self
+    }
+}
+
+/// An owned read-operation guard. Just like [`ReadGuard`], it ensures no entry
+/// allocation is mutated or freed while potential reads are performed, but it
+/// is not bound to the lifetime of a borrow of the [`Map`]. Instead, it keeps
+/// the [`Map`] alive through an [`Arc`]. Created by
+/// [`Map::get_owned`](super::Map::get_owned).
+///
+/// Keep in mind that while this guard is alive, the incinerator of the [`Map`]
+/// is paused, and so garbage will not be reclaimed.
+pub struct OwnedReadGuard<K, V, H = DefaultState> {
+    pair: NonNull<(K, V)>,
+    // The pause must be dropped before the map.
+    pause: OwnedPause<Garbage<K, V>>,
+    map: Arc<Map<K, V, H>>,
+}
+
+impl<K, V, H> OwnedReadGuard<K, V, H> {
+    pub(super) fn new(
+        guard: &ReadGuard<K, V>,
+        pause: OwnedPause<Garbage<K, V>>,
+        map: Arc<Map<K, V, H>>,
+    ) -> Self {
+        Self {
+            pair: NonNull::from(guard.pair),
+            pause,
+            map,
+        }
+    }
+
+    /// Utility method. Returns the key of this borrowed entry.
+    pub fn key(&self) -> &K {
+        let (k, _) = &**self;
+        k
+    }
+
+    /// Utility method. Returns the value of this borrowed entry.
+    pub fn val(&self) -> &V {
+        let (_, v) = &**self;
+        v
+    }
+
+    /// Returns the [`Map`] this guard keeps alive.
+    pub fn map(this: &Self) -> &Arc<Map<K, V, H>> {
+        &this.map
+    }
+}
+
+impl<K, V, H> Deref for OwnedReadGuard<K, V, H> {
+    type Target = (K, V);
+
+    fn deref(&self) -> &Self::Target {
+        // Safe because the map is alive and the incinerator is paused.
+        unsafe { self.pair.as_ref() }
+    }
+}
+
+impl<K, V, H> fmt::Debug for OwnedReadGuard<K, V, H>
+where
+    K: fmt::Debug,
+    V: fmt::Debug,
+{
+    fn fmt(&self, fmtr: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            fmtr,
+            "OwnedReadGuard {{ pair: {:?}, pause: {:?} }}",
+            **self, self.pause
+        )
+    }
+}
+
+impl<K, V, H> AsRef<(K, V)> for OwnedReadGuard<K, V, H> {
+    fn as_ref(&self) -> &(K, V) {
+        self
+    }
+}
+
+impl<K, V, H> Borrow<(K, V)> for OwnedReadGuard<K, V, H> {
+    fn borrow(&self) -> &(K, V) {
+        self
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<K, V, H> serde::Serialize for OwnedReadGuard<K, V, H>
+where
+    K: serde::Serialize,
+    V: serde::Serialize,
+{
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        (**self).serialize(serializer)
+    }
+}
+
+unsafe impl<K, V, H> Send for OwnedReadGuard<K, V, H>
+where
+    K: Send + Sync,
+    V: Send + Sync,
+    H: Send + Sync,
+{
+}
+
+unsafe impl<K, V, H> Sync for OwnedReadGuard<K, V, H>
+where
+    K: Send + Sync,
+    V: Send + Sync,
+    H: Send + Sync,
+{
+}
+
 /// A removed entry. It can be reinserted at the same [`Map`](super::Map) it was
 /// removed. It can also be inserted on another [`Map`](super::Map), but only if
 /// either the [`Map`](super::Map) is dropped, there are no sensitive reads
 /// running on that [`Map`](super::Map) or both [`Map`](super::Map)s share the
 /// same incinerator.
+///
+/// Just like a [`Box`](alloc::boxed::Box) of the pair, it is [`Send`] if both
+/// `K` and `V` are [`Send`], and [`Sync`] if both `K` and `V` are [`Sync`]:
+///
+/// ```rust,compile_fail
+/// use std::rc::Rc;
+/// use tux_lockfree::map::Map;
+///
+/// fn assert_send<T: Send>(_: &T) {}
+///
+/// let map = Map::new();
+/// map.insert(0, Rc::new(0));
+/// let removed = map.remove(&0).unwrap();
+/// assert_send(&removed);
+/// ```
+///
+/// # Handing off to other structures
+/// A removed entry can be moved into another structure, such as a
+/// [`Queue`](crate::queue::Queue) or a [`Stack`](crate::stack::Stack), without
+/// copying the pair and without waiting for the reads on the [`Map`](super::Map)
+/// to end. The consumer gets the wrapper back, can read the pair right away,
+/// and can move the pair out with [`Removed::try_into`] once the reads are
+/// over:
+///
+/// ```rust
+/// use tux_lockfree::{
+///     map::{Map, Removed},
+///     queue::Queue,
+/// };
+///
+/// let jobs = Map::new();
+/// jobs.insert(1, String::from("expired"));
+/// let retries = Queue::new();
+///
+/// // Some reader is still looking at the job.
+/// let guard = jobs.get(&1).unwrap();
+/// retries.push(jobs.remove(&1).unwrap());
+/// assert_eq!(guard.val(), "expired");
+///
+/// let removed = retries.pop().unwrap();
+/// assert_eq!(removed.val(), "expired");
+/// let removed = Removed::try_into(removed).unwrap_err();
+///
+/// drop(guard);
+/// assert_eq!(Removed::try_into(removed).ok(), Some((1, String::from("expired"))));
+/// ```
 pub struct Removed<K, V> {
     nnptr: NonNull<(K, V)>,
     origin: Weak<Incinerator<Garbage<K, V>>>,
+    on_reclaim: ReclaimCallbacks<K, V>,
 }
 
 impl<K, V> Removed<K, V> {
@@ -153,17 +559,31 @@ impl<K, V> Removed<K, V> {
         Self {
             nnptr: alloc.into_raw(),
             origin: Arc::downgrade(origin),
+            on_reclaim: None,
         }
     }
 
-    pub(super) fn into_alloc(mut this: Self) -> OwnedAlloc<(K, V)> {
+    pub(super) fn into_alloc(this: Self) -> OwnedAlloc<(K, V)> {
+        // The allocation goes back to a map, so the callback is discarded.
+        let (nnptr, _) = Self::into_raw_parts(this);
         // It is safe because we own the allocation.
-        let alloc = unsafe { OwnedAlloc::from_raw(this.nnptr) };
-        // There is no other way of dropping the weak and forgetting ourselves.
-        // Rust does not let us move fields of a `Drop` struct.
-        unsafe { (&mut this.origin as *mut Weak<_>).drop_in_place() }
+        unsafe { OwnedAlloc::from_raw(nnptr) }
+    }
+
+    fn into_raw_parts(this: Self) -> (NonNull<(K, V)>, ReclaimCallbacks<K, V>) {
+        // There is no other way of moving the fields and forgetting ourselves.
+        // Rust does not let us move fields of a `Drop` struct. Reading is safe
+        // because we forget the original right after.
+        let (origin, on_reclaim) = unsafe {
+            (
+                (&this.origin as *const Weak<_>).read(),
+                (&this.on_reclaim as *const Option<_>).read(),
+            )
+        };
+        let nnptr = this.nnptr;
         forget(this);
-        alloc
+        drop(origin);
+        (nnptr, on_reclaim)
     }
 
     pub(super) fn raw(this: &Self) -> NonNull<(K, V)> {
@@ -233,26 +653,219 @@ impl<K, V> Removed<K, V> {
         };
 
         if success {
+            let (nnptr, on_reclaim) = Self::into_raw_parts(this);
             // We own the allocation. This must be safe.
-            let (ret, _) = unsafe { OwnedAlloc::from_raw(this.nnptr) }.move_inner();
-            forget(this);
+            let alloc = unsafe { OwnedAlloc::from_raw(nnptr) };
+            if let Some(callback) = on_reclaim {
+                callback.call(&alloc);
+            }
+            let (ret, _) = alloc.move_inner();
             Ok(ret)
         } else {
             Err(this)
         }
     }
+
+    /// Registers a callback to be called when the memory of this removed
+    /// entry is actually reclaimed. This happens when the incinerator finally
+    /// drops the entry, which might be right when this wrapper is dropped (if
+    /// no sensitive reads are being performed) or later. The callback is also
+    /// called if the pair is moved out by [`Removed::try_into`]. If the entry
+    /// is reinserted in a [`Map`](super::Map), the callback is discarded
+    /// without being called. Registering more than one callback calls all of
+    /// them, in order of registration.
+    ///
+    /// # Example
+    /// ```rust
+    /// use std::sync::{
+    ///     atomic::{AtomicUsize, Ordering::*},
+    ///     Arc,
+    /// };
+    /// use tux_lockfree::map::{Map, Removed};
+    ///
+    /// let live_bytes = Arc::new(AtomicUsize::new(0));
+    /// let map = Map::new();
+    /// map.insert(1, vec![0u8; 64]);
+    /// live_bytes.fetch_add(64, Relaxed);
+    ///
+    /// let mut removed = map.remove(&1).unwrap();
+    /// let counter = live_bytes.clone();
+    /// Removed::on_reclaim(&mut removed, move |(_, bytes)| {
+    ///     counter.fetch_sub(bytes.len(), Relaxed);
+    /// });
+    /// drop(removed);
+    /// assert_eq!(live_bytes.load(Relaxed), 0);
+    /// ```
+    pub fn on_reclaim<F>(this: &mut Self, callback: F)
+    where
+        F: FnOnce(&(K, V)) + Send + 'static,
+    {
+        let mut slot = &mut this.on_reclaim;
+        while let Some(node) = slot {
+            slot = &mut node.next;
+        }
+        *slot = Some(Box::new(ReclaimCallback {
+            callback: Box::new(callback),
+            next: None,
+        }));
+    }
+
+    /// Tries to convert this wrapper into the pair, retrying according to the
+    /// given policy while sensitive reads are being performed. The policy
+    /// receives the number of the attempt that just failed (starting from
+    /// `0`) and decides how to wait before the next one, or whether to give
+    /// up. On giving up, the wrapper is handed back intact.
+    /// [`exponential_backoff`](crate::backoff::exponential_backoff) is a
+    /// sensible default policy.
+    ///
+    /// # Example
+    /// ```rust
+    /// use tux_lockfree::{backoff::exponential_backoff, map::{Map, Removed}};
+    ///
+    /// let map = Map::new();
+    /// map.insert("five", 5);
+    /// let removed = map.remove("five").unwrap();
+    /// let pair = Removed::try_into_with(removed, &mut exponential_backoff);
+    /// assert_eq!(pair.unwrap(), ("five", 5));
+    /// ```
+    pub fn try_into_with<F>(mut this: Self, backoff: &mut F) -> Result<(K, V), Self>
+    where
+        F: FnMut(u32) -> RetryDecision,
+    {
+        let mut attempt = 0;
+        loop {
+            this = match Self::try_into(this) {
+                Ok(pair) => break Ok(pair),
+                Err(this) => this,
+            };
+
+            if !backoff(attempt).wait() {
+                break Err(this);
+            }
+            attempt = attempt.saturating_add(1);
+        }
+    }
+
+    /// Tries to move the key out of this wrapper, dropping the value. Succeeds
+    /// under the same conditions as [`Removed::try_into`].
+    pub fn try_into_key(this: Self) -> Result<K, Self> {
+        Self::try_into(this).map(|(key, _)| key)
+    }
+
+    /// Tries to move the value out of this wrapper, dropping the key. Succeeds
+    /// under the same conditions as [`Removed::try_into`].
+    ///
+    /// # Example
+    /// ```rust
+    /// use tux_lockfree::map::{Map, Removed};
+    ///
+    /// let map = Map::new();
+    /// map.insert("job", vec![1, 2]);
+    /// let guard = map.get("job").unwrap();
+    /// let removed = map.remove("job").unwrap();
+    ///
+    /// // The guard still reads the entry, so it cannot be moved out yet.
+    /// let removed = Removed::try_into_val(removed).unwrap_err();
+    /// drop(guard);
+    /// assert_eq!(Removed::try_into_val(removed).unwrap(), [1, 2]);
+    /// ```
+    pub fn try_into_val(this: Self) -> Result<V, Self> {
+        Self::try_into(this).map(|(_, val)| val)
+    }
+
+    /// Takes the key out of this wrapper, moving it if [`Removed::try_into`]
+    /// succeeds, and cloning it otherwise, in which case the entry is left
+    /// for the incinerator to drop.
+    pub fn into_key(this: Self) -> K
+    where
+        K: Clone,
+    {
+        Self::try_into_key(this).unwrap_or_else(|this| this.key().clone())
+    }
+
+    /// Takes the value out of this wrapper, moving it if [`Removed::try_into`]
+    /// succeeds, and cloning it otherwise, in which case the entry is left
+    /// for the incinerator to drop. Unlike [`Removed::try_into_val`], this
+    /// never fails.
+    ///
+    /// # Example
+    /// ```rust
+    /// use tux_lockfree::map::{Map, Removed};
+    ///
+    /// let map = Map::new();
+    /// map.insert("job", String::from("compress"));
+    /// let val = Removed::into_val(map.remove("job").unwrap());
+    /// assert_eq!(val, "compress");
+    /// ```
+    pub fn into_val(this: Self) -> V
+    where
+        V: Clone,
+    {
+        Self::try_into_val(this).unwrap_or_else(|this| this.val().clone())
+    }
 }
 
 impl<K, V> Drop for Removed<K, V> {
     fn drop(&mut self) {
         // We own the allocation. This must be safe.
-        let alloc = unsafe { OwnedAlloc::from_raw(self.nnptr) };
+        let pair = unsafe { OwnedAlloc::from_raw(self.nnptr) };
+        let garbage = match self.on_reclaim.take() {
+            None => Garbage::Pair(pair),
+            Some(callback) => Garbage::Reclaim(Reclaim {
+                pair,
+                callback: Some(callback),
+            }),
+        };
         if let Some(incin) = self.origin.upgrade() {
-            incin.add(Garbage::Pair(alloc))
+            incin.add(garbage)
+        }
+    }
+}
+
+// A callback registered with `Removed::on_reclaim`, chained to the ones
+// registered after it. Removed entries without callbacks only carry a null
+// slot.
+pub(super) struct ReclaimCallback<K, V> {
+    callback: ReclaimFn<K, V>,
+    next: ReclaimCallbacks<K, V>,
+}
+
+type ReclaimFn<K, V> = Box<dyn FnOnce(&(K, V)) + Send>;
+
+pub(super) type ReclaimCallbacks<K, V> = Option<Box<ReclaimCallback<K, V>>>;
+
+impl<K, V> ReclaimCallback<K, V> {
+    fn call(self: Box<Self>, pair: &(K, V)) {
+        let mut node = Some(self);
+        while let Some(curr) = node {
+            let ReclaimCallback { callback, next } = *curr;
+            callback(pair);
+            node = next;
+        }
+    }
+}
+
+// A removed pair whose reclamation must be reported to a callback.
+pub struct Reclaim<K, V> {
+    pair: OwnedAlloc<(K, V)>,
+    // Only taken when dropped.
+    callback: ReclaimCallbacks<K, V>,
+}
+
+impl<K, V> Drop for Reclaim<K, V> {
+    fn drop(&mut self) {
+        if let Some(callback) = self.callback.take() {
+            callback.call(&self.pair);
         }
     }
 }
 
+impl<K, V> fmt::Debug for Reclaim<K, V> {
+    fn fmt(&self, fmtr: &mut fmt::Formatter) -> fmt::Result {
+        write!(fmtr, "Reclaim {{ pair: {:?} }}", self.pair)
+    }
+}
+
 impl<K, V> Deref for Removed<K, V> {
     type Target = (K, V);
 
@@ -342,6 +955,20 @@ impl<K, V> Borrow<(K, V)> for Removed<K, V> {
     }
 }
 
+#[cfg(feature = "serde")]
+impl<K, V> serde::Serialize for Removed<K, V>
+where
+    K: serde::Serialize,
+    V: serde::Serialize,
+{
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        (**self).serialize(serializer)
+    }
+}
+
 unsafe impl<K, V> Send for Removed<K, V>
 where
     K: Send,
@@ -355,3 +982,34 @@ where
     V: Sync,
 {
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use alloc::{rc::Rc, string::String};
+    use core::cell::Cell;
+    use static_assertions::{assert_impl_all, assert_not_impl_any};
+    #[cfg(feature = "std")]
+    use std::sync::MutexGuard;
+
+    assert_impl_all!(ReadGuard<'static, u64, String>: Send, Sync);
+    #[cfg(feature = "std")]
+    assert_impl_all!(ReadGuard<'static, u64, MutexGuard<'static, u8>>: Send, Sync);
+    assert_not_impl_any!(ReadGuard<'static, u64, Cell<u8>>: Send, Sync);
+    assert_not_impl_any!(ReadGuard<'static, Rc<u64>, u8>: Send, Sync);
+
+    assert_impl_all!(Removed<u64, String>: Send, Sync);
+    assert_impl_all!(Removed<u64, Cell<u8>>: Send);
+    assert_not_impl_any!(Removed<u64, Cell<u8>>: Sync);
+    #[cfg(feature = "std")]
+    assert_impl_all!(Removed<u64, MutexGuard<'static, u8>>: Sync);
+    #[cfg(feature = "std")]
+    assert_not_impl_any!(Removed<u64, MutexGuard<'static, u8>>: Send);
+    assert_not_impl_any!(Removed<Rc<u64>, u8>: Send, Sync);
+
+    assert_impl_all!(MappedGuard<'static, String>: Send, Sync);
+    assert_not_impl_any!(MappedGuard<'static, Cell<u8>>: Send, Sync);
+
+    assert_impl_all!(OwnedReadGuard<u64, String>: Send, Sync);
+    assert_not_impl_any!(OwnedReadGuard<u64, Cell<u8>>: Send, Sync);
+}
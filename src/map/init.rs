@@ -0,0 +1,251 @@
+use crate::{
+    backoff::{exponential_backoff, RetryDecision},
+    sync::{AtomicPtr, AtomicUsize},
+};
+use core::{
+    hint,
+    ptr::{self, null_mut, NonNull},
+    sync::atomic::Ordering::*,
+};
+use owned_alloc::OwnedAlloc;
+
+// Markers of the keys whose value is being initialized by `Map::get_or_init`.
+// A marker is derived from the hash of a key, and while the initializer runs
+// its slot also points to the key itself, so keys which share a marker only
+// wait for each other if they are equal. Before the key is moved into the map,
+// the claim is detached from it, and from then until the claim is released,
+// every key with the same marker waits.
+//
+// The markers are stored in a list of slots which only grows. Free slots are
+// reused, so the list is only as long as the largest number of simultaneous
+// initializations, and it is freed with the map.
+pub struct InitMarkers<K> {
+    head: AtomicPtr<Slot<K>>,
+}
+
+impl<K> InitMarkers<K> {
+    pub fn new() -> Self {
+        Self {
+            head: AtomicPtr::new(null_mut()),
+        }
+    }
+
+    // Computes the marker of a key from its hash. A marker is never zero,
+    // which is reserved for free slots.
+    pub fn marker(hash: u64) -> usize {
+        hash as usize | 1
+    }
+
+    // Tries to claim the given marker for the given key. Fails if some other
+    // claim holds an equal key, or a detached claim holds the marker, in which
+    // case the caller should wait for it to be released and retry.
+    //
+    // Safety: the key must not be moved nor dropped until the claim is
+    // detached or dropped.
+    pub unsafe fn claim(&self, marker: usize, key: &K) -> Option<InitClaim<'_, K>>
+    where
+        K: Eq,
+    {
+        if self.is_held(marker, key) {
+            return None;
+        }
+
+        let slot = match self.slots().find(|slot| {
+            slot.marker
+                .compare_exchange(0, marker, SeqCst, Relaxed)
+                .is_ok()
+        }) {
+            Some(slot) => slot,
+            None => self.push(marker),
+        };
+        slot.key.store(key as *const K as *mut K, SeqCst);
+        let claim = InitClaim { slot };
+
+        // Some other thread may have claimed the same key in another slot
+        // after our check. Both stores of the keys and both checks are
+        // sequentially consistent, so at least one of the threads sees the
+        // other and backs off.
+        let contended = self
+            .slots()
+            .any(|other| !ptr::eq(other, slot) && other.holds(marker, key));
+        if contended {
+            None
+        } else {
+            Some(claim)
+        }
+    }
+
+    // Waits until nobody holds the given key.
+    pub fn wait(&self, marker: usize, key: &K)
+    where
+        K: Eq,
+    {
+        let mut attempt = 0;
+        while self.is_held(marker, key) {
+            let decision = match exponential_backoff(attempt) {
+                RetryDecision::GiveUp => RetryDecision::Yield,
+                decision => decision,
+            };
+            decision.wait();
+            attempt = attempt.saturating_add(1);
+        }
+    }
+
+    fn is_held(&self, marker: usize, key: &K) -> bool
+    where
+        K: Eq,
+    {
+        self.slots().any(|slot| slot.holds(marker, key))
+    }
+
+    fn push(&self, marker: usize) -> &Slot<K> {
+        let mut slot = OwnedAlloc::new(Slot {
+            marker: AtomicUsize::new(marker),
+            key: AtomicPtr::new(null_mut()),
+            readers: AtomicUsize::new(0),
+            next: null_mut(),
+        });
+        let mut head = self.head.load(Acquire);
+
+        loop {
+            slot.next = head;
+            match self
+                .head
+                .compare_exchange(head, slot.raw().as_ptr(), SeqCst, Acquire)
+            {
+                // Safe because slots are only freed along with the markers.
+                Ok(_) => break unsafe { &*slot.into_raw().as_ptr() },
+                Err(new) => head = new,
+            }
+        }
+    }
+
+    fn slots(&self) -> Slots<'_, K> {
+        Slots {
+            curr: self.head.load(Acquire),
+            _markers: self,
+        }
+    }
+}
+
+impl<K> Drop for InitMarkers<K> {
+    fn drop(&mut self) {
+        let mut curr = self.head.load(Relaxed);
+        while let Some(nnptr) = NonNull::new(curr) {
+            // Safe because every slot was allocated by `push` and we are in
+            // the destructor, so nobody else is using them.
+            let slot = unsafe { OwnedAlloc::from_raw(nnptr) };
+            curr = slot.next;
+        }
+    }
+}
+
+// A claimed marker. Releases the marker when dropped, including when the
+// initializer panics, so waiting threads retry the initialization.
+pub struct InitClaim<'markers, K> {
+    slot: &'markers Slot<K>,
+}
+
+impl<'markers, K> InitClaim<'markers, K> {
+    // Stops pointing to the key, which may then be moved. Keys which share
+    // the marker wait until the claim is dropped.
+    pub fn detach(&self) {
+        self.slot.key.store(null_mut(), SeqCst);
+        // Readers which loaded the key before it was cleared may still be
+        // comparing it.
+        while self.slot.readers.load(SeqCst) != 0 {
+            hint::spin_loop();
+        }
+    }
+}
+
+impl<'markers, K> Drop for InitClaim<'markers, K> {
+    fn drop(&mut self) {
+        self.detach();
+        self.slot.marker.store(0, Release);
+    }
+}
+
+struct Slot<K> {
+    marker: AtomicUsize,
+    // The key of the claim, or null if the slot is free, not yet pointing to
+    // the key or detached from it.
+    key: AtomicPtr<K>,
+    // How many threads may be comparing the key.
+    readers: AtomicUsize,
+    // Only written before the slot is published.
+    next: *mut Slot<K>,
+}
+
+impl<K> Slot<K> {
+    // Tests whether the slot holds the given key. A claimed slot with no key
+    // conservatively holds every key with its marker.
+    fn holds(&self, marker: usize, key: &K) -> bool
+    where
+        K: Eq,
+    {
+        if self.marker.load(SeqCst) != marker {
+            return false;
+        }
+
+        self.readers.fetch_add(1, SeqCst);
+        // Safe because the claimer only moves its key after clearing this
+        // pointer and waiting for the readers which may have loaded it.
+        let held = unsafe { self.key.load(SeqCst).as_ref() }.map_or(true, |held| held == key);
+        self.readers.fetch_sub(1, Release);
+        held
+    }
+}
+
+struct Slots<'markers, K> {
+    curr: *mut Slot<K>,
+    _markers: &'markers InitMarkers<K>,
+}
+
+impl<'markers, K> Iterator for Slots<'markers, K> {
+    type Item = &'markers Slot<K>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        // Safe because published slots are only freed with the markers, which
+        // outlive this iterator.
+        let slot = unsafe { self.curr.as_ref()? };
+        self.curr = slot.next;
+        Some(slot)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn claims_are_exclusive_and_reused() {
+        let markers = InitMarkers::new();
+        let (three, five) = (3, 5);
+        let first = unsafe { markers.claim(3, &three) }.unwrap();
+        assert!(unsafe { markers.claim(3, &three) }.is_none());
+        let other = unsafe { markers.claim(5, &five) }.unwrap();
+        drop(first);
+        let again = unsafe { markers.claim(3, &three) }.unwrap();
+        assert_eq!(markers.slots().count(), 2);
+        drop(again);
+        drop(other);
+        markers.wait(3, &three);
+    }
+
+    #[test]
+    fn equal_markers_of_other_keys_are_not_held() {
+        let markers = InitMarkers::new();
+        let (first, second) = (2, 4);
+        let claim = unsafe { markers.claim(1, &first) }.unwrap();
+        let other = unsafe { markers.claim(1, &second) }.unwrap();
+        assert!(unsafe { markers.claim(1, &first) }.is_none());
+
+        // Once detached, the claim holds every key with its marker.
+        claim.detach();
+        assert!(markers.is_held(1, &(first + second)));
+        drop(claim);
+        drop(other);
+        assert!(!markers.is_held(1, &first));
+    }
+}
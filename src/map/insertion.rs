@@ -1,61 +1,10 @@
-use super::Removed;
+use super::{ReadGuard, Removed};
 use core::{mem::forget, ptr::NonNull};
 use owned_alloc::{OwnedAlloc, UninitAlloc};
 
-/// A [`insert_with`](super::Map::insert_with) operation result.
-#[derive(Debug, PartialEq, Eq)]
-pub enum Insertion<K, V, E> {
-    /// The entry was created.
-    Created,
-    /// The entry was updated and this was the old pair.
-    Updated(Removed<K, V>),
-    /// The insertion failed and no operation was performed. Failure of an
-    /// insertion might happen because the closure rejected the conditions.
-    /// Another reason is that method-specific contract was not respected (such
-    /// as the one of [`reinsert_with`](super::Map::reinsert_with)).
-    Failed(E),
-}
-
-impl<K, V, E> Insertion<K, V, E> {
-    /// Returns whether the insertion created an entry.
-    pub fn created(&self) -> bool {
-        matches!(self, Insertion::Created)
-    }
-
-    /// Returns whether the insertion updated an entry.
-    pub fn updated(&self) -> Option<&Removed<K, V>> {
-        match self {
-            Insertion::Updated(pair) => Some(pair),
-            _ => None,
-        }
-    }
-
-    /// Tries to take the updated entry of this insertion and encodes it as a
-    /// [`Result`]. [`Ok`] is returned only if this insertion updated a value.
-    pub fn take_updated(self) -> Result<Removed<K, V>, Self> {
-        match self {
-            Insertion::Updated(pair) => Ok(pair),
-            this => Err(this),
-        }
-    }
-
-    /// Returns whether the insertion failed.
-    pub fn failed(&self) -> Option<&E> {
-        match self {
-            Insertion::Failed(err) => Some(err),
-            _ => None,
-        }
-    }
-
-    /// Tries to take the failure of this insertion and encodes it as a
-    /// [`Result`]. [`Ok`] is returned only if this insertion has a failure.
-    pub fn take_failed(self) -> Result<E, Self> {
-        match self {
-            Insertion::Failed(e) => Ok(e),
-            this => Err(this),
-        }
-    }
-}
+/// A [`insert_with`](super::Map::insert_with) operation result. The old pair
+/// of an update is given back as a [`Removed`].
+pub type Insertion<K, V, E> = crate::insertion::Insertion<Removed<K, V>, E>;
 
 /// The preview of an _interactive_ insertion. It is used by the
 /// [`insert_with`](super::Map::insert_with) method and it is the return value
@@ -72,10 +21,55 @@ pub enum Preview<V> {
     /// [`Preview::Discard`].
     Keep,
     /// Tells the `Map to use this value instead of the previously generated
-    /// (if any).
+    /// (if any). The value may be computed from the found entry, e.g. to
+    /// append to a stored collection, see
+    /// [`insert_with`](super::Map::insert_with).
     New(V),
 }
 
+/// The error of [`Map::compare_and_swap`](super::Map::compare_and_swap).
+/// Occurs if the key was not found or its value was not the expected one.
+#[derive(Debug)]
+pub struct CasError<'map, K, V> {
+    /// The value which was attempted to be stored.
+    pub new: V,
+    /// A guard to the entry found after the failure, if any. Since the map
+    /// may change meanwhile, it is not necessarily the entry which failed
+    /// the comparison.
+    pub current: Option<ReadGuard<'map, K, V>>,
+}
+
+/// The error of [`Map::try_insert`](super::Map::try_insert). Occurs if the
+/// key was already present.
+#[derive(Debug)]
+pub struct OccupiedError<'map, K, V> {
+    /// The key and value which were attempted to be inserted.
+    pub pair: (K, V),
+    /// A guard to the stored entry which prevented the insertion. Since the
+    /// map may change meanwhile, it might have been replaced or removed
+    /// since then.
+    pub current: ReadGuard<'map, K, V>,
+}
+
+/// The error of [`Map::compare_and_remove`](super::Map::compare_and_remove).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RemoveError {
+    /// Returned when no entry with the given key was found.
+    NotFound,
+    /// Returned when the entry was found, but its value was not the expected
+    /// one.
+    Mismatch,
+}
+
+/// The error of [`Map::fetch_update`](super::Map::fetch_update).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FetchUpdateError {
+    /// Returned when no entry with the given key was found.
+    NotFound,
+    /// Returned when the closure returned `None` for the latest value.
+    Aborted,
+}
+
 // A trait we use to insert stuff with interactive generation of entries and
 // validation of conditions.
 pub trait Inserter<K, V>: Sized {
@@ -103,6 +97,10 @@ where
     interactive: F,
     nnptr: NonNull<(K, V)>,
     is_val_init: bool,
+    // The stored entry, or its absence, passed to the closure last time. The
+    // insertion runs under a single pause, so a found entry is never
+    // deallocated, and the same address means the same entry.
+    seen: Option<Option<NonNull<(K, V)>>>,
 }
 
 impl<F, K, V> InsertNew<F, K, V>
@@ -120,6 +118,7 @@ where
                 alloc.into_raw()
             },
             is_val_init: false,
+            seen: None,
         }
     }
 
@@ -128,9 +127,16 @@ where
             interactive,
             nnptr: OwnedAlloc::new(pair).forget_inner().into_raw(),
             is_val_init: true,
+            seen: None,
         }
     }
 
+    // The allocation this inserter would insert. It does not change when the
+    // value is generated.
+    pub fn raw(&self) -> NonNull<(K, V)> {
+        self.nnptr
+    }
+
     pub fn into_pair(self) -> (K, Option<V>) {
         // Doing this is safe by itself. However, callers should be careful if
         // they used the pointer.
@@ -173,6 +179,14 @@ where
     F: FnMut(&K, Option<&mut V>, Option<&(K, V)>) -> Preview<V>,
 {
     fn input(&mut self, found: Option<&(K, V)>) {
+        // A retry caused by a change elsewhere in the map observes the same
+        // state as before, so the previous answer of the closure still holds.
+        let observed = found.map(NonNull::from);
+        if self.seen == Some(observed) {
+            return;
+        }
+        self.seen = Some(observed);
+
         // This is safe. This allocation is owned by us.
         let (key, val) = unsafe { self.nnptr.as_mut() };
 
@@ -223,6 +237,118 @@ where
     }
 }
 
+// An inserter which inserts the given pair only if its key is absent.
+pub struct InsertAbsent<K, V> {
+    pair: OwnedAlloc<(K, V)>,
+    found: Option<NonNull<(K, V)>>,
+}
+
+impl<K, V> InsertAbsent<K, V> {
+    pub fn new(pair: (K, V)) -> Self {
+        Self {
+            pair: OwnedAlloc::new(pair),
+            found: None,
+        }
+    }
+
+    // The stored entry which made the insertion fail, if it did. Only valid
+    // while the pause used to insert is alive.
+    pub fn found(&self) -> Option<NonNull<(K, V)>> {
+        self.found
+    }
+
+    pub fn into_pair(self) -> (K, V) {
+        let (pair, _) = self.pair.move_inner();
+        pair
+    }
+
+    pub fn raw(&self) -> NonNull<(K, V)> {
+        self.pair.raw()
+    }
+}
+
+impl<K, V> Inserter<K, V> for InsertAbsent<K, V> {
+    fn input(&mut self, found: Option<&(K, V)>) {
+        self.found = found.map(NonNull::from);
+    }
+
+    fn pointer(&self) -> Option<NonNull<(K, V)>> {
+        match self.found {
+            Some(_) => None,
+            None => Some(self.pair.raw()),
+        }
+    }
+
+    fn key(&self) -> &K {
+        let (key, _) = &*self.pair;
+        key
+    }
+
+    fn take_pointer(self) {
+        self.pair.into_raw();
+    }
+}
+
+// An inserter which replaces an entry with the given pair only if its key is
+// present and the stored entry is approved by the given closure.
+pub struct InsertPresent<F, K, V>
+where
+    F: FnMut(&(K, V)) -> bool,
+{
+    interactive: F,
+    pair: OwnedAlloc<(K, V)>,
+    is_valid: bool,
+}
+
+impl<F, K, V> InsertPresent<F, K, V>
+where
+    F: FnMut(&(K, V)) -> bool,
+{
+    pub fn new(interactive: F, pair: (K, V)) -> Self {
+        Self {
+            interactive,
+            pair: OwnedAlloc::new(pair),
+            is_valid: false,
+        }
+    }
+
+    // The allocation this inserter would insert.
+    pub fn raw(&self) -> NonNull<(K, V)> {
+        self.pair.raw()
+    }
+
+    pub fn into_pair(self) -> (K, V) {
+        let (pair, _) = self.pair.move_inner();
+        pair
+    }
+}
+
+impl<F, K, V> Inserter<K, V> for InsertPresent<F, K, V>
+where
+    F: FnMut(&(K, V)) -> bool,
+{
+    fn input(&mut self, found: Option<&(K, V)>) {
+        self.is_valid = found.map_or(false, &mut self.interactive);
+    }
+
+    fn pointer(&self) -> Option<NonNull<(K, V)>> {
+        if self.is_valid {
+            Some(self.pair.raw())
+        } else {
+            None
+        }
+    }
+
+    fn key(&self) -> &K {
+        let (key, _) = &*self.pair;
+        key
+    }
+
+    fn take_pointer(self) {
+        self.pair.into_raw();
+    }
+}
+
 // An inserter which reinserts a previously removed allocation.
 pub struct Reinsert<F, K, V>
 where
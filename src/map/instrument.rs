@@ -0,0 +1,102 @@
+#[cfg(feature = "instrument")]
+use core::sync::atomic::{AtomicUsize, Ordering::*};
+
+// Counts events of the internal algorithms of a map. Without the `instrument`
+// feature, this is a zero-sized type whose methods do nothing, so the
+// counting compiles out entirely.
+pub struct Counters {
+    #[cfg(feature = "instrument")]
+    cas_retries: AtomicUsize,
+    #[cfg(feature = "instrument")]
+    table_splits: AtomicUsize,
+    #[cfg(feature = "instrument")]
+    chain_traversals: AtomicUsize,
+    #[cfg(feature = "instrument")]
+    incin_deferrals: AtomicUsize,
+}
+
+impl Counters {
+    pub fn new() -> Self {
+        Self {
+            #[cfg(feature = "instrument")]
+            cas_retries: AtomicUsize::new(0),
+            #[cfg(feature = "instrument")]
+            table_splits: AtomicUsize::new(0),
+            #[cfg(feature = "instrument")]
+            chain_traversals: AtomicUsize::new(0),
+            #[cfg(feature = "instrument")]
+            incin_deferrals: AtomicUsize::new(0),
+        }
+    }
+
+    // A compare-and-swap failed because some other thread changed the
+    // structure first, and the operation has to try again.
+    #[inline]
+    pub fn cas_retry(&self) {
+        #[cfg(feature = "instrument")]
+        self.cas_retries.fetch_add(1, Relaxed);
+    }
+
+    // A bucket was moved into a new nested table to make room for a bucket
+    // with a different hash.
+    #[inline]
+    pub fn table_split(&self) {
+        #[cfg(feature = "instrument")]
+        self.table_splits.fetch_add(1, Relaxed);
+    }
+
+    // An entry with a different key was stepped over while searching a
+    // bucket.
+    #[inline]
+    pub fn chain_traversal(&self) {
+        #[cfg(feature = "instrument")]
+        self.chain_traversals.fetch_add(1, Relaxed);
+    }
+
+    // The given number of internal nodes were unlinked and handed to the
+    // incinerator, instead of being deallocated right away.
+    #[inline]
+    pub fn incin_deferrals(&self, _count: usize) {
+        #[cfg(feature = "instrument")]
+        self.incin_deferrals.fetch_add(_count, Relaxed);
+    }
+
+    #[cfg(feature = "instrument")]
+    pub fn load(&self) -> Instrumentation {
+        Instrumentation {
+            cas_retries: self.cas_retries.load(Relaxed),
+            table_splits: self.table_splits.load(Relaxed),
+            chain_traversals: self.chain_traversals.load(Relaxed),
+            incin_deferrals: self.incin_deferrals.load(Relaxed),
+        }
+    }
+}
+
+/// Counters of the internal events of a [`Map`](super::Map) since it was
+/// created, returned by
+/// [`Map::instrumentation`](super::Map::instrumentation). Only available with
+/// the `instrument` feature.
+///
+/// The counters are incremented with relaxed atomics by insertions, removals
+/// and lookups, so they are only a consistent snapshot when no operation runs
+/// concurrently. Comparing them between hashers, or over time, tells how much
+/// contention and how many collisions the map suffers.
+#[cfg(feature = "instrument")]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct Instrumentation {
+    /// The number of compare-and-swap operations which failed because some
+    /// other thread changed the map first, forcing a retry. Many retries mean
+    /// threads contend on the same part of the map.
+    pub cas_retries: usize,
+    /// The number of nested tables created because two buckets with
+    /// different hashes fell in the same node of a table.
+    pub table_splits: usize,
+    /// The number of entries with a different key stepped over while
+    /// searching a bucket, which only holds keys with the very same hash.
+    /// Anything but a tiny count means the hasher produces full collisions,
+    /// and some buckets are hot.
+    pub chain_traversals: usize,
+    /// The number of internal nodes which were unlinked and handed to the
+    /// incinerator, to be deallocated once no thread reads them anymore.
+    pub incin_deferrals: usize,
+}
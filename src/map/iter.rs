@@ -1,11 +1,20 @@
 use super::{
     bucket::{self, Bucket, Garbage},
-    guard::ReadGuard,
-    table::Table,
+    guard::{MappedGuard, ReadGuard},
+    table::{Table, NODES},
+};
+use crate::{
+    incin::Pause,
+    tagged_ptr::{is_tagged, untag},
 };
-use crate::incin::Pause;
 use alloc::vec::Vec;
-use core::{fmt, ptr::NonNull, sync::atomic::Ordering::*};
+use core::{
+    fmt,
+    iter::FusedIterator,
+    marker::PhantomData,
+    ptr::NonNull,
+    sync::atomic::{AtomicUsize, Ordering::*},
+};
 use owned_alloc::OwnedAlloc;
 
 /// An iterator over key-vaue entries of a [`Map`](super::Map). The `Item` of
@@ -14,6 +23,13 @@ use owned_alloc::OwnedAlloc;
 /// the `Map` since the iterator creation and the current call to
 /// [`next`](Iterator::next). However, it is not guaranteed to yield all items
 /// present in the `Map` at some point if the `Map` is shared between threads.
+///
+/// Since entries of a shared `Map` may be inserted or removed while iterating,
+/// the lower bound of the [`size_hint`](Iterator::size_hint) of this iterator
+/// only counts the entries it has already found, and its upper bound is only
+/// an estimate, taken from [`Map::len`](super::Map::len) minus the entries
+/// already yielded: entries inserted meanwhile may be yielded too. See
+/// [`Map::iter_exact`](super::Map::iter_exact) for an iterator of known length.
 #[derive(Debug)]
 pub struct Iter<'map, K, V>
 where
@@ -23,16 +39,45 @@ where
     pause: Pause<'map, Garbage<K, V>>,
     tables: Vec<&'map Table<K, V>>,
     curr_table: Option<(&'map Table<K, V>, usize)>,
+    // The index at which the walk of the current table stops.
+    end: usize,
     cache: Vec<ReadGuard<'map, K, V>>,
+    // The length counter of the map, if known, and how many entries were
+    // yielded, which estimate how many are left.
+    len: Option<&'map AtomicUsize>,
+    yielded: usize,
 }
 
 impl<'map, K, V> Iter<'map, K, V> {
     pub(super) fn new(pause: Pause<'map, Garbage<K, V>>, top: &'map Table<K, V>) -> Self {
+        Self::with_range(pause, top, 0, NODES)
+    }
+
+    // Walks only the nodes of the top table from `start` up to, but not
+    // including, `end`, and everything nested in them.
+    pub(super) fn with_range(
+        pause: Pause<'map, Garbage<K, V>>,
+        top: &'map Table<K, V>,
+        start: usize,
+        end: usize,
+    ) -> Self {
         Self {
             pause,
             tables: Vec::new(),
-            curr_table: Some((top, 0)),
+            curr_table: Some((top, start)),
+            end,
             cache: Vec::new(),
+            len: None,
+            yielded: 0,
+        }
+    }
+
+    // Estimates the upper bound of the size hint with the given length
+    // counter of the map.
+    pub(super) fn with_len(self, len: &'map AtomicUsize) -> Self {
+        Self {
+            len: Some(len),
+            ..self
         }
     }
 }
@@ -44,19 +89,25 @@ impl<'map, K, V> Iterator for Iter<'map, K, V> {
         loop {
             // If we have something in the cache return it.
             if let Some(guard) = self.cache.pop() {
+                self.yielded += 1;
                 break Some(guard);
             }
 
             // If the iterator was empty, let's try to get a new one from
             // another bucket.
             let (table, index) = self.curr_table?;
-            self.curr_table = match table.load_index(index, Acquire) {
+            let loaded = if index < self.end {
+                table.load_index(index, Acquire)
+            } else {
+                None
+            };
+            self.curr_table = match loaded {
                 // If the pointer is null, simply go to the next element.
                 Some(ptr) if ptr.is_null() => Some((table, index + 1)),
 
                 // If the pointer is a bucket, collect all entries into the
                 // cache.
-                Some(ptr) if ptr as usize & 1 == 0 => {
+                Some(ptr) if !is_tagged(ptr) => {
                     let ptr = ptr as *mut Bucket<K, V>;
                     let mut cache = core::mem::take(&mut self.cache);
 
@@ -76,7 +127,7 @@ impl<'map, K, V> Iterator for Iter<'map, K, V> {
 
                 // If the pointer is a table, put it on the table list.
                 Some(ptr) => {
-                    let ptr = (ptr as usize & !1) as *mut Table<K, V>;
+                    let ptr = untag(ptr) as *mut Table<K, V>;
                     // This is safe because:
                     //
                     // 1. The incinerator is paused.
@@ -92,16 +143,30 @@ impl<'map, K, V> Iterator for Iter<'map, K, V> {
                 }
 
                 // If the pointer is null, get the next table.
-                None => self.tables.pop().map(|tbl| (tbl, 0)),
+                None => {
+                    self.end = NODES;
+                    self.tables.pop().map(|tbl| (tbl, 0))
+                }
             };
         }
     }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let found = self.cache.len();
+        let left = self.len.map(|len| {
+            let len = super::clamp_len(len.load(Relaxed));
+            len.saturating_sub(self.yielded).max(found)
+        });
+        (found, left)
+    }
 }
 
+impl<'map, K, V> FusedIterator for Iter<'map, K, V> {}
+
 unsafe impl<'map, K, V> Send for Iter<'map, K, V>
 where
-    K: Send,
-    V: Send,
+    K: Sync,
+    V: Sync,
 {
 }
 
@@ -112,19 +177,131 @@ where
 {
 }
 
-/// An owned iterator over key-vaue entries of a [`Map`](super::Map).
+/// An iterator over key-value entries of a [`Map`](super::Map) under
+/// exclusive access, created by [`Map::iter_exact`](super::Map::iter_exact).
+/// Since no entry can be inserted or removed while it is alive, it knows
+/// exactly how many entries it yields.
+#[derive(Debug)]
+pub struct ExactIter<'map, K, V>
+where
+    K: 'map,
+    V: 'map,
+{
+    inner: Iter<'map, K, V>,
+    len: usize,
+}
+
+impl<'map, K, V> ExactIter<'map, K, V> {
+    pub(super) fn new(inner: Iter<'map, K, V>, len: usize) -> Self {
+        Self { inner, len }
+    }
+}
+
+impl<'map, K, V> Iterator for ExactIter<'map, K, V> {
+    type Item = ReadGuard<'map, K, V>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let guard = self.inner.next()?;
+        self.len -= 1;
+        Some(guard)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.len, Some(self.len))
+    }
+}
+
+impl<'map, K, V> ExactSizeIterator for ExactIter<'map, K, V> {}
+
+impl<'map, K, V> FusedIterator for ExactIter<'map, K, V> {}
+
+/// An iterator over the keys of a [`Map`](super::Map), created by
+/// [`Map::keys`](super::Map::keys). The `Item` of this iterator is a
+/// [`MappedGuard`] dereferencing to the key, which keeps the entry alive just
+/// like a [`ReadGuard`]. It has the same consistency as [`Iter`], which it is
+/// built on.
+#[derive(Debug)]
+pub struct Keys<'map, K, V>
+where
+    K: 'map,
+    V: 'map,
+{
+    inner: Iter<'map, K, V>,
+}
+
+impl<'map, K, V> Keys<'map, K, V> {
+    pub(super) fn new(inner: Iter<'map, K, V>) -> Self {
+        Self { inner }
+    }
+}
+
+impl<'map, K, V> Iterator for Keys<'map, K, V> {
+    type Item = MappedGuard<'map, K>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let guard = self.inner.next()?;
+        Some(ReadGuard::key_guard(guard))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl<'map, K, V> FusedIterator for Keys<'map, K, V> {}
+
+/// An iterator over the values of a [`Map`](super::Map), created by
+/// [`Map::values`](super::Map::values). The `Item` of this iterator is a
+/// [`MappedGuard`] dereferencing to the value, which keeps the entry alive just
+/// like a [`ReadGuard`]. It has the same consistency as [`Iter`], which it is
+/// built on.
+#[derive(Debug)]
+pub struct Values<'map, K, V>
+where
+    K: 'map,
+    V: 'map,
+{
+    inner: Iter<'map, K, V>,
+}
+
+impl<'map, K, V> Values<'map, K, V> {
+    pub(super) fn new(inner: Iter<'map, K, V>) -> Self {
+        Self { inner }
+    }
+}
+
+impl<'map, K, V> Iterator for Values<'map, K, V> {
+    type Item = MappedGuard<'map, V>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let guard = self.inner.next()?;
+        Some(ReadGuard::val_guard(guard))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl<'map, K, V> FusedIterator for Values<'map, K, V> {}
+
+/// An owned iterator over key-vaue entries of a [`Map`](super::Map). Tables
+/// and buckets are freed as soon as their entries are moved out, and entries
+/// not yielded are dropped along with the iterator.
 pub struct IntoIter<K, V> {
     tables: Vec<OwnedAlloc<Table<K, V>>>,
     curr_table: Option<(OwnedAlloc<Table<K, V>>, usize)>,
     entries: bucket::IntoIter<K, V>,
+    remaining: usize,
 }
 
 impl<K, V> IntoIter<K, V> {
-    pub(super) fn new(top: OwnedAlloc<Table<K, V>>) -> Self {
+    pub(super) fn new(top: OwnedAlloc<Table<K, V>>, len: usize) -> Self {
         Self {
             tables: Vec::new(),
             curr_table: Some((top, 0)),
             entries: bucket::IntoIter::empty(),
+            remaining: len,
         }
     }
 }
@@ -137,6 +314,7 @@ impl<K, V> Iterator for IntoIter<K, V> {
             // We try to run the bucket's iterator first.
             if let Some(alloc) = self.entries.next() {
                 let (pair, _) = alloc.move_inner();
+                self.remaining = self.remaining.saturating_sub(1);
                 break Some(pair);
             }
 
@@ -148,7 +326,7 @@ impl<K, V> Iterator for IntoIter<K, V> {
                 Some(ptr) if ptr.is_null() => Some((table, index + 1)),
 
                 // If the pointer is a bucket, get the new bucket iterator.
-                Some(ptr) if ptr as usize & 1 == 0 => {
+                Some(ptr) if !is_tagged(ptr) => {
                     let ptr = ptr as *mut Bucket<K, V>;
                     // This is safe because:
                     //
@@ -166,7 +344,7 @@ impl<K, V> Iterator for IntoIter<K, V> {
 
                 // If the pointer is a table, put it on the table list.
                 Some(ptr) => {
-                    let ptr = (ptr as usize & !1) as *mut Table<K, V>;
+                    let ptr = untag(ptr) as *mut Table<K, V>;
                     // This is safe because:
                     //
                     // 1. We checked for null already.
@@ -187,8 +365,16 @@ impl<K, V> Iterator for IntoIter<K, V> {
             };
         }
     }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
 }
 
+impl<K, V> ExactSizeIterator for IntoIter<K, V> {}
+
+impl<K, V> FusedIterator for IntoIter<K, V> {}
+
 impl<K, V> Drop for IntoIter<K, V> {
     fn drop(&mut self) {
         for _ in self.by_ref() {}
@@ -199,8 +385,8 @@ impl<K, V> fmt::Debug for IntoIter<K, V> {
     fn fmt(&self, fmtr: &mut fmt::Formatter) -> fmt::Result {
         write!(
             fmtr,
-            "IntoIter {{ tables: {:?}, curr_table: {:?}, entries: {:?} }}",
-            self.tables, self.curr_table, self.entries
+            "IntoIter {{ tables: {:?}, curr_table: {:?}, entries: {:?}, remaining: {} }}",
+            self.tables, self.curr_table, self.entries, self.remaining
         )
     }
 }
@@ -219,9 +405,49 @@ where
 {
 }
 
-/// An owned iterator over references to key-vaue entries of a
-/// [`Map`](super::Map). The reference to the value is mutable (but not the one
-/// to the key).
+/// A draining iterator over key-value entries of a [`Map`](super::Map),
+/// created by [`Map::drain`](super::Map::drain). The entries are moved out of
+/// the `Map`, which is already empty when this iterator is created. Entries
+/// not yielded are dropped along with the iterator.
+pub struct Drain<'map, K, V> {
+    inner: IntoIter<K, V>,
+    _map: PhantomData<&'map mut ()>,
+}
+
+impl<'map, K, V> Drain<'map, K, V> {
+    pub(super) fn new(top: OwnedAlloc<Table<K, V>>, len: usize) -> Self {
+        Self {
+            inner: IntoIter::new(top, len),
+            _map: PhantomData,
+        }
+    }
+}
+
+impl<'map, K, V> Iterator for Drain<'map, K, V> {
+    type Item = (K, V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl<'map, K, V> ExactSizeIterator for Drain<'map, K, V> {}
+
+impl<'map, K, V> FusedIterator for Drain<'map, K, V> {}
+
+impl<'map, K, V> fmt::Debug for Drain<'map, K, V> {
+    fn fmt(&self, fmtr: &mut fmt::Formatter) -> fmt::Result {
+        write!(fmtr, "Drain {{ inner: {:?} }}", self.inner)
+    }
+}
+
+/// An iterator over references to key-value entries of a
+/// [`Map`](super::Map), created by [`Map::iter_mut`](super::Map::iter_mut).
+/// The reference to the value is mutable (but not the one to the key).
 pub struct IterMut<'map, K, V>
 where
     K: 'map,
@@ -260,7 +486,7 @@ impl<'map, K, V> Iterator for IterMut<'map, K, V> {
                 Some(ptr) if ptr.is_null() => Some((table, index + 1)),
 
                 // If the pointer is a bucket, get the new bucket iterator.
-                Some(ptr) if ptr as usize & 1 == 0 => {
+                Some(ptr) if !is_tagged(ptr) => {
                     let ptr = ptr as *mut Bucket<K, V>;
                     // This is safe because:
                     //
@@ -277,7 +503,7 @@ impl<'map, K, V> Iterator for IterMut<'map, K, V> {
 
                 // If the pointer is a table, put it on the table list.
                 Some(ptr) => {
-                    let ptr = (ptr as usize & !1) as *mut Table<K, V>;
+                    let ptr = untag(ptr) as *mut Table<K, V>;
                     // This is safe because:
                     //
                     // 1. We checked for null already.
@@ -299,6 +525,8 @@ impl<'map, K, V> Iterator for IterMut<'map, K, V> {
     }
 }
 
+impl<'map, K, V> FusedIterator for IterMut<'map, K, V> {}
+
 unsafe impl<'map, K, V> Send for IterMut<'map, K, V>
 where
     K: Send,
@@ -317,8 +545,36 @@ impl<'map, K, V> fmt::Debug for IterMut<'map, K, V> {
     fn fmt(&self, fmtr: &mut fmt::Formatter) -> fmt::Result {
         write!(
             fmtr,
-            "IntoIter {{ tables: {:?}, curr_table: {:?}, entries: {:?} }}",
+            "IterMut {{ tables: {:?}, curr_table: {:?}, entries: {:?} }}",
             self.tables, self.curr_table, self.entries
         )
     }
 }
+
+/// An iterator over mutable references to the values of a
+/// [`Map`](super::Map), created by [`Map::values_mut`](super::Map::values_mut).
+/// Just like [`IterMut`], which it is built on, it needs no guards.
+#[derive(Debug)]
+pub struct ValuesMut<'map, K, V>
+where
+    K: 'map,
+    V: 'map,
+{
+    inner: IterMut<'map, K, V>,
+}
+
+impl<'map, K, V> ValuesMut<'map, K, V> {
+    pub(super) fn new(inner: IterMut<'map, K, V>) -> Self {
+        Self { inner }
+    }
+}
+
+impl<'map, K, V> Iterator for ValuesMut<'map, K, V> {
+    type Item = &'map mut V;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next().map(|(_, val)| val)
+    }
+}
+
+impl<'map, K, V> FusedIterator for ValuesMut<'map, K, V> {}
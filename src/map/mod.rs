@@ -1,34 +1,76 @@
+mod bounded;
 mod bucket;
+mod cell;
+mod entry;
+mod events;
 mod guard;
+mod init;
 mod insertion;
+mod instrument;
 mod iter;
+#[cfg(feature = "rayon")]
+mod par;
+mod snapshot;
+mod stats;
 mod table;
 
+#[cfg(feature = "instrument")]
+pub use self::instrument::Instrumentation;
+#[cfg(feature = "rayon")]
+pub use self::par::ParIter;
 pub use self::{
-    guard::{ReadGuard, Removed},
-    insertion::{Insertion, Preview},
-    iter::{IntoIter, Iter, IterMut},
+    bounded::BoundedMap,
+    cell::{AtomicCell, AtomicValue},
+    entry::Entry,
+    events::MapEvent,
+    guard::{MappedGuard, OwnedReadGuard, ReadGuard, Removed},
+    insertion::{CasError, FetchUpdateError, Insertion, OccupiedError, Preview, RemoveError},
+    iter::{Drain, ExactIter, IntoIter, Iter, IterMut, Keys, Values, ValuesMut},
+    snapshot::{Snapshot, SnapshotError},
+    stats::{MapStats, MergeStats, OptimizeStatus},
 };
-use alloc::vec::Vec;
+use alloc::{boxed::Box, sync::Arc, vec::Vec};
 
 use self::{
-    bucket::{Bucket, Garbage},
-    insertion::{InsertNew, Reinsert},
-    table::Table,
+    bucket::Garbage,
+    events::ChangeHook,
+    init::InitMarkers,
+    insertion::{InsertAbsent, InsertNew, InsertPresent, Reinsert},
+    instrument::Counters,
+    snapshot::Writes,
+    table::{Table, NODES},
+};
+use crate::{
+    footprint::MemoryFootprint,
+    hash::{DefaultState, SeededState},
+    incin::Reclaimer,
+    traits::{ApproxLen, ConcurrentInsert, ConcurrentPop},
 };
-use crate::ptr::check_null_align;
 use core::{
     borrow::Borrow,
+    cell::Cell,
     fmt,
     hash::{BuildHasher, Hash, Hasher},
     iter::FromIterator,
     mem,
+    ptr::{self, NonNull},
+    sync::atomic::{AtomicBool, AtomicUsize, Ordering::*},
 };
 use owned_alloc::OwnedAlloc;
-use std::collections::hash_map::RandomState;
+
+// How many tables `Map::optimize_space_step` collapses at most per call.
+const OPTIMIZE_STEP_TABLES: usize = 16;
+
+// The replaced entry and a guard to the new one, as returned by
+// `Map::fetch_update`.
+type Transition<'map, K, V> = (Removed<K, V>, ReadGuard<'map, K, V>);
+
+// A guard to an inserted pair and its address, as returned by
+// `Map::insert_absent`.
+type Created<'map, K, V> = (ReadGuard<'map, K, V>, NonNull<(K, V)>);
 
 /// A lock-free map. Implemented using multi-level hash-tables (in a tree
-/// fashion) with ordered buckets.
+/// fashion) with buckets of keys with equal hashes.
 ///
 /// # Design
 /// In order to implement this map, we shall fix a constant named `BITS`, which
@@ -46,38 +88,87 @@ use std::collections::hash_map::RandomState;
 /// create a sub-table, insert the old leaf into the new sub-table, and insert
 /// our pair after.
 ///
-/// Entries in a bucket are a single linked list ordered by key. The ordering
-/// of the list is because of possible race conditions if e.g. new nodes were
-/// always inserted at end. And if a bucket is detected to be empty, the
+/// Entries in a bucket are a single linked list in insertion order. A new
+/// entry is only linked after the last one, once the whole list was searched
+/// for its key, so concurrent insertions of the same key race for the same
+/// node and only one of them succeeds. Therefore, keys only need [`Eq`] and
+/// [`Hash`], not an ordering. And if a bucket is detected to be empty, the
 /// table will be requested to delete the bucket.
 ///
 /// For searching, in a similar way, the hash is shifted and sub-tables are
 /// entered until either a node is empty or a leaf is found. If the hash of the
 /// leaf's bucket is equal to our hash, we search for the entry into the bucket.
-/// Because the bucket is ordered, we may know the entry is not present with
-/// ease.
+/// Since a bucket only holds keys with the very same hash, it is usually very
+/// short.
 ///
 /// Because of limitation of sharing in concurrent contexts, we do return plain
 /// references to the entries, neither allow the user to move out removed
 /// values, as they must be deinitialized correctly. Instead, we return guarded
 /// references to the entries and wrappers over removed entries.
-pub struct Map<K, V, H = RandomState> {
+///
+/// # Panic safety
+/// The closures given to methods such as [`Map::insert_with`],
+/// [`Map::remove_with`], [`Map::reinsert_with`] and [`Map::get_or_init`] are
+/// always called before the operation publishes any change. If a closure
+/// panics, the entry it was called for is left exactly as it was, and
+/// whatever the operation owned, such as the key, a value generated by the
+/// closure or a removed entry, is dropped once. The [`Map`] remains valid.
+pub struct Map<K, V, H = DefaultState> {
     top: OwnedAlloc<Table<K, V>>,
     incin: SharedIncin<K, V>,
     builder: H,
+    inits: InitMarkers<K>,
+    // Entries created minus entries removed. A removal may be counted before
+    // the creation of its entry, so this may briefly wrap below zero.
+    len: AtomicUsize,
+    // Rotates the start of the scans of `Map::any`.
+    cursor: AtomicUsize,
+    writes: Writes,
+    counters: Counters,
+    // Whether some thread runs `Map::optimize_space_step`.
+    optimizing: AtomicBool,
+    on_change: Option<ChangeHook<K, V>>,
 }
 
 impl<K, V> Map<K, V> {
     /// Creates a new [`Map`] with the default hasher builder.
     pub fn new() -> Self {
-        check_null_align::<Table<K, V>>();
-        check_null_align::<Bucket<K, V>>();
         Self::default()
     }
 
     /// Creates the [`Map`] using the given shared incinerator.
     pub fn with_incin(incin: SharedIncin<K, V>) -> Self {
-        Self::with_hasher_and_incin(RandomState::default(), incin)
+        Self::with_hasher_and_incin(DefaultState::default(), incin)
+    }
+
+    /// Creates a new [`Map`] whose removed entries and internal garbage are
+    /// handed to the given reclaimer, such as the `EpochReclaim` of the
+    /// `crossbeam-interop` feature. Guards still keep the entries they
+    /// reference alive. See
+    /// [`Incinerator::with_reclaimer`](crate::incin::Incinerator::with_reclaimer).
+    pub fn with_reclaimer<R>(reclaimer: R) -> Self
+    where
+        K: Send + 'static,
+        V: Send + 'static,
+        R: Reclaimer,
+    {
+        Self::with_incin(SharedIncin::with_reclaimer(reclaimer))
+    }
+
+    /// Creates a new [`Map`] with the default hasher builder, ready to hold
+    /// about `capacity` entries. See [`Map::with_capacity_and_hasher`].
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self::with_capacity_and_hasher(capacity, DefaultState::default())
+    }
+}
+
+impl<K, V> Map<K, V, SeededState> {
+    /// Creates a new [`Map`] whose hasher builder is a [`SeededState`] with
+    /// the given seed. Maps created with the same seed and subjected to the
+    /// same operations iterate in the same order, which is useful for
+    /// reproducible tests.
+    pub fn with_seed(seed: [u8; 16]) -> Self {
+        Self::with_hasher(SeededState::new(seed))
     }
 }
 
@@ -87,26 +178,540 @@ impl<K, V, H> Map<K, V, H> {
         self.into_iter()
     }
 
+    /// Creates an iterator over guarded references to the entries of one
+    /// shard out of `of` shards, so that threads can split a walk of the
+    /// [`Map`] among themselves. Shards are contiguous ranges of the top-level
+    /// table: the shards `0..of` are disjoint and together cover every entry,
+    /// with the same consistency as [`Map::iter`]. Any positive `of` works,
+    /// but there are 256 top-level nodes, so with more shards than that some
+    /// shards are always empty.
+    ///
+    /// # Panics
+    /// Panics if `shard` is not less than `of`.
+    ///
+    /// # Example
+    /// ```rust
+    /// use std::{sync::Arc, thread};
+    /// use tux_lockfree::map::Map;
+    ///
+    /// let map = Arc::new((0..1000).map(|i| (i, i)).collect::<Map<_, _>>());
+    /// let workers = (0..3)
+    ///     .map(|shard| {
+    ///         let map = map.clone();
+    ///         thread::spawn(move || map.iter_shard(shard, 3).map(|g| *g.val()).sum::<i32>())
+    ///     })
+    ///     .collect::<Vec<_>>();
+    ///
+    /// let sum = workers.into_iter().map(|w| w.join().unwrap()).sum::<i32>();
+    /// assert_eq!(sum, (0..1000).sum());
+    /// ```
+    pub fn iter_shard(&self, shard: usize, of: usize) -> Iter<'_, K, V> {
+        assert!(shard < of, "shard {} out of {} shards", shard, of);
+        let bound = |shard: usize| (shard as u128 * NODES as u128 / of as u128) as usize;
+        let pause = self.incin.get_unchecked().pause();
+        Iter::with_range(pause, &self.top, bound(shard), bound(shard + 1)).with_len(&self.len)
+    }
+
+    /// Creates an iterator over clones of the key-value entries, taken at a
+    /// single point in time. Unlike [`Map::iter`], which walks the live
+    /// [`Map`] and may see a concurrent change in one part of it but not an
+    /// earlier one in another part, the entries yielded here are exactly the
+    /// contents of the [`Map`] at some moment between the call and its
+    /// return. No key is yielded twice.
+    ///
+    /// Snapshots must have been enabled with [`Map::enable_snapshots`],
+    /// otherwise [`SnapshotError::Disabled`] is returned. The entries are
+    /// cloned into a buffer before the iterator is returned. If some insertion
+    /// or removal runs while cloning, the buffer is discarded and the entries
+    /// are cloned again, and while some insertion or removal is in progress,
+    /// this method waits for it, backing off like
+    /// [`exponential_backoff`](crate::backoff::exponential_backoff). Once that
+    /// gives up, [`SnapshotError::Busy`] is returned. This happens under a
+    /// constant stream of writes, and when called from a closure passed to an
+    /// operation changing this [`Map`], which waits for this method to return.
+    /// The hook registered with [`Map::on_change`] may call it, though.
+    ///
+    /// # Example
+    /// ```rust
+    /// use tux_lockfree::map::{Map, SnapshotError};
+    ///
+    /// let mut map = Map::new();
+    /// assert_eq!(map.iter_snapshot().unwrap_err(), SnapshotError::Disabled);
+    /// map.enable_snapshots();
+    /// map.insert("a", 1);
+    /// map.insert("b", 2);
+    ///
+    /// let mut snapshot = map.iter_snapshot().unwrap().collect::<Vec<_>>();
+    /// map.insert("c", 3);
+    /// snapshot.sort();
+    /// assert_eq!(snapshot, [("a", 1), ("b", 2)]);
+    /// ```
+    pub fn iter_snapshot(&self) -> Result<Snapshot<K, V>, SnapshotError>
+    where
+        K: Clone,
+        V: Clone,
+    {
+        let mut entries = Vec::new();
+        let pause = self.incin.get_unchecked().pause();
+        self.writes.quiescent(|| {
+            entries.clear();
+            let iter = Iter::new(pause.clone(), &self.top);
+            entries.extend(iter.map(|guard| (guard.key().clone(), guard.val().clone())));
+        })?;
+        Ok(Snapshot::new(entries))
+    }
+
+    /// Makes this [`Map`] count the insertions and removals made through a
+    /// shared reference, which [`Map::iter_snapshot`] needs to tell whether
+    /// the [`Map`] changed while reading it. Counting costs every write two
+    /// atomic operations on counters shared by all threads, so it is disabled
+    /// by default. Clones of this [`Map`] count their writes too.
+    pub fn enable_snapshots(&mut self) {
+        self.writes.enable();
+    }
+
+    /// Returns the number of entries in this [`Map`], without walking it. It
+    /// is exact when no other thread modifies the [`Map`]. Otherwise, it is a
+    /// point-in-time estimate: insertions and removals running concurrently
+    /// may or may not be counted yet.
+    ///
+    /// # Example
+    /// ```rust
+    /// use tux_lockfree::map::Map;
+    ///
+    /// let map = Map::new();
+    /// assert!(map.is_empty());
+    /// map.insert("a", 1);
+    /// map.insert("b", 2);
+    /// map.insert("a", 3);
+    /// assert_eq!(map.len(), 2);
+    /// map.remove("b");
+    /// assert_eq!(map.len(), 1);
+    /// ```
+    pub fn len(&self) -> usize {
+        clamp_len(self.len.load(Acquire))
+    }
+
+    /// Returns whether this [`Map`] has no entries, with the same precision as
+    /// [`Map::len`].
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Creates an iterator over guarded references to the keys. Just like
+    /// [`Map::iter`], it keeps working while other threads insert or remove
+    /// entries. The guards dereference to the keys, so cloning a guard clones
+    /// the key.
+    ///
+    /// # Example
+    /// ```rust
+    /// use tux_lockfree::map::Map;
+    ///
+    /// let map = Map::new();
+    /// map.insert("a".to_owned(), 1);
+    /// map.insert("b".to_owned(), 2);
+    ///
+    /// let mut keys = map.keys().map(|key| key.clone()).collect::<Vec<String>>();
+    /// keys.sort();
+    /// assert_eq!(keys, ["a", "b"]);
+    /// ```
+    pub fn keys(&self) -> Keys<'_, K, V> {
+        Keys::new(self.iter())
+    }
+
+    /// Creates an iterator over guarded references to the values. Just like
+    /// [`Map::iter`], it keeps working while other threads insert or remove
+    /// entries.
+    ///
+    /// # Example
+    /// ```rust
+    /// use tux_lockfree::map::Map;
+    ///
+    /// let map = Map::new();
+    /// map.insert("a", 1);
+    /// map.insert("b", 2);
+    ///
+    /// let mut sum = 0;
+    /// for val in map.values() {
+    ///     sum += *val;
+    /// }
+    /// assert_eq!(sum, 3);
+    /// ```
+    pub fn values(&self) -> Values<'_, K, V> {
+        Values::new(self.iter())
+    }
+
+    /// Clones the keys of the entries into a vector. Entries inserted or
+    /// removed concurrently may or may not be included, but every key was in
+    /// the [`Map`] at some point during the call.
+    pub fn keys_cloned(&self) -> Vec<K>
+    where
+        K: Clone,
+    {
+        let mut keys = Vec::with_capacity(ApproxLen::len(self));
+        keys.extend(self.iter().map(|guard| guard.key().clone()));
+        keys
+    }
+
+    /// Clones the values of the entries into a vector. Entries inserted or
+    /// removed concurrently may or may not be included, but every value was
+    /// in the [`Map`] at some point during the call.
+    pub fn values_cloned(&self) -> Vec<V>
+    where
+        V: Clone,
+    {
+        let mut vals = Vec::with_capacity(ApproxLen::len(self));
+        vals.extend(self.iter().map(|guard| guard.val().clone()));
+        vals
+    }
+
+    /// Calls the given closure on every entry, just like iterating with
+    /// [`Map::iter`], but faster: the incinerator is paused once for the whole
+    /// walk and no guard is created per entry. Entries inserted or removed
+    /// concurrently may or may not be visited, but no entry is visited twice.
+    ///
+    /// The closure may call any method of this map, including the ones which
+    /// insert or remove entries. However, nothing removed is deallocated until
+    /// the walk ends, so long walks over a map with heavy removals hold on to
+    /// memory, just like a long-lived guard.
+    ///
+    /// # Example
+    /// ```rust
+    /// use tux_lockfree::map::Map;
+    ///
+    /// let map = (1..=10).map(|i| (i, i * i)).collect::<Map<_, _>>();
+    /// let mut sum = 0;
+    /// map.for_each(|_, val| sum += val);
+    /// assert_eq!(sum, 385);
+    /// ```
+    pub fn for_each<F>(&self, mut visit: F)
+    where
+        F: FnMut(&K, &V),
+    {
+        let pause = self.incin.get_unchecked().pause();
+        let mut buf = Vec::new();
+        // Safe because we paused properly, and the references gathered in the
+        // buffer do not outlive the pause.
+        unsafe { self.top.for_each(&pause, &mut buf, &mut visit) }
+    }
+
+    /// Folds every entry into an accumulator, walking the map just like
+    /// [`Map::for_each`].
+    ///
+    /// # Example
+    /// ```rust
+    /// use tux_lockfree::map::Map;
+    ///
+    /// let map = (1..=10).map(|i| (i, i * i)).collect::<Map<_, _>>();
+    /// let largest = map.fold(None, |max: Option<(i32, i32)>, &key, &val| match max {
+    ///     Some(max) if max.1 >= val => Some(max),
+    ///     _ => Some((key, val)),
+    /// });
+    /// assert_eq!(largest, Some((10, 100)));
+    /// ```
+    pub fn fold<B, F>(&self, init: B, mut fold: F) -> B
+    where
+        F: FnMut(B, &K, &V) -> B,
+    {
+        let mut acc = Some(init);
+        self.for_each(|key, val| {
+            // Always put back right after it is taken.
+            let taken = acc.take().expect("accumulator is always put back");
+            acc = Some(fold(taken, key, val));
+        });
+        acc.expect("accumulator is always put back")
+    }
+
+    /// Creates an iterator over guarded references to the key-value entries
+    /// which knows its exact length, unlike [`Map::iter`]. The exclusive
+    /// access ensures no entry is inserted or removed while iterating, so
+    /// [`Map::len`] is exact.
+    pub fn iter_exact(&mut self) -> ExactIter<'_, K, V> {
+        let len = self.len();
+        ExactIter::new(self.iter(), len)
+    }
+
+    /// Creates a parallel iterator over guarded references to the key-value
+    /// entries, with the `rayon` feature. Workers walk disjoint parts of the
+    /// map, and the incinerator stays paused until every part is done.
+    ///
+    /// # Example
+    /// ```rust
+    /// use rayon::prelude::*;
+    /// use tux_lockfree::map::Map;
+    ///
+    /// let map = Map::new();
+    /// for i in 0..10_000u64 {
+    ///     map.insert(i, i * 2);
+    /// }
+    ///
+    /// let sum = map.par_iter().map(|guard| *guard.val()).sum::<u64>();
+    /// assert_eq!(sum, 9_999 * 10_000);
+    /// ```
+    #[cfg(feature = "rayon")]
+    pub fn par_iter(&self) -> ParIter<'_, K, V> {
+        ParIter::new(self.incin.get_unchecked().pause(), &self.top)
+    }
+
+    /// Returns a value whose [`Debug`](fmt::Debug) output is the internal
+    /// structure of this [`Map`], i.e. its tables and buckets, its incinerator
+    /// and its hasher builder, instead of the entries printed by the
+    /// [`Debug`](fmt::Debug) implementation of the [`Map`].
+    ///
+    /// # Example
+    /// ```rust
+    /// use tux_lockfree::map::Map;
+    ///
+    /// let map = Map::new();
+    /// map.insert(1, 2);
+    /// assert_eq!(format!("{:?}", map), "{1: 2}");
+    /// assert!(format!("{:?}", map.debug_structure()).starts_with("Map { top_table"));
+    /// ```
+    pub fn debug_structure<'map>(&'map self) -> impl fmt::Debug + 'map
+    where
+        H: fmt::Debug,
+    {
+        DebugStructure(self)
+    }
+
+    /// Gathers statistics of the internal structure of this map, such as how
+    /// many tables it has and how deep they go. See [`MapStats`].
+    ///
+    /// # Example
+    /// ```rust
+    /// use tux_lockfree::map::Map;
+    ///
+    /// let map = Map::new();
+    /// for i in 0..1000 {
+    ///     map.insert(i, i);
+    /// }
+    ///
+    /// let stats = map.stats();
+    /// assert_eq!(stats.entries, 1000);
+    /// assert!(stats.tables >= 1);
+    /// assert!(stats.bytes > 1000 * std::mem::size_of::<(i32, i32)>());
+    /// ```
+    pub fn stats(&self) -> MapStats {
+        let pause = self.incin.get_unchecked().pause();
+        let mut stats = MapStats::default();
+        // Safe because we paused the incinerator of the map, which is the only
+        // one used with its tables.
+        unsafe { self.top.stats(1, &pause, &mut stats) };
+        stats
+    }
+
+    /// Returns the counters of the internal events of this [`Map`] since it
+    /// was created, such as failed compare-and-swaps and collisions stepped
+    /// over. Only available with the `instrument` feature.
+    ///
+    /// # Example
+    /// ```rust
+    /// use tux_lockfree::map::Map;
+    ///
+    /// let map = Map::new();
+    /// for i in 0..1000 {
+    ///     map.insert(i, i);
+    /// }
+    ///
+    /// let instrumentation = map.instrumentation();
+    /// assert!(instrumentation.table_splits >= 1);
+    /// ```
+    #[cfg(feature = "instrument")]
+    pub fn instrumentation(&self) -> Instrumentation {
+        self.counters.load()
+    }
+
     /// Creates an iterator over the key-value entries, with a mutable reference
-    /// to the value.
+    /// to the value. The exclusive access means no guard is needed, so values
+    /// can be updated in place instead of removed and reinserted.
     pub fn iter_mut(&mut self) -> IterMut<K, V> {
         self.into_iter()
     }
 
+    /// Creates an iterator over mutable references to the values, just like
+    /// [`Map::iter_mut`] without the keys.
+    ///
+    /// # Example
+    /// ```rust
+    /// use tux_lockfree::map::Map;
+    ///
+    /// let mut map = Map::new();
+    /// map.insert("a", " padded ".to_owned());
+    /// for val in map.values_mut() {
+    ///     *val = val.trim().to_owned();
+    /// }
+    /// assert_eq!(*map.get("a").unwrap().val(), "padded");
+    /// ```
+    pub fn values_mut(&mut self) -> ValuesMut<'_, K, V> {
+        ValuesMut::new(self.iter_mut())
+    }
+
+    /// Removes some of the unnecessary tables *without removing any entry*,
+    /// like [`Map::optimize_space`], but through a shared reference, while
+    /// other threads use the [`Map`]. At most a few tables are collapsed per
+    /// call, and calling it repeatedly until [`OptimizeStatus::done`] is `true`
+    /// gives the same structure as [`Map::optimize_space`], unless other
+    /// threads keep changing the [`Map`]. Unlike it, this does not clear the
+    /// delayed resource destruction.
+    ///
+    /// A table is collapsed by freezing its nodes and then replacing it in its
+    /// parent table. Lookups and removals never wait for it, but insertions
+    /// which need to change the nodes of that very table retry until the
+    /// collapse completes. If another thread already runs this method,
+    /// nothing is done.
+    ///
+    /// # Example
+    /// ```rust
+    /// use tux_lockfree::map::Map;
+    ///
+    /// let map = Map::new();
+    /// for i in 0..10000 {
+    ///     map.insert(i, i);
+    /// }
+    /// for i in 0..10000 {
+    ///     map.remove(&i);
+    /// }
+    ///
+    /// let mut collapsed = 0;
+    /// loop {
+    ///     let status = map.optimize_space_step();
+    ///     collapsed += status.collapsed;
+    ///     if status.done {
+    ///         break;
+    ///     }
+    /// }
+    /// assert!(collapsed > 0);
+    /// assert_eq!(map.stats().tables, 1);
+    /// ```
+    pub fn optimize_space_step(&self) -> OptimizeStatus {
+        if self.optimizing.swap(true, Acquire) {
+            return OptimizeStatus::default();
+        }
+
+        let pause = self.incin.get_unchecked().pause();
+        let mut budget = OPTIMIZE_STEP_TABLES;
+        // Safe because we paused properly, and no other thread collapses
+        // tables meanwhile.
+        let done = unsafe { self.top.collapse(&mut budget, &pause, &self.counters) };
+        self.optimizing.store(false, Release);
+
+        OptimizeStatus {
+            collapsed: OPTIMIZE_STEP_TABLES - budget,
+            done,
+        }
+    }
+
     /// Tries to optimize space by removing unnecessary tables *without removing
     /// any entry*. This method might also clear delayed resource destruction.
     /// This method cannot be performed in a shared context.
     pub fn optimize_space(&mut self) {
+        trace_event!(debug, "map optimized its space");
         self.incin.clear();
         self.top.optimize_space();
     }
 
+    /// Removes the entries for which the given predicate returns false, visiting
+    /// every entry exactly once. Since this method cannot be performed in a
+    /// shared context, the removed entries are freed right away instead of
+    /// going through the incinerator. If the predicate panics, the entries
+    /// already rejected are removed and the others are kept.
+    ///
+    /// # Example
+    /// ```rust
+    /// use tux_lockfree::map::Map;
+    ///
+    /// let mut map = (0..10).map(|i| (i, i * i)).collect::<Map<_, _>>();
+    /// map.retain(|_, square| square % 2 == 0);
+    /// assert_eq!(map.len(), 5);
+    /// assert!(map.get(&3).is_none());
+    /// ```
+    pub fn retain<F>(&mut self, mut pred: F)
+    where
+        F: FnMut(&K, &V) -> bool,
+    {
+        self.top.retain(&mut pred, self.len.get_mut());
+    }
+
+    /// Removes the entries for which the given predicate returns true, and
+    /// returns them in a new [`Map`] with a clone of the hasher builder and a
+    /// new incinerator. The entries are moved into the new [`Map`] without
+    /// being cloned or allocated again, and it gets the same shape of tables.
+    /// Since this method cannot be performed in a shared context, the
+    /// predicate visits every entry exactly once. If the predicate panics, the
+    /// entries already moved are dropped, and the others are kept.
+    ///
+    /// # Example
+    /// ```rust
+    /// use tux_lockfree::map::Map;
+    ///
+    /// let mut map = (0..10).map(|i| (i, i * i)).collect::<Map<_, _>>();
+    /// let odd = map.partition(|_, square| square % 2 == 1);
+    /// assert_eq!((map.len(), odd.len()), (5, 5));
+    /// assert_eq!(*odd.get(&3).unwrap().val(), 9);
+    /// assert!(map.get(&3).is_none());
+    /// ```
+    pub fn partition<F>(&mut self, mut pred: F) -> Self
+    where
+        H: Clone,
+        F: FnMut(&K, &V) -> bool,
+    {
+        let mut moved = Self {
+            top: Table::new_alloc(),
+            incin: SharedIncin::new(),
+            builder: self.builder.clone(),
+            inits: InitMarkers::new(),
+            len: AtomicUsize::new(0),
+            cursor: AtomicUsize::new(0),
+            writes: Writes::new(self.writes.is_enabled()),
+            counters: Counters::new(),
+            optimizing: AtomicBool::new(false),
+            on_change: None,
+        };
+
+        let len = *self.len.get_mut();
+        // Safe because the new table is empty, and the hasher builders are
+        // clones, just like in `Clone`.
+        unsafe {
+            self.top
+                .partition(&mut moved.top, &mut pred, self.len.get_mut())
+        };
+        *moved.len.get_mut() = len - *self.len.get_mut();
+        moved
+    }
+
+    /// Removes all entries and returns an iterator over them as owned pairs.
+    /// The [`Map`] is left empty even if the iterator is dropped before being
+    /// exhausted, in which case the remaining entries are dropped. Since this
+    /// method cannot be performed in a shared context, the pairs are moved
+    /// out without going through [`Removed`].
+    ///
+    /// # Example
+    /// ```rust
+    /// use tux_lockfree::map::Map;
+    ///
+    /// let mut map = Map::new();
+    /// map.insert("a", 1);
+    /// map.insert("b", 2);
+    ///
+    /// let mut batch = map.drain().collect::<Vec<_>>();
+    /// batch.sort();
+    /// assert_eq!(batch, [("a", 1), ("b", 2)]);
+    /// assert!(map.is_empty());
+    /// ```
+    pub fn drain(&mut self) -> Drain<'_, K, V> {
+        let len = self.len();
+        let top = mem::replace(&mut self.top, Table::new_alloc());
+        *self.len.get_mut() = 0;
+        Drain::new(top, len)
+    }
+
     /// Removes all entries. This method might also clear delayed resource
     /// destruction. This method cannot be performed in a shared context.
     pub fn clear(&mut self) {
         self.incin.clear();
         let mut tables = Vec::new();
         self.top.clear(&mut tables);
+        self.len.store(0, Relaxed);
 
         while let Some(mut table) = tables.pop() {
             // This is safe because we won't be using these tables anymore. We
@@ -114,6 +719,120 @@ impl<K, V, H> Map<K, V, H> {
             unsafe { table.free_nodes(&mut tables) }
         }
     }
+
+    /// Removes all entries through a shared reference. Unlike
+    /// [`Map::clear`], this may run while other threads use the [`Map`]: every
+    /// node of the top-level table is detached atomically and replaced by an
+    /// empty one, and the detached buckets and nested tables are handed to the
+    /// incinerator, so guards into them stay valid until released.
+    ///
+    /// The top-level table is cleared node by node, so operations running
+    /// concurrently may find some entries still there. Insertions racing with
+    /// the clear may land either in the cleared table, or in the detached
+    /// structure, in which case they are removed along with it. Either way,
+    /// every racing insertion and removal is counted once, so [`Map::len`]
+    /// stays exact.
+    ///
+    /// # Example
+    /// ```rust
+    /// use std::{sync::Arc, thread};
+    /// use tux_lockfree::map::Map;
+    ///
+    /// let map = Arc::new(Map::new());
+    /// for i in 0..100 {
+    ///     map.insert(i, i);
+    /// }
+    /// let guard = map.get(&7).unwrap();
+    ///
+    /// let cleaner = map.clone();
+    /// thread::spawn(move || cleaner.clear_shared()).join().unwrap();
+    /// assert!(map.is_empty());
+    /// assert!(map.get(&7).is_none());
+    /// assert_eq!(*guard.val(), 7);
+    /// ```
+    pub fn clear_shared(&self) {
+        let pause = self.incin.get_unchecked().pause();
+        let _writing = self.writes.begin();
+        // Safe because we paused properly.
+        let detached = unsafe { self.top.detach_nodes(&pause, &self.counters) };
+        self.len.fetch_sub(detached, AcqRel);
+    }
+
+    /// Registers the hook which receives every change made to this [`Map`]
+    /// through a shared reference, replacing any previous one. The hook is
+    /// called once per successful operation, after the change is published,
+    /// never for the attempts which had to be retried. Entries replaced by
+    /// [`Map::insert`], [`Map::update`], [`Map::swap`] and the like are
+    /// reported as [`MapEvent::Updated`].
+    ///
+    /// The hook is called by the thread which made the change, with no lock
+    /// held, so it may use the map freely, even to change it again. Changes
+    /// racing on the same key may be reported in a different order than the
+    /// one they took effect. Methods taking exclusive access, such as
+    /// [`Map::retain`] and [`Map::drain`], and [`Map::clear_shared`], which
+    /// detaches the entries in bulk, are not reported. Neither are in-place
+    /// changes of [`AtomicCell`] values. Clones of the map do not get the hook.
+    ///
+    /// # Example
+    /// ```rust
+    /// use std::sync::{Arc, Mutex};
+    /// use tux_lockfree::map::{Map, MapEvent};
+    ///
+    /// let log = Arc::new(Mutex::new(Vec::new()));
+    /// let mut map = Map::new();
+    /// let sink = log.clone();
+    /// map.on_change(move |event| {
+    ///     let entry = match event {
+    ///         MapEvent::Created { key, val } => (*key, None, Some(*val)),
+    ///         MapEvent::Updated { key, old, new } => (*key, Some(*old), Some(*new)),
+    ///         MapEvent::Removed { key, val } => (*key, Some(*val), None),
+    ///     };
+    ///     sink.lock().unwrap().push(entry);
+    /// });
+    ///
+    /// map.insert("a", 1);
+    /// map.insert("a", 2);
+    /// map.remove("a");
+    /// assert_eq!(
+    ///     *log.lock().unwrap(),
+    ///     [("a", None, Some(1)), ("a", Some(1), Some(2)), ("a", Some(2), None)]
+    /// );
+    /// ```
+    pub fn on_change<F>(&mut self, hook: F)
+    where
+        F: Fn(MapEvent<K, V>) + Send + Sync + 'static,
+    {
+        self.on_change = Some(Box::new(hook));
+    }
+
+    // Reports the insertion of the pair at `new`, which replaced `old`, if
+    // any, to the hook. Must be called once the write is over, so the hook may
+    // take snapshots, and unsafe because the caller must still be paused, so
+    // the pair is not deallocated even if some other thread removed it.
+    unsafe fn notify_insert(&self, new: NonNull<(K, V)>, old: Option<&Removed<K, V>>) {
+        if let Some(hook) = &self.on_change {
+            let (key, val) = &*new.as_ptr();
+            hook(match old {
+                Some(old) => MapEvent::Updated {
+                    key,
+                    old: old.val(),
+                    new: val,
+                },
+                None => MapEvent::Created { key, val },
+            });
+        }
+    }
+
+    // Reports the removal of an entry to the hook. Must be called once the
+    // write is over.
+    fn notify_remove(&self, removed: &Removed<K, V>) {
+        if let Some(hook) = &self.on_change {
+            hook(MapEvent::Removed {
+                key: removed.key(),
+                val: removed.val(),
+            });
+        }
+    }
 }
 
 impl<K, V, H> Map<K, V, H>
@@ -125,6 +844,39 @@ where
         Self::with_hasher_and_incin(builder, SharedIncin::new())
     }
 
+    /// Creates the [`Map`] using the given hasher builder, ready to hold about
+    /// `capacity` entries. The nested tables the first `capacity` insertions
+    /// would most likely create are allocated upfront, so those insertions
+    /// rarely need to grow the structure. Small capacities allocate nothing
+    /// more than [`Map::with_hasher`], and at most two levels of nested tables
+    /// are allocated upfront, however large the capacity. Unused tables are
+    /// freed by [`Map::optimize_space`] and [`Map::clear`].
+    ///
+    /// # Example
+    /// ```rust
+    /// use tux_lockfree::{hash::DefaultState, map::Map};
+    ///
+    /// let map = Map::with_capacity_and_hasher(10_000, DefaultState::default());
+    /// for i in 0..10_000 {
+    ///     map.insert(i, i * 2);
+    /// }
+    /// assert_eq!(map.len(), 10_000);
+    /// ```
+    pub fn with_capacity_and_hasher(capacity: usize, builder: H) -> Self {
+        Self {
+            top: Table::with_capacity_alloc(capacity),
+            incin: SharedIncin::new(),
+            builder,
+            inits: InitMarkers::new(),
+            len: AtomicUsize::new(0),
+            cursor: AtomicUsize::new(0),
+            writes: Writes::new(false),
+            counters: Counters::new(),
+            optimizing: AtomicBool::new(false),
+            on_change: None,
+        }
+    }
+
     /// Creates the [`Map`] using the given hasher builder and shared
     /// incinerator.
     pub fn with_hasher_and_incin(builder: H, incin: SharedIncin<K, V>) -> Self {
@@ -132,9 +884,52 @@ where
             top: Table::new_alloc(),
             incin,
             builder,
+            inits: InitMarkers::new(),
+            len: AtomicUsize::new(0),
+            cursor: AtomicUsize::new(0),
+            writes: Writes::new(false),
+            counters: Counters::new(),
+            optimizing: AtomicBool::new(false),
+            on_change: None,
         }
     }
 
+    /// Creates a [`Map`] with the given entries and hasher builder, building
+    /// the tables directly instead of inserting the entries one by one. The
+    /// keys are hashed and sorted first, so every table is allocated once, in
+    /// a single pass, with no atomic operation. Of entries with equal keys,
+    /// the last one is kept.
+    ///
+    /// # Example
+    /// ```rust
+    /// use tux_lockfree::{hash::DefaultState, map::Map};
+    ///
+    /// let entries = (0..10_000).map(|i| (i, i * 2)).chain([(7, 0)]).collect();
+    /// let map = Map::from_entries_bulk(entries, DefaultState::default());
+    /// assert_eq!(map.len(), 10_000);
+    /// assert_eq!(*map.get(&7).unwrap().val(), 0);
+    /// assert_eq!(*map.get(&8).unwrap().val(), 16);
+    /// ```
+    pub fn from_entries_bulk(entries: Vec<(K, V)>, builder: H) -> Self
+    where
+        K: Hash + Eq,
+    {
+        let mut map = Self::with_hasher(builder);
+        let mut pairs = entries
+            .into_iter()
+            .map(|pair| (map.hash_key(&pair.0), Some(pair)))
+            .collect::<Vec<_>>();
+        // Swapping the bytes puts the bits of the top-level index first, then
+        // the bits of the next level, and so on, just like a trie walks them.
+        // The sort is stable, so pairs with equal keys keep their order.
+        pairs.sort_by_key(|&(hash, _)| hash.swap_bytes());
+
+        let mut len = 0;
+        map.top.build(0, &mut pairs, &mut len);
+        *map.len.get_mut() = len;
+        map
+    }
+
     /// The shared incinerator used by this [`Map`].
     pub fn incin(&self) -> SharedIncin<K, V> {
         self.incin.clone()
@@ -145,639 +940,5056 @@ where
         &self.builder
     }
 
+    /// Moves every entry into a new [`Map`] using the given hasher builder,
+    /// which places the entries according to the new hashes. The keys and
+    /// values are moved, never cloned. The new [`Map`] keeps the shared
+    /// incinerator of this one.
+    ///
+    /// # Example
+    /// ```rust
+    /// use std::collections::hash_map::RandomState;
+    /// use tux_lockfree::map::Map;
+    ///
+    /// let map = Map::new();
+    /// map.insert(1, "one");
+    /// map.insert(2, "two");
+    ///
+    /// let map = map.into_with_hasher(RandomState::new());
+    /// assert_eq!(map.len(), 2);
+    /// assert_eq!(*map.get(&2).unwrap().val(), "two");
+    /// ```
+    pub fn into_with_hasher<H2>(self, builder: H2) -> Map<K, V, H2>
+    where
+        H2: BuildHasher,
+        K: Hash + Eq,
+    {
+        let rehashed = Map::with_hasher_and_incin(builder, self.incin());
+        rehashed.extend(self);
+        rehashed
+    }
+
+    /// Replaces the hasher builder with the given one and moves every entry
+    /// to the place given by its new hash, e.g. to use a new seed. The keys
+    /// and values are moved, never cloned. This method cannot be performed in
+    /// a shared context.
+    ///
+    /// # Example
+    /// ```rust
+    /// use tux_lockfree::{hash::SeededState, map::Map};
+    ///
+    /// let mut map = Map::with_seed([1; 16]);
+    /// map.insert("a", 1);
+    /// map.rehash_with(SeededState::new([2; 16]));
+    /// assert_eq!(*map.get("a").unwrap().val(), 1);
+    /// ```
+    pub fn rehash_with(&mut self, builder: H)
+    where
+        K: Hash + Eq,
+    {
+        let len = self.len();
+        let top = mem::replace(&mut self.top, Table::new_alloc());
+        *self.len.get_mut() = 0;
+        self.builder = builder;
+        self.extend(IntoIter::new(top, len));
+    }
+
     /// Searches for the entry identified by the given key. The returned value
     /// is a guarded reference. Guarded to ensure no thread deallocates the
     /// allocation for the entry while it is being used. The method accepts
     /// a type resulted from borrowing the stored key. This method will only
-    /// work correctly if [`Hash`] and [`Ord`] are implemented in the same way
+    /// work correctly if [`Hash`] and [`Eq`] are implemented in the same way
     /// for the borrowed type and the stored type. If the entry was not
     /// found, [`None`] is returned.
     pub fn get<'map, Q>(&'map self, key: &Q) -> Option<ReadGuard<'map, K, V>>
     where
-        Q: ?Sized + Hash + Ord,
+        Q: ?Sized + Hash + Eq,
         K: Borrow<Q>,
     {
-        let hash = self.hash_of(key);
-        let pause = self.incin.get_unchecked().pause();
-        // Safe because we paused properly.
-        unsafe { self.top.get(key, hash, pause) }
+        self.get_hashed(self.hash_key(key), key)
     }
 
-    /// Inserts unconditionally the given key and value. If there was a
-    /// previously stored value, it is returned.
-    pub fn insert(&self, key: K, val: V) -> Option<Removed<K, V>>
+    /// Computes the hash of the given key with the hasher of this map. The
+    /// hash can be given to [`Map::get_hashed`] and [`Map::insert_hashed`],
+    /// and to the same methods of any other map whose hasher builder is equal
+    /// to this one's, so that a key looked up in many maps is hashed only
+    /// once.
+    pub fn hash_key<Q>(&self, key: &Q) -> u64
     where
-        K: Hash + Ord,
+        Q: ?Sized + Hash,
     {
-        let pause = self.incin.get_unchecked().pause();
-        let hash = self.hash_of(&key);
-        // Safe because we paused properly.
-        let insertion = unsafe {
-            self.top.insert(
-                InsertNew::with_pair(|_, _, _| Preview::Keep, (key, val)),
-                hash,
-                &pause,
-                self.incin.get_unchecked(),
-            )
-        };
-
-        match insertion {
-            Insertion::Created => None,
-            Insertion::Updated(old) => Some(old),
-            Insertion::Failed(_) => unreachable!(),
-        }
+        let mut hasher = self.builder.build_hasher();
+        key.hash(&mut hasher);
+        hasher.finish()
     }
 
-    /// Inserts _interactively_ the given key. A closure is passed to generate
-    /// the value part of the entry and validate it with the found value. Even
-    /// though the closure may have already accepted some condition, it might
-    /// get recalled many times due to concurrent modifications of the [`Map`].
+    /// Searches for the entry identified by the given key, just like
+    /// [`Map::get`], but with the hash of the key already computed by
+    /// [`Map::hash_key`]. The key is still compared with the stored ones.
     ///
-    /// The first argument passed to the closure is the key passed in first
-    /// place. The second argument is an optional mutable reference to a
-    /// previously generated value. Obviously, if no value was ever generated,
-    /// it is [`None`]. The third argument is a reference to the found stored
-    /// entry. Obviously, if no stored entry was found, it is `None`. The return
-    /// value of the closure is a specification of "what to do with the
-    /// insertion now".
-    pub fn insert_with<F>(&self, key: K, interactive: F) -> Insertion<K, V, (K, Option<V>)>
+    /// The hash must be the one of the key under the hasher of this map.
+    /// Otherwise, the entry is simply not found, even if present.
+    ///
+    /// # Example
+    /// ```rust
+    /// use tux_lockfree::map::Map;
+    ///
+    /// let map = Map::new();
+    /// map.insert(String::from("long key"), 1);
+    /// let hash = map.hash_key("long key");
+    /// assert_eq!(*map.get_hashed(hash, "long key").unwrap().val(), 1);
+    /// assert!(map.get_hashed(hash, "other key").is_none());
+    /// ```
+    pub fn get_hashed<'map, Q>(&'map self, hash: u64, key: &Q) -> Option<ReadGuard<'map, K, V>>
     where
-        K: Hash + Ord,
-        F: FnMut(&K, Option<&mut V>, Option<&(K, V)>) -> Preview<V>,
+        Q: ?Sized + Eq,
+        K: Borrow<Q>,
     {
-        let hash = self.hash_of(&key);
         let pause = self.incin.get_unchecked().pause();
         // Safe because we paused properly.
-        let insertion = unsafe {
-            self.top.insert(
-                InsertNew::with_key(interactive, key),
-                hash,
-                &pause,
-                self.incin.get_unchecked(),
-            )
-        };
-
-        match insertion {
-            Insertion::Created => Insertion::Created,
-            Insertion::Updated(old) => Insertion::Updated(old),
-            Insertion::Failed(inserter) => Insertion::Failed(inserter.into_pair()),
-        }
+        unsafe { self.top.get(key, hash, pause, &self.counters) }
     }
 
-    /// Reinserts a previously removed entry. The entry must have been either:
+    /// Searches for the entry identified by the given key, just like
+    /// [`Map::get`], but panics if it is not found. Meant for code which
+    /// already knows the key is present, such as tests, where it makes a
+    /// failure more descriptive than [`Option::unwrap`].
     ///
-    /// 1. Removed from any [`Map`] using the same [`SharedIncin`] as this
-    /// [`Map`]. 2. Removed from an already dead [`Map`] with dead
-    /// [`SharedIncin`]. 3. Removed from a [`Map`] whose `SharedIncin` has
-    /// no sensitive reads active.
+    /// # Panics
+    /// Panics with a message showing the key if the entry was not found.
     ///
-    /// If the removed entry does not fit any category, the insertion will fail.
-    /// Otherwise, insertion cannot fail.
-    pub fn reinsert(&self, mut removed: Removed<K, V>) -> Insertion<K, V, Removed<K, V>>
+    /// # Example
+    /// ```rust
+    /// use tux_lockfree::map::Map;
+    ///
+    /// let map = Map::new();
+    /// map.insert("a".to_owned(), 1);
+    /// assert_eq!(*map.get_expect("a").val(), 1);
+    /// ```
+    #[track_caller]
+    pub fn get_expect<'map, Q>(&'map self, key: &Q) -> ReadGuard<'map, K, V>
     where
-        K: Hash + Ord,
+        Q: ?Sized + Hash + Eq + fmt::Debug,
+        K: Borrow<Q>,
     {
-        if !Removed::is_usable_by(&mut removed, self.incin.get_unchecked()) {
-            return Insertion::Failed(removed);
+        match self.get(key) {
+            Some(guard) => guard,
+            None => panic!("no entry found in the map for key {:?}", key),
         }
+    }
 
-        let hash = self.hash_of(removed.key());
-
+    /// Searches for the entries identified by each of the given keys, just
+    /// like calling [`Map::get`] for each of them, but pausing the incinerator
+    /// only once for the whole batch. The results are in the same order as
+    /// the keys.
+    ///
+    /// # Example
+    /// ```rust
+    /// use tux_lockfree::map::Map;
+    ///
+    /// let map = Map::new();
+    /// map.insert(1, "one");
+    /// map.insert(3, "three");
+    ///
+    /// let found = map.get_many(&[3, 2, 1]);
+    /// let vals = found
+    ///     .iter()
+    ///     .map(|guard| guard.as_ref().map(|guard| *guard.val()))
+    ///     .collect::<Vec<_>>();
+    /// assert_eq!(vals, [Some("three"), None, Some("one")]);
+    /// ```
+    pub fn get_many<'map, 'key, Q, I>(&'map self, keys: I) -> Vec<Option<ReadGuard<'map, K, V>>>
+    where
+        Q: ?Sized + Hash + Eq + 'key,
+        K: Borrow<Q>,
+        I: IntoIterator<Item = &'key Q>,
+    {
+        // Every guard found holds its own pause, but keeping this one active
+        // meanwhile means the incinerator is never resumed in between.
         let pause = self.incin.get_unchecked().pause();
-        // Safe because we paused properly.
-        let insertion = unsafe {
-            self.top.insert(
-                Reinsert::new(|_, _| true, removed),
-                hash,
-                &pause,
-                self.incin.get_unchecked(),
-            )
-        };
-
-        match insertion {
-            Insertion::Created => Insertion::Created,
-            Insertion::Updated(old) => Insertion::Updated(old),
-            Insertion::Failed(_) => unreachable!(),
-        }
+        keys.into_iter()
+            .map(|key| {
+                // Safe because we paused properly.
+                unsafe {
+                    self.top
+                        .get(key, self.hash_key(key), pause.clone(), &self.counters)
+                }
+            })
+            .collect()
     }
 
-    /// Reinserts _interactively_ a previously removed entry. A closure will be
-    /// passed to validate if the conditions are correct for the reinsertion.
-    /// The first argument passed to the closure is a reference to the removed
-    /// entry, the second argument is a reference to the stored found entry.
-    /// Obviously, if no stored entry was found, the argument is [`None`]. The
-    /// returned value is a boolean indicating if the reinsertion should go on.
-    /// Even though the closure may have already accepted some condition, it
-    /// might get recalled many times due to concurrent modifications of the
-    /// [`Map`].
+    /// Returns some entry of this [`Map`], or `None` if it is empty. Each call
+    /// starts scanning from a different place of the table, so repeated calls
+    /// spread over the entries instead of always returning the same one, which
+    /// suits taking items from a pool. No entry is removed. Which entry is
+    /// returned is unspecified, and there is no guarantee every entry is
+    /// eventually returned.
     ///
-    /// The entry must have been either:
+    /// # Example
+    /// ```rust
+    /// use tux_lockfree::map::Map;
     ///
-    /// 1. Removed from any [`Map`] using the same [`SharedIncin`] as this
-    /// [`Map`]. 2. Removed from an already dead [`Map`] with dead
-    /// `SharedIncin`. 3. Removed from a [`Map`] whose `SharedIncin` has no
-    /// sensitive reads active.
+    /// let map = Map::new();
+    /// assert!(map.any().is_none());
     ///
-    /// If the removed entry does not fit any category, the insertion will fail.
-    /// Otherwise, insertion cannot fail.
-    pub fn reinsert_with<F>(
-        &self,
-        mut removed: Removed<K, V>,
-        interactive: F,
-    ) -> Insertion<K, V, Removed<K, V>>
-    where
-        K: Hash + Ord,
-        F: FnMut(&(K, V), Option<&(K, V)>) -> bool,
-    {
-        if !Removed::is_usable_by(&mut removed, self.incin.get_unchecked()) {
-            return Insertion::Failed(removed);
-        }
-
-        let hash = self.hash_of(removed.key());
+    /// map.insert(1, "one");
+    /// map.insert(2, "two");
+    /// let guard = map.any().unwrap();
+    /// assert!(*guard.key() == 1 || *guard.key() == 2);
+    /// ```
+    pub fn any<'map>(&'map self) -> Option<ReadGuard<'map, K, V>> {
+        self.any_from(self.cursor.fetch_add(1, Relaxed) as u64)
+    }
 
+    /// Like [`Map::any`], but the place where the scan starts is derived from
+    /// the given seed instead of rotating. The same seed on the same entries
+    /// returns the same entry, which makes this deterministic for tests.
+    pub fn any_from<'map>(&'map self, seed: u64) -> Option<ReadGuard<'map, K, V>> {
+        // Consecutive seeds scatter over the slots of every table level.
+        let spread = seed.wrapping_mul(0x9E37_79B9_7F4A_7C15);
         let pause = self.incin.get_unchecked().pause();
         // Safe because we paused properly.
-        let insertion = unsafe {
-            self.top.insert(
-                Reinsert::new(interactive, removed),
-                hash,
-                &pause,
-                self.incin.get_unchecked(),
-            )
-        };
-
-        match insertion {
-            Insertion::Created => Insertion::Created,
-            Insertion::Updated(old) => Insertion::Updated(old),
-            Insertion::Failed(inserter) => Insertion::Failed(inserter.into_removed()),
-        }
+        unsafe { self.top.any(spread, &pause) }
     }
 
-    /// Removes unconditionally the entry identified by the given key. If no
-    /// entry was found, [`None`] is returned. This method will only work
-    /// correctly if [`Hash`] and [`Ord`] are implemented in the same way for
-    /// the borrowed type and the stored type. If the entry was not found,
-    /// `None` is returned.
-    pub fn remove<Q>(&self, key: &Q) -> Option<Removed<K, V>>
+    /// Returns whether the entry identified by the given key is present. It
+    /// accepts borrowed keys just like [`Map::get`], but no guard is created:
+    /// only keys are compared, and the incinerator is only paused while
+    /// searching.
+    ///
+    /// # Example
+    /// ```rust
+    /// use tux_lockfree::map::Map;
+    ///
+    /// let map = Map::new();
+    /// map.insert("a".to_owned(), 1);
+    /// assert!(map.contains_key("a"));
+    /// assert!(!map.contains_key("b"));
+    /// ```
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
     where
-        Q: ?Sized + Hash + Ord,
+        Q: ?Sized + Hash + Eq,
         K: Borrow<Q>,
     {
-        self.remove_with(key, |_| true)
+        let hash = self.hash_key(key);
+        let pause = self.incin.get_unchecked().pause();
+        // Safe because we paused properly.
+        unsafe { self.top.contains_key(key, hash, &pause, &self.counters) }
     }
 
-    /// Removes _interactively_ the entry identified by the given key. A closure
-    /// is passed to validate the removal. The only argument passed to the
-    /// closure is a reference to the found entry. The closure returns if the
-    /// removal should go on. If no entry was found, `None` is returned. This
-    /// method will only work correctly if [`Hash`] and [`Ord`] are implemented
-    /// in the same way for the borrowed type and the stored type. If the
-    /// entry was not found, [`None`] is returned.
-    pub fn remove_with<Q, F>(&self, key: &Q, interactive: F) -> Option<Removed<K, V>>
+    /// Searches for the entry identified by the given key, just like
+    /// [`Map::get`], and clones its value. No guard outlives the call, so the
+    /// value can be kept across await points or stored in structures.
+    ///
+    /// # Example
+    /// ```rust
+    /// use tux_lockfree::map::Map;
+    ///
+    /// let map = Map::new();
+    /// map.insert("name".to_owned(), "lockfree".to_owned());
+    /// assert_eq!(map.get_value("name").as_deref(), Some("lockfree"));
+    /// assert_eq!(map.get_value("version"), None);
+    /// ```
+    pub fn get_value<Q>(&self, key: &Q) -> Option<V>
     where
-        Q: ?Sized + Hash + Ord,
+        Q: ?Sized + Hash + Eq,
         K: Borrow<Q>,
-        F: FnMut(&(K, V)) -> bool,
+        V: Clone,
     {
-        let hash = self.hash_of(key);
-        let pause = self.incin.get_unchecked().pause();
-        // Safe because we paused properly.
-        unsafe {
-            self.top
-                .remove(key, interactive, hash, &pause, self.incin.get_unchecked())
-        }
+        self.get_and(key, V::clone)
     }
 
-    /// Acts just like [`Extend::extend`] but does not require mutability.
-    pub fn extend<I>(&self, iterable: I)
+    /// Searches for the entry identified by the given key, just like
+    /// [`Map::get`], and maps its value through the given closure, which runs
+    /// while the entry is guarded. Only the result of the closure leaves the
+    /// call, so no guard can be held for longer than needed. Unlike
+    /// [`Map::get_value`], the value does not need to implement [`Clone`].
+    ///
+    /// # Example
+    /// ```rust
+    /// use tux_lockfree::map::Map;
+    ///
+    /// let map = Map::new();
+    /// map.insert(1, vec![1, 2, 3]);
+    /// assert_eq!(map.get_and(&1, |vals| vals.len()), Some(3));
+    /// assert_eq!(map.get_and(&2, |vals| vals.len()), None);
+    /// ```
+    pub fn get_and<Q, F, T>(&self, key: &Q, read: F) -> Option<T>
     where
-        I: IntoIterator<Item = (K, V)>,
-        K: Hash + Ord,
+        Q: ?Sized + Hash + Eq,
+        K: Borrow<Q>,
+        F: FnOnce(&V) -> T,
     {
-        for (key, val) in iterable {
-            self.insert(key, val);
-        }
+        self.get_pair_and(key, |_, val| read(val))
     }
 
-    fn hash_of<Q>(&self, key: &Q) -> u64
+    /// Searches for the entry identified by the given key, just like
+    /// [`Map::get_and`], but passes both the stored key and the value to the
+    /// closure. The stored key may differ from the searched one in ways
+    /// [`Eq`] ignores, such as the case of a case-insensitive key.
+    ///
+    /// # Example
+    /// ```rust
+    /// use tux_lockfree::map::Map;
+    ///
+    /// let map = Map::new();
+    /// map.insert("port".to_owned(), 8080);
+    /// let line = map.get_pair_and("port", |key, val| format!("{}={}", key, val));
+    /// assert_eq!(line.as_deref(), Some("port=8080"));
+    /// ```
+    pub fn get_pair_and<Q, F, T>(&self, key: &Q, read: F) -> Option<T>
     where
-        Q: ?Sized + Hash,
+        Q: ?Sized + Hash + Eq,
+        K: Borrow<Q>,
+        F: FnOnce(&K, &V) -> T,
     {
-        let mut hasher = self.builder.build_hasher();
-        key.hash(&mut hasher);
-        hasher.finish()
+        self.get(key).map(|guard| read(guard.key(), guard.val()))
     }
-}
 
-impl<K, V, H> Default for Map<K, V, H>
-where
-    H: BuildHasher + Default,
-{
-    fn default() -> Self {
-        Self::with_hasher(H::default())
+    /// Same as [`Map::get_and`], named after [`Map::get_value`].
+    pub fn get_value_with<Q, F, T>(&self, key: &Q, read: F) -> Option<T>
+    where
+        Q: ?Sized + Hash + Eq,
+        K: Borrow<Q>,
+        F: FnOnce(&V) -> T,
+    {
+        self.get_and(key, read)
     }
-}
 
-impl<K, V, H> fmt::Debug for Map<K, V, H>
-where
-    H: fmt::Debug,
-{
-    fn fmt(&self, fmtr: &mut fmt::Formatter) -> fmt::Result {
-        write!(
-            fmtr,
-            "Map {{ top_table: {:?}, incin: {:?}, build_hasher: {:?} }}",
-            self.top,
-            self.incin.get_unchecked(),
-            self.builder
-        )
+    /// Searches for the entry identified by the given key, just like
+    /// [`Map::get`], but returns an owned guard which is not bound to a borrow
+    /// of the [`Map`]. The guard keeps a handle to the [`Map`], and so it can
+    /// be stored in structures or moved into other tasks.
+    ///
+    /// # Example
+    /// ```rust
+    /// use std::sync::Arc;
+    /// use tux_lockfree::map::{Map, OwnedReadGuard};
+    ///
+    /// struct Task {
+    ///     config: OwnedReadGuard<&'static str, u32>,
+    /// }
+    ///
+    /// let map = Arc::new(Map::new());
+    /// map.insert("retries", 3);
+    /// let task = Task {
+    ///     config: map.get_owned("retries").unwrap(),
+    /// };
+    /// drop(map);
+    /// assert_eq!(*task.config.val(), 3);
+    /// ```
+    pub fn get_owned<Q>(self: &Arc<Self>, key: &Q) -> Option<OwnedReadGuard<K, V, H>>
+    where
+        Q: ?Sized + Hash + Eq,
+        K: Borrow<Q>,
+    {
+        let guard = self.get(key)?;
+        // The owned pause must be acquired while the borrowed one is still
+        // active, so the entry cannot be reclaimed in between.
+        let pause = self.incin.get_unchecked().owned_pause();
+        Some(OwnedReadGuard::new(&guard, pause, self.clone()))
     }
-}
 
-impl<K, V, H> Drop for Map<K, V, H> {
-    fn drop(&mut self) {
-        let mut tables = Vec::new();
+    /// Searches for the entry identified by the given key, and if it is not
+    /// found, inserts a value computed by the given closure. The closure runs
+    /// at most once among threads racing to initialize the same key: the
+    /// other threads wait until the value is published and then return it.
+    /// If the closure panics, nothing is inserted and waiting threads retry
+    /// the initialization with their own closures.
+    ///
+    /// Calling this method for the same key from within the closure
+    /// deadlocks, while calling it for other keys does not. Entries inserted by other methods are not coordinated with
+    /// the closure: if one appears while the closure runs, it is returned and
+    /// the computed value is dropped.
+    ///
+    /// # Example
+    /// ```rust
+    /// use tux_lockfree::map::Map;
+    ///
+    /// let map = Map::new();
+    /// assert_eq!(*map.get_or_init("answer", || 42).val(), 42);
+    /// assert_eq!(*map.get_or_init("answer", || unreachable!()).val(), 42);
+    /// ```
+    pub fn get_or_init<'map, F>(&'map self, key: K, init: F) -> ReadGuard<'map, K, V>
+    where
+        K: Hash + Eq,
+        F: FnOnce() -> V,
+    {
+        let hash = self.hash_key(&key);
+        let marker = InitMarkers::<K>::marker(hash);
 
-        // Safe because we won't use these nodes anymore. We are in the
-        // destructor.
-        unsafe { self.top.free_nodes(&mut tables) }
+        loop {
+            if let Some(guard) = self.get_hashed(hash, &key) {
+                break guard;
+            }
 
-        while let Some(mut table) = tables.pop() {
-            // Safe because we won't use these nodes anymore. We are in the
-            // destructor.
-            unsafe { table.free_nodes(&mut tables) }
+            // Safe because the claim is detached before the key is moved.
+            match unsafe { self.inits.claim(marker, &key) } {
+                Some(claim) => {
+                    // The previous holder of the key may have published the
+                    // value after our search.
+                    if let Some(guard) = self.get_hashed(hash, &key) {
+                        break guard;
+                    }
+                    let val = init();
+                    claim.detach();
+                    // The claim is released before the insertion is notified,
+                    // so the callbacks may initialize any key.
+                    break self.publish_then((key, val), hash, move || drop(claim));
+                }
+
+                None => self.inits.wait(marker, &key),
+            }
         }
     }
-}
-
-impl<'map, K, V, H> IntoIterator for &'map Map<K, V, H> {
-    type Item = ReadGuard<'map, K, V>;
-
-    type IntoIter = Iter<'map, K, V>;
 
-    fn into_iter(self) -> Self::IntoIter {
-        Iter::new(self.incin.get_unchecked().pause(), &self.top)
+    /// Searches for the entry identified by the given key, and if it is not
+    /// found, inserts the given value. Returns a guard to whichever entry
+    /// ended up stored, hashing the key only once. The value is only consumed
+    /// if this call created the entry. Otherwise, it is dropped.
+    ///
+    /// # Example
+    /// ```rust
+    /// use tux_lockfree::map::Map;
+    ///
+    /// let map = Map::new();
+    /// assert_eq!(*map.get_or_insert("retries", 3).val(), 3);
+    /// assert_eq!(*map.get_or_insert("retries", 5).val(), 3);
+    /// ```
+    pub fn get_or_insert<'map>(&'map self, key: K, val: V) -> ReadGuard<'map, K, V>
+    where
+        K: Hash + Eq,
+    {
+        let hash = self.hash_key(&key);
+        match self.get_hashed(hash, &key) {
+            Some(guard) => guard,
+            None => self.publish((key, val), hash),
+        }
     }
-}
 
-impl<'map, K, V, H> IntoIterator for &'map mut Map<K, V, H> {
-    type Item = (&'map K, &'map mut V);
-
-    type IntoIter = IterMut<'map, K, V>;
-
-    fn into_iter(self) -> Self::IntoIter {
-        IterMut::new(&mut self.top)
+    /// Searches for the entry identified by the given key, and if it is not
+    /// found, inserts a value built by the given closure, just like
+    /// [`Map::get_or_insert`]. The closure is called at most once, only after
+    /// the key was found absent, and its value is kept across retries. If
+    /// another thread inserts the key in the meantime, the built value is
+    /// dropped and the existing entry is returned.
+    ///
+    /// Unlike [`Map::get_or_init`], threads racing on the same key may each
+    /// call their closure, although only one value is stored.
+    ///
+    /// # Example
+    /// ```rust
+    /// use tux_lockfree::map::Map;
+    ///
+    /// let map = Map::new();
+    /// map.insert("a", vec![1]);
+    /// assert_eq!(*map.get_or_insert_with("a", || unreachable!()).val(), [1]);
+    /// assert_eq!(*map.get_or_insert_with("b", || vec![2]).val(), [2]);
+    /// ```
+    pub fn get_or_insert_with<'map, F>(&'map self, key: K, make: F) -> ReadGuard<'map, K, V>
+    where
+        K: Hash + Eq,
+        F: FnOnce() -> V,
+    {
+        let hash = self.hash_key(&key);
+        match self.get_hashed(hash, &key) {
+            Some(guard) => guard,
+            None => self.publish((key, make()), hash),
+        }
     }
-}
-
-impl<K, V, H> IntoIterator for Map<K, V, H> {
-    type Item = (K, V);
-
-    type IntoIter = IntoIter<K, V>;
 
-    fn into_iter(mut self) -> Self::IntoIter {
-        let raw = self.top.raw();
-        // Unfortunately, this unsafe is needed since there is no other way of
-        // dropping the field and forgetting the Map.
-        unsafe {
-            (&mut self.builder as *mut H).drop_in_place();
-            (&mut self.incin as *mut SharedIncin<K, V>).drop_in_place();
-            mem::forget(self);
-            IntoIter::new(OwnedAlloc::from_raw(raw))
-        }
+    /// Gets the entry of the given key, to insert or modify it with the
+    /// methods of [`Entry`]. Nothing is looked up until a terminal method of
+    /// the [`Entry`] is called. See [`Entry`] for how it behaves when other
+    /// threads change the [`Map`] meanwhile.
+    ///
+    /// # Example
+    /// ```rust
+    /// use tux_lockfree::map::Map;
+    ///
+    /// let map = Map::new();
+    /// let guard = map.entry("a").or_insert_with(Vec::new);
+    /// assert!(guard.val().is_empty());
+    ///
+    /// let guard = map
+    ///     .entry("a")
+    ///     .and_modify(|vals| {
+    ///         let mut vals = vals.clone();
+    ///         vals.push(1);
+    ///         vals
+    ///     })
+    ///     .or_default();
+    /// assert_eq!(*guard.val(), [1]);
+    /// ```
+    pub fn entry<'map>(&'map self, key: K) -> Entry<'map, K, V, H> {
+        Entry::new(self, key)
     }
-}
 
-impl<K, V, H> Extend<(K, V)> for Map<K, V, H>
-where
-    H: BuildHasher,
-    K: Hash + Ord,
-{
-    fn extend<I>(&mut self, iterable: I)
+    /// Inserts unconditionally the given key and value. If there was a
+    /// previously stored value, it is returned.
+    pub fn insert(&self, key: K, val: V) -> Option<Removed<K, V>>
     where
-        I: IntoIterator<Item = (K, V)>,
+        K: Hash + Eq,
     {
-        (*self).extend(iterable)
+        self.insert_hashed(self.hash_key(&key), key, val)
     }
-}
 
-impl<K, V, H> FromIterator<(K, V)> for Map<K, V, H>
-where
-    H: BuildHasher + Default,
-    K: Hash + Ord,
-{
-    fn from_iter<I>(iterable: I) -> Self
+    /// Inserts unconditionally the given key and value, just like
+    /// [`Map::insert`], but with the hash of the key already computed by
+    /// [`Map::hash_key`].
+    ///
+    /// The hash must be the one of the key under the hasher of this map.
+    /// Otherwise, the entry is stored where no lookup finds it, and another
+    /// entry with the same key may be stored as well; memory safety is not
+    /// affected.
+    ///
+    /// # Example
+    /// ```rust
+    /// use tux_lockfree::map::Map;
+    ///
+    /// let first = Map::new();
+    /// let second = Map::with_hasher(first.hasher().clone());
+    /// let hash = first.hash_key("key");
+    /// first.insert_hashed(hash, "key", 1);
+    /// second.insert_hashed(hash, "key", 2);
+    /// assert_eq!(*second.get("key").unwrap().val(), 2);
+    /// assert_eq!(*first.insert_hashed(hash, "key", 3).unwrap().val(), 1);
+    /// ```
+    pub fn insert_hashed(&self, hash: u64, key: K, val: V) -> Option<Removed<K, V>>
     where
-        I: IntoIterator<Item = (K, V)>,
+        K: Eq,
     {
-        let this = Self::default();
-        this.extend(iterable);
-        this
+        let pause = self.incin.get_unchecked().pause();
+        let inserter = InsertNew::with_pair(|_, _, _| Preview::Keep, (key, val));
+        let nnptr = inserter.raw();
+        let writing = self.writes.begin();
+        // Safe because we paused properly.
+        let insertion = unsafe {
+            self.top.insert(
+                inserter,
+                hash,
+                &pause,
+                self.incin.get_unchecked(),
+                &self.counters,
+            )
+        };
+        drop(writing);
+
+        let old = match insertion {
+            Insertion::Created => {
+                self.len.fetch_add(1, AcqRel);
+                None
+            }
+            Insertion::Updated(old) => Some(old),
+            Insertion::Failed(_) => unreachable!(),
+        };
+        // Safe because we are still paused.
+        unsafe { self.notify_insert(nnptr, old.as_ref()) };
+        old
     }
-}
 
-unsafe impl<K, V, H> Send for Map<K, V, H>
-where
-    K: Send,
-    V: Send,
-    H: Send,
-{
-}
+    /// Inserts the given key and value only if the key is absent, and returns
+    /// a guard to the inserted entry. Otherwise, the key and value are given
+    /// back in the error, along with a guard to the stored entry which
+    /// prevented the insertion. The stored entry is the one found by the
+    /// insertion itself, so it is never searched for again.
+    ///
+    /// This inherent method takes precedence over
+    /// [`ConcurrentInsert::try_insert`] in method calls, which is still
+    /// available through the trait.
+    ///
+    /// # Example
+    /// ```rust
+    /// use tux_lockfree::map::Map;
+    ///
+    /// let map = Map::new();
+    /// assert_eq!(*map.try_insert("a", 1).unwrap().val(), 1);
+    ///
+    /// let error = map.try_insert("a", 2).unwrap_err();
+    /// assert_eq!(error.pair, ("a", 2));
+    /// assert_eq!(*error.current.val(), 1);
+    /// ```
+    pub fn try_insert<'map>(
+        &'map self,
+        key: K,
+        val: V,
+    ) -> Result<ReadGuard<'map, K, V>, OccupiedError<'map, K, V>>
+    where
+        K: Hash + Eq,
+    {
+        let pause = self.incin.get_unchecked().pause();
+        let hash = self.hash_key(&key);
+        let inserter = InsertAbsent::new((key, val));
+        let nnptr = inserter.raw();
+        let writing = self.writes.begin();
+        // Safe because we paused properly.
+        let insertion = unsafe {
+            self.top.insert(
+                inserter,
+                hash,
+                &pause,
+                self.incin.get_unchecked(),
+                &self.counters,
+            )
+        };
+        drop(writing);
 
-unsafe impl<K, V, H> Sync for Map<K, V, H>
-where
-    K: Sync,
-    V: Sync,
-    H: Sync,
-{
-}
+        match insertion {
+            Insertion::Created => {
+                self.len.fetch_add(1, AcqRel);
+                // Safe because the pair is now in the map, and the pause keeps
+                // it from being deallocated even if it is removed.
+                unsafe { self.notify_insert(nnptr, None) };
+                Ok(ReadGuard::new(unsafe { &*nnptr.as_ptr() }, pause))
+            }
+            Insertion::Failed(inserter) => {
+                let found = inserter
+                    .found()
+                    .expect("insertion failed without a stored entry");
+                // Safe because the found entry was in the map while we were
+                // paused, and the pause keeps it from being deallocated.
+                let current = ReadGuard::new(unsafe { &*found.as_ptr() }, pause);
+                Err(OccupiedError {
+                    pair: inserter.into_pair(),
+                    current,
+                })
+            }
+            Insertion::Updated(_) => unreachable!(),
+        }
+    }
+
+    /// Inserts unconditionally the given key and value, just like
+    /// [`Map::insert`], and returns a guard to the inserted entry along with
+    /// the previously stored entry, if any. The guard refers to the very
+    /// entry installed, without searching for it again, and it stays valid
+    /// even if some other thread replaces or removes the entry right away.
+    ///
+    /// # Example
+    /// ```rust
+    /// use tux_lockfree::map::Map;
+    ///
+    /// let map = Map::new();
+    /// let (old, guard) = map.insert_and_get("a", 1);
+    /// assert!(old.is_none());
+    /// assert_eq!(*guard.val(), 1);
+    ///
+    /// let (old, guard) = map.insert_and_get("a", 2);
+    /// assert_eq!(*old.unwrap().val(), 1);
+    /// map.insert("a", 3);
+    /// assert_eq!(*guard.val(), 2);
+    /// ```
+    pub fn insert_and_get<'map>(
+        &'map self,
+        key: K,
+        val: V,
+    ) -> (Option<Removed<K, V>>, ReadGuard<'map, K, V>)
+    where
+        K: Hash + Eq,
+    {
+        let pause = self.incin.get_unchecked().pause();
+        let hash = self.hash_key(&key);
+        let inserter = InsertNew::with_pair(|_, _, _| Preview::Keep, (key, val));
+        let nnptr = inserter.raw();
+        let writing = self.writes.begin();
+        // Safe because we paused properly.
+        let insertion = unsafe {
+            self.top.insert(
+                inserter,
+                hash,
+                &pause,
+                self.incin.get_unchecked(),
+                &self.counters,
+            )
+        };
+        drop(writing);
+
+        let old = match insertion {
+            Insertion::Created => {
+                self.len.fetch_add(1, AcqRel);
+                None
+            }
+            Insertion::Updated(old) => Some(old),
+            Insertion::Failed(_) => unreachable!(),
+        };
+        // Safe because the pair is now in the map, and the pause keeps it from
+        // being deallocated even if it is removed.
+        unsafe { self.notify_insert(nnptr, old.as_ref()) };
+        (old, ReadGuard::new(unsafe { &*nnptr.as_ptr() }, pause))
+    }
+
+    /// Inserts _interactively_ the given key. A closure is passed to generate
+    /// the value part of the entry and validate it with the found value. Even
+    /// though the closure may have already accepted some condition, it might
+    /// get recalled many times due to concurrent modifications of the [`Map`].
+    /// However, it is only recalled when the stored entry found for the key
+    /// differs from the one of the previous call, or its presence does: a
+    /// retry caused by a change elsewhere reuses the previous answer, so
+    /// expensive values are not rebuilt for nothing.
+    ///
+    /// The first argument passed to the closure is the key passed in first
+    /// place. The second argument is an optional mutable reference to a
+    /// previously generated value. Obviously, if no value was ever generated,
+    /// it is [`None`]. The third argument is a reference to the found stored
+    /// entry. Obviously, if no stored entry was found, it is `None`. The return
+    /// value of the closure is a specification of "what to do with the
+    /// insertion now".
+    ///
+    /// To derive the new value from the stored one, return [`Preview::New`]
+    /// with a value computed from the found entry. The stored value is only
+    /// borrowed and never moved out, since other threads may still be reading
+    /// it through guards, even after it is replaced. Since the closure gets
+    /// the latest stored entry on every call, and the new value only replaces
+    /// the very entry it was computed from, no concurrent update is lost.
+    ///
+    /// # Example
+    /// ```rust
+    /// use tux_lockfree::map::{Map, Preview};
+    ///
+    /// // A multimap, appending to the values of a key.
+    /// let map = Map::<&str, Vec<i32>>::new();
+    /// for (key, val) in [("a", 1), ("b", 2), ("a", 3)] {
+    ///     map.insert_with(key, |_, _, stored| match stored {
+    ///         Some((_, vals)) => {
+    ///             let mut vals = vals.clone();
+    ///             vals.push(val);
+    ///             Preview::New(vals)
+    ///         }
+    ///         None => Preview::New(vec![val]),
+    ///     });
+    /// }
+    /// assert_eq!(*map.get("a").unwrap().val(), [1, 3]);
+    /// assert_eq!(*map.get("b").unwrap().val(), [2]);
+    /// ```
+    pub fn insert_with<F>(&self, key: K, interactive: F) -> Insertion<K, V, (K, Option<V>)>
+    where
+        K: Hash + Eq,
+        F: FnMut(&K, Option<&mut V>, Option<&(K, V)>) -> Preview<V>,
+    {
+        let hash = self.hash_key(&key);
+        let pause = self.incin.get_unchecked().pause();
+        let inserter = InsertNew::with_key(interactive, key);
+        let nnptr = inserter.raw();
+        let writing = self.writes.begin();
+        // Safe because we paused properly.
+        let insertion = unsafe {
+            self.top.insert(
+                inserter,
+                hash,
+                &pause,
+                self.incin.get_unchecked(),
+                &self.counters,
+            )
+        };
+        drop(writing);
+
+        // Safe in the arms below because the pair is now in the map, and we
+        // are still paused.
+        match insertion {
+            Insertion::Created => {
+                self.len.fetch_add(1, AcqRel);
+                unsafe { self.notify_insert(nnptr, None) };
+                Insertion::Created
+            }
+            Insertion::Updated(old) => {
+                unsafe { self.notify_insert(nnptr, Some(&old)) };
+                Insertion::Updated(old)
+            }
+            Insertion::Failed(inserter) => Insertion::Failed(inserter.into_pair()),
+        }
+    }
+
+    /// Inserts the value made by `insert` if the given key is absent, or
+    /// replaces the stored value with the one computed by `update` from it if
+    /// the key is present, in a single atomic operation. Returns the old
+    /// entry if there was one, just like [`Map::insert`].
+    ///
+    /// Since concurrent modifications may force the operation to be retried,
+    /// both closures may be called many times, each time according to the
+    /// latest state of the entry. Only the last computed value is stored.
+    ///
+    /// # Example
+    /// ```rust
+    /// use tux_lockfree::map::Map;
+    ///
+    /// let counts = Map::new();
+    /// for word in "a b a c a".split(' ') {
+    ///     counts.modify_or_insert(word, || 1, |count| count + 1);
+    /// }
+    /// assert_eq!(*counts.get("a").unwrap().val(), 3);
+    /// assert_eq!(*counts.get("c").unwrap().val(), 1);
+    /// ```
+    pub fn modify_or_insert<F, G>(
+        &self,
+        key: K,
+        mut insert: F,
+        mut update: G,
+    ) -> Option<Removed<K, V>>
+    where
+        K: Hash + Eq,
+        F: FnMut() -> V,
+        G: FnMut(&V) -> V,
+    {
+        let insertion = self.insert_with(key, |_, _, found| match found {
+            Some((_, val)) => Preview::New(update(val)),
+            None => Preview::New(insert()),
+        });
+
+        match insertion {
+            Insertion::Created => None,
+            Insertion::Updated(old) => Some(old),
+            Insertion::Failed(_) => unreachable!(),
+        }
+    }
+
+    /// Replaces the value of the entry identified by the given key with the
+    /// one computed by the given closure from the current value. The new entry
+    /// replaces the old one atomically, so other threads never observe the
+    /// key as absent during the update. The new entry holds a clone of the
+    /// stored key. Returns a guard to the updated entry, or [`None`] if the
+    /// key was not found.
+    ///
+    /// Since concurrent modifications may force the update to be retried, the
+    /// closure may be called many times, each time with the latest value. Only
+    /// the last computed value is stored.
+    ///
+    /// # Example
+    /// ```rust
+    /// use tux_lockfree::map::Map;
+    ///
+    /// let map = Map::new();
+    /// map.insert("hits".to_owned(), 1u64);
+    /// let guard = map.update("hits", |hits| hits + 1).unwrap();
+    /// assert_eq!(*guard.val(), 2);
+    /// assert!(map.update("misses", |misses| misses + 1).is_none());
+    /// ```
+    pub fn update<'map, Q, F>(&'map self, key: &Q, mut f: F) -> Option<ReadGuard<'map, K, V>>
+    where
+        Q: ?Sized + Hash + Eq,
+        K: Borrow<Q> + Hash + Eq + Clone,
+        F: FnMut(&V) -> V,
+    {
+        let hash = self.hash_key(key);
+        let key = self.get_hashed(hash, key)?.key().clone();
+        let inserter = InsertNew::with_key(
+            |_, _, found: Option<&(K, V)>| match found {
+                Some((_, val)) => Preview::New(f(val)),
+                None => Preview::Discard,
+            },
+            key,
+        );
+        let nnptr = inserter.raw();
+
+        let pause = self.incin.get_unchecked().pause();
+        let writing = self.writes.begin();
+        // Safe because we paused properly.
+        let insertion = unsafe {
+            self.top.insert(
+                inserter,
+                hash,
+                &pause,
+                self.incin.get_unchecked(),
+                &self.counters,
+            )
+        };
+        drop(writing);
+
+        match insertion {
+            // Safe because the pair is now in the map, and the pause keeps it
+            // from being deallocated even if it is removed.
+            Insertion::Updated(old) => {
+                unsafe { self.notify_insert(nnptr, Some(&old)) };
+                Some(ReadGuard::new(unsafe { &*nnptr.as_ptr() }, pause))
+            }
+            Insertion::Failed(_) => None,
+            Insertion::Created => unreachable!(),
+        }
+    }
+
+    /// Replaces the value of the entry identified by the given key with the
+    /// one computed by the given closure from the current value, just like
+    /// [`Map::update`], but the closure may return `None` to abort, as with
+    /// [`AtomicUsize::fetch_update`](core::sync::atomic::AtomicUsize::fetch_update).
+    /// On success, returns the replaced entry along with a guard to the new
+    /// one. The closure may be called many times with stale values, but the
+    /// returned entries are always the two ends of the replacement which
+    /// actually happened.
+    ///
+    /// # Example
+    /// ```rust
+    /// use tux_lockfree::map::{FetchUpdateError, Map};
+    ///
+    /// let balances = Map::new();
+    /// balances.insert("alice".to_owned(), 10u64);
+    ///
+    /// let (old, new) = balances.fetch_update("alice", |val| val.checked_sub(4)).unwrap();
+    /// assert_eq!((*old.val(), *new.val()), (10, 6));
+    ///
+    /// let res = balances.fetch_update("alice", |val| val.checked_sub(7));
+    /// assert_eq!(res.unwrap_err(), FetchUpdateError::Aborted);
+    /// let res = balances.fetch_update("bob", |val| val.checked_sub(1));
+    /// assert_eq!(res.unwrap_err(), FetchUpdateError::NotFound);
+    /// ```
+    pub fn fetch_update<'map, Q, F>(
+        &'map self,
+        key: &Q,
+        mut f: F,
+    ) -> Result<Transition<'map, K, V>, FetchUpdateError>
+    where
+        Q: ?Sized + Hash + Eq,
+        K: Borrow<Q> + Hash + Eq + Clone,
+        F: FnMut(&V) -> Option<V>,
+    {
+        let hash = self.hash_key(key);
+        let key = match self.get_hashed(hash, key) {
+            Some(guard) => guard.key().clone(),
+            None => return Err(FetchUpdateError::NotFound),
+        };
+        // Whether the last call of the closure aborted, since the entry may
+        // be removed after an earlier call instead.
+        let aborted = Cell::new(false);
+        let inserter = InsertNew::with_key(
+            |_, _, found: Option<&(K, V)>| {
+                let new = found.and_then(|(_, val)| f(val));
+                aborted.set(found.is_some() && new.is_none());
+                new.map_or(Preview::Discard, Preview::New)
+            },
+            key,
+        );
+        let nnptr = inserter.raw();
+
+        let pause = self.incin.get_unchecked().pause();
+        let writing = self.writes.begin();
+        // Safe because we paused properly.
+        let insertion = unsafe {
+            self.top.insert(
+                inserter,
+                hash,
+                &pause,
+                self.incin.get_unchecked(),
+                &self.counters,
+            )
+        };
+        drop(writing);
+
+        match insertion {
+            // Safe because the pair is now in the map, and the pause keeps it
+            // from being deallocated even if it is removed.
+            Insertion::Updated(old) => {
+                unsafe { self.notify_insert(nnptr, Some(&old)) };
+                Ok((old, ReadGuard::new(unsafe { &*nnptr.as_ptr() }, pause)))
+            }
+            Insertion::Failed(_) => {
+                if aborted.get() {
+                    Err(FetchUpdateError::Aborted)
+                } else {
+                    Err(FetchUpdateError::NotFound)
+                }
+            }
+            Insertion::Created => unreachable!(),
+        }
+    }
+
+    /// Replaces the value of the entry identified by the given key with the
+    /// given value, returning the old entry. Unlike [`Map::insert`], the key
+    /// is never created: if it is not found, the key and the value are given
+    /// back. The new entry replaces the old one atomically in a single pass,
+    /// so other threads never observe the key as absent.
+    ///
+    /// Guards to the old entry may still be reading it, so it cannot be
+    /// overwritten in place. Each replacement allocates a new pair, and the old
+    /// one is dropped once no guard can read it anymore.
+    ///
+    /// # Example
+    /// ```rust
+    /// use tux_lockfree::map::Map;
+    ///
+    /// let map = Map::new();
+    /// map.insert("latency", 10u64);
+    ///
+    /// let old = map.swap("latency", 12).unwrap();
+    /// assert_eq!(*old.val(), 10);
+    /// assert_eq!(*map.get("latency").unwrap().val(), 12);
+    /// assert_eq!(map.swap("errors", 1).unwrap_err(), ("errors", 1));
+    /// assert!(map.get("errors").is_none());
+    /// ```
+    pub fn swap(&self, key: K, val: V) -> Result<Removed<K, V>, (K, V)>
+    where
+        K: Hash + Eq,
+    {
+        let hash = self.hash_key(&key);
+        let pause = self.incin.get_unchecked().pause();
+        let inserter = InsertPresent::new(|_| true, (key, val));
+        let nnptr = inserter.raw();
+        let writing = self.writes.begin();
+        // Safe because we paused properly.
+        let insertion = unsafe {
+            self.top.insert(
+                inserter,
+                hash,
+                &pause,
+                self.incin.get_unchecked(),
+                &self.counters,
+            )
+        };
+        drop(writing);
+
+        match insertion {
+            Insertion::Updated(old) => {
+                // Safe because the pair is now in the map, and we are still
+                // paused.
+                unsafe { self.notify_insert(nnptr, Some(&old)) };
+                Ok(old)
+            }
+            Insertion::Failed(inserter) => Err(inserter.into_pair()),
+            Insertion::Created => unreachable!(),
+        }
+    }
+
+    /// Replaces the value of the entry identified by the given key with the
+    /// given value, only if the stored value is equal to the expected one.
+    /// The comparison and the replacement happen atomically with respect to
+    /// other modifications of the entry. On success, the old entry is
+    /// returned, just like [`Map::swap`]. On failure, the new value is given
+    /// back along with a guard to the current entry, if any, so the operation
+    /// can be retried.
+    ///
+    /// # Example
+    /// ```rust
+    /// use tux_lockfree::map::Map;
+    ///
+    /// let map = Map::new();
+    /// map.insert("version", 1u32);
+    ///
+    /// // Optimistically bumps the version, retrying on conflicts.
+    /// let mut expected = *map.get("version").unwrap().val();
+    /// loop {
+    ///     match map.compare_and_swap("version", &expected, expected + 1) {
+    ///         Ok(old) => break assert_eq!(*old.val(), 1),
+    ///         Err(err) => expected = *err.current.unwrap().val(),
+    ///     }
+    /// }
+    /// assert_eq!(*map.get("version").unwrap().val(), 2);
+    ///
+    /// let err = map.compare_and_swap("version", &1, 3).unwrap_err();
+    /// assert_eq!(err.new, 3);
+    /// assert_eq!(err.current.map(|guard| *guard.val()), Some(2));
+    /// ```
+    pub fn compare_and_swap<'map, Q>(
+        &'map self,
+        key: &Q,
+        expected: &V,
+        new: V,
+    ) -> Result<Removed<K, V>, CasError<'map, K, V>>
+    where
+        Q: ?Sized + Hash + Eq,
+        K: Borrow<Q> + Hash + Eq + Clone,
+        V: PartialEq,
+    {
+        let hash = self.hash_key(key);
+        let key = match self.get_hashed(hash, key) {
+            Some(guard) if guard.val() == expected => guard.key().clone(),
+            current => return Err(CasError { new, current }),
+        };
+
+        let pause = self.incin.get_unchecked().pause();
+        let inserter = InsertPresent::new(|(_, val)| val == expected, (key, new));
+        let nnptr = inserter.raw();
+        let writing = self.writes.begin();
+        // Safe because we paused properly.
+        let insertion = unsafe {
+            self.top.insert(
+                inserter,
+                hash,
+                &pause,
+                self.incin.get_unchecked(),
+                &self.counters,
+            )
+        };
+        drop(writing);
+
+        match insertion {
+            Insertion::Updated(old) => {
+                // Safe because the pair is now in the map, and we are still
+                // paused.
+                unsafe { self.notify_insert(nnptr, Some(&old)) };
+                Ok(old)
+            }
+            Insertion::Failed(inserter) => {
+                let (key, new) = inserter.into_pair();
+                let current = self.get_hashed(hash, key.borrow());
+                Err(CasError { new, current })
+            }
+            Insertion::Created => unreachable!(),
+        }
+    }
+
+    /// Reinserts a previously removed entry. The entry must have been either:
+    ///
+    /// 1. Removed from any [`Map`] using the same [`SharedIncin`] as this
+    /// [`Map`]. 2. Removed from an already dead [`Map`] with dead
+    /// [`SharedIncin`]. 3. Removed from a [`Map`] whose `SharedIncin` has
+    /// no sensitive reads active.
+    ///
+    /// If the removed entry does not fit any category, the insertion will fail.
+    /// Otherwise, insertion cannot fail.
+    pub fn reinsert(&self, mut removed: Removed<K, V>) -> Insertion<K, V, Removed<K, V>>
+    where
+        K: Hash + Eq,
+    {
+        if !Removed::is_usable_by(&mut removed, self.incin.get_unchecked()) {
+            return Insertion::Failed(removed);
+        }
+
+        let hash = self.hash_key(removed.key());
+
+        let pause = self.incin.get_unchecked().pause();
+        let nnptr = Removed::raw(&removed);
+        let writing = self.writes.begin();
+        // Safe because we paused properly.
+        let insertion = unsafe {
+            self.top.insert(
+                Reinsert::new(|_, _| true, removed),
+                hash,
+                &pause,
+                self.incin.get_unchecked(),
+                &self.counters,
+            )
+        };
+        drop(writing);
+
+        // Safe in the arms below because the pair is now in the map, and we
+        // are still paused.
+        match insertion {
+            Insertion::Created => {
+                self.len.fetch_add(1, AcqRel);
+                unsafe { self.notify_insert(nnptr, None) };
+                Insertion::Created
+            }
+            Insertion::Updated(old) => {
+                unsafe { self.notify_insert(nnptr, Some(&old)) };
+                Insertion::Updated(old)
+            }
+            Insertion::Failed(_) => unreachable!(),
+        }
+    }
+
+    /// Reinserts _interactively_ a previously removed entry. A closure will be
+    /// passed to validate if the conditions are correct for the reinsertion.
+    /// The first argument passed to the closure is a reference to the removed
+    /// entry, the second argument is a reference to the stored found entry.
+    /// Obviously, if no stored entry was found, the argument is [`None`]. The
+    /// returned value is a boolean indicating if the reinsertion should go on.
+    /// Even though the closure may have already accepted some condition, it
+    /// might get recalled many times due to concurrent modifications of the
+    /// [`Map`].
+    ///
+    /// The entry must have been either:
+    ///
+    /// 1. Removed from any [`Map`] using the same [`SharedIncin`] as this
+    /// [`Map`]. 2. Removed from an already dead [`Map`] with dead
+    /// `SharedIncin`. 3. Removed from a [`Map`] whose `SharedIncin` has no
+    /// sensitive reads active.
+    ///
+    /// If the removed entry does not fit any category, the insertion will fail.
+    /// Otherwise, insertion cannot fail.
+    pub fn reinsert_with<F>(
+        &self,
+        mut removed: Removed<K, V>,
+        interactive: F,
+    ) -> Insertion<K, V, Removed<K, V>>
+    where
+        K: Hash + Eq,
+        F: FnMut(&(K, V), Option<&(K, V)>) -> bool,
+    {
+        if !Removed::is_usable_by(&mut removed, self.incin.get_unchecked()) {
+            return Insertion::Failed(removed);
+        }
+
+        let hash = self.hash_key(removed.key());
+
+        let pause = self.incin.get_unchecked().pause();
+        let nnptr = Removed::raw(&removed);
+        let writing = self.writes.begin();
+        // Safe because we paused properly.
+        let insertion = unsafe {
+            self.top.insert(
+                Reinsert::new(interactive, removed),
+                hash,
+                &pause,
+                self.incin.get_unchecked(),
+                &self.counters,
+            )
+        };
+        drop(writing);
+
+        // Safe in the arms below because the pair is now in the map, and we
+        // are still paused.
+        match insertion {
+            Insertion::Created => {
+                self.len.fetch_add(1, AcqRel);
+                unsafe { self.notify_insert(nnptr, None) };
+                Insertion::Created
+            }
+            Insertion::Updated(old) => {
+                unsafe { self.notify_insert(nnptr, Some(&old)) };
+                Insertion::Updated(old)
+            }
+            Insertion::Failed(inserter) => Insertion::Failed(inserter.into_removed()),
+        }
+    }
+
+    /// Removes unconditionally the entry identified by the given key. If no
+    /// entry was found, [`None`] is returned. This method will only work
+    /// correctly if [`Hash`] and [`Eq`] are implemented in the same way for
+    /// the borrowed type and the stored type. If the entry was not found,
+    /// `None` is returned.
+    pub fn remove<Q>(&self, key: &Q) -> Option<Removed<K, V>>
+    where
+        Q: ?Sized + Hash + Eq,
+        K: Borrow<Q>,
+    {
+        self.remove_with(key, |_| true)
+    }
+
+    /// Removes unconditionally the entry identified by the given key, just like
+    /// [`Map::remove`], and returns its value. The value is moved out of the
+    /// removed entry if no sensitive read is active, and cloned otherwise, so
+    /// callers do not need to handle [`Removed`].
+    ///
+    /// # Example
+    /// ```rust
+    /// use tux_lockfree::map::Map;
+    ///
+    /// let map = Map::new();
+    /// map.insert("token".to_owned(), "abc".to_owned());
+    /// assert_eq!(map.remove_value("token").as_deref(), Some("abc"));
+    /// assert_eq!(map.remove_value("token"), None);
+    /// ```
+    pub fn remove_value<Q>(&self, key: &Q) -> Option<V>
+    where
+        Q: ?Sized + Hash + Eq,
+        K: Borrow<Q>,
+        V: Clone,
+    {
+        self.remove(key).map(Removed::into_val)
+    }
+
+    /// Removes the entry identified by the given key only if its value is
+    /// equal to the expected one. The comparison and the removal happen
+    /// atomically with respect to other modifications of the entry, so an
+    /// entry overwritten meanwhile by some other thread is never removed. The
+    /// error tells whether the key was not found or its value did not match.
+    ///
+    /// # Example
+    /// ```rust
+    /// use tux_lockfree::map::{Map, RemoveError};
+    ///
+    /// let leases = Map::new();
+    /// leases.insert("disk", "worker 1");
+    /// // Worker 2 took the lease over, so worker 1 cannot release it.
+    /// leases.insert("disk", "worker 2");
+    ///
+    /// let res = leases.compare_and_remove("disk", &"worker 1");
+    /// assert_eq!(res.unwrap_err(), RemoveError::Mismatch);
+    /// assert!(leases.compare_and_remove("disk", &"worker 2").is_ok());
+    /// let res = leases.compare_and_remove("disk", &"worker 2");
+    /// assert_eq!(res.unwrap_err(), RemoveError::NotFound);
+    /// ```
+    pub fn compare_and_remove<Q>(&self, key: &Q, expected: &V) -> Result<Removed<K, V>, RemoveError>
+    where
+        Q: ?Sized + Hash + Eq,
+        K: Borrow<Q>,
+        V: PartialEq,
+    {
+        // A rejected entry fails the removal right away, so the last answer
+        // tells which case happened.
+        let mut mismatch = false;
+        let removed = self.remove_with(key, |(_, val)| {
+            mismatch = val != expected;
+            !mismatch
+        });
+
+        match removed {
+            Some(removed) => Ok(removed),
+            None if mismatch => Err(RemoveError::Mismatch),
+            None => Err(RemoveError::NotFound),
+        }
+    }
+
+    /// Removes _interactively_ the entry identified by the given key. A closure
+    /// is passed to validate the removal. The only argument passed to the
+    /// closure is a reference to the found entry. The closure returns if the
+    /// removal should go on. If no entry was found, `None` is returned. This
+    /// method will only work correctly if [`Hash`] and [`Eq`] are implemented
+    /// in the same way for the borrowed type and the stored type. If the
+    /// entry was not found, [`None`] is returned.
+    pub fn remove_with<Q, F>(&self, key: &Q, interactive: F) -> Option<Removed<K, V>>
+    where
+        Q: ?Sized + Hash + Eq,
+        K: Borrow<Q>,
+        F: FnMut(&(K, V)) -> bool,
+    {
+        let hash = self.hash_key(key);
+        let pause = self.incin.get_unchecked().pause();
+        let writing = self.writes.begin();
+        // Safe because we paused properly.
+        let removed = unsafe {
+            self.top.remove(
+                key,
+                interactive,
+                hash,
+                &pause,
+                self.incin.get_unchecked(),
+                &self.counters,
+            )
+        };
+        drop(writing);
+
+        if let Some(removed) = &removed {
+            self.len.fetch_sub(1, AcqRel);
+            self.notify_remove(removed);
+        }
+        removed
+    }
+
+    /// Removes some entry of this [`Map`] and returns it, or returns `None` if
+    /// the map is empty. The entry is picked just like [`Map::any`] does, so
+    /// threads calling this concurrently start from different places and
+    /// rarely contend on the same entry. Calling this in a loop from many
+    /// threads until it returns `None` empties the map, and no entry is
+    /// returned twice, unlike [`Map::drain`], which requires exclusive access.
+    ///
+    /// # Example
+    /// ```rust
+    /// use std::{sync::Arc, thread};
+    /// use tux_lockfree::map::Map;
+    ///
+    /// let map = Arc::new((0..100).map(|i| (i, i)).collect::<Map<_, _>>());
+    /// let workers = (0..4)
+    ///     .map(|_| {
+    ///         let map = map.clone();
+    ///         thread::spawn(move || {
+    ///             let mut sum = 0;
+    ///             while let Some(removed) = map.pop_any() {
+    ///                 sum += *removed.val();
+    ///             }
+    ///             sum
+    ///         })
+    ///     })
+    ///     .collect::<Vec<_>>();
+    ///
+    /// let sum = workers.into_iter().map(|w| w.join().unwrap()).sum::<i32>();
+    /// assert_eq!(sum, (0..100).sum());
+    /// assert!(map.is_empty());
+    /// ```
+    pub fn pop_any(&self) -> Option<Removed<K, V>>
+    where
+        K: Hash + Eq,
+    {
+        loop {
+            let guard = self.any()?;
+            // Only the very entry we found is removed. If some other thread
+            // removed or replaced it meanwhile, we pick another one.
+            let removed = self.remove_with(guard.key(), |pair| ptr::eq(pair, &*guard));
+            if removed.is_some() {
+                break removed;
+            }
+        }
+    }
+
+    /// Removes unconditionally the entries identified by each of the given
+    /// keys, just like calling [`Map::remove`] for each of them, but pausing
+    /// the incinerator only once for the whole batch. The results are in the
+    /// same order as the keys.
+    ///
+    /// # Example
+    /// ```rust
+    /// use tux_lockfree::map::Map;
+    ///
+    /// let map = (0..10).map(|i| (i, i * 10)).collect::<Map<_, _>>();
+    /// let removed = map.remove_all(&[3, 42, 5]);
+    /// let vals = removed
+    ///     .iter()
+    ///     .map(|removed| removed.as_ref().map(|removed| *removed.val()))
+    ///     .collect::<Vec<_>>();
+    /// assert_eq!(vals, [Some(30), None, Some(50)]);
+    /// assert_eq!(map.len(), 8);
+    /// ```
+    pub fn remove_all<'key, Q, I>(&self, keys: I) -> Vec<Option<Removed<K, V>>>
+    where
+        Q: ?Sized + Hash + Eq + 'key,
+        K: Borrow<Q>,
+        I: IntoIterator<Item = &'key Q>,
+    {
+        let pause = self.incin.get_unchecked().pause();
+        let writing = self.writes.begin();
+        let mut count = 0;
+        let removed: Vec<_> = keys
+            .into_iter()
+            .map(|key| {
+                // Safe because we paused properly.
+                let removed = unsafe {
+                    self.top.remove(
+                        key,
+                        |_| true,
+                        self.hash_key(key),
+                        &pause,
+                        self.incin.get_unchecked(),
+                        &self.counters,
+                    )
+                };
+                count += removed.is_some() as usize;
+                removed
+            })
+            .collect();
+        drop(writing);
+
+        if count > 0 {
+            self.len.fetch_sub(count, AcqRel);
+        }
+        for removed in removed.iter().flatten() {
+            self.notify_remove(removed);
+        }
+        removed
+    }
+
+    /// Acts just like [`Extend::extend`] but does not require mutability.
+    /// Since this method takes precedence over the trait, pairs of references
+    /// to clonable keys and values are inserted through the trait instead,
+    /// which is implemented by both `Map` and `&Map`.
+    pub fn extend<I>(&self, iterable: I)
+    where
+        I: IntoIterator<Item = (K, V)>,
+        K: Hash + Eq,
+    {
+        for (key, val) in iterable {
+            self.insert(key, val);
+        }
+    }
+
+    /// Moves every entry of the given map into this one. If the key of an
+    /// entry is absent, the entry is inserted as it is. Otherwise, `resolve`
+    /// is called with the key, the value being merged and the stored value,
+    /// and the value it returns replaces the stored one. Returns how many
+    /// entries were created and how many were merged.
+    ///
+    /// To replace a stored value, its entry is removed, resolved and inserted
+    /// back, so concurrent readers may briefly find the key absent. If some
+    /// other thread inserts the key meanwhile, `resolve` is called again with
+    /// the value it returned and the newly stored one. Therefore, no stored
+    /// value is ever lost, and accumulating values, such as counts merged
+    /// from maps of many threads, is exact.
+    ///
+    /// # Example
+    /// ```rust
+    /// use tux_lockfree::map::Map;
+    ///
+    /// let total = Map::new();
+    /// total.insert("a", 1);
+    ///
+    /// let local = Map::new();
+    /// local.insert("a", 2);
+    /// local.insert("b", 3);
+    ///
+    /// let stats = total.merge_from(local, |_, new, stored| new + stored);
+    /// assert_eq!((stats.created, stats.merged), (1, 1));
+    /// assert_eq!(*total.get("a").unwrap().val(), 3);
+    /// assert_eq!(*total.get("b").unwrap().val(), 3);
+    /// ```
+    pub fn merge_from<H2, F>(&self, other: Map<K, V, H2>, mut resolve: F) -> MergeStats
+    where
+        K: Hash + Eq,
+        F: FnMut(&K, V, &V) -> V,
+    {
+        let mut stats = MergeStats::default();
+
+        for mut pair in other {
+            let mut merged = false;
+            loop {
+                let (key, val) = match ConcurrentInsert::try_insert(self, pair) {
+                    Ok(()) => break,
+                    Err(pair) => pair,
+                };
+                pair = match self.remove(&key) {
+                    Some(removed) => {
+                        merged = true;
+                        let val = resolve(&key, val, removed.val());
+                        (key, val)
+                    }
+                    // Removed by some other thread meanwhile.
+                    None => (key, val),
+                };
+            }
+
+            if merged {
+                stats.merged += 1;
+            } else {
+                stats.created += 1;
+            }
+        }
+
+        stats
+    }
+
+    // Inserts the pair if the key is absent, and returns a guard to the stored
+    // entry, which is either the given pair or one which was already there.
+    // The hash must be the one of the key.
+    fn publish<'map>(&'map self, pair: (K, V), hash: u64) -> ReadGuard<'map, K, V>
+    where
+        K: Hash + Eq,
+    {
+        self.publish_then(pair, hash, || ())
+    }
+
+    // Same as `publish`, but calls `published` once the entry is stored and
+    // before the insertion is notified.
+    fn publish_then<'map, F>(
+        &'map self,
+        mut pair: (K, V),
+        hash: u64,
+        published: F,
+    ) -> ReadGuard<'map, K, V>
+    where
+        K: Hash + Eq,
+        F: FnOnce(),
+    {
+        let (guard, created) = loop {
+            match self.insert_absent(pair, hash) {
+                Ok((guard, nnptr)) => break (guard, Some(nnptr)),
+                Err(back) => {
+                    pair = back;
+                    // Some other thread may remove the entry before we read
+                    // it, in which case we try again.
+                    if let Some(guard) = self.get_hashed(hash, &pair.0) {
+                        break (guard, None);
+                    }
+                }
+            }
+        };
+
+        published();
+        if let Some(nnptr) = created {
+            // Safe because the guard still pauses the incinerator.
+            unsafe { self.notify_insert(nnptr, None) };
+        }
+        guard
+    }
+
+    // Inserts the pair if the key is absent, and returns a guard to it, whose
+    // insertion is not notified yet. Otherwise, gives the pair back. The hash
+    // must be the one of the key.
+    fn insert_absent<'map>(
+        &'map self,
+        pair: (K, V),
+        hash: u64,
+    ) -> Result<Created<'map, K, V>, (K, V)>
+    where
+        K: Hash + Eq,
+    {
+        let pause = self.incin.get_unchecked().pause();
+        let inserter = InsertAbsent::new(pair);
+        let nnptr = inserter.raw();
+        let writing = self.writes.begin();
+        // Safe because we paused properly.
+        let insertion = unsafe {
+            self.top.insert(
+                inserter,
+                hash,
+                &pause,
+                self.incin.get_unchecked(),
+                &self.counters,
+            )
+        };
+        drop(writing);
+
+        match insertion {
+            Insertion::Created => {
+                self.len.fetch_add(1, AcqRel);
+                // Safe because the pair is now in the map, and the pause keeps
+                // it from being deallocated even if it is removed.
+                let guard = ReadGuard::new(unsafe { &*nnptr.as_ptr() }, pause);
+                Ok((guard, nnptr))
+            }
+            Insertion::Failed(inserter) => Err(inserter.into_pair()),
+            Insertion::Updated(_) => unreachable!(),
+        }
+    }
+}
+
+impl<K, V, H> Map<K, AtomicCell<V>, H>
+where
+    H: BuildHasher,
+    V: AtomicValue,
+{
+    /// Replaces the value of the given key with the one computed from it by
+    /// the given closure, in place, and returns the previous value. Nothing is
+    /// allocated, since the entry is not replaced. Returns [`None`] if the key
+    /// is absent. The closure may be called many times if other threads
+    /// change the value or the entry meanwhile, each time with the latest
+    /// value.
+    ///
+    /// Since the update happens in place, it is not seen by the hook
+    /// registered with [`Map::on_change`]. A thread replacing or removing the
+    /// entry meanwhile, e.g. with [`Map::insert`], waits for the update to be
+    /// stored, so the update is never lost in an entry which was already
+    /// replaced: it is either seen by the replaced value, or applied to the new
+    /// one. Methods computing the new entry from the stored value, such as
+    /// [`Map::insert_with`], may still overwrite a concurrent update, just as
+    /// they overwrite a concurrent [`Map::insert`].
+    ///
+    /// # Example
+    /// ```rust
+    /// use tux_lockfree::map::{AtomicCell, Map};
+    ///
+    /// let map = Map::new();
+    /// map.insert("limit", AtomicCell::new(10u64));
+    /// assert_eq!(map.fetch_update_copy("limit", |val| val / 2), Some(10));
+    /// assert_eq!(map.get("limit").unwrap().val().load(), 5);
+    /// assert_eq!(map.fetch_update_copy("other", |val| val / 2), None);
+    /// ```
+    pub fn fetch_update_copy<Q, F>(&self, key: &Q, mut update: F) -> Option<V>
+    where
+        Q: ?Sized + Hash + Eq,
+        K: Borrow<Q>,
+        F: FnMut(V) -> V,
+    {
+        let hash = self.hash_key(key);
+        self.update_cell(
+            key,
+            hash,
+            |cell| {
+                let current = cell.load();
+                (current, update(current))
+            },
+            |cell, (current, new)| cell.compare_exchange(current, new).ok(),
+        )
+    }
+
+    /// Adds the given delta to the value of the given key, in place, wrapping
+    /// around on overflow, and returns the previous value. If the key is
+    /// absent, it is inserted with the default value plus the delta, so only
+    /// the first increment of a key allocates. Just like with
+    /// [`Map::fetch_update_copy`], the hook registered with [`Map::on_change`]
+    /// is only told about that insertion, and the increment is never lost in
+    /// an entry which another thread replaced or removed meanwhile.
+    ///
+    /// # Example
+    /// ```rust
+    /// use tux_lockfree::map::Map;
+    ///
+    /// let counts = Map::new();
+    /// for word in "a b a c a".split(' ') {
+    ///     counts.increment(word, 1u64);
+    /// }
+    /// assert_eq!(counts.get("a").unwrap().val().load(), 3);
+    /// assert_eq!(counts.increment("c", 4), 1);
+    /// ```
+    pub fn increment(&self, key: K, delta: V) -> V
+    where
+        K: Hash + Eq,
+        V: Default,
+    {
+        let hash = self.hash_key(&key);
+        // The increment is applied before the insertion, so it is never
+        // applied to an entry someone else already replaced.
+        let cell = AtomicCell::default();
+        let prev = cell.fetch_add(delta);
+        let mut pair = (key, cell);
+
+        loop {
+            let updated = self.update_cell(
+                &pair.0,
+                hash,
+                |_| (),
+                |cell, ()| Some(cell.fetch_add(delta)),
+            );
+            if let Some(updated) = updated {
+                break updated;
+            }
+
+            match self.insert_absent(pair, hash) {
+                Ok((_guard, nnptr)) => {
+                    // Safe because the guard still pauses the incinerator.
+                    unsafe { self.notify_insert(nnptr, None) };
+                    break prev;
+                }
+                // Someone inserted the key meanwhile, so we update it.
+                Err(back) => pair = back,
+            }
+        }
+    }
+
+    // Updates the cell of the given key in place. `prepare` computes the
+    // update from the cell, and `apply` stores it while the entry is pinned,
+    // so the entry cannot be replaced or removed until it returns. `apply`
+    // must thus only do a few atomic operations, and returns `None` to start
+    // over. Returns `None` if the key is absent.
+    fn update_cell<Q, P, A, T, R>(
+        &self,
+        key: &Q,
+        hash: u64,
+        mut prepare: P,
+        mut apply: A,
+    ) -> Option<R>
+    where
+        Q: ?Sized + Eq,
+        K: Borrow<Q>,
+        P: FnMut(&AtomicCell<V>) -> T,
+        A: FnMut(&AtomicCell<V>, T) -> Option<R>,
+    {
+        let pause = self.incin.get_unchecked().pause();
+        loop {
+            // Safe because we paused properly.
+            let found = unsafe { self.top.find(key, hash, &pause, &self.counters)? };
+            // Safe because the pause keeps the pair from being deallocated.
+            let cell = unsafe { &(*found.pair().as_ptr()).1 };
+            let prepared = prepare(cell);
+
+            // The entry was replaced or removed since we found it, unless we
+            // pin it.
+            if let Some(_pin) = found.pin() {
+                if let Some(res) = apply(cell, prepared) {
+                    break Some(res);
+                }
+            }
+            self.counters.cas_retry();
+        }
+    }
+}
+
+impl<K, T, H> Map<K, Arc<T>, H>
+where
+    H: BuildHasher,
+    T: ?Sized,
+{
+    /// Searches for the entry identified by the given key, just like
+    /// [`Map::get`], and clones the [`Arc`] stored as its value. The
+    /// incinerator is only paused during the search, so the returned value
+    /// can be held across await points and stored without keeping any guard
+    /// alive.
+    ///
+    /// # Example
+    /// ```rust
+    /// use std::sync::Arc;
+    /// use tux_lockfree::map::Map;
+    ///
+    /// let sessions = Map::new();
+    /// sessions.insert(1, Arc::new("alice".to_owned()));
+    /// let session = sessions.get_arc(&1).unwrap();
+    /// sessions.remove(&1);
+    /// assert_eq!(*session, "alice");
+    /// ```
+    pub fn get_arc<Q>(&self, key: &Q) -> Option<Arc<T>>
+    where
+        Q: ?Sized + Hash + Eq,
+        K: Borrow<Q>,
+    {
+        self.get(key).map(|guard| guard.val().clone())
+    }
+
+    /// Inserts unconditionally the given key and value, just like
+    /// [`Map::insert`], and returns the previously stored [`Arc`], if any,
+    /// instead of the removed entry.
+    ///
+    /// # Example
+    /// ```rust
+    /// use std::sync::Arc;
+    /// use tux_lockfree::map::Map;
+    ///
+    /// let sessions = Map::new();
+    /// assert!(sessions.insert_arc(1, Arc::new("alice")).is_none());
+    /// let old = sessions.insert_arc(1, Arc::new("bob")).unwrap();
+    /// assert_eq!(*old, "alice");
+    /// ```
+    pub fn insert_arc(&self, key: K, val: Arc<T>) -> Option<Arc<T>>
+    where
+        K: Hash + Eq,
+    {
+        self.insert(key, val).map(|removed| removed.val().clone())
+    }
+
+    /// Searches for the entry identified by the given key, and if it is not
+    /// found, inserts the value built by the given closure wrapped in an
+    /// [`Arc`], just like [`Map::get_or_insert_with`]. Returns a clone of the
+    /// [`Arc`] stored in whichever entry ended up in the map, so no guard is
+    /// kept alive.
+    ///
+    /// # Example
+    /// ```rust
+    /// use tux_lockfree::map::Map;
+    ///
+    /// let sessions = Map::new();
+    /// let alice = sessions.get_or_insert_arc(1, || "alice".to_owned());
+    /// let again = sessions.get_or_insert_arc(1, || unreachable!());
+    /// assert!(std::sync::Arc::ptr_eq(&alice, &again));
+    /// ```
+    pub fn get_or_insert_arc<F>(&self, key: K, make: F) -> Arc<T>
+    where
+        K: Hash + Eq,
+        T: Sized,
+        F: FnOnce() -> T,
+    {
+        self.get_or_insert_with(key, || Arc::new(make()))
+            .val()
+            .clone()
+    }
+}
+
+impl<K, V, H> Default for Map<K, V, H>
+where
+    H: BuildHasher + Default,
+{
+    fn default() -> Self {
+        Self::with_hasher(H::default())
+    }
+}
+
+impl<K, V, H> Clone for Map<K, V, H>
+where
+    K: Clone,
+    V: Clone,
+    H: Clone,
+{
+    /// Creates an independent [`Map`] with clones of the entries and of the
+    /// hasher builder, and a new incinerator. The tables are rebuilt directly
+    /// while the incinerator of this [`Map`] is paused once. If other threads
+    /// modify this [`Map`] meanwhile, entries inserted or removed concurrently
+    /// may or may not be included, just like with [`Map::iter`].
+    fn clone(&self) -> Self {
+        let mut clone = Self {
+            top: Table::new_alloc(),
+            incin: SharedIncin::new(),
+            builder: self.builder.clone(),
+            inits: InitMarkers::new(),
+            len: AtomicUsize::new(0),
+            cursor: AtomicUsize::new(0),
+            writes: Writes::new(self.writes.is_enabled()),
+            counters: Counters::new(),
+            optimizing: AtomicBool::new(false),
+            on_change: None,
+        };
+
+        let pause = self.incin.get_unchecked().pause();
+        // Safe because we paused properly, and if cloning panics, the clone
+        // frees what was built so far.
+        unsafe { clone.top.clone_from(&self.top, &pause, clone.len.get_mut()) };
+        clone
+    }
+}
+
+impl<K, V, H, H2> PartialEq<Map<K, V, H2>> for Map<K, V, H>
+where
+    K: Hash + Eq,
+    V: PartialEq,
+    H: BuildHasher,
+    H2: BuildHasher,
+{
+    /// Two maps are equal if they have the same length and every key of this
+    /// [`Map`] maps to an equal value in the other, regardless of the hasher
+    /// builders. The result is only meaningful if neither [`Map`] is modified
+    /// concurrently.
+    fn eq(&self, other: &Map<K, V, H2>) -> bool {
+        self.len() == other.len()
+            && self.iter().all(|guard| {
+                other
+                    .get(guard.key())
+                    .map_or(false, |found| found.val() == guard.val())
+            })
+    }
+}
+
+impl<K, V, H> Eq for Map<K, V, H>
+where
+    K: Hash + Eq,
+    V: Eq,
+    H: BuildHasher,
+{
+}
+
+// At most this many entries are printed by the `Debug` implementation of
+// `Map`, so that printing a huge map by accident stays cheap.
+const DEBUG_ENTRIES: usize = 1000;
+
+impl<K, V, H> fmt::Debug for Map<K, V, H>
+where
+    K: fmt::Debug,
+    V: fmt::Debug,
+{
+    /// Prints the entries in the form `{key: value, ...}`, just like
+    /// `HashMap`, walking the [`Map`] just like [`Map::iter`]. After the first
+    /// thousand entries, the others are elided as `...`. See
+    /// [`Map::debug_structure`] to print the internal structure instead.
+    fn fmt(&self, fmtr: &mut fmt::Formatter) -> fmt::Result {
+        fmtr.write_str("{")?;
+        for (index, guard) in self.iter().enumerate() {
+            if index > 0 {
+                fmtr.write_str(", ")?;
+            }
+            if index == DEBUG_ENTRIES {
+                fmtr.write_str("...")?;
+                break;
+            }
+            write!(fmtr, "{:?}: {:?}", guard.key(), guard.val())?;
+        }
+        fmtr.write_str("}")
+    }
+}
+
+// The internal structure of a map, printed by `Map::debug_structure`.
+struct DebugStructure<'map, K, V, H>(&'map Map<K, V, H>);
+
+impl<'map, K, V, H> fmt::Debug for DebugStructure<'map, K, V, H>
+where
+    H: fmt::Debug,
+{
+    fn fmt(&self, fmtr: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            fmtr,
+            "Map {{ top_table: {:?}, incin: {:?}, build_hasher: {:?} }}",
+            self.0.top,
+            self.0.incin.get_unchecked(),
+            self.0.builder
+        )
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<K, V, H> serde::Serialize for Map<K, V, H>
+where
+    K: serde::Serialize,
+    V: serde::Serialize,
+{
+    /// Serializes the entries as a map. The guards are collected first, so
+    /// the announced length is exact even if other threads modify the [`Map`].
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        use serde::ser::SerializeMap;
+
+        let guards = self.iter().collect::<Vec<_>>();
+        let mut ser_map = serializer.serialize_map(Some(guards.len()))?;
+        for guard in &guards {
+            ser_map.serialize_entry(guard.key(), guard.val())?;
+        }
+        ser_map.end()
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de, K, V, H> serde::Deserialize<'de> for Map<K, V, H>
+where
+    K: serde::Deserialize<'de> + Hash + Eq,
+    V: serde::Deserialize<'de>,
+    H: BuildHasher + Default,
+{
+    /// Deserializes a map, inserting its entries with the default hasher
+    /// builder. If a key is repeated, its last value is kept.
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        struct MapVisitor<K, V, H>(core::marker::PhantomData<Map<K, V, H>>);
+
+        impl<'de, K, V, H> serde::de::Visitor<'de> for MapVisitor<K, V, H>
+        where
+            K: serde::Deserialize<'de> + Hash + Eq,
+            V: serde::Deserialize<'de>,
+            H: BuildHasher + Default,
+        {
+            type Value = Map<K, V, H>;
+
+            fn expecting(&self, fmtr: &mut fmt::Formatter) -> fmt::Result {
+                fmtr.write_str("a map")
+            }
+
+            fn visit_map<A>(self, mut access: A) -> Result<Self::Value, A::Error>
+            where
+                A: serde::de::MapAccess<'de>,
+            {
+                let map = Map::default();
+                while let Some((key, val)) = access.next_entry()? {
+                    map.insert(key, val);
+                }
+                Ok(map)
+            }
+        }
+
+        deserializer.deserialize_map(MapVisitor(core::marker::PhantomData))
+    }
+}
+
+impl<K, V, H> MemoryFootprint for Map<K, V, H> {
+    fn bytes_allocated(&self) -> usize {
+        let pause = self.incin.get_unchecked().pause();
+        // Safe because we paused the incinerator of the map, which is the only
+        // one used with its tables.
+        unsafe { self.top.bytes_allocated(&pause) }
+    }
+
+    fn garbage_bytes(&self) -> usize {
+        self.incin.get_unchecked().garbage_len() * Garbage::<K, V>::ESTIMATED_BYTES
+    }
+}
+
+impl<K, V, H> ConcurrentInsert<(K, V)> for Map<K, V, H>
+where
+    H: BuildHasher,
+    K: Hash + Eq,
+{
+    fn try_insert(&self, pair: (K, V)) -> Result<(), (K, V)> {
+        let hash = self.hash_key(&pair.0);
+        let pause = self.incin.get_unchecked().pause();
+        let inserter = InsertAbsent::new(pair);
+        let nnptr = inserter.raw();
+        let writing = self.writes.begin();
+        // Safe because we paused properly.
+        let insertion = unsafe {
+            self.top.insert(
+                inserter,
+                hash,
+                &pause,
+                self.incin.get_unchecked(),
+                &self.counters,
+            )
+        };
+        drop(writing);
+
+        match insertion {
+            Insertion::Created => {
+                self.len.fetch_add(1, AcqRel);
+                // Safe because the pair is now in the map, and we are still
+                // paused.
+                unsafe { self.notify_insert(nnptr, None) };
+                Ok(())
+            }
+            Insertion::Failed(inserter) => Err(inserter.into_pair()),
+            Insertion::Updated(_) => unreachable!(),
+        }
+    }
+}
+
+impl<K, V, H> ConcurrentPop<(K, V)> for Map<K, V, H>
+where
+    H: BuildHasher,
+    K: Hash + Eq + Clone,
+    V: Clone,
+{
+    fn pop(&self) -> Option<(K, V)> {
+        loop {
+            let removed = {
+                let guard = self.iter().next()?;
+                self.remove(guard.key())
+            };
+
+            // Some other thread may have removed the entry meanwhile.
+            if let Some(removed) = removed {
+                // If sensitive reads are still active, the pair cannot be
+                // moved out, so it is cloned instead.
+                break Some(
+                    Removed::try_into(removed)
+                        .unwrap_or_else(|removed| (removed.key().clone(), removed.val().clone())),
+                );
+            }
+        }
+    }
+}
+
+impl<K, V, H> ApproxLen for Map<K, V, H> {
+    fn len(&self) -> usize {
+        Map::len(self)
+    }
+}
+
+impl<K, V, H> Drop for Map<K, V, H> {
+    fn drop(&mut self) {
+        let mut tables = Vec::new();
+
+        // Safe because we won't use these nodes anymore. We are in the
+        // destructor.
+        unsafe { self.top.free_nodes(&mut tables) }
+
+        while let Some(mut table) = tables.pop() {
+            // Safe because we won't use these nodes anymore. We are in the
+            // destructor.
+            unsafe { table.free_nodes(&mut tables) }
+        }
+    }
+}
+
+impl<'map, K, V, H> IntoIterator for &'map Map<K, V, H> {
+    type Item = ReadGuard<'map, K, V>;
+
+    type IntoIter = Iter<'map, K, V>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        Iter::new(self.incin.get_unchecked().pause(), &self.top).with_len(&self.len)
+    }
+}
+
+impl<'map, K, V, H> IntoIterator for &'map mut Map<K, V, H> {
+    type Item = (&'map K, &'map mut V);
+
+    type IntoIter = IterMut<'map, K, V>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        IterMut::new(&mut self.top)
+    }
+}
+
+impl<K, V, H> IntoIterator for Map<K, V, H> {
+    type Item = (K, V);
+
+    type IntoIter = IntoIter<K, V>;
+
+    fn into_iter(mut self) -> Self::IntoIter {
+        let len = self.len();
+        let raw = self.top.raw();
+        // Unfortunately, this unsafe is needed since there is no other way of
+        // dropping the field and forgetting the Map.
+        unsafe {
+            (&mut self.builder as *mut H).drop_in_place();
+            (&mut self.incin as *mut SharedIncin<K, V>).drop_in_place();
+            (&mut self.inits as *mut InitMarkers<K>).drop_in_place();
+            (&mut self.on_change as *mut Option<ChangeHook<K, V>>).drop_in_place();
+            mem::forget(self);
+            IntoIter::new(OwnedAlloc::from_raw(raw), len)
+        }
+    }
+}
+
+impl<K, V, H> Extend<(K, V)> for Map<K, V, H>
+where
+    H: BuildHasher,
+    K: Hash + Eq,
+{
+    fn extend<I>(&mut self, iterable: I)
+    where
+        I: IntoIterator<Item = (K, V)>,
+    {
+        (*self).extend(iterable)
+    }
+}
+
+impl<K, V, H> Extend<(K, V)> for &Map<K, V, H>
+where
+    H: BuildHasher,
+    K: Hash + Eq,
+{
+    fn extend<I>(&mut self, iterable: I)
+    where
+        I: IntoIterator<Item = (K, V)>,
+    {
+        (**self).extend(iterable)
+    }
+}
+
+impl<'a, K, V, H> Extend<(&'a K, &'a V)> for Map<K, V, H>
+where
+    H: BuildHasher,
+    K: Hash + Eq + Clone,
+    V: Clone,
+{
+    fn extend<I>(&mut self, iterable: I)
+    where
+        I: IntoIterator<Item = (&'a K, &'a V)>,
+    {
+        (*self).extend(
+            iterable
+                .into_iter()
+                .map(|(key, val)| (key.clone(), val.clone())),
+        )
+    }
+}
+
+impl<'a, K, V, H> Extend<(&'a K, &'a V)> for &Map<K, V, H>
+where
+    H: BuildHasher,
+    K: Hash + Eq + Clone,
+    V: Clone,
+{
+    fn extend<I>(&mut self, iterable: I)
+    where
+        I: IntoIterator<Item = (&'a K, &'a V)>,
+    {
+        (**self).extend(
+            iterable
+                .into_iter()
+                .map(|(key, val)| (key.clone(), val.clone())),
+        )
+    }
+}
+
+impl<K, V, H> FromIterator<(K, V)> for Map<K, V, H>
+where
+    H: BuildHasher + Default,
+    K: Hash + Eq,
+{
+    fn from_iter<I>(iterable: I) -> Self
+    where
+        I: IntoIterator<Item = (K, V)>,
+    {
+        let this = Self::default();
+        this.extend(iterable);
+        this
+    }
+}
+
+#[cfg(feature = "std")]
+impl<K, V, H> From<std::collections::HashMap<K, V, H>> for Map<K, V, H>
+where
+    H: BuildHasher + Clone,
+    K: Hash + Eq,
+{
+    /// Moves the entries of the `HashMap` into a new [`Map`] with a clone of
+    /// its hasher builder, building the tables in one pass just like
+    /// [`Map::from_entries_bulk`].
+    fn from(source: std::collections::HashMap<K, V, H>) -> Self {
+        let builder = source.hasher().clone();
+        Self::from_entries_bulk(source.into_iter().collect(), builder)
+    }
+}
+
+#[cfg(feature = "std")]
+impl<K, V, H> From<Map<K, V, H>> for std::collections::HashMap<K, V, H>
+where
+    H: BuildHasher + Clone,
+    K: Hash + Eq,
+{
+    /// Moves the entries of the [`Map`] into a new `HashMap` with a clone of
+    /// its hasher builder. Nothing is cloned but the builder.
+    fn from(source: Map<K, V, H>) -> Self {
+        let mut target = Self::with_capacity_and_hasher(source.len(), source.hasher().clone());
+        target.extend(source);
+        target
+    }
+}
+
+unsafe impl<K, V, H> Send for Map<K, V, H>
+where
+    K: Send,
+    V: Send,
+    H: Send,
+{
+}
+
+unsafe impl<K, V, H> Sync for Map<K, V, H>
+where
+    K: Sync,
+    V: Sync,
+    H: Sync,
+{
+}
 
 make_shared_incin! {
     { "[`Map`]" }
     pub SharedIncin<K, V> of Garbage<K, V>
 }
 
-impl<K, V> fmt::Debug for SharedIncin<K, V> {
-    fn fmt(&self, fmtr: &mut fmt::Formatter) -> fmt::Result {
-        write!(fmtr, "SharedIncin {{ inner: {:?} }}", self.inner)
+impl<K, V> fmt::Debug for SharedIncin<K, V> {
+    fn fmt(&self, fmtr: &mut fmt::Formatter) -> fmt::Result {
+        write!(fmtr, "SharedIncin {{ inner: {:?} }}", self.inner)
+    }
+}
+
+// Reads the length counter of a map. A concurrent removal may have been
+// counted before its creation, wrapping the counter below zero.
+fn clamp_len(len: usize) -> usize {
+    if len > isize::MAX as usize {
+        0
+    } else {
+        len
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod test {
+    use super::*;
+    use crate::{backoff::RetryDecision, test_util::with_writers};
+    use alloc::format;
+    use alloc::sync::Arc;
+    use core::{
+        hash::BuildHasherDefault,
+        sync::atomic::{AtomicBool, AtomicUsize},
+    };
+    use std::{
+        collections::HashMap,
+        panic::{catch_unwind, AssertUnwindSafe},
+        sync::Barrier,
+        thread,
+    };
+
+    // Removes and reinserts the keys below 100, each mapped to its double.
+    fn churn(map: &Map<u64, u64>, id: usize) {
+        for round in 0..50 {
+            for i in 0..100u64 {
+                if (i + round + id as u64) % 3 == 0 {
+                    map.remove(&i);
+                } else {
+                    map.insert(i, i * 2);
+                }
+            }
+        }
+    }
+
+    // Sends every key to one of only four buckets.
+    #[derive(Default)]
+    struct CollidingHasher(u64);
+
+    impl Hasher for CollidingHasher {
+        fn finish(&self) -> u64 {
+            self.0 % 4
+        }
+
+        fn write(&mut self, bytes: &[u8]) {
+            for &byte in bytes {
+                self.0 = self.0.wrapping_add(u64::from(byte));
+            }
+        }
+    }
+
+    #[test]
+    fn custom_hasher() {
+        let map = Map::with_hasher(BuildHasherDefault::<CollidingHasher>::default());
+        for i in 0..64u32 {
+            assert!(map.insert(i, i * 2).is_none());
+        }
+        for i in (0..64).step_by(2) {
+            assert_eq!(map.remove(&i).unwrap().val(), &(i * 2));
+        }
+        for i in 0..64 {
+            assert_eq!(
+                map.get(&i).map(|guard| *guard.val()),
+                (i % 2 == 1).then(|| i * 2)
+            );
+        }
+    }
+
+    #[test]
+    fn same_seed_same_order() {
+        let seed = *b"0123456789abcdef";
+        let first = Map::with_seed(seed);
+        let second = Map::with_seed(seed);
+        for i in 0..512u32 {
+            first.insert(i, i);
+            second.insert(i, i);
+        }
+        for i in (0..512).step_by(3) {
+            first.remove(&i);
+            second.remove(&i);
+        }
+        let first_keys = first.iter().map(|guard| *guard.key()).collect::<Vec<_>>();
+        let second_keys = second.iter().map(|guard| *guard.key()).collect::<Vec<_>>();
+        assert_eq!(first_keys, second_keys);
+    }
+
+    #[test]
+    fn inserts_and_gets() {
+        let map = Map::new();
+        assert!(map.get("five").is_none());
+        assert!(map.insert("five".to_owned(), 5).is_none());
+        assert_eq!(*map.get("five").unwrap().val(), 5);
+        assert!(map.get("four").is_none());
+        assert!(map.insert("four".to_owned(), 4).is_none());
+        assert_eq!(*map.get("five").unwrap().val(), 5);
+        assert_eq!(*map.get("four").unwrap().val(), 4);
+        let guard = map.get("four").unwrap();
+        assert_eq!(guard.key(), "four");
+        assert_eq!(*guard.val(), 4);
+    }
+
+    #[test]
+    fn create() {
+        let map = Map::new();
+        assert!(map
+            .insert_with("five".to_owned(), |_, _, stored| if stored.is_none() {
+                Preview::New(5)
+            } else {
+                Preview::Discard
+            },)
+            .created());
+        assert_eq!(*map.get("five").unwrap().val(), 5);
+        assert!(map
+            .insert_with("five".to_owned(), |_, _, stored| if stored.is_none() {
+                Preview::New(500)
+            } else {
+                Preview::Discard
+            },)
+            .failed()
+            .is_some());
+    }
+
+    #[test]
+    fn update() {
+        let map = Map::new();
+        assert!(map
+            .insert_with("five".to_owned(), |_, _, stored| {
+                if let Some((_, n)) = stored {
+                    Preview::New(*n + 6)
+                } else {
+                    Preview::Discard
+                }
+            })
+            .failed()
+            .is_some());
+        assert!(map.insert("five".to_owned(), 5).is_none());
+        let guard = map
+            .insert_with("five".to_owned(), |_, _, stored| {
+                if let Some((_, n)) = stored {
+                    Preview::New(*n + 7)
+                } else {
+                    Preview::Discard
+                }
+            })
+            .take_updated()
+            .unwrap();
+        assert_eq!(guard.key(), "five");
+        assert_eq!(*guard.val(), 5);
+        assert_eq!(*map.get("five").unwrap().val(), 12);
+    }
+
+    #[test]
+    fn never_inserts() {
+        let map = Map::new();
+        assert!(map
+            .insert_with("five".to_owned(), |_, _, _| Preview::Discard)
+            .failed()
+            .is_some());
+        assert!(map.insert("five".to_owned(), 5).is_none());
+        assert!(map
+            .insert_with("five".to_owned(), |_, _, _| Preview::Discard)
+            .failed()
+            .is_some());
+    }
+
+    #[test]
+    fn inserts_reinserts() {
+        let map = Map::new();
+        assert!(map.insert("four".to_owned(), 4).is_none());
+        let prev = map.insert("four".to_owned(), 40).unwrap();
+        assert_eq!(prev.key(), "four");
+        assert_eq!(*prev.val(), 4);
+        let prev = map.reinsert(prev).take_updated().unwrap();
+        assert_eq!(prev.key(), "four");
+        assert_eq!(*prev.val(), 40);
+        assert!(*map.get("four").unwrap().val() == 4);
+    }
+
+    #[test]
+    fn never_reinserts() {
+        let map = Map::new();
+        map.insert("five".to_owned(), 5);
+        let prev = map.remove("five").unwrap();
+        let prev = map.reinsert_with(prev, |_, _| false).take_failed().unwrap();
+        assert!(map.insert("five".to_owned(), 5).is_none());
+        map.reinsert_with(prev, |_, _| false).take_failed().unwrap();
+    }
+
+    #[test]
+    fn reinserts_create() {
+        let map = Map::new();
+        map.insert("five".to_owned(), 5);
+        let first = map.remove("five").unwrap();
+        map.insert("five".to_owned(), 5);
+        let second = map.remove("five").unwrap();
+        assert!(map
+            .reinsert_with(first, |_, stored| stored.is_none())
+            .created());
+        assert_eq!(*map.get("five").unwrap().val(), 5);
+        assert!(map
+            .reinsert_with(second, |_, stored| stored.is_none())
+            .failed()
+            .is_some());
+    }
+
+    #[test]
+    fn reinserts_update() {
+        let map = Map::new();
+        map.insert("five".to_owned(), 5);
+        let prev = map.remove("five").unwrap();
+        let prev = map
+            .reinsert_with(prev, |_, stored| stored.is_some())
+            .take_failed()
+            .unwrap();
+        map.insert("five".to_owned(), 5);
+        assert!(map
+            .reinsert_with(prev, |_, stored| stored.is_some())
+            .updated()
+            .is_some());
+    }
+
+    #[test]
+    fn inserts_and_removes() {
+        let map = Map::new();
+        assert!(map.remove("five").is_none());
+        assert!(map.remove("four").is_none());
+        map.insert("five".to_owned(), 5);
+        let removed = map.remove("five").unwrap();
+        assert_eq!(removed.key(), "five");
+        assert_eq!(*removed.val(), 5);
+        assert!(map.insert("four".to_owned(), 4).is_none());
+        map.insert("three".to_owned(), 3);
+        assert!(map.remove("two").is_none());
+        map.insert("two".to_owned(), 2);
+        let removed = map.remove("three").unwrap();
+        assert_eq!(removed.key(), "three");
+        assert_eq!(*removed.val(), 3);
+        let removed = map.remove("two").unwrap();
+        assert_eq!(removed.key(), "two");
+        assert_eq!(*removed.val(), 2);
+        let removed = map.remove("four").unwrap();
+        assert_eq!(removed.key(), "four");
+        assert_eq!(*removed.val(), 4);
+    }
+
+    #[test]
+    fn repeated_inserts() {
+        let map = Map::new();
+        assert!(map.insert("five".to_owned(), 5).is_none());
+        assert!(*map.insert("five".to_owned(), 5).unwrap().val() == 5);
+    }
+
+    #[test]
+    fn reinsert_from_other_map_fails() {
+        let other = Map::new();
+        other.insert(5, 3);
+        other.insert(0, 0);
+        let removed = other.remove(&5).unwrap();
+        let _active_read = other.get(&0).unwrap();
+        let map = Map::new();
+        map.reinsert(removed).failed().unwrap();
+    }
+
+    #[test]
+    fn iter_valid_items() {
+        let map = Map::new();
+        for i in 0..10u128 {
+            for j in 0..32 {
+                map.insert((i, j), i << j);
+            }
+        }
+
+        let mut result = HashMap::new();
+        for guard in &map {
+            let (k, v) = *guard;
+            let in_place = result.get(&(k, v)).map_or(0, |&x| x);
+            result.insert((k, v), in_place + 1);
+        }
+
+        for i in 0..10 {
+            for j in 0..32 {
+                let pair = ((i, j), i << j);
+                assert_eq!(*result.get(&pair).unwrap(), 1);
+            }
+        }
+    }
+
+    #[test]
+    fn footprint_follows_entries() {
+        let mut map = Map::new();
+        let mut last = map.bytes_allocated();
+        for i in 0..1000u64 {
+            map.insert(i, i);
+            let now = map.bytes_allocated();
+            assert!(now > last);
+            last = now;
+        }
+        assert_eq!(map.garbage_bytes(), 0);
+
+        {
+            // Keeps removed entries from being dropped.
+            let _guard = map.get(&0);
+            for i in 0..1000 {
+                map.remove(&i);
+            }
+            assert!(map.garbage_bytes() > 0);
+        }
+
+        map.optimize_space();
+        assert_eq!(map.garbage_bytes(), 0);
+        assert!(map.bytes_allocated() < last);
+    }
+
+    #[test]
+    fn optimize_space_preserves_entries() {
+        let mut map = Map::new();
+        for i in 0..200u128 {
+            for j in 0..128 {
+                map.insert((i, j), i << j);
+            }
+        }
+
+        for i in 0..200 {
+            for j in 0..16 {
+                map.remove(&(i, j));
+            }
+        }
+
+        map.optimize_space();
+
+        let mut result = HashMap::new();
+        for guard in &map {
+            let (k, v) = *guard;
+            let in_place = result.get(&(k, v)).map_or(0, |&x| x);
+            result.insert((k, v), in_place + 1);
+        }
+
+        for i in 0..200 {
+            for j in 16..128 {
+                let pair = ((i, j), i << j);
+                assert_eq!(*result.get(&pair).unwrap(), 1);
+            }
+        }
+    }
+
+    #[test]
+    fn iter_exact_knows_its_length() {
+        let mut map = Map::new();
+        assert_eq!(map.iter_exact().len(), 0);
+        for i in 0..1000u32 {
+            map.insert(i, i);
+        }
+
+        let mut iter = map.iter_exact();
+        assert_eq!(iter.len(), 1000);
+        iter.next().unwrap();
+        assert_eq!(iter.size_hint(), (999, Some(999)));
+        let rest = iter.collect::<Vec<_>>();
+        assert_eq!(rest.len(), 999);
+        assert!(rest.capacity() >= 999);
+        drop(rest);
+
+        let mut iter = map.iter();
+        assert_eq!(iter.size_hint(), (0, Some(1000)));
+        iter.next().unwrap();
+        let (lower, upper) = iter.size_hint();
+        assert!(lower < 1000);
+        assert_eq!(upper, Some(999));
+        assert_eq!(iter.count(), 999);
+
+        // Entries inserted meanwhile may exceed the estimate.
+        let map = Map::new();
+        map.insert(0, 0);
+        let mut iter = map.iter();
+        assert_eq!(iter.size_hint(), (0, Some(1)));
+        for i in 1..100 {
+            map.insert(i, i);
+        }
+        iter.next().unwrap();
+        let (lower, upper) = iter.size_hint();
+        assert!(upper.unwrap() >= lower);
+    }
+
+    #[test]
+    fn iter_mut_and_into_iter() {
+        let mut map = Map::new();
+        for i in 0..10u128 {
+            for j in 0..32 {
+                map.insert((i, j), i << j);
+            }
+        }
+
+        let mut result = HashMap::new();
+        for (k, v) in &mut map {
+            let in_place = result.get(&(*k, *v)).map_or(0, |&x| x);
+            result.insert((*k, *v), in_place + 1);
+            *v += 1;
+        }
+
+        for i in 0..10 {
+            for j in 0..32 {
+                let pair = ((i, j), i << j);
+                assert_eq!(*result.get(&pair).unwrap(), 1);
+            }
+        }
+
+        result.clear();
+
+        for (k, v) in map {
+            let in_place = result.get(&(k, v)).map_or(0, |&x| x);
+            result.insert((k, v), in_place + 1);
+        }
+
+        for i in 0..10 {
+            for j in 0..32 {
+                let pair = ((i, j), (i << j) + 1);
+                assert_eq!(*result.get(&pair).unwrap(), 1);
+            }
+        }
+    }
+
+    #[test]
+    fn iter_mut_updates_in_place() {
+        let mut map = Map::new();
+        for i in 0..100 {
+            map.insert(i, i);
+        }
+        for i in (0..100).step_by(3) {
+            map.remove(&i);
+        }
+
+        let mut visited = 0;
+        for (key, val) in map.iter_mut() {
+            assert_eq!(key, val);
+            *val *= 2;
+            visited += 1;
+        }
+        assert_eq!(visited, map.len());
+
+        for i in 0..100 {
+            let expected = if i % 3 == 0 { None } else { Some(i * 2) };
+            assert_eq!(map.get(&i).map(|guard| *guard.val()), expected);
+        }
+        assert!(format!("{:?}", map.iter_mut()).starts_with("IterMut"));
+    }
+
+    #[test]
+    fn into_iter_is_exact_and_drops_the_rest() {
+        let map = Map::with_hasher(BuildHasherDefault::<CollidingHasher>::default());
+        let val = Arc::new(());
+        for i in 0..500u32 {
+            map.insert(i, val.clone());
+        }
+        map.remove(&3);
+
+        let mut iter = map.into_iter();
+        assert_eq!(iter.len(), 499);
+        assert_eq!(iter.by_ref().take(100).count(), 100);
+        assert_eq!(iter.size_hint(), (399, Some(399)));
+        drop(iter);
+        assert_eq!(Arc::strong_count(&val), 1);
+    }
+
+    #[test]
+    fn with_capacity_preallocates_tables() {
+        let empty = Map::<u32, u32>::new().bytes_allocated();
+        assert_eq!(Map::<u32, u32>::with_capacity(100).bytes_allocated(), empty);
+
+        let mut map = Map::with_capacity(10_000);
+        assert!(map.bytes_allocated() > 100 * empty);
+        for i in 0..10_000u32 {
+            map.insert(i, i);
+        }
+        for i in 0..10_000 {
+            assert_eq!(*map.get(&i).unwrap().val(), i);
+        }
+        assert_eq!(map.len(), 10_000);
+
+        map.clear();
+        map.optimize_space();
+        assert_eq!(map.bytes_allocated(), empty);
+    }
+
+    #[test]
+    fn with_huge_capacity_is_bounded() {
+        let large = Map::<u32, u32>::with_capacity(1 << 20).bytes_allocated();
+        let map = Map::<u32, u32>::with_capacity(usize::MAX >> 8);
+        assert_eq!(map.bytes_allocated(), large);
+        map.insert(1, 1);
+        assert_eq!(*map.get(&1).unwrap().val(), 1);
+    }
+
+    #[test]
+    fn stats_describe_the_structure() {
+        let map = Map::with_hasher(BuildHasherDefault::<CollidingHasher>::default());
+        for i in 0..100u32 {
+            map.insert(i, i);
+        }
+        let stats = map.stats();
+        assert_eq!(stats.tables, 1);
+        assert_eq!(stats.buckets, 4);
+        assert_eq!(stats.entries, 100);
+        assert_eq!(stats.max_depth, 1);
+        assert_eq!(stats.bytes, map.bytes_allocated());
+
+        let map = Map::new();
+        for i in 0..10_000u32 {
+            map.insert(i, i);
+        }
+        for i in 0..5000 {
+            map.remove(&i);
+        }
+        let stats = map.stats();
+        assert_eq!(stats.entries, 5000);
+        assert!(stats.tables > 1);
+        assert!(stats.max_depth > 1);
+        assert!(stats.buckets <= stats.entries);
+        assert_eq!(stats.bytes, map.bytes_allocated());
+    }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn par_iter_visits_every_entry_once() {
+        use rayon::prelude::*;
+
+        let map = Map::new();
+        for i in 0..100_000u64 {
+            map.insert(i, i);
+        }
+        for i in (0..100_000).step_by(7) {
+            map.remove(&i);
+        }
+
+        let mut keys = map.par_iter().map(|guard| *guard.key()).collect::<Vec<_>>();
+        keys.sort_unstable();
+        let expected = (0..100_000).filter(|i| i % 7 != 0).collect::<Vec<_>>();
+        assert_eq!(keys, expected);
+
+        let max = (&map).into_par_iter().map(|guard| *guard.val()).max();
+        assert_eq!(max, Some(99_999));
+        let sum = map.par_iter().map(|guard| *guard.val()).sum::<u64>();
+        assert_eq!(sum, expected.iter().sum::<u64>());
+    }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn par_iter_while_clearing_and_optimizing() {
+        use rayon::prelude::*;
+
+        const NKEY: u64 = 20_000;
+
+        let map = Arc::new(Map::new());
+        let done = Arc::new(AtomicBool::new(false));
+
+        let shrinker = {
+            let map = map.clone();
+            let done = done.clone();
+            thread::spawn(move || {
+                while !done.load(Acquire) {
+                    for i in 0..NKEY {
+                        map.insert(i, i);
+                    }
+                    for i in (0..NKEY).filter(|i| i % 4 != 0) {
+                        map.remove(&i);
+                    }
+                    while !map.optimize_space_step().done {}
+                    map.clear_shared();
+                }
+            })
+        };
+
+        for _ in 0..50 {
+            let keys = map.par_iter().map(|guard| *guard.key()).collect::<Vec<_>>();
+            for key in keys {
+                assert!(key < NKEY);
+            }
+            let sum = map
+                .par_iter()
+                .map(|guard| *guard.key() - *guard.val())
+                .sum::<u64>();
+            assert_eq!(sum, 0);
+        }
+
+        done.store(true, Release);
+        shrinker.join().unwrap();
+    }
+
+    #[test]
+    fn owned_guard_survives_removals() {
+        let map = Arc::new(Map::new());
+        for i in 0..64 {
+            map.insert(i, i * 2);
+        }
+        let guard = map.get_owned(&7).unwrap();
+        for i in 0..64 {
+            map.remove(&i);
+        }
+        map.insert(7, 100);
+        assert_eq!(*guard.key(), 7);
+        assert_eq!(*guard.val(), 14);
+        assert_eq!(*map.get(&7).unwrap().val(), 100);
+    }
+
+    #[test]
+    fn owned_guard_outlives_handle() {
+        let map = Arc::new(Map::new());
+        map.insert("five".to_owned(), 5);
+        let guard = map.get_owned("five").unwrap();
+        assert!(map.get_owned("four").is_none());
+        drop(map);
+        let handle = thread::spawn(move || *guard.val());
+        assert_eq!(handle.join().unwrap(), 5);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serializes_guards_and_removed() {
+        let map = Arc::new(Map::new());
+        map.insert("five".to_owned(), vec![5u8]);
+        let expected = serde_json::to_string(&("five".to_owned(), vec![5u8])).unwrap();
+        let guard = map.get("five").unwrap();
+        assert_eq!(serde_json::to_string(&guard).unwrap(), expected);
+        drop(guard);
+        let owned = map.get_owned("five").unwrap();
+        assert_eq!(serde_json::to_string(&owned).unwrap(), expected);
+        drop(owned);
+        let removed = map.remove("five").unwrap();
+        assert_eq!(serde_json::to_string(&removed).unwrap(), expected);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_round_trip() {
+        let map = Map::<String, Map<u32, Vec<String>>>::new();
+        for i in 0..10 {
+            let inner = (0..i).map(|j| (j, vec![format!("{}", j)])).collect();
+            map.insert(format!("key{}", i), inner);
+        }
+        // Serializing while a guard is held works just the same.
+        let _guard = map.get("key3").unwrap();
+
+        let json = serde_json::to_string(&map).unwrap();
+        let back: Map<String, Map<u32, Vec<String>>> = serde_json::from_str(&json).unwrap();
+        assert_eq!(back, map);
+        assert_eq!(back.get("key9").unwrap().val().len(), 9);
+
+        // The last value of a repeated key wins.
+        let map: Map<String, u32> = serde_json::from_str(r#"{"a": 1, "b": 2, "a": 3}"#).unwrap();
+        assert_eq!(map.len(), 2);
+        assert_eq!(map.get_value("a"), Some(3));
+    }
+
+    #[test]
+    fn try_into_with_gives_up_intact() {
+        let map = Map::new();
+        map.insert(5, 3);
+        map.insert(0, 0);
+        let removed = map.remove(&5).unwrap();
+        let _active_read = map.get(&0).unwrap();
+        let mut attempts = 0;
+        let removed = Removed::try_into_with(removed, &mut |attempt| {
+            attempts = attempt + 1;
+            if attempt < 3 {
+                RetryDecision::Spin(4)
+            } else {
+                RetryDecision::GiveUp
+            }
+        })
+        .unwrap_err();
+        assert_eq!(attempts, 4);
+        assert_eq!(*removed, (5, 3));
+    }
+
+    #[test]
+    fn try_into_with_succeeds_after_reads_end() {
+        let map = Map::new();
+        map.insert(5, 3);
+        map.insert(0, 0);
+        let removed = map.remove(&5).unwrap();
+        let mut active_read = Some(map.get(&0).unwrap());
+        let pair = Removed::try_into_with(removed, &mut |attempt| {
+            if attempt == 2 {
+                active_read = None;
+            }
+            RetryDecision::Yield
+        });
+        assert_eq!(pair.unwrap(), (5, 3));
+    }
+
+    fn count_reclaims(removed: &mut Removed<u64, u64>, counter: &Arc<AtomicUsize>) {
+        let counter = counter.clone();
+        Removed::on_reclaim(removed, move |_| {
+            counter.fetch_add(1, Relaxed);
+        });
+    }
+
+    #[test]
+    fn reclaim_callback_waits_for_pause() {
+        let map = Map::new();
+        map.insert(5, 3);
+        map.insert(0, 0);
+        let reclaimed = Arc::new(AtomicUsize::new(0));
+        let active_read = map.get(&0).unwrap();
+        let mut removed = map.remove(&5).unwrap();
+        count_reclaims(&mut removed, &reclaimed);
+        drop(removed);
+        assert_eq!(reclaimed.load(Relaxed), 0);
+        drop(active_read);
+        assert_eq!(reclaimed.load(Relaxed), 1);
+        map.insert(1, 1);
+        map.remove(&1);
+        assert_eq!(reclaimed.load(Relaxed), 1);
+    }
+
+    #[test]
+    fn reclaim_callback_runs_once() {
+        let map = Map::new();
+        let reclaimed = Arc::new(AtomicUsize::new(0));
+
+        map.insert(5, 3);
+        let mut removed = map.remove(&5).unwrap();
+        count_reclaims(&mut removed, &reclaimed);
+        count_reclaims(&mut removed, &reclaimed);
+        drop(removed);
+        assert_eq!(reclaimed.load(Relaxed), 2);
+
+        map.insert(5, 3);
+        let mut removed = map.remove(&5).unwrap();
+        count_reclaims(&mut removed, &reclaimed);
+        assert_eq!(Removed::try_into(removed).unwrap(), (5, 3));
+        assert_eq!(reclaimed.load(Relaxed), 3);
+
+        map.insert(5, 3);
+        let mut removed = map.remove(&5).unwrap();
+        count_reclaims(&mut removed, &reclaimed);
+        assert!(map.reinsert(removed).created());
+        drop(map);
+        assert_eq!(reclaimed.load(Relaxed), 3);
+    }
+
+    #[test]
+    fn reclaim_callbacks_run_in_order() {
+        let map = Map::new();
+        map.insert(5, 3);
+        let mut removed = map.remove(&5).unwrap();
+        let order = Arc::new(AtomicUsize::new(0));
+        for i in 1..=3 {
+            let order = order.clone();
+            Removed::on_reclaim(&mut removed, move |_| {
+                assert_eq!(order.fetch_add(1, Relaxed), i - 1);
+            });
+        }
+        drop(removed);
+        assert_eq!(order.load(Relaxed), 3);
+    }
+
+    #[test]
+    fn get_or_init_runs_once() {
+        const NTHREAD: usize = 16;
+
+        for _ in 0..20 {
+            let map = Arc::new(Map::new());
+            let runs = Arc::new(AtomicUsize::new(0));
+            let barrier = Arc::new(Barrier::new(NTHREAD));
+            let threads = (0..NTHREAD)
+                .map(|i| {
+                    let map = map.clone();
+                    let runs = runs.clone();
+                    let barrier = barrier.clone();
+                    thread::spawn(move || {
+                        barrier.wait();
+                        let guard = map.get_or_init(7u32, || {
+                            runs.fetch_add(1, Relaxed);
+                            i
+                        });
+                        *guard.val()
+                    })
+                })
+                .collect::<Vec<_>>();
+
+            let vals = threads
+                .into_iter()
+                .map(|thread| thread.join().unwrap())
+                .collect::<Vec<_>>();
+            assert_eq!(runs.load(Relaxed), 1);
+            assert!(vals.iter().all(|&val| val == vals[0]));
+        }
+    }
+
+    #[test]
+    fn get_or_init_retries_after_panic() {
+        let map = Arc::new(Map::new());
+        let barrier = Arc::new(Barrier::new(2));
+
+        let panicking = {
+            let map = map.clone();
+            let barrier = barrier.clone();
+            thread::spawn(move || {
+                map.get_or_init("key", || {
+                    barrier.wait();
+                    panic!("failed initialization");
+                });
+            })
+        };
+
+        // We start waiting while the other thread holds the key.
+        barrier.wait();
+        assert_eq!(*map.get_or_init("key", || 5).val(), 5);
+        assert!(panicking.join().is_err());
+        assert_eq!(*map.get_or_init("key", || unreachable!()).val(), 5);
+    }
+
+    #[test]
+    fn get_or_init_nested_colliding_keys() {
+        // Both keys hash to the same value, so they share an init marker.
+        let map = Map::with_hasher(BuildHasherDefault::<CollidingHasher>::default());
+        assert_eq!(map.hash_key(&0u32), map.hash_key(&4u32));
+
+        let outer = map.get_or_init(0u32, || *map.get_or_init(4, || 8).val() + 1);
+        assert_eq!(*outer.val(), 9);
+        assert_eq!(map.get(&4).map(|guard| *guard.val()), Some(8));
+    }
+
+    #[test]
+    fn get_or_insert_keeps_existing() {
+        let map = Map::new();
+        let first = Arc::new(1);
+        let second = Arc::new(2);
+
+        assert_eq!(**map.get_or_insert("key", first.clone()).val(), 1);
+        assert_eq!(Arc::strong_count(&first), 2);
+        let guard = map.get_or_insert("key", second.clone());
+        assert_eq!(**guard.val(), 1);
+        // The rejected value was dropped.
+        assert_eq!(Arc::strong_count(&second), 1);
+        assert_eq!(map.len(), 1);
+    }
+
+    #[test]
+    fn get_or_insert_races() {
+        const NTHREAD: usize = 8;
+
+        let map = Arc::new(Map::new());
+        let barrier = Arc::new(Barrier::new(NTHREAD));
+        let threads = (0..NTHREAD)
+            .map(|t| {
+                let map = map.clone();
+                let barrier = barrier.clone();
+                thread::spawn(move || {
+                    barrier.wait();
+                    (0..100)
+                        .map(|key| *map.get_or_insert(key, t).val())
+                        .collect::<Vec<_>>()
+                })
+            })
+            .collect::<Vec<_>>();
+
+        let seen = threads
+            .into_iter()
+            .map(|thread| thread.join().unwrap())
+            .collect::<Vec<_>>();
+        // Every thread sees the value of whichever thread won each key.
+        for key in 0..100 {
+            let winner = *map.get(&key).unwrap().val();
+            assert!(seen.iter().all(|vals| vals[key] == winner));
+        }
+        assert_eq!(map.len(), 100);
+    }
+
+    #[test]
+    fn get_or_insert_with_builds_once() {
+        const NTHREAD: usize = 8;
+
+        let map = Arc::new(Map::new());
+        let built = Arc::new(AtomicUsize::new(0));
+        let barrier = Arc::new(Barrier::new(NTHREAD));
+        let threads = (0..NTHREAD)
+            .map(|t| {
+                let map = map.clone();
+                let built = built.clone();
+                let barrier = barrier.clone();
+                thread::spawn(move || {
+                    barrier.wait();
+                    for key in 0..100 {
+                        let mut calls = 0;
+                        let guard = map.get_or_insert_with(key, || {
+                            calls += 1;
+                            built.fetch_add(1, Relaxed);
+                            t
+                        });
+                        if *guard.val() == t {
+                            assert_eq!(calls, 1);
+                        }
+                    }
+                })
+            })
+            .collect::<Vec<_>>();
+        for thread in threads {
+            thread.join().unwrap();
+        }
+
+        assert_eq!(map.len(), 100);
+        // Racing threads may build values that are dropped, but no more than
+        // one per thread and key.
+        let built = built.load(Relaxed);
+        assert!((100..=100 * NTHREAD).contains(&built));
+
+        let mut calls = 0;
+        map.get_or_insert_with(100, || {
+            calls += 1;
+            0
+        });
+        assert_eq!(calls, 1);
+
+        let mut calls = 0;
+        map.get_or_insert_with(0, || {
+            calls += 1;
+            0
+        });
+        assert_eq!(calls, 0);
+    }
+
+    #[test]
+    fn update_never_hides_the_key() {
+        const NTHREAD: usize = 4;
+        const NITER: u64 = 1000;
+
+        let map = Arc::new(Map::new());
+        map.insert("counter".to_owned(), 0u64);
+        let done = Arc::new(AtomicBool::new(false));
+
+        let reader = {
+            let map = map.clone();
+            let done = done.clone();
+            thread::spawn(move || {
+                let mut last = 0;
+                while !done.load(Acquire) {
+                    let val = *map.get("counter").unwrap().val();
+                    assert!(val >= last);
+                    last = val;
+                }
+            })
+        };
+
+        let writers = (0..NTHREAD)
+            .map(|_| {
+                let map = map.clone();
+                thread::spawn(move || {
+                    for _ in 0..NITER {
+                        let guard = map.update("counter", |val| val + 1).unwrap();
+                        assert!(*guard.val() > 0);
+                    }
+                })
+            })
+            .collect::<Vec<_>>();
+        for writer in writers {
+            writer.join().unwrap();
+        }
+        done.store(true, Release);
+        reader.join().unwrap();
+
+        assert_eq!(*map.get("counter").unwrap().val(), NTHREAD as u64 * NITER);
+        assert!(map.update("missing", |val| val + 1).is_none());
+        assert_eq!(map.len(), 1);
+    }
+
+    #[test]
+    fn remove_value_moves_or_clones() {
+        let map = Map::new();
+        let val = Arc::new(0);
+        map.insert(0, val.clone());
+        map.insert(1, val.clone());
+
+        // Nothing reads the entry, so the value is moved out.
+        let moved = map.remove_value(&0).unwrap();
+        assert_eq!(Arc::strong_count(&val), 3);
+        drop(moved);
+
+        // A guard keeps reading the entry, so the value is cloned.
+        let guard = map.get(&1).unwrap();
+        let cloned = map.remove_value(&1).unwrap();
+        assert_eq!(Arc::strong_count(&val), 3);
+        assert!(Arc::ptr_eq(&cloned, guard.val()));
+        assert_eq!(map.remove_value(&1), None);
+        assert!(map.is_empty());
+    }
+
+    #[test]
+    fn collect_and_extend() {
+        let map = Vec::<(u32, u32)>::new().into_iter().collect::<Map<_, _>>();
+        assert!(map.is_empty());
+
+        // The last write wins on duplicate keys.
+        let mut map = vec![(1, 1), (2, 2), (1, 3)]
+            .into_iter()
+            .collect::<Map<_, _>>();
+        assert_eq!(map.len(), 2);
+        assert_eq!(*map.get(&1).unwrap().val(), 3);
+
+        map.extend(vec![(2, 4), (3, 3)]);
+        map.extend(Vec::new());
+        // The inherent `extend` takes precedence over the trait methods.
+        let source = vec![(4, 4), (3, 5)].into_iter().collect::<HashMap<_, _>>();
+        Extend::extend(&mut map, &source);
+        assert_eq!(map.len(), 4);
+
+        let shared = Arc::new(map);
+        let threads = (0..4)
+            .map(|t| {
+                let shared = shared.clone();
+                thread::spawn(move || {
+                    let mut map = &*shared;
+                    map.extend((0..100).map(|i| (10 + t * 100 + i, i)));
+                    Extend::extend(
+                        &mut map,
+                        &[(5, 5)].iter().copied().collect::<HashMap<_, _>>(),
+                    );
+                })
+            })
+            .collect::<Vec<_>>();
+        for thread in threads {
+            thread.join().unwrap();
+        }
+
+        assert_eq!(shared.len(), 405);
+        let mut pairs = shared.iter().map(|guard| *guard).collect::<Vec<_>>();
+        pairs.sort_unstable();
+        assert_eq!(pairs[..5], [(1, 3), (2, 4), (3, 5), (4, 4), (5, 5)]);
+    }
+
+    #[test]
+    fn retain_visits_every_entry_once() {
+        // Colliding hashes force nested tables and buckets with many entries.
+        let mut map = Map::with_hasher(BuildHasherDefault::<CollidingHasher>::default());
+        for i in 0..1000u32 {
+            map.insert(i, Arc::new(i));
+        }
+        // Logically removed entries are left behind in the buckets.
+        let kept = map.remove(&0).unwrap();
+
+        let mut visited = Vec::new();
+        map.retain(|&key, _| {
+            visited.push(key);
+            key % 3 != 0
+        });
+        visited.sort_unstable();
+        assert_eq!(visited, (1..1000).collect::<Vec<_>>());
+
+        assert_eq!(map.len(), 666);
+        assert_eq!(map.iter().count(), 666);
+        assert!(map.iter().all(|guard| guard.key() % 3 != 0));
+        assert_eq!(**kept.val(), 0);
+    }
+
+    #[test]
+    fn retain_unwinds() {
+        let mut map = Map::new();
+        let val = Arc::new(());
+        for i in 0..100 {
+            map.insert(i, val.clone());
+        }
+
+        let mut calls = 0;
+        let res = catch_unwind(AssertUnwindSafe(|| {
+            map.retain(|_, _| {
+                calls += 1;
+                if calls == 50 {
+                    panic!("predicate failed");
+                }
+                false
+            })
+        }));
+        assert!(res.is_err());
+
+        // The first 49 entries visited were freed, and no other leaked.
+        assert_eq!(map.len(), 51);
+        assert_eq!(Arc::strong_count(&val), 52);
+        map.retain(|_, _| true);
+        assert_eq!(map.iter().count(), 51);
+        drop(map);
+        assert_eq!(Arc::strong_count(&val), 1);
+    }
+
+    #[test]
+    fn drain_empties_the_map() {
+        let mut map = Map::with_hasher(BuildHasherDefault::<CollidingHasher>::default());
+        let val = Arc::new(());
+        for i in 0..1000u32 {
+            map.insert(i, val.clone());
+        }
+
+        let mut keys = map.drain().map(|(key, _)| key).collect::<Vec<_>>();
+        keys.sort_unstable();
+        assert_eq!(keys, (0..1000).collect::<Vec<_>>());
+        assert!(map.is_empty());
+        assert_eq!(map.iter().count(), 0);
+        assert_eq!(Arc::strong_count(&val), 1);
+
+        // Dropping the iterator halfway drops the remaining entries.
+        for i in 0..1000u32 {
+            map.insert(i, val.clone());
+        }
+        assert_eq!(map.drain().take(10).count(), 10);
+        assert!(map.is_empty());
+        assert_eq!(map.iter().count(), 0);
+        assert_eq!(Arc::strong_count(&val), 1);
+
+        // The map is still usable afterwards.
+        map.insert(7, val.clone());
+        assert_eq!(map.len(), 1);
+        assert!(map.get(&7).is_some());
+    }
+
+    #[test]
+    fn contains_key_follows_removals() {
+        let map = Map::new();
+        map.insert("a".to_owned(), 1);
+        map.insert("b".to_owned(), 1);
+        assert!(map.contains_key("a"));
+        assert!(map.contains_key(&"b".to_owned()));
+
+        let removed = map.remove("a").unwrap();
+        assert!(!map.contains_key("a"));
+        assert!(map.contains_key("b"));
+        assert!(map.reinsert(removed).created());
+        assert!(map.contains_key("a"));
+
+        // Only keys matter, not values.
+        map.insert("a".to_owned(), 2);
+        assert!(map.contains_key("a"));
+        assert!(!map.contains_key("c"));
+    }
+
+    #[test]
+    fn contains_key_compares_only_keys() {
+        // Keys 0, 4 and 8 share a hash, and all entries share a value.
+        let map = Map::with_hasher(BuildHasherDefault::<CollidingHasher>::default());
+        map.insert(0u32, ());
+        map.insert(4, ());
+        assert!(map.contains_key(&0));
+        assert!(map.contains_key(&4));
+        assert!(!map.contains_key(&8));
+
+        // Searching keeps nothing paused, so the removed entry can be taken
+        // back and reinserted.
+        let removed = map.remove(&4).unwrap();
+        assert!(!map.contains_key(&4));
+        assert!(map.contains_key(&0));
+        let (key, val) = Removed::try_into(removed).unwrap();
+        map.insert(key, val);
+        assert!(map.contains_key(&4));
+    }
+
+    #[test]
+    fn contains_key_during_churn() {
+        let map = Arc::new(Map::with_hasher(
+            BuildHasherDefault::<CollidingHasher>::default(),
+        ));
+        map.insert(0u32, 0u32);
+        with_writers(
+            &map,
+            2,
+            |map, id| {
+                // Colliding keys are inserted and removed next to the one
+                // being searched.
+                let key = 4 * (id as u32 + 1);
+                for i in 0..1000 {
+                    map.insert(key, i);
+                    map.remove(&key);
+                }
+            },
+            |map| assert!(map.contains_key(&0)),
+        );
+        assert!(!map.contains_key(&4));
+        assert!(!map.contains_key(&8));
+    }
+
+    #[test]
+    fn clone_is_independent() {
+        let map = Map::with_hasher(BuildHasherDefault::<CollidingHasher>::default());
+        for i in 0..1000u32 {
+            map.insert(i, vec![i]);
+        }
+
+        let clone = map.clone();
+        assert_eq!(clone.len(), 1000);
+        for i in 0..1000u32 {
+            assert_eq!(*clone.get(&i).unwrap().val(), [i]);
+        }
+
+        map.remove(&0);
+        clone.insert(1000, vec![]);
+        assert!(clone.get(&0).is_some());
+        assert!(map.get(&1000).is_none());
+        assert_eq!(clone.iter().count(), 1001);
+    }
+
+    #[test]
+    fn clone_while_mutating() {
+        let map = Arc::new(Map::new());
+        with_writers(&map, 2, churn, |map| {
+            let clone = map.clone();
+            assert_eq!(clone.len(), clone.iter().count());
+            assert!(clone.iter().all(|guard| *guard.val() == guard.key() * 2));
+        });
+    }
+
+    #[test]
+    fn clone_unwinds() {
+        static CLONES: AtomicUsize = AtomicUsize::new(0);
+
+        struct Fragile(Arc<()>);
+
+        impl Clone for Fragile {
+            fn clone(&self) -> Self {
+                if CLONES.fetch_add(1, Relaxed) == 500 {
+                    panic!("clone failed");
+                }
+                Fragile(self.0.clone())
+            }
+        }
+
+        let map = Map::with_hasher(BuildHasherDefault::<CollidingHasher>::default());
+        let counter = Arc::new(());
+        for i in 0..1000u32 {
+            map.insert(i, Fragile(counter.clone()));
+        }
+
+        assert!(catch_unwind(AssertUnwindSafe(|| map.clone())).is_err());
+        // Everything cloned before the panic was dropped.
+        assert_eq!(Arc::strong_count(&counter), 1001);
+        assert_eq!(map.len(), 1000);
+    }
+
+    #[test]
+    fn eq_compares_entries() {
+        let expected = (0..100).map(|i| (i, i * 2)).collect::<Map<_, _>>();
+
+        let map = Arc::new(Map::with_hasher(
+            BuildHasherDefault::<CollidingHasher>::default(),
+        ));
+        let threads = (0..4)
+            .map(|t| {
+                let map = map.clone();
+                thread::spawn(move || {
+                    for i in (t..100).step_by(4) {
+                        map.insert(i, i * 2);
+                    }
+                })
+            })
+            .collect::<Vec<_>>();
+        for thread in threads {
+            thread.join().unwrap();
+        }
+
+        assert!(*map == expected);
+        assert!(expected == *map);
+        assert_eq!(expected, expected.clone());
+
+        map.insert(0, 1);
+        assert!(*map != expected);
+        map.insert(0, 0);
+        map.insert(100, 200);
+        assert!(*map != expected);
+        assert!(expected != *map);
+        map.remove(&100);
+        assert!(*map == expected);
+    }
+
+    #[test]
+    fn len_counts_entries() {
+        let mut map = Map::new();
+        assert!(map.is_empty());
+        for i in 0..100 {
+            map.insert(i, i);
+        }
+        map.insert(0, 0);
+        assert_eq!(map.len(), 100);
+
+        let created = map.insert_with(100, |_, _, _| Preview::New(100));
+        assert!(created.created());
+        let failed = map.insert_with(100, |_, _, _| Preview::Discard);
+        assert!(failed.failed().is_some());
+        assert!(ConcurrentInsert::try_insert(&map, (100, 0)).is_err());
+        assert_eq!(map.len(), 101);
+
+        let removed = map.remove(&100).unwrap();
+        assert!(map.remove_with(&0, |_| false).is_none());
+        assert_eq!(map.len(), 100);
+        assert!(map.reinsert_with(removed, |_, _| true).created());
+        assert_eq!(map.len(), 101);
+        map.get_or_init(101, || 101);
+        assert_eq!(map.len(), 102);
+
+        map.clear();
+        assert!(map.is_empty());
+    }
+
+    #[test]
+    fn len_under_concurrency() {
+        const NTHREAD: usize = 8;
+        const NITER: usize = 1000;
+
+        let map = Arc::new(Map::new());
+        let threads = (0..NTHREAD)
+            .map(|t| {
+                let map = map.clone();
+                thread::spawn(move || {
+                    for i in 0..NITER {
+                        let key = (t * NITER + i) % (NITER * 2);
+                        map.insert_with(key, |_, _, _| Preview::New(i));
+                        if i % 3 == 0 {
+                            map.remove(&key);
+                        }
+                    }
+                })
+            })
+            .collect::<Vec<_>>();
+        for thread in threads {
+            thread.join().unwrap();
+        }
+
+        assert_eq!(map.len(), map.iter().count());
+    }
+
+    #[test]
+    fn get_value_outlives_removal() {
+        let map = Map::new();
+        map.insert("key".to_owned(), Arc::new(5));
+
+        let val = map.get_value("key").unwrap();
+        assert_eq!(Arc::strong_count(&val), 2);
+        assert_eq!(map.get_value_with("key", |val| **val * 2), Some(10));
+        map.remove("key");
+        assert_eq!(*val, 5);
+        assert_eq!(map.get_value("key"), None);
+        assert_eq!(map.get_value_with("key", |_| unreachable!()), None::<()>);
+    }
+
+    #[test]
+    fn projected_guards_keep_the_entry() {
+        let map = Map::new();
+        map.insert(format!("{}", 3), (3u8, "three"));
+
+        let key = ReadGuard::key_guard(map.get("3").unwrap());
+        let val = ReadGuard::val_guard(map.get("3").unwrap());
+        let name = ReadGuard::map(map.get("3").unwrap(), |(_, (_, name))| *name);
+        let num = MappedGuard::map(val, |(num, _)| num);
+        map.remove("3");
+
+        assert_eq!(&*key, "3");
+        assert_eq!(*num, 3);
+        assert_eq!(&*name, "three");
+        assert!(map.get("3").is_none());
+    }
+
+    #[test]
+    fn get_many_keeps_the_order() {
+        let map = Map::new();
+        for i in (0..100u32).step_by(2) {
+            map.insert(i, i * 10);
+        }
+
+        let keys = (0..100).rev().collect::<Vec<_>>();
+        let found = map.get_many(&keys);
+        assert_eq!(found.len(), keys.len());
+        for (key, guard) in keys.iter().zip(&found) {
+            let expected = if key % 2 == 0 { Some(key * 10) } else { None };
+            assert_eq!(guard.as_ref().map(|guard| *guard.val()), expected);
+        }
+
+        // The guards keep removed entries readable.
+        map.remove(&0);
+        assert_eq!(*found.last().unwrap().as_ref().unwrap().val(), 0);
+        assert!(map.get_many(Vec::<&u32>::new()).is_empty());
+    }
+
+    #[test]
+    fn collect_while_removing() {
+        let map = Arc::new(Map::new());
+        for i in 0..10_000u32 {
+            map.insert(i, i);
+        }
+
+        let remover = {
+            let map = map.clone();
+            thread::spawn(move || {
+                for i in (0..10_000).rev() {
+                    map.remove(&i);
+                }
+            })
+        };
+
+        let mut iter = map.iter();
+        let mut keys = iter.by_ref().map(|guard| *guard.key()).collect::<Vec<_>>();
+        assert!(iter.next().is_none());
+        remover.join().unwrap();
+
+        // No entry is yielded twice, and none that was never there.
+        let found = keys.len();
+        keys.sort_unstable();
+        keys.dedup();
+        assert_eq!(keys.len(), found);
+        assert!(keys.iter().all(|&key| key < 10_000));
+        assert_eq!(map.keys().count(), 0);
+    }
+
+    #[test]
+    fn swap_only_replaces_present_keys() {
+        let map = Arc::new(Map::new());
+        for i in 0..8u64 {
+            map.insert(i, 0u64);
+        }
+
+        let threads = (0..4u64)
+            .map(|t| {
+                let map = map.clone();
+                thread::spawn(move || {
+                    for round in 1..=500 {
+                        for i in 0..8 {
+                            let old = map.swap(i, t * 1000 + round).unwrap();
+                            assert!(*old.val() < 4000);
+                        }
+                        assert_eq!(map.swap(100, round), Err((100, round)));
+                    }
+                })
+            })
+            .collect::<Vec<_>>();
+        for thread in threads {
+            thread.join().unwrap();
+        }
+
+        assert_eq!(map.len(), 8);
+        assert!(map.get(&100).is_none());
+        for i in 0..8 {
+            assert_eq!(*map.get(&i).unwrap().val() % 1000, 500);
+        }
+        map.remove(&3);
+        assert_eq!(map.swap(3, 7), Err((3, 7)));
     }
-}
 
-#[cfg(test)]
-mod test {
-    use super::*;
-    use alloc::format;
-    use alloc::sync::Arc;
-    use std::{collections::HashMap, thread};
+    #[test]
+    fn compare_and_swap_counts_exactly() {
+        const NTHREAD: usize = 4;
+        const NITER: u64 = 1000;
+
+        let map = Arc::new(Map::new());
+        map.insert("count".to_owned(), 0u64);
+
+        let threads = (0..NTHREAD)
+            .map(|_| {
+                let map = map.clone();
+                thread::spawn(move || {
+                    for _ in 0..NITER {
+                        let mut expected = *map.get("count").unwrap().val();
+                        while let Err(err) = map.compare_and_swap("count", &expected, expected + 1)
+                        {
+                            assert_eq!(err.new, expected + 1);
+                            expected = *err.current.unwrap().val();
+                        }
+                    }
+                })
+            })
+            .collect::<Vec<_>>();
+        for thread in threads {
+            thread.join().unwrap();
+        }
+
+        assert_eq!(*map.get("count").unwrap().val(), NTHREAD as u64 * NITER);
+        let err = map.compare_and_swap("missing", &0, 1).unwrap_err();
+        assert_eq!(err.new, 1);
+        assert!(err.current.is_none());
+        assert!(map.get("missing").is_none());
+    }
 
     #[test]
-    fn inserts_and_gets() {
+    fn modify_or_insert_counts_exactly() {
+        const NTHREAD: usize = 4;
+        const NITER: usize = 1000;
+
+        let map = Arc::new(Map::new());
+        let threads = (0..NTHREAD)
+            .map(|_| {
+                let map = map.clone();
+                thread::spawn(move || {
+                    for i in 0..NITER {
+                        map.modify_or_insert(i % 10, || 1, |count| count + 1);
+                    }
+                })
+            })
+            .collect::<Vec<_>>();
+        for thread in threads {
+            thread.join().unwrap();
+        }
+
+        assert_eq!(map.len(), 10);
+        for i in 0..10 {
+            assert_eq!(*map.get(&i).unwrap().val(), NTHREAD * NITER / 10);
+        }
+        let old = map.modify_or_insert(3, || unreachable!(), |count| count * 2);
+        assert_eq!(old.map(|old| *old.val()), Some(400));
+        assert_eq!(*map.get(&3).unwrap().val(), 800);
+    }
+
+    #[test]
+    #[should_panic(expected = "no entry found in the map for key \"b\"")]
+    fn get_expect_shows_the_key() {
         let map = Map::new();
-        assert!(map.get("five").is_none());
-        assert!(map.insert("five".to_owned(), 5).is_none());
-        assert_eq!(*map.get("five").unwrap().val(), 5);
-        assert!(map.get("four").is_none());
-        assert!(map.insert("four".to_owned(), 4).is_none());
-        assert_eq!(*map.get("five").unwrap().val(), 5);
-        assert_eq!(*map.get("four").unwrap().val(), 4);
-        let guard = map.get("four").unwrap();
-        assert_eq!(guard.key(), "four");
-        assert_eq!(*guard.val(), 4);
+        map.insert("a", ());
+        assert_eq!(*map.get_expect("a").key(), "a");
+        map.get_expect("b");
     }
 
     #[test]
-    fn create() {
+    fn compare_and_remove_respects_owners() {
+        const NTHREAD: usize = 4;
+
+        let map = Arc::new(Map::new());
+        let released = Arc::new(AtomicUsize::new(0));
+        let threads = (0..NTHREAD)
+            .map(|owner| {
+                let map = map.clone();
+                let released = released.clone();
+                thread::spawn(move || {
+                    for lease in 0..500 {
+                        map.insert(lease % 5, owner);
+                        match map.compare_and_remove(&(lease % 5), &owner) {
+                            Ok(removed) => {
+                                assert_eq!(*removed.val(), owner);
+                                released.fetch_add(1, Relaxed);
+                            }
+                            Err(RemoveError::Mismatch) | Err(RemoveError::NotFound) => (),
+                        }
+                    }
+                })
+            })
+            .collect::<Vec<_>>();
+        for thread in threads {
+            thread.join().unwrap();
+        }
+
+        assert!(released.load(Relaxed) > 0);
+        assert_eq!(map.len(), map.iter().count());
+        map.insert(9, 1);
+        assert_eq!(
+            map.compare_and_remove(&9, &2).unwrap_err(),
+            RemoveError::Mismatch
+        );
+        assert_eq!(
+            map.compare_and_remove(&8, &1).unwrap_err(),
+            RemoveError::NotFound
+        );
+        assert_eq!(*map.compare_and_remove(&9, &1).unwrap().val(), 1);
+    }
+
+    #[test]
+    fn any_spreads_over_the_entries() {
         let map = Map::new();
-        assert!(map
-            .insert_with("five".to_owned(), |_, _, stored| if stored.is_none() {
-                Preview::New(5)
-            } else {
-                Preview::Discard
-            },)
-            .created());
-        assert_eq!(*map.get("five").unwrap().val(), 5);
-        assert!(map
-            .insert_with("five".to_owned(), |_, _, stored| if stored.is_none() {
-                Preview::New(500)
+        assert!(map.any().is_none());
+        assert!(map.any_from(7).is_none());
+
+        for i in 0..64u32 {
+            map.insert(i, i * 2);
+        }
+
+        let mut seen = [false; 64];
+        for _ in 0..64 {
+            let guard = map.any().unwrap();
+            assert_eq!(*guard.val(), *guard.key() * 2);
+            seen[*guard.key() as usize] = true;
+        }
+        assert!(seen.iter().filter(|&&seen| seen).count() > 16);
+
+        for seed in 0..16 {
+            let first = *map.any_from(seed).unwrap().key();
+            assert_eq!(*map.any_from(seed).unwrap().key(), first);
+        }
+
+        for i in 0..63u32 {
+            map.remove(&i);
+        }
+        assert_eq!(*map.any().unwrap().key(), 63);
+        assert_eq!(*map.any_from(3).unwrap().key(), 63);
+    }
+
+    #[test]
+    fn iter_snapshot_is_consistent() {
+        let mut map = Map::new();
+        map.enable_snapshots();
+        let map = Arc::new(map);
+        for key in 0..256u32 {
+            map.insert(key, 0u32);
+        }
+
+        // Every writer owns the keys `2 * writer` and `2 * writer + 1`, and
+        // writes each step to the first one and then to the second one. At
+        // any point in time, the first one is equal to the second one or
+        // ahead of it by one step.
+        let writers = (0..4u32)
+            .map(|writer| {
+                let map = map.clone();
+                thread::spawn(move || {
+                    for step in 1..=2000 {
+                        map.insert(2 * writer, step);
+                        map.insert(2 * writer + 1, step);
+                    }
+                })
+            })
+            .collect::<Vec<_>>();
+
+        for _ in 0..50 {
+            let mut snapshot = loop {
+                match map.iter_snapshot() {
+                    Ok(snapshot) => break snapshot.collect::<Vec<_>>(),
+                    Err(err) => assert_eq!(err, SnapshotError::Busy),
+                }
+            };
+            snapshot.sort();
+            assert_eq!(snapshot.len(), 256);
+            for (index, &(key, _)) in snapshot.iter().enumerate() {
+                assert_eq!(key, index as u32);
+            }
+            for writer in 0..4 {
+                let first = snapshot[2 * writer].1;
+                let second = snapshot[2 * writer + 1].1;
+                assert!(first == second || first == second + 1);
+            }
+        }
+
+        for writer in writers {
+            writer.join().unwrap();
+        }
+        let snapshot = map.iter_snapshot().unwrap().collect::<Vec<_>>();
+        assert_eq!(snapshot.iter().map(|&(_, val)| val).sum::<u32>(), 8 * 2000);
+    }
+
+    #[test]
+    fn iter_snapshot_gives_up_inside_a_write() {
+        let mut map = Map::new();
+        map.enable_snapshots();
+        map.insert(1, 1);
+
+        let mut inner = None;
+        map.insert_with(2, |_, _, _| {
+            inner = Some(map.iter_snapshot().map(|snapshot| snapshot.count()));
+            Preview::New(2)
+        });
+        assert_eq!(inner, Some(Err(SnapshotError::Busy)));
+        assert_eq!(map.iter_snapshot().unwrap().count(), 2);
+    }
+
+    #[test]
+    fn merge_from_counts_exactly() {
+        let total = Arc::new(Map::new());
+        let mergers = (0..4u32)
+            .map(|merger| {
+                let total = total.clone();
+                thread::spawn(move || {
+                    let local = Map::new();
+                    for key in 0..100u32 {
+                        local.insert(key, merger + 1);
+                    }
+                    if merger == 0 {
+                        local.insert(100, 1);
+                    }
+                    total.merge_from(local, |_, new, stored| new + stored)
+                })
+            })
+            .collect::<Vec<_>>();
+
+        let mut stats = MergeStats::default();
+        for merger in mergers {
+            let merged = merger.join().unwrap();
+            stats.created += merged.created;
+            stats.merged += merged.merged;
+        }
+
+        // A key may be created again while some other merger holds its entry,
+        // but every entry is either created or merged, exactly once.
+        assert!(stats.created >= 101);
+        assert_eq!(stats.created + stats.merged, 401);
+        assert_eq!(total.len(), 101);
+        for key in 0..100 {
+            assert_eq!(*total.get(&key).unwrap().val(), 10);
+        }
+        assert_eq!(*total.get(&100).unwrap().val(), 1);
+    }
+
+    #[test]
+    fn partition_moves_the_entries() {
+        let mut map = Map::new();
+        for i in 0..2000u32 {
+            map.insert(i, i);
+        }
+        assert!(map.stats().max_depth > 1);
+        map.remove(&7);
+        let removed = map.remove(&8).unwrap();
+        let guard = map.get(&9).unwrap();
+        let pair = &*guard as *const (u32, u32);
+        drop(guard);
+
+        let mut visited = 0;
+        let mut odd = map.partition(|key, val| {
+            visited += 1;
+            assert_eq!(key, val);
+            key % 2 == 1
+        });
+
+        assert_eq!(visited, 1998);
+        assert_eq!((map.len(), odd.len()), (999, 999));
+        assert_eq!(*removed.val(), 8);
+        assert_eq!(&*odd.get(&9).unwrap() as *const (u32, u32), pair);
+        assert!(map.get(&9).is_none());
+        assert!(odd.get(&7).is_none());
+        assert!(map.iter().all(|guard| guard.key() % 2 == 0));
+        assert!(odd.iter().all(|guard| guard.key() % 2 == 1));
+
+        let all = odd.partition(|_, _| true);
+        assert!(odd.is_empty());
+        assert_eq!(all.len(), 999);
+        assert_eq!(all.iter().count(), 999);
+        odd.insert(1, 1);
+        assert_eq!(*odd.get(&1).unwrap().val(), 1);
+
+        let mut colliding = Map::with_hasher(BuildHasherDefault::<CollidingHasher>::default());
+        for i in 0..64u32 {
+            colliding.insert(i, i);
+        }
+        let small = colliding.partition(|key, _| *key < 10);
+        assert_eq!((colliding.len(), small.len()), (54, 10));
+        for i in 0..64 {
+            assert_eq!(small.get(&i).is_some(), i < 10);
+            assert_eq!(colliding.get(&i).is_some(), i >= 10);
+        }
+    }
+
+    #[test]
+    fn insert_with_appends_without_losing_values() {
+        let map = Arc::new(Map::<u32, Vec<u32>>::new());
+        let appenders = (0..4u32)
+            .map(|appender| {
+                let map = map.clone();
+                thread::spawn(move || {
+                    for i in 0..200u32 {
+                        let val = appender * 200 + i;
+                        map.insert_with(i % 8, |_, _, stored| match stored {
+                            Some((_, vals)) => {
+                                let mut vals = vals.clone();
+                                vals.push(val);
+                                Preview::New(vals)
+                            }
+                            None => Preview::New(alloc::vec![val]),
+                        });
+                    }
+                })
+            })
+            .collect::<Vec<_>>();
+        for appender in appenders {
+            appender.join().unwrap();
+        }
+
+        let mut all = Vec::new();
+        for key in 0..8 {
+            let guard = map.get(&key).unwrap();
+            assert!(guard.val().iter().all(|val| val % 8 == key));
+            all.extend_from_slice(guard.val());
+        }
+        all.sort();
+        assert_eq!(all, (0..800).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn insert_and_get_guards_the_installed_entry() {
+        let map = Map::new();
+        let (old, first) = map.insert_and_get(1, 10);
+        assert!(old.is_none());
+        let (old, second) = map.insert_and_get(1, 20);
+        assert_eq!(*old.unwrap().val(), 10);
+        assert_eq!((*first.val(), *second.val()), (10, 20));
+
+        let removed = map.remove(&1).unwrap();
+        assert!(core::ptr::eq(&*removed, &*second));
+        assert_eq!(*second.val(), 20);
+        assert_eq!(map.len(), 0);
+    }
+
+    #[test]
+    fn keys_need_no_ordering() {
+        // Only `Eq` and `Hash`, no ordering.
+        #[derive(Debug, Clone, PartialEq, Eq, Hash)]
+        struct Key(u32);
+
+        let map = Arc::new(Map::with_hasher(
+            BuildHasherDefault::<CollidingHasher>::default(),
+        ));
+        let created = Arc::new(AtomicUsize::new(0));
+        let inserters = (0..4)
+            .map(|_| {
+                let map = map.clone();
+                let created = created.clone();
+                thread::spawn(move || {
+                    for i in 0..64 {
+                        if map.try_insert(Key(i), i).is_ok() {
+                            created.fetch_add(1, Relaxed);
+                        }
+                    }
+                })
+            })
+            .collect::<Vec<_>>();
+        for inserter in inserters {
+            inserter.join().unwrap();
+        }
+
+        // Every key raced in a bucket shared with many others.
+        assert_eq!(created.load(Relaxed), 64);
+        assert_eq!(map.len(), 64);
+        assert_eq!(map.iter().count(), 64);
+        for i in 0..64 {
+            assert_eq!(*map.get(&Key(i)).unwrap().val(), i);
+        }
+        assert_eq!(*map.remove(&Key(5)).unwrap().val(), 5);
+        assert!(map.get(&Key(5)).is_none());
+        assert!(map.insert(Key(6), 60).is_some());
+        assert_eq!(*map.get(&Key(6)).unwrap().val(), 60);
+    }
+
+    #[test]
+    fn rehashing_moves_every_entry() {
+        let map = Map::with_seed([3; 16]);
+        for i in 0..1000u32 {
+            map.insert(i, i * 2);
+        }
+        map.remove(&10);
+
+        let mut colliding = map.into_with_hasher(BuildHasherDefault::<CollidingHasher>::default());
+        assert_eq!(colliding.len(), 999);
+        assert_eq!(colliding.stats().buckets, 4);
+        for i in 0..1000 {
+            assert_eq!(
+                colliding.get(&i).map(|guard| *guard.val()),
+                (i != 10).then(|| i * 2)
+            );
+        }
+
+        colliding.rehash_with(BuildHasherDefault::default());
+        assert_eq!(colliding.len(), 999);
+        assert_eq!(colliding.stats().entries, 999);
+        assert_eq!(*colliding.get(&999).unwrap().val(), 1998);
+        colliding.insert(10, 20);
+        assert_eq!(colliding.iter().count(), 1000);
+    }
+
+    #[test]
+    fn debug_prints_the_entries() {
+        let map = Map::new();
+        assert_eq!(format!("{:?}", map), "{}");
+        map.insert("a", 1);
+        assert_eq!(format!("{:?}", map), "{\"a\": 1}");
+        map.insert("b", 2);
+        let printed = format!("{:?}", map);
+        assert!(printed == "{\"a\": 1, \"b\": 2}" || printed == "{\"b\": 2, \"a\": 1}");
+
+        let map = (0..DEBUG_ENTRIES + 5)
+            .map(|i| (i, i))
+            .collect::<Map<_, _>>();
+        let printed = format!("{:?}", map);
+        assert!(printed.ends_with(", ...}"));
+        assert_eq!(printed.matches(": ").count(), DEBUG_ENTRIES);
+    }
+
+    #[test]
+    fn entry_counts_exactly() {
+        let map = Arc::new(Map::new());
+        let made = Arc::new(AtomicUsize::new(0));
+        let counters = (0..4)
+            .map(|_| {
+                let map = map.clone();
+                let made = made.clone();
+                thread::spawn(move || {
+                    for i in 0..400u32 {
+                        let guard = map
+                            .entry(i % 16)
+                            .and_modify(|count| count + 1)
+                            .or_insert_with(|| {
+                                made.fetch_add(1, Relaxed);
+                                1
+                            });
+                        assert!(*guard.val() >= 1);
+                    }
+                })
+            })
+            .collect::<Vec<_>>();
+        for counter in counters {
+            counter.join().unwrap();
+        }
+
+        // A value may be made and then lose the race for an absent key, but
+        // every call is counted exactly once.
+        assert!(made.load(Relaxed) >= 16);
+        let total = map.iter().map(|guard| *guard.val()).sum::<u32>();
+        assert_eq!(total, 4 * 400);
+
+        let map = Map::new();
+        assert_eq!(*map.entry(1).or_insert(10).val(), 10);
+        assert_eq!(*map.entry(1).or_insert(20).val(), 10);
+        assert_eq!(
+            *map.entry(1).and_modify(|val| val * 3).or_default().val(),
+            30
+        );
+        assert_eq!(
+            *map.entry(2).and_modify(|val| val * 3).or_default().val(),
+            0
+        );
+        assert_eq!(map.len(), 2);
+        assert_eq!(*map.entry(3).key(), 3);
+    }
+
+    #[test]
+    fn try_insert_guards_the_rejecting_entry() {
+        let map = Arc::new(Map::with_hasher(
+            BuildHasherDefault::<CollidingHasher>::default(),
+        ));
+        let created = Arc::new(AtomicUsize::new(0));
+        let threads = (0..4)
+            .map(|_| {
+                let map = map.clone();
+                let created = created.clone();
+                thread::spawn(move || {
+                    for i in 0..100 {
+                        match map.try_insert(i, i * 2) {
+                            Ok(guard) => {
+                                assert_eq!(*guard.key(), i);
+                                created.fetch_add(1, Relaxed);
+                            }
+                            Err(error) => {
+                                assert_eq!(error.pair, (i, i * 2));
+                                assert_eq!(*error.current.key(), i);
+                                assert_eq!(*error.current.val(), i * 2);
+                            }
+                        }
+                    }
+                })
+            })
+            .collect::<Vec<_>>();
+        for thread in threads {
+            thread.join().unwrap();
+        }
+
+        assert_eq!(created.load(Relaxed), 100);
+        assert_eq!(map.len(), 100);
+        let guard = map.try_insert(100, 0).unwrap();
+        map.remove(&100);
+        assert_eq!(*guard.val(), 0);
+    }
+
+    #[cfg(feature = "instrument")]
+    #[test]
+    fn instrumentation_counts_collisions() {
+        let map = Map::with_hasher(BuildHasherDefault::<CollidingHasher>::default());
+        for i in 0..8 {
+            map.insert(i, i);
+        }
+        // Only four hashes, each in its own node of the top table.
+        let before = map.instrumentation();
+        assert_eq!(before.cas_retries, 0);
+        assert_eq!(before.table_splits, 0);
+
+        // Every key of a bucket after the first one is found by stepping over
+        // the keys before it.
+        map.get(&7).unwrap();
+        assert!(map.instrumentation().chain_traversals > before.chain_traversals);
+
+        map.insert(0, 1);
+        map.remove(&1);
+        assert!(map.instrumentation().incin_deferrals > before.incin_deferrals);
+
+        let fresh = Map::<u32, u32>::new();
+        assert_eq!(fresh.instrumentation(), Instrumentation::default());
+    }
+
+    #[test]
+    fn clear_shared_races_with_insertions() {
+        let map = Arc::new(Map::with_hasher(
+            BuildHasherDefault::<CollidingHasher>::default(),
+        ));
+        for i in 0..1000 {
+            map.insert(i, i);
+        }
+        let guard = map.get(&500).unwrap();
+
+        let threads = (0..4)
+            .map(|thread| {
+                let map = map.clone();
+                thread::spawn(move || {
+                    for i in 0..1000 {
+                        map.insert(1000 * (thread + 1) + i, i);
+                        if i % 100 == 0 {
+                            map.clear_shared();
+                        }
+                    }
+                })
+            })
+            .collect::<Vec<_>>();
+        for thread in threads {
+            thread.join().unwrap();
+        }
+        assert_eq!(*guard.val(), 500);
+        drop(guard);
+
+        map.clear_shared();
+        assert_eq!(map.iter().count(), 0);
+        assert!(map.get(&500).is_none());
+    }
+
+    #[test]
+    fn clear_shared_counts_the_detached_entries() {
+        let map = Map::new();
+        for i in 0..1000 {
+            map.insert(i, i);
+        }
+        map.clear_shared();
+        assert!(map.is_empty());
+        assert_eq!(map.iter().count(), 0);
+
+        map.insert(0, 0);
+        assert_eq!(map.len(), 1);
+        assert_eq!(map.stats().entries, 1);
+    }
+
+    #[test]
+    fn clear_shared_keeps_len_exact() {
+        fn race<H>(map: Map<u64, u64, H>)
+        where
+            H: BuildHasher + Send + Sync + 'static,
+        {
+            let map = Arc::new(map);
+            let threads = (0..4u64)
+                .map(|thread| {
+                    let map = map.clone();
+                    thread::spawn(move || {
+                        for i in 0..2000 {
+                            let key = thread * 2000 + i;
+                            map.insert(key, i);
+                            if i % 3 == 0 {
+                                map.remove(&(key - i / 2));
+                            }
+                            if thread == 0 && i % 250 == 0 {
+                                map.clear_shared();
+                            }
+                            if thread == 1 && i % 500 == 0 {
+                                map.optimize_space_step();
+                            }
+                        }
+                    })
+                })
+                .collect::<Vec<_>>();
+            for thread in threads {
+                thread.join().unwrap();
+            }
+            assert_eq!(map.len(), map.iter().count());
+        }
+
+        for _ in 0..10 {
+            race(Map::with_hasher(
+                BuildHasherDefault::<CollidingHasher>::default(),
+            ));
+            race(Map::new());
+        }
+    }
+
+    #[test]
+    fn optimize_space_step_converges() {
+        let mut map = Map::new();
+        for i in 0..20000u32 {
+            map.insert(i, i);
+        }
+        let before = map.stats();
+        for i in 0..20000 {
+            if i % 1000 != 0 {
+                map.remove(&i);
+            }
+        }
+
+        let mut collapsed = 0;
+        loop {
+            let status = map.optimize_space_step();
+            collapsed += status.collapsed;
+            if status.done {
+                break;
+            }
+        }
+        let stepped = map.stats();
+        assert_eq!(stepped.tables, before.tables - collapsed);
+        assert_eq!(
+            map.optimize_space_step(),
+            OptimizeStatus {
+                collapsed: 0,
+                done: true
+            }
+        );
+
+        // The exclusive version finds nothing else to remove.
+        map.optimize_space();
+        assert_eq!(map.stats(), stepped);
+        for i in (0..20000).step_by(1000) {
+            assert_eq!(*map.get(&i).unwrap().val(), i);
+        }
+    }
+
+    #[test]
+    fn optimize_space_step_runs_concurrently() {
+        let map = Arc::new(Map::new());
+        let stop = Arc::new(AtomicUsize::new(0));
+        let optimizer = {
+            let map = map.clone();
+            let stop = stop.clone();
+            thread::spawn(move || {
+                while stop.load(Relaxed) == 0 {
+                    map.optimize_space_step();
+                }
+            })
+        };
+
+        let threads = (0..4u32)
+            .map(|thread| {
+                let map = map.clone();
+                thread::spawn(move || {
+                    for round in 0..4 {
+                        for i in 0..2000 {
+                            map.insert((thread, i), round);
+                        }
+                        for i in 0..2000 {
+                            assert_eq!(*map.get(&(thread, i)).unwrap().val(), round);
+                            if round < 3 || i % 2 == 0 {
+                                assert!(map.remove(&(thread, i)).is_some());
+                            }
+                        }
+                    }
+                })
+            })
+            .collect::<Vec<_>>();
+        for thread in threads {
+            thread.join().unwrap();
+        }
+        stop.store(1, Relaxed);
+        optimizer.join().unwrap();
+        while !map.optimize_space_step().done {}
+
+        assert_eq!(map.len(), 4000);
+        for thread in 0..4 {
+            for i in 0..2000 {
+                assert_eq!(map.get(&(thread, i)).is_some(), i % 2 == 1);
+            }
+        }
+    }
+
+    #[test]
+    fn bulk_load_matches_insertions() {
+        let mut entries = (0..20000u32).map(|i| (i, i)).collect::<Vec<_>>();
+        entries.extend((0..20000).step_by(7).map(|i| (i, i + 1)));
+        let inserted = Map::with_hasher(SeededState::new([7; 16]));
+        for (key, val) in entries.iter().cloned() {
+            inserted.insert(key, val);
+        }
+
+        let bulk = Map::from_entries_bulk(entries, SeededState::new([7; 16]));
+        assert_eq!(bulk.len(), 20000);
+        assert_eq!(bulk, inserted);
+        assert_eq!(bulk.stats(), inserted.stats());
+
+        let colliding = Map::from_entries_bulk(
+            (0..100u32).rev().map(|i| (i, i)).collect(),
+            BuildHasherDefault::<CollidingHasher>::default(),
+        );
+        assert_eq!(colliding.len(), 100);
+        assert_eq!(colliding.stats().buckets, 4);
+        for i in 0..100 {
+            assert_eq!(*colliding.get(&i).unwrap().val(), i);
+        }
+        colliding.insert(100, 100);
+        assert_eq!(colliding.remove_value(&3), Some(3));
+
+        let empty = Map::<u32, u32, _>::from_entries_bulk(Vec::new(), DefaultState::default());
+        assert!(empty.is_empty());
+    }
+
+    #[test]
+    fn increments_count_exactly() {
+        let map = Arc::new(Map::with_hasher(
+            BuildHasherDefault::<CollidingHasher>::default(),
+        ));
+        let threads = (0..4)
+            .map(|_| {
+                let map = map.clone();
+                thread::spawn(move || {
+                    for i in 0..1000u32 {
+                        map.increment(i % 10, 1u64);
+                        map.fetch_update_copy(&(i % 10), |val| val + 1);
+                    }
+                })
+            })
+            .collect::<Vec<_>>();
+        for thread in threads {
+            thread.join().unwrap();
+        }
+
+        assert_eq!(map.len(), 10);
+        for i in 0..10 {
+            assert_eq!(map.get(&i).unwrap().val().load(), 800);
+        }
+        let guard = map.get(&0).unwrap();
+        map.increment(0, u64::MAX);
+        assert_eq!(guard.val().load(), 799);
+    }
+
+    #[test]
+    fn in_place_updates_survive_replacements() {
+        const WRITES: u64 = 100_000;
+
+        let map = Arc::new(Map::with_hasher(
+            BuildHasherDefault::<CollidingHasher>::default(),
+        ));
+        let churn = |map: &Map<u32, AtomicCell<u64>, _>, id: usize| {
+            for i in 0..WRITES {
+                let key = (i % 2) as u32;
+                if id == 0 {
+                    map.increment(key, 1);
+                } else {
+                    // The key may be removed again before it is updated.
+                    map.increment(key, 0);
+                    while map.fetch_update_copy(&key, |val| val + 1).is_none() {
+                        map.increment(key, 0);
+                    }
+                }
+            }
+        };
+
+        // Every update must be seen either by a replaced or removed value, or
+        // by the value left in the map.
+        let mut replaced = 0;
+        let mut round = 0u32;
+        with_writers(&map, 2, churn, |map| {
+            round += 1;
+            let key = round % 2;
+            let removed = if round % 3 == 0 {
+                map.remove(&key)
             } else {
-                Preview::Discard
-            },)
-            .failed()
-            .is_some());
+                map.insert(key, AtomicCell::new(0))
+            };
+            if let Some(removed) = removed {
+                replaced += removed.val().load();
+            }
+        });
+
+        let left = (0..2)
+            .filter_map(|key| map.get(&key))
+            .map(|guard| guard.val().load())
+            .sum::<u64>();
+        assert_eq!(replaced + left, WRITES * 2);
+    }
+
+    #[test]
+    fn hashed_operations_share_the_hash() {
+        let first = Map::with_seed([5; 16]);
+        let second = Map::with_seed([5; 16]);
+        let colliding = Map::with_hasher(BuildHasherDefault::<CollidingHasher>::default());
+        for i in 0..100u32 {
+            let key = i.to_string();
+            let hash = first.hash_key(&key);
+            assert_eq!(hash, second.hash_key(&key));
+            first.insert_hashed(hash, key.clone(), i);
+            second.insert_hashed(hash, key.clone(), i * 2);
+            colliding.insert_hashed(colliding.hash_key(&key), key, i);
+        }
+
+        for i in 0..100u32 {
+            let key = i.to_string();
+            let hash = first.hash_key(key.as_str());
+            assert_eq!(*first.get_hashed(hash, key.as_str()).unwrap().val(), i);
+            assert_eq!(*second.get_hashed(hash, key.as_str()).unwrap().val(), i * 2);
+            let hash = colliding.hash_key(key.as_str());
+            assert_eq!(*colliding.get_hashed(hash, key.as_str()).unwrap().val(), i);
+        }
+        // The keys are still compared, so a colliding key is not found.
+        let hash = colliding.hash_key("100");
+        assert!(colliding.get_hashed(hash, "100").is_none());
+        assert_eq!(colliding.len(), 100);
     }
 
     #[test]
-    fn update() {
-        let map = Map::new();
-        assert!(map
-            .insert_with("five".to_owned(), |_, _, stored| {
-                if let Some((_, n)) = stored {
-                    Preview::New(*n + 6)
-                } else {
-                    Preview::Discard
-                }
-            })
-            .failed()
-            .is_some());
-        assert!(map.insert("five".to_owned(), 5).is_none());
-        let guard = map
-            .insert_with("five".to_owned(), |_, _, stored| {
-                if let Some((_, n)) = stored {
-                    Preview::New(*n + 7)
-                } else {
-                    Preview::Discard
-                }
+    fn pop_any_yields_every_entry_once() {
+        let map = Arc::new(Map::with_hasher(
+            BuildHasherDefault::<CollidingHasher>::default(),
+        ));
+        for i in 0..1000u32 {
+            map.insert(i, i);
+        }
+        let threads = (0..4)
+            .map(|_| {
+                let map = map.clone();
+                thread::spawn(move || {
+                    let mut popped = Vec::new();
+                    while let Some(removed) = map.pop_any() {
+                        assert_eq!(removed.key(), removed.val());
+                        popped.push(*removed.key());
+                    }
+                    popped
+                })
             })
-            .take_updated()
-            .unwrap();
-        assert_eq!(guard.key(), "five");
-        assert_eq!(*guard.val(), 5);
-        assert_eq!(*map.get("five").unwrap().val(), 12);
+            .collect::<Vec<_>>();
+
+        let mut popped = Vec::new();
+        for thread in threads {
+            popped.extend(thread.join().unwrap());
+        }
+        popped.sort_unstable();
+        assert_eq!(popped, (0..1000).collect::<Vec<_>>());
+        assert!(map.is_empty());
+        assert!(map.pop_any().is_none());
     }
 
     #[test]
-    fn never_inserts() {
-        let map = Map::new();
-        assert!(map
-            .insert_with("five".to_owned(), |_, _, _| Preview::Discard)
-            .failed()
-            .is_some());
-        assert!(map.insert("five".to_owned(), 5).is_none());
-        assert!(map
-            .insert_with("five".to_owned(), |_, _, _| Preview::Discard)
-            .failed()
-            .is_some());
+    fn arc_values_outlive_their_entries() {
+        let map: Map<u32, Arc<str>> = Map::new();
+        assert!(map.get_arc(&1).is_none());
+        assert!(map.insert_arc(1, Arc::from("one")).is_none());
+
+        let one = map.get_arc(&1).unwrap();
+        let old = map.insert_arc(1, Arc::from("uno")).unwrap();
+        assert!(Arc::ptr_eq(&one, &old));
+        drop(old);
+        map.remove(&1);
+        assert_eq!(&*one, "one");
+
+        let mut map = Map::new();
+        let first = map.get_or_insert_arc("a", || 1);
+        let second = map.get_or_insert_arc("a", || 2);
+        assert!(Arc::ptr_eq(&first, &second));
+        map.clear();
+        assert_eq!(Arc::strong_count(&first), 2);
     }
 
+    #[cfg(feature = "std")]
     #[test]
-    fn inserts_reinserts() {
-        let map = Map::new();
-        assert!(map.insert("four".to_owned(), 4).is_none());
-        let prev = map.insert("four".to_owned(), 40).unwrap();
-        assert_eq!(prev.key(), "four");
-        assert_eq!(*prev.val(), 4);
-        let prev = map.reinsert(prev).take_updated().unwrap();
-        assert_eq!(prev.key(), "four");
-        assert_eq!(*prev.val(), 40);
-        assert!(*map.get("four").unwrap().val() == 4);
+    fn hash_map_round_trip() {
+        let source = (0..1000u32)
+            .map(|i| (i, i.to_string()))
+            .collect::<HashMap<_, _>>();
+        let map = Map::from(source.clone());
+        assert_eq!(map.len(), 1000);
+        for (key, val) in &source {
+            assert_eq!(map.get(key).unwrap().val(), val);
+        }
+
+        let back = HashMap::from(map);
+        assert_eq!(back, source);
+
+        let colliding = (0..100u32)
+            .map(|i| (i, i))
+            .collect::<Map<_, _, BuildHasherDefault<CollidingHasher>>>();
+        let back = HashMap::from(colliding);
+        assert_eq!(back.len(), 100);
+        assert!(back.iter().all(|(key, val)| key == val));
     }
 
     #[test]
-    fn never_reinserts() {
-        let map = Map::new();
-        map.insert("five".to_owned(), 5);
-        let prev = map.remove("five").unwrap();
-        let prev = map.reinsert_with(prev, |_, _| false).take_failed().unwrap();
-        assert!(map.insert("five".to_owned(), 5).is_none());
-        map.reinsert_with(prev, |_, _| false).take_failed().unwrap();
+    fn for_each_visits_every_entry_once() {
+        let map = Map::with_hasher(BuildHasherDefault::<CollidingHasher>::default());
+        for i in 0..1000u32 {
+            map.insert(i, i * 2);
+        }
+        let mut visited = Vec::new();
+        map.for_each(|&key, &val| {
+            assert_eq!(val, key * 2);
+            visited.push(key);
+        });
+        visited.sort_unstable();
+        assert_eq!(visited, (0..1000).collect::<Vec<_>>());
+        assert_eq!(map.fold(0u64, |sum, _, &val| sum + u64::from(val)), 999_000);
+
+        // Removing from within the closure is allowed.
+        map.for_each(|key, _| {
+            if key % 2 == 0 {
+                map.remove(key);
+            }
+        });
+        assert_eq!(map.len(), 500);
+        assert_eq!(map.fold(0, |count, key, _| count + key % 2), 500);
     }
 
     #[test]
-    fn reinserts_create() {
-        let map = Map::new();
-        map.insert("five".to_owned(), 5);
-        let first = map.remove("five").unwrap();
-        map.insert("five".to_owned(), 5);
-        let second = map.remove("five").unwrap();
-        assert!(map
-            .reinsert_with(first, |_, stored| stored.is_none())
-            .created());
-        assert_eq!(*map.get("five").unwrap().val(), 5);
-        assert!(map
-            .reinsert_with(second, |_, stored| stored.is_none())
-            .failed()
-            .is_some());
+    fn shards_cover_every_entry_once() {
+        let map = Map::with_capacity(10_000);
+        for i in 0..10_000u32 {
+            map.insert(i, ());
+        }
+        for &of in &[1, 3, 7, 256, 1000] {
+            let mut keys = (0..of)
+                .flat_map(|shard| map.iter_shard(shard, of))
+                .map(|guard| *guard.key())
+                .collect::<Vec<_>>();
+            keys.sort_unstable();
+            assert_eq!(keys, (0..10_000).collect::<Vec<_>>());
+        }
+        assert_eq!(map.iter_shard(0, 1000).count(), 0);
     }
 
     #[test]
-    fn reinserts_update() {
-        let map = Map::new();
-        map.insert("five".to_owned(), 5);
-        let prev = map.remove("five").unwrap();
-        let prev = map
-            .reinsert_with(prev, |_, stored| stored.is_some())
-            .take_failed()
-            .unwrap();
-        map.insert("five".to_owned(), 5);
-        assert!(map
-            .reinsert_with(prev, |_, stored| stored.is_some())
-            .updated()
-            .is_some());
+    #[should_panic]
+    fn shard_out_of_range() {
+        Map::<u32, u32>::new().iter_shard(3, 3);
     }
 
     #[test]
-    fn inserts_and_removes() {
+    fn removed_parts_are_moved_or_cloned() {
         let map = Map::new();
-        assert!(map.remove("five").is_none());
-        assert!(map.remove("four").is_none());
-        map.insert("five".to_owned(), 5);
-        let removed = map.remove("five").unwrap();
-        assert_eq!(removed.key(), "five");
-        assert_eq!(*removed.val(), 5);
-        assert!(map.insert("four".to_owned(), 4).is_none());
-        map.insert("three".to_owned(), 3);
-        assert!(map.remove("two").is_none());
-        map.insert("two".to_owned(), 2);
-        let removed = map.remove("three").unwrap();
-        assert_eq!(removed.key(), "three");
-        assert_eq!(*removed.val(), 3);
-        let removed = map.remove("two").unwrap();
-        assert_eq!(removed.key(), "two");
-        assert_eq!(*removed.val(), 2);
-        let removed = map.remove("four").unwrap();
-        assert_eq!(removed.key(), "four");
-        assert_eq!(*removed.val(), 4);
+        map.insert(String::from("a"), String::from("x"));
+        map.insert(String::from("b"), String::from("y"));
+
+        let guard = map.get("a").unwrap();
+        let removed = map.remove("a").unwrap();
+        let removed = Removed::try_into_key(removed).unwrap_err();
+        assert_eq!(Removed::into_key(removed), "a");
+        assert_eq!(*guard.val(), "x");
+        drop(guard);
+
+        let removed = map.remove("b").unwrap();
+        assert_eq!(Removed::try_into_val(removed).unwrap(), "y");
+        assert!(map.is_empty());
     }
 
     #[test]
-    fn repeated_inserts() {
-        let map = Map::new();
-        assert!(map.insert("five".to_owned(), 5).is_none());
-        assert!(*map.insert("five".to_owned(), 5).unwrap().val() == 5);
+    fn insert_with_does_not_rerun_for_unrelated_changes() {
+        let map = Map::with_hasher(BuildHasherDefault::<CollidingHasher>::default());
+        map.insert(0, 0);
+        // Keys 0, 4, 8, ... have the same hash, so inserting one of them from
+        // the closure changes the bucket under the insertion, forcing it to
+        // retry even though the stored entry of its own key did not change.
+        let mut calls = 0;
+        map.insert_with(0, |_, _, stored| {
+            calls += 1;
+            map.insert(calls * 4, calls);
+            Preview::New(stored.map_or(0, |(_, val)| val + 1))
+        });
+        assert_eq!(calls, 1);
+        assert_eq!(*map.get(&0).unwrap().val(), 1);
+
+        let mut calls = 0;
+        map.insert_with(2, |_, _, stored| {
+            calls += 1;
+            assert!(stored.is_none());
+            map.insert(calls * 4 + 2, calls);
+            Preview::New(100)
+        });
+        assert_eq!(calls, 1);
+        assert_eq!(*map.get(&2).unwrap().val(), 100);
+        assert_eq!(map.len(), 4);
     }
 
     #[test]
-    fn reinsert_from_other_map_fails() {
-        let other = Map::new();
-        other.insert(5, 3);
-        other.insert(0, 0);
-        let removed = other.remove(&5).unwrap();
-        let _active_read = other.get(&0).unwrap();
-        let map = Map::new();
-        map.reinsert(removed).failed().unwrap();
+    fn zero_sized_values_take_no_space() {
+        use self::bucket::{Bucket, Entry, List};
+        use core::mem::size_of;
+
+        assert_eq!(size_of::<(u64, ())>(), size_of::<u64>());
+        assert_eq!(size_of::<Entry<u64, ()>>(), size_of::<Entry<u64, u64>>());
+        assert_eq!(size_of::<List<u64, ()>>(), size_of::<List<u64, u64>>());
+        assert_eq!(size_of::<Bucket<u64, ()>>(), size_of::<Bucket<u64, u64>>());
+        assert_eq!(
+            Bucket::<u64, u64>::bytes_with_len(10) - Bucket::<u64, ()>::bytes_with_len(10),
+            10 * size_of::<u64>()
+        );
+
+        let set = Map::with_capacity_and_hasher(1000, SeededState::new([9; 16]));
+        let map = Map::with_capacity_and_hasher(1000, SeededState::new([9; 16]));
+        for i in 0..1000u64 {
+            set.insert(i, ());
+            map.insert(i, i);
+        }
+        let (set, map) = (set.stats(), map.stats());
+        assert_eq!(set.entries, map.entries);
+        assert_eq!(map.bytes - set.bytes, 1000 * size_of::<u64>());
     }
 
     #[test]
-    fn iter_valid_items() {
+    fn get_and_reads_under_the_pause() {
         let map = Map::new();
-        for i in 0..10u128 {
-            for j in 0..32 {
-                map.insert((i, j), i << j);
-            }
-        }
+        map.insert(String::from("a"), vec![1, 2]);
+        assert_eq!(map.get_and("a", Vec::len), Some(2));
+        assert_eq!(map.get_and("b", |_| unreachable!()), None::<()>);
+        let pair = map.get_pair_and("a", |key, val| (key.clone(), val[1]));
+        assert_eq!(pair, Some((String::from("a"), 2)));
+        assert_eq!(map.get_pair_and("b", |_, _| unreachable!()), None::<()>);
+    }
 
-        let mut result = HashMap::new();
-        for guard in &map {
-            let (k, v) = *guard;
-            let in_place = result.get(&(k, v)).map_or(0, |&x| x);
-            result.insert((k, v), in_place + 1);
-        }
+    #[test]
+    fn fetch_update_returns_the_actual_transition() {
+        let map = Arc::new(Map::new());
+        map.insert(0, 0u64);
+        let threads = (0..4)
+            .map(|_| {
+                let map = map.clone();
+                thread::spawn(move || {
+                    let mut seen = Vec::new();
+                    loop {
+                        match map.fetch_update(&0, |&val| Some(val + 1).filter(|&v| v <= 1000)) {
+                            Ok((old, new)) => {
+                                assert_eq!(*old.val() + 1, *new.val());
+                                seen.push(*new.val());
+                            }
+                            Err(error) => {
+                                assert_eq!(error, FetchUpdateError::Aborted);
+                                break seen;
+                            }
+                        }
+                    }
+                })
+            })
+            .collect::<Vec<_>>();
 
-        for i in 0..10 {
-            for j in 0..32 {
-                let pair = ((i, j), i << j);
-                assert_eq!(*result.get(&pair).unwrap(), 1);
-            }
+        let mut seen = Vec::new();
+        for thread in threads {
+            seen.extend(thread.join().unwrap());
         }
+        seen.sort_unstable();
+        assert_eq!(seen, (1..=1000).collect::<Vec<_>>());
+        map.remove(&0);
+        assert_eq!(
+            map.fetch_update(&0, |&val| Some(val)).unwrap_err(),
+            FetchUpdateError::NotFound
+        );
     }
 
     #[test]
-    fn optimize_space_preserves_entries() {
-        let mut map = Map::new();
-        for i in 0..200u128 {
-            for j in 0..128 {
-                map.insert((i, j), i << j);
-            }
+    fn remove_all_keeps_the_order_of_the_keys() {
+        let map = Map::with_hasher(BuildHasherDefault::<CollidingHasher>::default());
+        for i in 0..100u32 {
+            map.insert(i, i);
         }
-
-        for i in 0..200 {
-            for j in 0..16 {
-                map.remove(&(i, j));
+        let keys = (0..150).rev().step_by(2).collect::<Vec<u32>>();
+        let removed = map.remove_all(&keys);
+        assert_eq!(removed.len(), keys.len());
+        for (key, removed) in keys.iter().zip(&removed) {
+            match removed {
+                Some(removed) => assert_eq!(removed.key(), key),
+                None => assert!(*key >= 100),
             }
         }
+        assert_eq!(map.len(), 50);
+        assert!(map.iter().all(|guard| guard.key() % 2 == 0));
+        assert!(map.remove_all(&keys).iter().all(Option::is_none));
+    }
 
-        map.optimize_space();
+    #[test]
+    fn change_hook_sees_each_winning_change_once() {
+        const THREADS: usize = 4;
+        const INCRS: usize = 200;
 
-        let mut result = HashMap::new();
-        for guard in &map {
-            let (k, v) = *guard;
-            let in_place = result.get(&(k, v)).map_or(0, |&x| x);
-            result.insert((k, v), in_place + 1);
-        }
+        let created = Arc::new(AtomicUsize::new(0));
+        let updated = Arc::new(AtomicUsize::new(0));
+        let removed = Arc::new(AtomicUsize::new(0));
+        let mut map = Map::with_hasher(BuildHasherDefault::<CollidingHasher>::default());
+        let counts = (created.clone(), updated.clone(), removed.clone());
+        map.on_change(move |event| {
+            let count = match event {
+                MapEvent::Created { .. } => &counts.0,
+                MapEvent::Updated { old, new, .. } => {
+                    assert_eq!(*old + 1, *new);
+                    &counts.1
+                }
+                MapEvent::Removed { .. } => &counts.2,
+            };
+            count.fetch_add(1, Relaxed);
+        });
 
-        for i in 0..200 {
-            for j in 16..128 {
-                let pair = ((i, j), i << j);
-                assert_eq!(*result.get(&pair).unwrap(), 1);
-            }
+        let map = Arc::new(map);
+        let threads = (0..THREADS)
+            .map(|_| {
+                let map = map.clone();
+                thread::spawn(move || {
+                    for i in 0..INCRS {
+                        map.modify_or_insert(i % 8, || 1, |count| count + 1);
+                    }
+                })
+            })
+            .collect::<Vec<_>>();
+        for thread in threads {
+            thread.join().unwrap();
+        }
+        for key in 0..8 {
+            map.remove(&key);
         }
+
+        assert_eq!(created.load(Relaxed), 8);
+        assert_eq!(updated.load(Relaxed), THREADS * INCRS - 8);
+        assert_eq!(removed.load(Relaxed), 8);
     }
 
     #[test]
-    fn iter_mut_and_into_iter() {
+    fn change_hook_may_use_the_map() {
+        let snapshots = Arc::new(AtomicUsize::new(0));
+        let sink = snapshots.clone();
+        let map = Arc::new_cyclic(|weak: &alloc::sync::Weak<Map<u32, u32>>| {
+            let weak = weak.clone();
+            let mut map = Map::new();
+            map.enable_snapshots();
+            map.on_change(move |event| {
+                let map = weak.upgrade().unwrap();
+                let snapshot = map.iter_snapshot().unwrap().collect::<Vec<_>>();
+                match event {
+                    MapEvent::Removed { key, .. } => {
+                        assert!(!snapshot.iter().any(|(k, _)| k == key));
+                        map.insert(key + 1, 0);
+                    }
+                    _ => assert!(snapshot.iter().any(|(k, _)| k == event.key())),
+                }
+                sink.fetch_add(1, Relaxed);
+            });
+            map
+        });
+
+        map.insert(1, 1);
+        map.remove(&1);
+        assert_eq!(*map.get(&2).unwrap().val(), 0);
+        assert_eq!(snapshots.load(Relaxed), 3);
+    }
+
+    #[test]
+    fn into_iter_drops_change_hook() {
+        let captured = Arc::new(());
         let mut map = Map::new();
-        for i in 0..10u128 {
-            for j in 0..32 {
-                map.insert((i, j), i << j);
-            }
+        let hook = captured.clone();
+        map.on_change(move |_| drop(hook.clone()));
+        map.insert(1, 1);
+        assert_eq!(Arc::strong_count(&captured), 2);
+
+        let entries = map.into_iter().collect::<Vec<_>>();
+        assert_eq!(entries, [(1, 1)]);
+        assert_eq!(Arc::strong_count(&captured), 1);
+    }
+
+    #[test]
+    fn values_mut_updates_every_value() {
+        let map = Arc::new(Map::new());
+        let threads = (0..4u64)
+            .map(|t| {
+                let map = map.clone();
+                thread::spawn(move || {
+                    for i in 0..250 {
+                        map.insert(t * 250 + i, t * 250 + i);
+                    }
+                })
+            })
+            .collect::<Vec<_>>();
+        for thread in threads {
+            thread.join().unwrap();
         }
 
-        let mut result = HashMap::new();
-        for (k, v) in &mut map {
-            let in_place = result.get(&(*k, *v)).map_or(0, |&x| x);
-            result.insert((*k, *v), in_place + 1);
-            *v += 1;
+        let mut map = Arc::try_unwrap(map).unwrap();
+        let mut count = 0;
+        for val in map.values_mut() {
+            *val *= 3;
+            count += 1;
+        }
+        assert_eq!(count, 1000);
+        for i in 0..1000 {
+            assert_eq!(*map.get(&i).unwrap().val(), i * 3);
         }
+    }
 
+    #[test]
+    fn keys_display_and_clone() {
+        let map = Map::new();
         for i in 0..10 {
-            for j in 0..32 {
-                let pair = ((i, j), i << j);
-                assert_eq!(*result.get(&pair).unwrap(), 1);
-            }
+            map.insert(format!("{}", i), ());
         }
 
-        result.clear();
+        let mut keys = map.keys().map(|key| key.clone()).collect::<Vec<String>>();
+        keys.sort_unstable();
+        assert_eq!(keys, (0..10).map(|i| format!("{}", i)).collect::<Vec<_>>());
 
-        for (k, v) in map {
-            let in_place = result.get(&(k, v)).map_or(0, |&x| x);
-            result.insert((k, v), in_place + 1);
+        let key = map.keys().find(|key| **key == "7").unwrap();
+        assert_eq!(format!("{}", key), "7");
+        assert_eq!(format!("{:?}", key), "\"7\"");
+        map.remove("7");
+        // The guard keeps the removed key alive.
+        assert_eq!(*key, "7");
+    }
+
+    #[test]
+    fn values_while_mutating() {
+        let map = Arc::new(Map::new());
+        for i in 0..100u64 {
+            map.insert(i, i * 2);
         }
 
-        for i in 0..10 {
-            for j in 0..32 {
-                let pair = ((i, j), (i << j) + 1);
-                assert_eq!(*result.get(&pair).unwrap(), 1);
-            }
+        with_writers(&map, 2, churn, |map| {
+            // Removed values must stay readable while their guards live.
+            let vals = map.values().collect::<Vec<_>>();
+            assert!(vals.len() <= 100);
+            assert!(vals.iter().all(|val| **val < 200 && **val % 2 == 0));
+        });
+
+        let mut vals = map.values().map(|val| *val).collect::<Vec<_>>();
+        vals.sort_unstable();
+        let mut expected = map.values_cloned();
+        expected.sort_unstable();
+        assert_eq!(vals, expected);
+    }
+
+    #[test]
+    fn cloned_while_mutating() {
+        let map = Arc::new(Map::new());
+        for i in 0..100u64 {
+            map.insert(i, i * 2);
         }
+
+        with_writers(&map, 2, churn, |map| {
+            let keys = map.keys_cloned();
+            assert!(keys.len() <= 100);
+            assert!(keys.iter().all(|&key| key < 100));
+            let vals = map.values_cloned();
+            assert!(vals.iter().all(|&val| val < 200 && val % 2 == 0));
+        });
+
+        let mut keys = map.keys_cloned();
+        keys.sort_unstable();
+        let mut expected = map.iter().map(|guard| *guard.key()).collect::<Vec<_>>();
+        expected.sort_unstable();
+        assert_eq!(keys, expected);
+    }
+
+    #[test]
+    fn insert_with_unwinds() {
+        let val = Arc::new(0);
+        let map = Map::new();
+        map.insert(1, val.clone());
+
+        let res = catch_unwind(AssertUnwindSafe(|| {
+            let captured = val.clone();
+            map.insert_with(2, move |_, _, _| {
+                let _captured = &captured;
+                panic!("rejected")
+            })
+        }));
+        assert!(res.is_err());
+        assert!(map.get(&2).is_none());
+
+        let res = catch_unwind(AssertUnwindSafe(|| {
+            map.insert_with(1, |_, _, _| -> Preview<Arc<i32>> { panic!("updating") })
+        }));
+        assert!(res.is_err());
+        assert!(Arc::ptr_eq(map.get(&1).unwrap().val(), &val));
+        assert_eq!(map.iter().count(), 1);
+
+        drop(map);
+        assert_eq!(Arc::strong_count(&val), 1);
+    }
+
+    #[test]
+    fn remove_with_unwinds() {
+        let val = Arc::new(0);
+        let map = Map::new();
+        map.insert(1, val.clone());
+
+        let res = catch_unwind(AssertUnwindSafe(|| {
+            map.remove_with(&1, |_| panic!("removing"))
+        }));
+        assert!(res.is_err());
+        assert!(Arc::ptr_eq(map.get(&1).unwrap().val(), &val));
+
+        assert!(map.remove(&1).is_some());
+        drop(map);
+        assert_eq!(Arc::strong_count(&val), 1);
+    }
+
+    #[test]
+    fn reinsert_with_unwinds() {
+        let val = Arc::new(0);
+        let mut map = Map::new();
+        map.insert(1, val.clone());
+        map.insert(2, Arc::new(2));
+        let removed = map.remove(&1).unwrap();
+
+        let res = catch_unwind(AssertUnwindSafe(|| {
+            map.reinsert_with(removed, |_, _| panic!("reinserting"))
+        }));
+        assert!(res.is_err());
+        assert!(map.get(&1).is_none());
+        assert_eq!(*map.get(&2).unwrap().val().as_ref(), 2);
+
+        // The removed entry was dropped during the unwinding.
+        map.optimize_space();
+        assert_eq!(Arc::strong_count(&val), 1);
     }
 
     #[test]
@@ -0,0 +1,185 @@
+use super::{
+    bucket::{Bucket, Garbage},
+    guard::ReadGuard,
+    iter::Iter,
+    table::{self, Table},
+    Map,
+};
+use crate::{
+    incin::Pause,
+    tagged_ptr::{is_tagged, untag},
+};
+use alloc::vec::Vec;
+use core::{fmt, sync::atomic::Ordering::*};
+use rayon::iter::{
+    plumbing::{bridge_unindexed, Folder, UnindexedConsumer, UnindexedProducer},
+    IntoParallelIterator, ParallelIterator,
+};
+
+/// A parallel iterator over key-value entries of a [`Map`], created by
+/// [`Map::par_iter`]. The `Item` of this iterator is a [`ReadGuard`]. The work
+/// is split across the slots of the top-level table, and further across
+/// nested tables, so different workers walk disjoint parts of the map.
+///
+/// Like [`Iter`], this iterator may be inconsistent, but still it is
+/// memory-safe. Entries inserted or removed while iterating may or may not be
+/// yielded. Just like [`Iter`], it keeps the incinerator paused until it is
+/// consumed.
+pub struct ParIter<'map, K, V> {
+    pause: Pause<'map, Garbage<K, V>>,
+    top: &'map Table<K, V>,
+}
+
+impl<'map, K, V> ParIter<'map, K, V> {
+    pub(super) fn new(pause: Pause<'map, Garbage<K, V>>, top: &'map Table<K, V>) -> Self {
+        Self { pause, top }
+    }
+}
+
+impl<'map, K, V> ParallelIterator for ParIter<'map, K, V>
+where
+    K: Send + Sync,
+    V: Send + Sync,
+{
+    type Item = ReadGuard<'map, K, V>;
+
+    fn drive_unindexed<C>(self, consumer: C) -> C::Result
+    where
+        C: UnindexedConsumer<Self::Item>,
+    {
+        let producer = Producer {
+            pause: self.pause,
+            table: self.top,
+            start: 0,
+            end: table::NODES,
+        };
+        bridge_unindexed(producer, consumer)
+    }
+}
+
+impl<'map, K, V> fmt::Debug for ParIter<'map, K, V> {
+    fn fmt(&self, fmtr: &mut fmt::Formatter) -> fmt::Result {
+        write!(fmtr, "ParIter {{ top: {:?} }}", self.top)
+    }
+}
+
+unsafe impl<'map, K, V> Send for ParIter<'map, K, V>
+where
+    K: Send + Sync,
+    V: Send + Sync,
+{
+}
+
+impl<'map, K, V, H> IntoParallelIterator for &'map Map<K, V, H>
+where
+    K: Send + Sync,
+    V: Send + Sync,
+{
+    type Item = ReadGuard<'map, K, V>;
+    type Iter = ParIter<'map, K, V>;
+
+    fn into_par_iter(self) -> Self::Iter {
+        self.par_iter()
+    }
+}
+
+// The slots `start..end` of a table. Nested tables may be detached by
+// `Map::clear_shared` or collapsed by `Map::optimize_space_step` through a
+// shared reference, so every producer holds a pause, cloned from the one of
+// the producer it was split from before that one is dropped. Therefore, the
+// tables and buckets a producer loads stay allocated as long as it lives.
+struct Producer<'map, K, V> {
+    pause: Pause<'map, Garbage<K, V>>,
+    table: &'map Table<K, V>,
+    start: usize,
+    end: usize,
+}
+
+impl<'map, K, V> UnindexedProducer for Producer<'map, K, V>
+where
+    K: Send + Sync,
+    V: Send + Sync,
+{
+    type Item = ReadGuard<'map, K, V>;
+
+    fn split(mut self) -> (Self, Option<Self>) {
+        if self.end - self.start > 1 {
+            let mid = self.start + (self.end - self.start) / 2;
+            let other = Self {
+                pause: self.pause.clone(),
+                table: self.table,
+                start: mid,
+                end: self.end,
+            };
+            self.end = mid;
+            return (self, Some(other));
+        }
+
+        // A single slot can still be split if it holds a nested table.
+        match self.table.load_index(self.start, Acquire) {
+            Some(ptr) if is_tagged(ptr) => {
+                // This is safe because we only store properly allocated tables
+                // marked with 1, we cleared the marked bit, and the pause keeps
+                // the table from being freed even if it is detached.
+                let nested = unsafe { &*(untag(ptr) as *mut Table<K, V>) };
+                let whole = Self {
+                    table: nested,
+                    start: 0,
+                    end: table::NODES,
+                    ..self
+                };
+                whole.split()
+            }
+            _ => (self, None),
+        }
+    }
+
+    fn fold_with<F>(self, mut folder: F) -> F
+    where
+        F: Folder<Self::Item>,
+    {
+        let pause = self.pause;
+        let mut cache = Vec::new();
+
+        for index in self.start..self.end {
+            if folder.full() {
+                break;
+            }
+
+            match self.table.load_index(index, Acquire) {
+                Some(ptr) if ptr.is_null() => (),
+
+                Some(ptr) if !is_tagged(ptr) => {
+                    let bucket = ptr as *mut Bucket<K, V>;
+                    // This is safe because:
+                    //
+                    // 1. The incinerator is paused by this producer.
+                    //
+                    // 2. We checked for null already.
+                    //
+                    // 3. We only store preoperly allocated nodes in the table
+                    // and mark buckets with 0.
+                    unsafe { (*bucket).collect(&pause, &mut cache) };
+                    folder = folder.consume_iter(cache.drain(..));
+                }
+
+                Some(ptr) => {
+                    // Safe for the same reasons as in `split`.
+                    let nested = unsafe { &*(untag(ptr) as *mut Table<K, V>) };
+                    folder = folder.consume_iter(Iter::new(pause.clone(), nested));
+                }
+
+                None => break,
+            }
+        }
+
+        folder
+    }
+}
+
+unsafe impl<'map, K, V> Send for Producer<'map, K, V>
+where
+    K: Send + Sync,
+    V: Send + Sync,
+{
+}
@@ -0,0 +1,148 @@
+use crate::backoff::exponential_backoff;
+use alloc::vec;
+use core::{
+    fmt,
+    iter::FusedIterator,
+    sync::atomic::{AtomicUsize, Ordering::*},
+};
+
+/// The error of [`Map::iter_snapshot`](super::Map::iter_snapshot).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SnapshotError {
+    /// Returned when snapshots were not enabled with
+    /// [`Map::enable_snapshots`](super::Map::enable_snapshots).
+    Disabled,
+    /// Returned when writes kept running while the entries were cloned, until
+    /// the retries ran out.
+    Busy,
+}
+
+// Counts the writes to a map, if enabled, so that readers can tell whether the
+// map changed while they were reading it. Every operation which changes the
+// structure counts itself as started before touching it and as finished once
+// done. Finished writes never outnumber the started ones.
+pub struct Writes {
+    enabled: bool,
+    started: AtomicUsize,
+    finished: AtomicUsize,
+}
+
+impl Writes {
+    pub fn new(enabled: bool) -> Self {
+        Self {
+            enabled,
+            started: AtomicUsize::new(0),
+            finished: AtomicUsize::new(0),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    pub fn enable(&mut self) {
+        self.enabled = true;
+    }
+
+    // Counts a write as started, if enabled. The write is counted as finished
+    // when the returned value is dropped, even if the write panics.
+    pub fn begin(&self) -> Option<Writing<'_>> {
+        if !self.enabled {
+            return None;
+        }
+        self.started.fetch_add(1, AcqRel);
+        Some(Writing {
+            finished: &self.finished,
+        })
+    }
+
+    // Runs `read` until no write starts while it runs, and no write was in
+    // progress when it started, returning the result of the last run. Then,
+    // the result reflects the state of the map between both checks. Backs off
+    // while writes are in progress, and gives up with `exponential_backoff`.
+    pub fn quiescent<F, T>(&self, mut read: F) -> Result<T, SnapshotError>
+    where
+        F: FnMut() -> T,
+    {
+        if !self.enabled {
+            return Err(SnapshotError::Disabled);
+        }
+
+        let mut attempt = 0;
+        loop {
+            // Loading the finished count first means that, if both are equal,
+            // no write was in progress when the started one was loaded.
+            let finished = self.finished.load(Acquire);
+            let started = self.started.load(Acquire);
+            if started == finished {
+                let res = read();
+                if self.started.load(Acquire) == started {
+                    break Ok(res);
+                }
+            }
+
+            if !exponential_backoff(attempt).wait() {
+                break Err(SnapshotError::Busy);
+            }
+            attempt += 1;
+        }
+    }
+}
+
+// A write in progress, counted as finished when dropped.
+pub struct Writing<'map> {
+    finished: &'map AtomicUsize,
+}
+
+impl<'map> Drop for Writing<'map> {
+    fn drop(&mut self) {
+        self.finished.fetch_add(1, Release);
+    }
+}
+
+/// An iterator over clones of the key-value entries of a
+/// [`Map`](super::Map), created by
+/// [`Map::iter_snapshot`](super::Map::iter_snapshot). The entries were
+/// cloned when the snapshot was taken, so they are owned and the map may be
+/// freely changed while iterating.
+pub struct Snapshot<K, V> {
+    entries: vec::IntoIter<(K, V)>,
+}
+
+impl<K, V> Snapshot<K, V> {
+    pub(super) fn new(entries: vec::Vec<(K, V)>) -> Self {
+        Self {
+            entries: entries.into_iter(),
+        }
+    }
+}
+
+impl<K, V> Iterator for Snapshot<K, V> {
+    type Item = (K, V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.entries.next()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.entries.size_hint()
+    }
+}
+
+impl<K, V> ExactSizeIterator for Snapshot<K, V> {}
+
+impl<K, V> FusedIterator for Snapshot<K, V> {}
+
+impl<K, V> fmt::Debug for Snapshot<K, V>
+where
+    K: fmt::Debug,
+    V: fmt::Debug,
+{
+    fn fmt(&self, fmtr: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            fmtr,
+            "Snapshot {{ entries: {:?} }}",
+            self.entries.as_slice()
+        )
+    }
+}
@@ -0,0 +1,46 @@
+/// Statistics of the internal structure of a [`Map`](super::Map), returned by
+/// [`Map::stats`](super::Map::stats). Useful to estimate how much the trie of
+/// tables costs compared to the entries themselves.
+///
+/// The statistics are gathered by walking the structure while other threads
+/// may change it, so they are only exact when no operation runs concurrently.
+/// Bytes are estimated from the sizes of the internal types, and do not
+/// count memory owned by the keys and values, such as the buffer of a
+/// `String`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct MapStats {
+    /// The number of tables, including the top-level one.
+    pub tables: usize,
+    /// The number of occupied buckets. Every bucket holds the entries whose
+    /// keys have the same hash.
+    pub buckets: usize,
+    /// The number of entries found in the buckets.
+    pub entries: usize,
+    /// The depth of the deepest table, where the top-level table has depth 1.
+    pub max_depth: usize,
+    /// An estimate of the heap bytes taken by the tables, buckets and entries.
+    pub bytes: usize,
+}
+
+/// How the entries of a map were merged by
+/// [`Map::merge_from`](super::Map::merge_from).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct MergeStats {
+    /// The number of entries whose key was absent, and which were inserted
+    /// as they were.
+    pub created: usize,
+    /// The number of entries whose key was present, and whose value was
+    /// resolved with the stored one.
+    pub merged: usize,
+}
+
+/// The progress of [`Map::optimize_space_step`](super::Map::optimize_space_step).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct OptimizeStatus {
+    /// The number of unnecessary tables collapsed by the call.
+    pub collapsed: usize,
+    /// Whether the whole map was walked and nothing else could be collapsed.
+    /// It is `false` if the call stopped early, or if another thread was
+    /// already optimizing the map.
+    pub done: bool,
+}
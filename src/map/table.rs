@@ -1,38 +1,106 @@
 use super::{
-    bucket::{Bucket, Garbage, GetRes, InsertRes},
+    bucket::{Bucket, Found, Garbage, GetRes, InsertRes},
     guard::{ReadGuard, Removed},
     insertion::{Inserter, Insertion},
+    instrument::Counters,
+    stats::MapStats,
+};
+use crate::{
+    incin::{Incinerator, Pause},
+    sync::AtomicPtr,
+    tagged_ptr::{check_align, is_tagged, tag, untag},
 };
-use crate::incin::{Incinerator, Pause};
 use alloc::sync::Arc;
 use alloc::vec::Vec;
 use core::{
     borrow::Borrow,
     fmt,
     marker::PhantomData,
+    mem,
     ptr::{null_mut, NonNull},
-    sync::atomic::{
-        AtomicPtr,
-        Ordering::{self, *},
-    },
+    sync::atomic::Ordering::{self, *},
 };
 use owned_alloc::{Cache, OwnedAlloc, UninitAlloc};
 
 const BITS: usize = 8;
 
+// The number of nodes in every table.
+pub const NODES: usize = 1 << BITS;
+
+// The most levels of nested tables allocated upfront for a capacity. Each
+// level takes `NODES` times as many tables as the previous one, so two levels
+// are already tens of thousands of tables, and larger maps grow their deeper
+// levels on demand.
+const MAX_PREALLOCATED_LEVELS: usize = 2;
+
+// The bit which marks the nodes of a nested table being collapsed by
+// `Table::collapse`, or detached by `Table::detach_nodes`, as frozen. Buckets
+// and tables are aligned to at least 4, so the bit is spare. Readers ignore it,
+// but nobody else changes a frozen node.
+const FROZEN_BIT: usize = 2;
+
+#[inline(always)]
+fn is_frozen(ptr: *mut ()) -> bool {
+    ptr as usize & FROZEN_BIT != 0
+}
+
+#[inline(always)]
+fn freeze(ptr: *mut ()) -> *mut () {
+    ptr.cast::<u8>().wrapping_add(FROZEN_BIT).cast()
+}
+
+#[inline(always)]
+fn thaw(ptr: *mut ()) -> *mut () {
+    ptr.cast::<u8>()
+        .wrapping_sub(ptr as usize & FROZEN_BIT)
+        .cast()
+}
+
 // If you remove this alignment, don't remove it. Please, set it to 2.
 #[repr(align(64))]
 pub struct Table<K, V> {
-    nodes: [Node<K, V>; 1 << BITS],
+    nodes: [Node<K, V>; NODES],
 }
 
 impl<K, V> Table<K, V> {
     pub fn new_alloc() -> OwnedAlloc<Self> {
+        check_align::<Self>();
         // Safe because it calls a correctly a function which correctly
         // initializes uninitialized memory with, indeed, uninitialized memory.
         unsafe { UninitAlloc::<Self>::new().init_in_place(|val| val.init_in_place()) }
     }
 
+    // Allocates a table along with the nested tables a map of the given
+    // capacity would most likely grow anyway. A level of nested tables is
+    // built when every slot above it would receive at least two entries on
+    // average, since such slots almost always end up branching, up to
+    // `MAX_PREALLOCATED_LEVELS`.
+    pub fn with_capacity_alloc(capacity: usize) -> OwnedAlloc<Self> {
+        let mut levels = 0;
+        let mut slots = 1usize << BITS;
+        while capacity / slots >= 2 && levels < MAX_PREALLOCATED_LEVELS {
+            levels += 1;
+            slots = slots.saturating_mul(1 << BITS);
+        }
+
+        let mut table = Self::new_alloc();
+        table.preallocate(levels);
+        table
+    }
+
+    fn preallocate(&mut self, levels: usize) {
+        if levels == 0 {
+            return;
+        }
+
+        for node in &self.nodes as &[Node<K, V>] {
+            let mut table = Self::new_alloc();
+            table.preallocate(levels - 1);
+            node.atomic
+                .store(tag(table.into_raw().as_ptr()) as *mut (), Relaxed);
+        }
+    }
+
     // Unsafe because passing ininitialized memory may cause leaks.
     #[inline]
     pub unsafe fn init_in_place(&mut self) {
@@ -49,9 +117,43 @@ impl<K, V> Table<K, V> {
         key: &Q,
         hash: u64,
         pause: Pause<'map, Garbage<K, V>>,
+        counters: &Counters,
     ) -> Option<ReadGuard<'map, K, V>>
     where
-        Q: ?Sized + Ord,
+        Q: ?Sized + Eq,
+        K: Borrow<Q>,
+    {
+        let found = self.find(key, hash, &pause, counters)?;
+        Some(ReadGuard::new(&*found.pair().as_ptr(), pause))
+    }
+
+    // Same as `get`, but only tests whether the key is present, so no guard
+    // is built and the pause may end with the search.
+    pub unsafe fn contains_key<Q>(
+        &self,
+        key: &Q,
+        hash: u64,
+        pause: &Pause<Garbage<K, V>>,
+        counters: &Counters,
+    ) -> bool
+    where
+        Q: ?Sized + Eq,
+        K: Borrow<Q>,
+    {
+        self.find(key, hash, pause, counters).is_some()
+    }
+
+    // Searches for the entry of the given key, which is only valid while the
+    // pause is active. Unsafe for the same reasons as `get`.
+    pub unsafe fn find<'map, Q>(
+        &'map self,
+        key: &Q,
+        hash: u64,
+        pause: &Pause<Garbage<K, V>>,
+        counters: &Counters,
+    ) -> Option<Found<'map, K, V>>
+    where
+        Q: ?Sized + Eq,
         K: Borrow<Q>,
     {
         let mut shifted = hash;
@@ -60,7 +162,7 @@ impl<K, V> Table<K, V> {
         loop {
             // Compute the index from the shifted hash's lower bits.
             let index = shifted as usize & ((1 << BITS) - 1);
-            let loaded = table.nodes[index].atomic.load(Acquire);
+            let loaded = table.nodes[index].load(Acquire);
 
             // Null means we have nothing.
             if loaded.is_null() {
@@ -68,7 +170,7 @@ impl<K, V> Table<K, V> {
             }
 
             // Cleared lower bit means this is a bucket.
-            if loaded as usize & 1 == 0 {
+            if !is_tagged(loaded) {
                 let bucket = &*(loaded as *mut Bucket<K, V>);
 
                 // This bucket only matters if it has the same hash we do.
@@ -76,15 +178,15 @@ impl<K, V> Table<K, V> {
                     break None;
                 }
 
-                break match bucket.get(key, pause) {
+                break match bucket.get(key, pause, counters) {
                     // Success.
-                    GetRes::Found(pair) => Some(pair),
+                    GetRes::Found(found) => Some(found),
 
                     // Not here.
                     GetRes::NotFound => None,
 
                     // Delete the bucket completely.
-                    GetRes::Delete(pause) => {
+                    GetRes::Delete => {
                         let res = table.nodes[index].atomic.compare_exchange(
                             loaded,
                             null_mut(),
@@ -98,6 +200,7 @@ impl<K, V> Table<K, V> {
                             // Needs to be destroyed by the incinerator as it is
                             // shared.
                             pause.add_to_incin(Garbage::Bucket(alloc));
+                            counters.incin_deferrals(1);
                         }
 
                         None
@@ -107,7 +210,7 @@ impl<K, V> Table<K, V> {
 
             // If none of other cases have been confirmed, the only remaining
             // case is a branching table. Let's try to look at it.
-            table = &*((loaded as usize & !1) as *mut Self);
+            table = &*(untag(loaded) as *mut Self);
             // Shifting the hash so we test some other bits.
             shifted >>= BITS;
         }
@@ -123,10 +226,11 @@ impl<K, V> Table<K, V> {
         hash: u64,
         pause: &Pause<Garbage<K, V>>,
         incin: &Arc<Incinerator<Garbage<K, V>>>,
+        counters: &Counters,
     ) -> Insertion<K, V, I>
     where
         I: Inserter<K, V>,
-        K: Ord,
+        K: Eq,
     {
         let mut table = self;
         let mut shifted = hash;
@@ -139,7 +243,17 @@ impl<K, V> Table<K, V> {
         let mut loaded = table.nodes[index].atomic.load(Acquire);
 
         loop {
-            if loaded.is_null() {
+            if is_frozen(loaded) {
+                // This table is being collapsed, and its nodes cannot change.
+                // Let's start over from the top, where the collapse shows up
+                // once it completes.
+                core::hint::spin_loop();
+                table = self;
+                shifted = hash;
+                depth = 1;
+                index = shifted as usize & ((1 << BITS) - 1);
+                loaded = table.nodes[index].atomic.load(Acquire);
+            } else if loaded.is_null() {
                 // Let's test the found conditions.
                 inserter.input(None);
                 let pair = match inserter.pointer() {
@@ -173,10 +287,11 @@ impl<K, V> Table<K, V> {
                         // If we failed this try, we have to clean up.
                         let mut bucket = OwnedAlloc::from_raw(bucket_nnptr);
                         bucket.take_first();
+                        counters.cas_retry();
                         loaded = new;
                     }
                 }
-            } else if loaded as usize & 1 == 0 {
+            } else if !is_tagged(loaded) {
                 // We keep pointers to Buckets with the lower bit cleared.
                 let bucket = &*(loaded as *mut Bucket<K, V>);
 
@@ -184,7 +299,7 @@ impl<K, V> Table<K, V> {
                 // for us to branch. Actually, we must not do it. We must insert
                 // in the bucket.
                 if bucket.hash() == hash {
-                    match bucket.insert(inserter, pause, incin) {
+                    match bucket.insert(inserter, pause, incin, counters) {
                         InsertRes::Created => break Insertion::Created,
 
                         InsertRes::Updated(old) => {
@@ -206,11 +321,13 @@ impl<K, V> Table<K, V> {
                                     let alloc = OwnedAlloc::from_raw(NonNull::new_unchecked(
                                         loaded as *mut _,
                                     ));
-                                    incin.add(Garbage::Bucket(alloc));
+                                    pause.add_to_incin(Garbage::Bucket(alloc));
+                                    counters.incin_deferrals(1);
                                     loaded = null_mut()
                                 }
 
                                 Err(new) => {
+                                    counters.cas_retry();
                                     loaded = new;
                                 }
                             }
@@ -231,7 +348,7 @@ impl<K, V> Table<K, V> {
                     let res = table.nodes[index].atomic.compare_exchange(
                         loaded,
                         // Note we mark the lower bit!
-                        (new_table_nnptr.as_ptr() as usize | 1) as *mut (),
+                        tag(new_table_nnptr.as_ptr()) as *mut (),
                         AcqRel,
                         Acquire,
                     );
@@ -241,6 +358,8 @@ impl<K, V> Table<K, V> {
                             // If we succeeded, let's act like we found another
                             // table in this index.
                             depth += 1;
+                            trace_event!(trace, depth, "map created a nested table");
+                            counters.table_split();
                             table = &*new_table_nnptr.as_ptr();
                             shifted >>= BITS;
                             // Compute the index from the shifted hash's lower
@@ -262,6 +381,7 @@ impl<K, V> Table<K, V> {
                                 .atomic
                                 .store(null_mut(), Relaxed);
                             tbl_cache.store(new_table);
+                            counters.cas_retry();
                             loaded = new;
                         }
                     }
@@ -271,7 +391,7 @@ impl<K, V> Table<K, V> {
                 // remaining case is a branching table. Let's
                 // try to look at it.
                 depth += 1;
-                table = &*((loaded as usize & !1) as *mut Self);
+                table = &*(untag(loaded) as *mut Self);
                 shifted >>= BITS;
 
                 // Compute the index from the shifted hash's lower
@@ -294,9 +414,10 @@ impl<K, V> Table<K, V> {
         hash: u64,
         pause: &Pause<Garbage<K, V>>,
         incin: &Arc<Incinerator<Garbage<K, V>>>,
+        counters: &Counters,
     ) -> Option<Removed<K, V>>
     where
-        Q: ?Sized + Ord,
+        Q: ?Sized + Eq,
         K: Borrow<Q>,
         F: FnMut(&(K, V)) -> bool,
     {
@@ -307,7 +428,7 @@ impl<K, V> Table<K, V> {
             // Compute the index from the shifted hash's lower bits.
             let index = shifted as usize & ((1 << BITS) - 1);
             // Let's load to see what is in there.
-            let loaded = table.nodes[index].atomic.load(Acquire);
+            let loaded = table.nodes[index].load(Acquire);
 
             // Null means we have nothing.
             if loaded.is_null() {
@@ -315,7 +436,7 @@ impl<K, V> Table<K, V> {
             }
 
             // Cleared lower bit means this is a bucket.
-            if loaded as usize & 1 == 0 {
+            if !is_tagged(loaded) {
                 let bucket = &*(loaded as *mut Bucket<K, V>);
 
                 // This bucket only matters if it has the same hash we do.
@@ -323,7 +444,7 @@ impl<K, V> Table<K, V> {
                     break None;
                 }
 
-                let res = bucket.remove(key, interactive, pause, incin);
+                let res = bucket.remove(key, interactive, pause, incin, counters);
 
                 // If this field is true it means the whole bucket must be
                 // removed. Regardless of failure or success.
@@ -337,7 +458,8 @@ impl<K, V> Table<K, V> {
 
                     if res.is_ok() {
                         let alloc = OwnedAlloc::from_raw(NonNull::new_unchecked(loaded as *mut _));
-                        incin.add(Garbage::Bucket(alloc));
+                        pause.add_to_incin(Garbage::Bucket(alloc));
+                        counters.incin_deferrals(1);
                     }
                 }
                 break res.pair;
@@ -345,12 +467,204 @@ impl<K, V> Table<K, V> {
 
             // If none of other cases have been confirmed, the only remaining
             // case is a branching table. Let's try to look at it.
-            table = &*((loaded as usize & !1) as *mut Self);
+            table = &*(untag(loaded) as *mut Self);
             // Shifting the hash so we test some other bits.
             shifted >>= BITS;
         }
     }
 
+    // Empties every node of this table, handing the detached buckets and
+    // nested tables to the incinerator. Returns how many entries were removed
+    // from them. Operations which loaded a node before it was detached either
+    // finish before its entries are removed, or find the nodes below frozen
+    // and the buckets empty, and start over from this table. Unsafe because
+    // the incinerator needs to be paused and there are no guarantees the passed
+    // pause comes from the incinerator used with the map by other threads. Map
+    // implementation guarantees that.
+    pub unsafe fn detach_nodes(&self, pause: &Pause<Garbage<K, V>>, counters: &Counters) -> usize {
+        let mut len = 0;
+
+        for node in &self.nodes as &[Node<K, V>] {
+            let loaded = node.atomic.swap(null_mut(), AcqRel);
+
+            if loaded.is_null() {
+                continue;
+            }
+
+            len += Self::seal_ptr(loaded, pause);
+            if !is_tagged(loaded) {
+                let alloc = OwnedAlloc::from_raw(NonNull::new_unchecked(loaded as *mut _));
+                pause.add_to_incin(Garbage::Bucket(alloc));
+            } else {
+                let table = untag(loaded) as *mut Self;
+                let table = OwnedAlloc::from_raw(NonNull::new_unchecked(table));
+                pause.add_to_incin(Garbage::Table(Subtable { table }));
+            }
+            counters.incin_deferrals(1);
+        }
+
+        len
+    }
+
+    // Freezes every node of this detached table, waiting for a collapse which
+    // froze some of them first, and seals what they hold. Returns how many
+    // entries were removed. Unsafe for the same reasons as `detach_nodes`.
+    unsafe fn seal(&self, pause: &Pause<Garbage<K, V>>) -> usize {
+        let mut len = 0;
+
+        for node in &self.nodes as &[Node<K, V>] {
+            let loaded = loop {
+                let loaded = node.atomic.load(Acquire);
+                if is_frozen(loaded) {
+                    core::hint::spin_loop();
+                    continue;
+                }
+                let res = node
+                    .atomic
+                    .compare_exchange(loaded, freeze(loaded), AcqRel, Relaxed);
+                if res.is_ok() {
+                    break loaded;
+                }
+            };
+            len += Self::seal_ptr(loaded, pause);
+        }
+
+        len
+    }
+
+    // Seals the bucket or table a detached node held. Unsafe for the same
+    // reasons as `detach_nodes`, and because the pointer must be loaded from a
+    // node.
+    unsafe fn seal_ptr(ptr: *mut (), pause: &Pause<Garbage<K, V>>) -> usize {
+        if ptr.is_null() {
+            0
+        } else if !is_tagged(ptr) {
+            (*(ptr as *mut Bucket<K, V>)).seal(pause)
+        } else {
+            (*(untag(ptr) as *mut Self)).seal(pause)
+        }
+    }
+
+    // Collapses the nested tables below this one which are empty or hold a
+    // single bucket, deepest first, and deletes the empty buckets, while other
+    // threads use the map. Stops once `budget` tables were collapsed,
+    // decrementing it for each one. Returns whether nothing else below this
+    // table could be collapsed. Must not run concurrently with itself. Unsafe
+    // because the incinerator needs to be paused and there are no guarantees
+    // the passed pause comes from the incinerator used with the map by other
+    // threads. Map implementation guarantees that.
+    pub unsafe fn collapse(
+        &self,
+        budget: &mut usize,
+        pause: &Pause<Garbage<K, V>>,
+        counters: &Counters,
+    ) -> bool {
+        let mut done = true;
+
+        for node in &self.nodes as &[Node<K, V>] {
+            if *budget == 0 {
+                return false;
+            }
+
+            let loaded = node.atomic.load(Acquire);
+
+            // A frozen node was detached by a shared clear, which removes what
+            // it holds.
+            if loaded.is_null() || is_frozen(loaded) {
+                continue;
+            }
+
+            if !is_tagged(loaded) {
+                // Nobody inserts into an empty bucket, so it can be deleted
+                // just like lookups do.
+                let bucket = &*(loaded as *mut Bucket<K, V>);
+                if bucket.is_empty()
+                    && node
+                        .atomic
+                        .compare_exchange(loaded, null_mut(), AcqRel, Relaxed)
+                        .is_ok()
+                {
+                    let alloc = OwnedAlloc::from_raw(NonNull::new_unchecked(loaded as *mut _));
+                    pause.add_to_incin(Garbage::Bucket(alloc));
+                    counters.incin_deferrals(1);
+                }
+                continue;
+            }
+
+            let table_ptr = untag(loaded) as *mut Self;
+            let table = &*table_ptr;
+            if !table.collapse(budget, pause, counters) || *budget == 0 {
+                done = false;
+                continue;
+            }
+
+            let moved = match table.freeze_nodes() {
+                Some(moved) => moved,
+                None => continue,
+            };
+            // The frozen nodes cannot change anymore, so the parent node can
+            // take whatever the table held.
+            let res = node.atomic.compare_exchange(loaded, moved, AcqRel, Relaxed);
+            if res.is_ok() {
+                trace_event!(trace, "map removed a nested table");
+                // The bucket, if any, now belongs to this table, so only the
+                // nested table itself is freed.
+                let alloc = OwnedAlloc::from_raw(NonNull::new_unchecked(table_ptr));
+                pause.add_to_incin(Garbage::Collapsed(alloc));
+                counters.incin_deferrals(1);
+                *budget -= 1;
+            } else {
+                // The node was detached by a shared clear.
+                table.thaw_nodes(NODES);
+                done = false;
+            }
+        }
+
+        done
+    }
+
+    // Freezes every node of this table if it holds at most one bucket and no
+    // nested table, and returns what its parent node should hold instead:
+    // either null or the bucket. Otherwise, nothing is left frozen.
+    unsafe fn freeze_nodes(&self) -> Option<*mut ()> {
+        let mut moved: *mut () = null_mut();
+
+        for (index, node) in (&self.nodes as &[Node<K, V>]).iter().enumerate() {
+            let frozen = loop {
+                let loaded = node.atomic.load(Acquire);
+                if is_tagged(loaded) || is_frozen(loaded) || !(loaded.is_null() || moved.is_null())
+                {
+                    break None;
+                }
+                let res = node
+                    .atomic
+                    .compare_exchange(loaded, freeze(loaded), AcqRel, Relaxed);
+                if res.is_ok() {
+                    break Some(loaded);
+                }
+            };
+
+            match frozen {
+                Some(loaded) if !loaded.is_null() => moved = loaded,
+                Some(_) => (),
+                None => {
+                    self.thaw_nodes(index);
+                    return None;
+                }
+            }
+        }
+
+        Some(moved)
+    }
+
+    // Thaws the first `count` nodes, which must be frozen. Nobody else changes
+    // frozen nodes, so storing is enough.
+    unsafe fn thaw_nodes(&self, count: usize) {
+        for node in &self.nodes[..count] {
+            node.atomic.store(thaw(node.atomic.load(Relaxed)), Release);
+        }
+    }
+
     // Unsafe because calling this function and using the table again later will
     // cause undefined behavior.
     #[inline]
@@ -370,6 +684,100 @@ impl<K, V> Table<K, V> {
         }
     }
 
+    // Retains only the entries for which the predicate returns true, freeing
+    // the others right away, along with the buckets left empty.
+    pub fn retain<F>(&mut self, pred: &mut F, len: &mut usize)
+    where
+        F: FnMut(&K, &V) -> bool,
+    {
+        for node in &self.nodes as &[Node<K, V>] {
+            let loaded = node.atomic.load(Relaxed);
+
+            if loaded.is_null() {
+                continue;
+            }
+
+            if !is_tagged(loaded) {
+                let bucket_ptr = loaded as *mut Bucket<K, V>;
+                // This is safe because:
+                //
+                // 1. We have exclusive reference to the table.
+                //
+                // 2. We proper allocate pointers stored in the table.
+                //
+                // 3. Bucket pointers are not marked and we checked for it.
+                let bucket = unsafe { &mut *bucket_ptr };
+                bucket.retain(pred, len);
+
+                // Safe because we have exclusive reference to the bucket.
+                if unsafe { bucket.is_empty() } {
+                    node.atomic.store(null_mut(), Relaxed);
+                    // This is safe because we have exclusive reference to the
+                    // map. Also, we remove the bucket from the table so no one
+                    // else will find it.
+                    unsafe {
+                        OwnedAlloc::from_raw(NonNull::new_unchecked(bucket_ptr));
+                    }
+                }
+            } else {
+                let table_ptr = untag(loaded) as *mut Table<K, V>;
+                // This is safe because:
+                //
+                // 1. We have exclusive reference to the table.
+                //
+                // 2. We proper allocate pointers stored in the table.
+                //
+                // 3. Table pointers are marked and we checked for it.
+                //
+                // 4. We cleared the marked bit.
+                unsafe { &mut *table_ptr }.retain(pred, len);
+            }
+        }
+    }
+
+    // Moves the entries for which the predicate returns true into `dest`,
+    // which must be empty and use the same hashes, keeping the same shape of
+    // tables. Decrements `len` for every entry moved. Unsafe because `dest`
+    // must not have any nodes yet, or they are overwritten.
+    pub unsafe fn partition<F>(&mut self, dest: &mut Self, pred: &mut F, len: &mut usize)
+    where
+        F: FnMut(&K, &V) -> bool,
+    {
+        let nodes = (&self.nodes as &[Node<K, V>]).iter();
+        for (node, dest_node) in nodes.zip(&dest.nodes as &[Node<K, V>]) {
+            let loaded = node.atomic.load(Relaxed);
+
+            if loaded.is_null() {
+                continue;
+            }
+
+            if !is_tagged(loaded) {
+                let bucket_ptr = loaded as *mut Bucket<K, V>;
+                // Safe for the same reasons as in `retain`.
+                let bucket = &mut *bucket_ptr;
+                if let Some(moved) = bucket.partition(pred, len) {
+                    let ptr = OwnedAlloc::new(moved).into_raw().as_ptr();
+                    dest_node.atomic.store(ptr as *mut (), Relaxed);
+                }
+
+                if bucket.is_empty() {
+                    node.atomic.store(null_mut(), Relaxed);
+                    // Safe for the same reasons as in `retain`.
+                    OwnedAlloc::from_raw(NonNull::new_unchecked(bucket_ptr));
+                }
+            } else {
+                // The nested table is stored before being filled, so it is
+                // freed with `dest` if the predicate panics.
+                let table = Self::new_alloc().into_raw();
+                dest_node
+                    .atomic
+                    .store(tag(table.as_ptr()) as *mut (), Relaxed);
+                // Safe for the same reasons as in `retain`.
+                (*(untag(loaded) as *mut Table<K, V>)).partition(&mut *table.as_ptr(), pred, len);
+            }
+        }
+    }
+
     pub fn optimize_space(&mut self) -> OptSpaceRes<K, V> {
         let mut removed = 0usize;
         let mut last_bucket = None;
@@ -379,7 +787,7 @@ impl<K, V> Table<K, V> {
 
             if loaded.is_null() {
                 removed += 1;
-            } else if loaded as usize & 1 == 0 {
+            } else if !is_tagged(loaded) {
                 let bucket_ptr = loaded as *mut Bucket<K, V>;
                 // This is safe because:
                 //
@@ -405,7 +813,7 @@ impl<K, V> Table<K, V> {
                     last_bucket = Some(nnptr);
                 }
             } else {
-                let table_ptr = (loaded as usize & !1) as *mut Table<K, V>;
+                let table_ptr = untag(loaded) as *mut Table<K, V>;
 
                 // This is safe because:
                 //
@@ -420,6 +828,7 @@ impl<K, V> Table<K, V> {
                     OptSpaceRes::NoOpt => (),
 
                     OptSpaceRes::Remove => {
+                        trace_event!(trace, "map removed a nested table");
                         node.atomic.store(null_mut(), Relaxed);
                         // This is safe because we have exclusive reference to
                         // the map. Also, we remove the inner table from the
@@ -432,6 +841,7 @@ impl<K, V> Table<K, V> {
                     }
 
                     OptSpaceRes::TableToBucket(bucket) => {
+                        trace_event!(trace, "map removed a nested table");
                         unsafe {
                             // This is safe because we have exclusive reference
                             // to the map. Also, we remove the inner table from
@@ -454,8 +864,212 @@ impl<K, V> Table<K, V> {
         }
     }
 
+    // Fills this table, which must be empty, with clones of the entries of the
+    // given one, rebuilding the nested tables and buckets directly. Every new
+    // node is stored before it is filled, so the tables own everything built
+    // so far if a clone panics. Adds the number of cloned entries to `len`.
+    // Unsafe because the incinerator needs to be paused and there are no
+    // guarantees the passed pause comes from the incinerator used with the
+    // source table by other threads. Map implementation guarantees that.
+    pub unsafe fn clone_from(&mut self, src: &Self, pause: &Pause<Garbage<K, V>>, len: &mut usize)
+    where
+        K: Clone,
+        V: Clone,
+    {
+        let nodes = (&self.nodes as &[Node<K, V>]).iter();
+        for (node, src_node) in nodes.zip(&src.nodes as &[Node<K, V>]) {
+            let loaded = src_node.load(Acquire);
+
+            if loaded.is_null() {
+                continue;
+            }
+
+            if !is_tagged(loaded) {
+                let bucket = &*(loaded as *mut Bucket<K, V>);
+                if let Some((bucket, count)) = bucket.clone_entries(pause) {
+                    let ptr = OwnedAlloc::new(bucket).into_raw().as_ptr();
+                    node.atomic.store(ptr as *mut (), Relaxed);
+                    *len += count;
+                }
+            } else {
+                let src_table = &*(untag(loaded) as *mut Table<K, V>);
+                let table = Self::new_alloc().into_raw();
+                node.atomic.store(tag(table.as_ptr()) as *mut (), Relaxed);
+                (*table.as_ptr()).clone_from(src_table, pause, len);
+            }
+        }
+    }
+
+    // Fills this empty table with the given hashed pairs, which must be sorted
+    // by their hashes with swapped bytes, so that the pairs of every node are
+    // contiguous, and whose hashes must share their lower `depth * BITS` bits.
+    // Of pairs with equal keys, only the last one is kept. Every node is
+    // stored before it is filled, so the tables own everything built so far if
+    // a comparison of keys panics. Adds the number of entries to `len`.
+    pub fn build(&mut self, depth: usize, mut pairs: &mut [(u64, Option<(K, V)>)], len: &mut usize)
+    where
+        K: Eq,
+    {
+        let index_of = |hash: u64| (hash >> (depth * BITS)) as usize & (NODES - 1);
+
+        while let Some(&(hash, _)) = pairs.first() {
+            let index = index_of(hash);
+            let end = pairs
+                .iter()
+                .position(|&(other, _)| index_of(other) != index)
+                .unwrap_or(pairs.len());
+            let (node_pairs, rest) = mem::take(&mut pairs).split_at_mut(end);
+            pairs = rest;
+            let node = &self.nodes[index];
+
+            if node_pairs[end - 1].0 != hash {
+                // Different hashes, so a nested table tells them apart.
+                let table = Self::new_alloc().into_raw();
+                node.atomic.store(tag(table.as_ptr()) as *mut (), Relaxed);
+                // Safe because we own the new table, and nobody else can
+                // reach it.
+                unsafe { (*table.as_ptr()).build(depth + 1, node_pairs, len) };
+                continue;
+            }
+
+            let mut bucket_pairs = Vec::<(K, V)>::with_capacity(end);
+            for (_, pair) in node_pairs {
+                // Safe to unwrap because every pair is only taken once.
+                let pair = pair.take().unwrap();
+                match bucket_pairs.iter_mut().find(|(key, _)| *key == pair.0) {
+                    Some(stored) => *stored = pair,
+                    None => bucket_pairs.push(pair),
+                }
+            }
+            *len += bucket_pairs.len();
+            let bucket = Bucket::from_pairs(hash, bucket_pairs);
+            let ptr = OwnedAlloc::new(bucket).into_raw().as_ptr();
+            node.atomic.store(ptr as *mut (), Relaxed);
+        }
+    }
+
+    // Returns how many heap bytes this table and everything below it take.
+    // Unsafe because the incinerator needs to be paused and there are no
+    // guarantees the passed pause comes from the incinerator used with the map
+    // by other threads. Map implementation guarantees that.
+    pub unsafe fn bytes_allocated(&self, pause: &Pause<Garbage<K, V>>) -> usize {
+        let mut bytes = mem::size_of::<Self>();
+
+        for node in &self.nodes as &[Node<K, V>] {
+            let loaded = node.load(Acquire);
+
+            if loaded.is_null() {
+                continue;
+            }
+
+            if !is_tagged(loaded) {
+                let bucket = &*(loaded as *mut Bucket<K, V>);
+                bytes += bucket.bytes_allocated(pause);
+            } else {
+                let table = &*(untag(loaded) as *mut Table<K, V>);
+                bytes += table.bytes_allocated(pause);
+            }
+        }
+
+        bytes
+    }
+
+    // Accumulates the statistics of this table and its children, given the
+    // depth of this table. Unsafe for the same reasons as `bytes_allocated`.
+    pub unsafe fn stats(&self, depth: usize, pause: &Pause<Garbage<K, V>>, stats: &mut MapStats) {
+        stats.tables += 1;
+        stats.max_depth = stats.max_depth.max(depth);
+        stats.bytes += mem::size_of::<Self>();
+
+        for node in &self.nodes as &[Node<K, V>] {
+            let loaded = node.load(Acquire);
+
+            if loaded.is_null() {
+                continue;
+            }
+
+            if !is_tagged(loaded) {
+                let bucket = &*(loaded as *mut Bucket<K, V>);
+                let len = bucket.len(pause);
+                stats.buckets += 1;
+                stats.entries += len;
+                stats.bytes += Bucket::<K, V>::bytes_with_len(len);
+            } else {
+                let table = &*(untag(loaded) as *mut Table<K, V>);
+                table.stats(depth + 1, pause, stats);
+            }
+        }
+    }
+
+    // Calls the visitor on every entry of this table and its children. The
+    // entries of a bucket are gathered in the given buffer before any of them
+    // is visited, so a bucket changed meanwhile is never visited twice.
+    // Unsafe for the same reasons as `bytes_allocated`.
+    pub unsafe fn for_each<'map, F>(
+        &'map self,
+        pause: &Pause<Garbage<K, V>>,
+        buf: &mut Vec<&'map (K, V)>,
+        visit: &mut F,
+    ) where
+        F: FnMut(&K, &V),
+    {
+        for node in &self.nodes as &[Node<K, V>] {
+            let loaded = node.load(Acquire);
+
+            if loaded.is_null() {
+                continue;
+            }
+
+            if !is_tagged(loaded) {
+                let bucket = &*(loaded as *mut Bucket<K, V>);
+                bucket.pairs(pause, buf);
+                for (key, val) in buf.drain(..) {
+                    visit(key, val);
+                }
+            } else {
+                let table = &*(untag(loaded) as *mut Table<K, V>);
+                table.for_each(pause, buf, visit);
+            }
+        }
+    }
+
+    // Returns some entry of this table or its children. Nodes are scanned
+    // starting at the index given by the lower bits of the seed, wrapping
+    // around, and nested tables take the next bits, just like with a hash.
+    // Unsafe for the same reasons as `bytes_allocated`.
+    pub unsafe fn any<'map>(
+        &'map self,
+        seed: u64,
+        pause: &Pause<'map, Garbage<K, V>>,
+    ) -> Option<ReadGuard<'map, K, V>> {
+        let start = seed as usize & (NODES - 1);
+
+        for offset in 0..NODES {
+            let node = &self.nodes[(start + offset) & (NODES - 1)];
+            let loaded = node.load(Acquire);
+
+            if loaded.is_null() {
+                continue;
+            }
+
+            let found = if !is_tagged(loaded) {
+                let bucket = &*(loaded as *mut Bucket<K, V>);
+                bucket.first(pause)
+            } else {
+                let table = &*(untag(loaded) as *mut Table<K, V>);
+                table.any(seed >> BITS, pause)
+            };
+
+            if found.is_some() {
+                return found;
+            }
+        }
+
+        None
+    }
+
     pub fn load_index(&self, index: usize, ordering: Ordering) -> Option<*mut ()> {
-        self.nodes.get(index).map(|node| node.atomic.load(ordering))
+        self.nodes.get(index).map(|node| node.load(ordering))
     }
 }
 
@@ -469,6 +1083,33 @@ impl<K, V> fmt::Debug for Table<K, V> {
     }
 }
 
+// A nested table detached from a map, which frees its whole subtree when
+// dropped.
+pub struct Subtable<K, V> {
+    table: OwnedAlloc<Table<K, V>>,
+}
+
+impl<K, V> Drop for Subtable<K, V> {
+    fn drop(&mut self) {
+        let mut tables = Vec::new();
+
+        // Safe because the incinerator only drops this once no thread reads
+        // the subtree anymore.
+        unsafe { self.table.free_nodes(&mut tables) }
+
+        while let Some(mut table) = tables.pop() {
+            // Safe for the same reason.
+            unsafe { table.free_nodes(&mut tables) }
+        }
+    }
+}
+
+impl<K, V> fmt::Debug for Subtable<K, V> {
+    fn fmt(&self, fmtr: &mut fmt::Formatter) -> fmt::Result {
+        write!(fmtr, "Subtable {{ table: {:?} }}", self.table)
+    }
+}
+
 struct Node<K, V> {
     // First lower bit is 0 for leaf and 1 for branch
     atomic: AtomicPtr<()>,
@@ -479,14 +1120,15 @@ impl<K, V> Node<K, V> {
     // Unsafe because it is *pretty easy* to make undefined behavior out of this
     // because the pointer does not have even a fixed type.
     unsafe fn free_ptr(ptr: *mut (), tbl_stack: &mut Vec<OwnedAlloc<Table<K, V>>>) {
+        let ptr = thaw(ptr);
         if ptr.is_null() {
             return;
         }
 
-        if ptr as usize & 1 == 0 {
+        if !is_tagged(ptr) {
             OwnedAlloc::from_raw(NonNull::new_unchecked(ptr as *mut Bucket<K, V>));
         } else {
-            let table_ptr = (ptr as usize & !1) as *mut Table<K, V>;
+            let table_ptr = untag(ptr) as *mut Table<K, V>;
 
             debug_assert!(!table_ptr.is_null());
             tbl_stack.push(OwnedAlloc::from_raw(NonNull::new_unchecked(table_ptr)));
@@ -501,6 +1143,12 @@ impl<K, V> Node<K, V> {
             _marker: PhantomData,
         }
     }
+
+    // Loads the pointer for reading, ignoring whether the node is frozen.
+    #[inline]
+    fn load(&self, ordering: Ordering) -> *mut () {
+        thaw(self.atomic.load(ordering))
+    }
 }
 
 impl<K, V> fmt::Debug for Node<K, V> {
@@ -1,9 +1,99 @@
+#[cfg(feature = "crossbeam-interop")]
+pub use crate::epoch::EpochReclaim;
+#[cfg(feature = "instrument")]
+pub use crate::map::Instrumentation as MapInstrumentation;
+#[cfg(feature = "rayon")]
+pub use crate::map::ParIter as MapParIter;
+pub use crate::{
+    backoff::RetryDecision,
+    footprint::MemoryFootprint,
+    insertion::Insertion,
+    map::{
+        AtomicCell as MapAtomicCell, AtomicValue as MapAtomicValue, BoundedMap,
+        CasError as MapCasError, Drain as MapDrain, Entry as MapEntry, ExactIter as MapExactIter,
+        FetchUpdateError as MapFetchUpdateError, Insertion as MapInsertion,
+        IntoIter as MapIntoIter, Iter as MapIter, IterMut as MapIterMut, Keys as MapKeys, Map,
+        MapEvent, MapStats, MappedGuard, MergeStats as MapMergeStats,
+        OccupiedError as MapOccupiedError, OptimizeStatus as MapOptimizeStatus,
+        OwnedReadGuard as MapOwnedReadGuard, Preview, ReadGuard as MapReadGuard,
+        RemoveError as MapRemoveError, Removed as MapRemoved, Snapshot as MapSnapshot,
+        SnapshotError as MapSnapshotError, Values as MapValues, ValuesMut as MapValuesMut,
+    },
+    queue::{PopIter as QueuePopIter, Queue},
+    removable::Removable,
+    set::{
+        Difference as SetDifference, Drain as SetDrain, ExactIter as SetExactIter,
+        Insertion as SetInsertion, Intersection as SetIntersection, IntoIter as SetIntoIter,
+        Iter as SetIter, ReadGuard as SetReadGuard, Removed as SetRemoved, Set,
+        SymmetricDifference as SetSymmetricDifference, Union as SetUnion,
+    },
+    stack::{PopIter as StackPopIter, Stack},
+    traits::{ApproxLen, ConcurrentInsert, ConcurrentPop},
+};
 #[cfg(feature = "std")]
 pub use crate::{
-    channel::{mpmc, mpsc, spmc, spsc},
-    map::Map,
-    queue::Queue,
-    set::Set,
-    stack::Stack,
-    tls::ThreadLocal,
+    channel::{mpmc, mpsc, spmc, spsc, NoRecv, RecvErr},
+    tls::{ThreadId, ThreadLocal},
 };
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use core::sync::atomic::Ordering;
+
+    #[test]
+    fn map_names() {
+        let map = Map::new();
+        map.insert(1, 2);
+        let guard: MapReadGuard<i32, i32> = map.get(&1).unwrap();
+        assert_eq!(guard.val(), &2);
+        drop(guard);
+        let insertion: MapInsertion<i32, i32, (i32, Option<i32>)> =
+            map.insert_with(1, |_, _, _| Preview::New(3));
+        assert!(insertion.is_updated());
+        let removed: MapRemoved<i32, i32> = map.remove(&1).unwrap();
+        assert_eq!(removed.val(), &3);
+        let pairs: MapIntoIter<i32, i32> = map.into_iter();
+        assert_eq!(pairs.count(), 0);
+    }
+
+    #[test]
+    fn set_names() {
+        let set = Set::new();
+        set.insert(1).unwrap();
+        let guard: SetReadGuard<i32> = set.get(&1).unwrap();
+        assert_eq!(*guard, 1);
+        drop(guard);
+        let insertion: SetInsertion<i32, i32> = set.insert_with(1, |_, _| false);
+        assert!(insertion.is_failed());
+        let removed: SetRemoved<i32> = set.remove(&1).unwrap();
+        assert_eq!(*removed, 1);
+        let mut iter: SetIter<i32> = set.iter();
+        assert!(iter.next().is_none());
+    }
+
+    #[test]
+    fn other_names() {
+        let queue = Queue::new();
+        queue.push(1);
+        let stack = Stack::new();
+        stack.push(2);
+        let removable = Removable::new(3);
+        assert_eq!(queue.pop(), Some(1));
+        assert_eq!(stack.pop(), Some(2));
+        assert_eq!(removable.take(Ordering::SeqCst), Some(3));
+        let _: RetryDecision = crate::backoff::exponential_backoff(0);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn std_names() {
+        let tls = ThreadLocal::new();
+        assert_eq!(*tls.with_init(|| 5), 5);
+        let (mut sender, mut receiver) = spsc::create();
+        sender.send(1).unwrap();
+        assert_eq!(receiver.recv(), Ok(1));
+        drop(sender);
+        assert_eq!(receiver.recv(), Err(RecvErr::NoSender));
+    }
+}
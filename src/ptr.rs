@@ -1,7 +1,4 @@
-use core::{
-    mem::align_of,
-    ptr::{null_mut, NonNull},
-};
+use core::ptr::NonNull;
 
 static _NON_NULL: u8 = /* dummy value */ 1;
 
@@ -16,8 +13,3 @@ pub unsafe fn bypass_null<T>(ptr: *mut T) -> NonNull<T> {
     debug_assert!(!ptr.is_null());
     NonNull::new_unchecked(ptr)
 }
-
-#[inline(always)]
-pub fn check_null_align<T>() {
-    debug_assert!(null_mut::<T>() as usize % align_of::<T>() == 0);
-}
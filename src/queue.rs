@@ -1,11 +1,14 @@
 use crate::{
-    incin::Pause,
-    ptr::{bypass_null, check_null_align},
+    footprint::MemoryFootprint,
+    incin::{Pause, Reclaimer},
+    ptr::bypass_null,
     removable::Removable,
+    traits::{ApproxLen, ConcurrentInsert, ConcurrentPop},
 };
 use core::{
     fmt,
     iter::FromIterator,
+    mem,
     ptr::{null_mut, NonNull},
     sync::atomic::{AtomicPtr, Ordering::*},
 };
@@ -22,10 +25,19 @@ pub struct Queue<T> {
 impl<T> Queue<T> {
     /// Creates a new empty queue.
     pub fn new() -> Self {
-        check_null_align::<Node<T>>();
         Self::with_incin(SharedIncin::new())
     }
 
+    /// Creates an empty queue whose removed nodes are handed to the given
+    /// reclaimer. See [`Incinerator::with_reclaimer`](crate::incin::Incinerator::with_reclaimer).
+    pub fn with_reclaimer<R>(reclaimer: R) -> Self
+    where
+        T: Send + 'static,
+        R: Reclaimer,
+    {
+        Self::with_incin(SharedIncin::with_reclaimer(reclaimer))
+    }
+
     /// Creates an empty queue using the passed shared incinerator.
     pub fn with_incin(incin: SharedIncin<T>) -> Self {
         let node = Node::new(Removable::empty());
@@ -64,6 +76,40 @@ impl<T> Queue<T> {
         }
     }
 
+    /// Pushes the elements of the given iterable into the back of the queue as
+    /// a single batch. The elements are contiguous in the queue and keep the
+    /// order of the iterable: nothing pushed concurrently, either alone or in
+    /// another batch, lands among them. This operation is also wait-free, and
+    /// does nothing for an empty iterable.
+    pub fn push_many<I>(&self, iterable: I)
+    where
+        I: IntoIterator<Item = T>,
+    {
+        let mut iter = iterable.into_iter();
+        let first = match iter.next() {
+            Some(item) => OwnedAlloc::new(Node::new(Removable::new(item))).into_raw(),
+            None => return,
+        };
+
+        // We link the nodes before publishing any of them. If the iterator
+        // panics, the nodes linked so far are leaked.
+        let mut last = first;
+        for item in iter {
+            let node = OwnedAlloc::new(Node::new(Removable::new(item))).into_raw();
+            // Safe because the node was allocated above and is not shared
+            // yet.
+            unsafe { last.as_ref().next.store(node.as_ptr(), Relaxed) };
+            last = node;
+        }
+
+        // Just like a single push, but the whole chain goes in between the
+        // previous back and the new one.
+        let prev_back = self.back.swap(last.as_ptr(), AcqRel);
+        unsafe {
+            (*prev_back).next.store(first.as_ptr(), Release);
+        }
+    }
+
     /// Takes a value from the front of the queue, if it is avaible.
     pub fn pop(&self) -> Option<T> {
         // Pausing because of ABA problem involving remotion from linked lists.
@@ -100,7 +146,9 @@ impl<T> Queue<T> {
     }
 
     /// Pushes elements from the given iterable. Acts just like
-    /// [`Extend::extend`] but does not require mutability.
+    /// [`Extend::extend`] but does not require mutability. The elements are
+    /// pushed one by one, so concurrent pushes may land among them; see
+    /// [`push_many`](Queue::push_many) to keep them together.
     pub fn extend<I>(&self, iterable: I)
     where
         I: IntoIterator<Item = T>,
@@ -235,6 +283,69 @@ impl<T> fmt::Debug for Queue<T> {
     }
 }
 
+impl<T> MemoryFootprint for Queue<T> {
+    /// Counts the nodes by walking the whole queue, so it takes time linear in
+    /// its length.
+    fn bytes_allocated(&self) -> usize {
+        // Pausing so no node is freed while we walk the queue.
+        let _pause = self.incin.get_unchecked().pause();
+        let mut count = 0;
+        let mut node = self.front.load(Acquire);
+
+        while let Some(nnptr) = NonNull::new(node) {
+            count += 1;
+            // Safe because we only delete nodes via incinerator and we have a
+            // pause now.
+            node = unsafe { nnptr.as_ref().next.load(Acquire) };
+        }
+
+        count * mem::size_of::<Node<T>>()
+    }
+
+    fn garbage_bytes(&self) -> usize {
+        self.incin.get_unchecked().garbage_len() * mem::size_of::<Node<T>>()
+    }
+}
+
+impl<T> ConcurrentInsert<T> for Queue<T> {
+    fn try_insert(&self, elem: T) -> Result<(), T> {
+        self.push(elem);
+        Ok(())
+    }
+}
+
+impl<T> ConcurrentPop<T> for Queue<T> {
+    fn pop(&self) -> Option<T> {
+        Queue::pop(self)
+    }
+}
+
+impl<T> ApproxLen for Queue<T> {
+    /// Counts the elements by walking the whole queue, so it takes time linear
+    /// in its length. No counter is kept, which would make pushing and popping
+    /// contend on it.
+    fn len(&self) -> usize {
+        // Pausing so no node is freed while we walk the queue.
+        let _pause = self.incin.get_unchecked().pause();
+        let mut count = 0;
+        let mut node = self.front.load(Acquire);
+
+        while let Some(nnptr) = NonNull::new(node) {
+            // Safe because we only delete nodes via incinerator and we have a
+            // pause now.
+            let node_ref = unsafe { nnptr.as_ref() };
+            // Nodes whose item was taken are still in the queue until they are
+            // cleared.
+            if node_ref.item.is_present(Relaxed) {
+                count += 1;
+            }
+            node = node_ref.next.load(Acquire);
+        }
+
+        count
+    }
+}
+
 unsafe impl<T> Send for Queue<T> where T: Send {}
 
 unsafe impl<T> Sync for Queue<T> where T: Send {}
@@ -292,10 +403,32 @@ impl<T> Node<T> {
 #[cfg(test)]
 mod test {
     use super::*;
-    use alloc::sync::Arc;
-    use alloc::vec::Vec;
+    use crate::footprint::MemoryFootprint;
+    #[cfg(feature = "std")]
+    use alloc::{sync::Arc, vec::Vec};
+    #[cfg(feature = "std")]
     use core::sync::atomic::AtomicUsize;
 
+    #[test]
+    fn footprint_follows_elements() {
+        let queue = Queue::new();
+        let mut last = queue.bytes_allocated();
+        for i in 0..100 {
+            queue.push(i);
+            let now = queue.bytes_allocated();
+            assert!(now > last);
+            last = now;
+        }
+
+        let incin = queue.incin.get_unchecked().clone();
+        let pause = incin.pause();
+        while queue.pop().is_some() {}
+        assert!(queue.bytes_allocated() < last);
+        assert!(queue.garbage_bytes() > 0);
+        drop(pause);
+        assert_eq!(queue.garbage_bytes(), 0);
+    }
+
     #[test]
     fn on_empty_first_pop_is_none() {
         let queue = Queue::<usize>::new();
@@ -323,6 +456,44 @@ mod test {
         assert_eq!(queue.pop(), Some(6));
     }
 
+    #[test]
+    fn push_many_keeps_order() {
+        let queue = Queue::new();
+        queue.push(1);
+        queue.push_many(2..5);
+        queue.push_many(None);
+        queue.push(5);
+        assert!(queue.pop_iter().eq(1..6));
+        queue.push_many(Some(6));
+        assert_eq!(queue.pop(), Some(6));
+        assert_eq!(queue.pop(), None);
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn push_many_batches_are_contiguous() {
+        use crate::test_util::{produce_batches, with_writers, BatchChecker, Produced};
+
+        const NPRODUCER: usize = 4;
+
+        let queue = Arc::new(Queue::new());
+        let mut checker = BatchChecker::new();
+        with_writers(
+            &queue,
+            NPRODUCER,
+            |queue, id| {
+                for produced in produce_batches(id) {
+                    match produced {
+                        Produced::Single(item) => queue.push(item),
+                        Produced::Batch(batch) => queue.push_many(batch),
+                    }
+                }
+            },
+            |queue| queue.pop_iter().for_each(|item| checker.consume(item)),
+        );
+        checker.finish(NPRODUCER);
+    }
+
     #[test]
     fn queue_iter() {
         let mut queue = Queue::new();
@@ -335,8 +506,62 @@ mod test {
         assert_eq!(queue.next(), None);
     }
 
+    #[test]
     #[cfg(feature = "std")]
+    fn removed_entries_hand_off_while_read() {
+        use crate::map::{Map, Removed};
+        use std::{sync::Barrier, thread};
+
+        let map = Arc::new(Map::new());
+        for i in 0..10u64 {
+            map.insert(i, i * 10);
+        }
+        let queue = Arc::new(Queue::new());
+        let barrier = Arc::new(Barrier::new(2));
+
+        // A reader holds a guard on one of the entries during the handoff.
+        let guard = map.get(&3).unwrap();
+
+        let consumer = {
+            let queue = queue.clone();
+            let barrier = barrier.clone();
+            thread::spawn(move || {
+                let mut popped = Vec::new();
+                while popped.len() < 10 {
+                    if let Some(removed) = queue.pop() {
+                        let removed: Removed<u64, u64> = removed;
+                        assert_eq!(*removed.val(), *removed.key() * 10);
+                        popped.push(removed);
+                    }
+                }
+
+                // The pairs cannot be moved out while the guard is alive.
+                let popped = popped
+                    .into_iter()
+                    .map(|removed| Removed::try_into(removed).unwrap_err())
+                    .collect::<Vec<_>>();
+                barrier.wait();
+                barrier.wait();
+                popped
+                    .into_iter()
+                    .map(|removed| Removed::try_into(removed).ok().unwrap())
+                    .collect::<Vec<_>>()
+            })
+        };
+
+        queue.extend((0..10).filter_map(|i| map.remove(&i)));
+        barrier.wait();
+        assert_eq!(*guard.val(), 30);
+        drop(guard);
+        barrier.wait();
+
+        let pairs = consumer.join().unwrap();
+        assert_eq!(pairs, (0..10).map(|i| (i, i * 10)).collect::<Vec<_>>());
+        assert!(map.get(&3).is_none());
+    }
+
     #[test]
+    #[cfg(feature = "std")]
     fn no_data_corruption() {
         use std::thread;
         const NTHREAD: usize = 20;
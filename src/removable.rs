@@ -1,77 +1,83 @@
-use core::mem::MaybeUninit;
 use core::{
+    cell::UnsafeCell,
     fmt,
-    mem::ManuallyDrop,
+    mem::{self, MaybeUninit},
     sync::atomic::{
-        AtomicBool,
+        AtomicU8,
         Ordering::{self, *},
     },
 };
 
-/// A shared removable value. You can only take values from this type (no
-/// insertion allowed). No extra allocation is necessary. It may be useful for
-/// things like shared `thread::JoinHandle`s.
+// No value is stored.
+const EMPTY: u8 = 0;
+// A value is stored and nobody is accessing it.
+const PRESENT: u8 = 1;
+// Some thread is accessing the slot exclusively.
+const BUSY: u8 = 2;
+
+/// A shared removable value. No extra allocation is necessary. It may be
+/// useful for things like shared `thread::JoinHandle`s or one-shot slots
+/// between threads.
+///
+/// [`Removable::take`] never waits. While [`Removable::take_if`] or
+/// [`Removable::swap`] accesses the value, the slot is reserved: takers find no
+/// value instead of waiting, and only [`Removable::swap`] waits for the
+/// reservation to end.
 pub struct Removable<T> {
-    item: ManuallyDrop<MaybeUninit<T>>,
-    present: AtomicBool,
+    item: UnsafeCell<MaybeUninit<T>>,
+    state: AtomicU8,
 }
 
 impl<T> Removable<T> {
     /// Creates a removable item with the passed argument as a present value.
     pub fn new(val: T) -> Self {
         Self {
-            item: ManuallyDrop::new(MaybeUninit::new(val)),
-            present: AtomicBool::new(true),
+            item: UnsafeCell::new(MaybeUninit::new(val)),
+            state: AtomicU8::new(PRESENT),
         }
     }
 
     /// Creates a removable item with no present value.
     pub fn empty() -> Self {
         Self {
-            // This is safe because we will only read from the item if present
-            // is true. Present will only be true if we write to it.
-            item: ManuallyDrop::new(MaybeUninit::uninit()),
-            present: AtomicBool::new(false),
+            // This is safe because we will only read from the item if the
+            // state is present. It will only be present if we write to it.
+            item: UnsafeCell::new(MaybeUninit::uninit()),
+            state: AtomicU8::new(EMPTY),
         }
     }
 
     /// Replaces the stored value with a given one and returns the old value.
     /// Requires a mutable reference since the type of the value might not be
-    /// atomic.
+    /// atomic. See [`Removable::swap`] for a shared version.
     pub fn replace(&mut self, val: Option<T>) -> Option<T> {
-        let present = self.present.get_mut();
+        let item = self.item.get_mut();
+        let state = self.state.get_mut();
+        // Safe because the state will only be present if item is initialized,
+        // and the exclusive reference means nobody else is accessing it.
+        let old = if *state == PRESENT {
+            Some(unsafe { item.as_ptr().read() })
+        } else {
+            None
+        };
 
         match val {
             Some(val) => {
-                if *present {
-                    let t = unsafe { self.item.assume_init_read() };
-                    self.item.write(val);
-                    Some(t)
-                } else {
-                    // Safe because we get the pointer from a valid reference
-                    // and present will only be false if item is uninitialized.
-                    *present = true;
-                    unsafe { (self.item.assume_init_mut() as *mut T).write(val) };
-                    None
-                }
+                item.write(val);
+                *state = PRESENT;
             }
-
-            None if *present => {
-                // Safe because we get the pointer from a valid reference
-                // and present will only be false if item is uninitialized.
-                *present = false;
-                Some(unsafe { (self.item.assume_init_ref() as *const T).read() })
-            }
-
-            None => None,
+            None => *state = EMPTY,
         }
+
+        old
     }
 
     /// Tries to get a mutable reference to the stored value. If the value was
     /// not present, `None` is returned.
     pub fn get_mut(&mut self) -> Option<&mut T> {
-        if *self.present.get_mut() {
-            unsafe { Some(self.item.assume_init_mut()) }
+        if *self.state.get_mut() == PRESENT {
+            // Safe because the state is only present if item is initialized.
+            unsafe { Some(self.item.get_mut().assume_init_mut()) }
         } else {
             None
         }
@@ -79,24 +85,113 @@ impl<T> Removable<T> {
 
     /// Tests if the stored value is present. Note that there are no guarantees
     /// that `take` will be successful if this method returns `true` because
-    /// some other thread could take the value meanwhile.
+    /// some other thread could take the value meanwhile. A value reserved by
+    /// another thread is not present.
     pub fn is_present(&self, ordering: Ordering) -> bool {
-        self.present.load(ordering)
+        self.state.load(ordering) == PRESENT
     }
 
-    /// Tries to take the value. If no value was present in first place, `None`
-    /// is returned. In terms of memory ordering, `AcqRel` should be enough.
+    /// Tries to take the value. If no value was present in first place, or it
+    /// is reserved by another thread, `None` is returned. In terms of memory
+    /// ordering, `AcqRel` should be enough.
     pub fn take(&self, ordering: Ordering) -> Option<T> {
-        if self.present.swap(false, ordering) {
-            // Safe because if present was true, the memory was initialized. All
-            // other reads won't happen because we set present to false.
-            Some(unsafe { (self.item.assume_init_ref() as *const T).read() })
+        self.state
+            .compare_exchange(PRESENT, EMPTY, acquiring(ordering), Relaxed)
+            .ok()?;
+        // Safe because the state was present, so the memory is initialized,
+        // and we set it to empty, so nobody else will read it.
+        Some(unsafe { (*self.item.get()).as_ptr().read() })
+    }
+
+    /// Tries to take the value, but only if the given predicate returns `true`
+    /// for it. If no value was present, it is reserved by another thread, or
+    /// the predicate returned `false`, `None` is returned. While the predicate
+    /// runs, the slot is reserved for this thread. If the predicate panics,
+    /// the value stays in place.
+    pub fn take_if<F>(&self, ordering: Ordering, pred: F) -> Option<T>
+    where
+        F: FnOnce(&T) -> bool,
+    {
+        self.state
+            .compare_exchange(PRESENT, BUSY, acquiring(ordering), Relaxed)
+            .ok()?;
+
+        // Puts the value back if the predicate rejects it or panics.
+        let restore = Restore(&self.state);
+        // Safe because the state was present, so the memory is initialized,
+        // and we set it to busy, so nobody else is accessing it.
+        let item = unsafe { &mut *self.item.get() };
+        if pred(unsafe { item.assume_init_ref() }) {
+            mem::forget(restore);
+            let val = unsafe { item.as_ptr().read() };
+            self.state.store(EMPTY, Release);
+            Some(val)
+        } else {
+            None
+        }
+    }
+
+    /// Atomically stores the given value, returning the old value if there
+    /// was one. This is the shared version of [`Removable::replace`]. If
+    /// another thread reserved the slot, this waits for it to finish.
+    pub fn swap(&self, val: T) -> Option<T> {
+        let state = self.reserve();
+        // Safe because we set the state to busy, so nobody else is accessing
+        // it. The old value is only read if the state was present, which means
+        // it was initialized.
+        let item = unsafe { &mut *self.item.get() };
+        let old = if state == PRESENT {
+            Some(unsafe { item.as_ptr().read() })
         } else {
             None
+        };
+        item.write(val);
+        self.state.store(PRESENT, Release);
+        old
+    }
+
+    // Sets the state to busy, waiting for any other thread reserving the
+    // slot, and returns the state it replaced: either present or empty.
+    fn reserve(&self) -> u8 {
+        let mut state = self.state.load(Acquire);
+        loop {
+            if state == BUSY {
+                core::hint::spin_loop();
+                state = self.state.load(Acquire);
+                continue;
+            }
+
+            match self
+                .state
+                .compare_exchange_weak(state, BUSY, Acquire, Acquire)
+            {
+                Ok(_) => break state,
+                Err(new) => state = new,
+            }
         }
     }
 }
 
+// Stores present in the state when dropped, which releases the reservation of
+// a value which is kept.
+struct Restore<'state>(&'state AtomicU8);
+
+impl<'state> Drop for Restore<'state> {
+    fn drop(&mut self) {
+        self.0.store(PRESENT, Release);
+    }
+}
+
+// Strengthens the given ordering so reading the item after a successful
+// reservation is never a data race with the thread that wrote it.
+fn acquiring(ordering: Ordering) -> Ordering {
+    match ordering {
+        Relaxed => Acquire,
+        Release => AcqRel,
+        other => other,
+    }
+}
+
 impl<T> fmt::Debug for Removable<T> {
     fn fmt(&self, fmtr: &mut fmt::Formatter) -> fmt::Result {
         write!(
@@ -115,10 +210,10 @@ impl<T> Default for Removable<T> {
 
 impl<T> Drop for Removable<T> {
     fn drop(&mut self) {
-        if *self.present.get_mut() {
-            // Safe because present will only be true when the memory is
+        if *self.state.get_mut() == PRESENT {
+            // Safe because the state will only be present when the memory is
             // initialized. And now we are at drop.
-            unsafe { ManuallyDrop::drop(&mut self.item) }
+            unsafe { self.item.get_mut().assume_init_drop() }
         }
     }
 }
@@ -135,3 +230,121 @@ impl<T> From<Option<T>> for Removable<T> {
 unsafe impl<T> Send for Removable<T> where T: Send {}
 
 unsafe impl<T> Sync for Removable<T> where T: Send {}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use alloc::sync::Arc;
+    #[cfg(feature = "std")]
+    use core::sync::atomic::AtomicBool;
+    #[cfg(feature = "std")]
+    use std::{
+        panic::{catch_unwind, AssertUnwindSafe},
+        sync::Barrier,
+        thread,
+    };
+
+    #[test]
+    fn swap_and_replace() {
+        let mut removable = Removable::empty();
+        assert_eq!(removable.swap(1), None);
+        assert_eq!(removable.swap(2), Some(1));
+        assert_eq!(removable.get_mut(), Some(&mut 2));
+        assert_eq!(removable.replace(None), Some(2));
+        assert!(!removable.is_present(Relaxed));
+        assert_eq!(removable.replace(Some(3)), None);
+        assert_eq!(removable.take(AcqRel), Some(3));
+    }
+
+    #[test]
+    fn take_if_rejects() {
+        let removable = Removable::new(5);
+        assert_eq!(removable.take_if(AcqRel, |&val| val > 5), None);
+        assert!(removable.is_present(Relaxed));
+        assert_eq!(removable.take_if(AcqRel, |&val| val == 5), Some(5));
+        assert_eq!(removable.take_if(AcqRel, |_| true), None);
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn take_if_survives_panicking_predicate() {
+        let removable = Removable::new(5);
+        let res = catch_unwind(AssertUnwindSafe(|| {
+            removable.take_if(AcqRel, |_| panic!("predicate failed"))
+        }));
+        assert!(res.is_err());
+        assert!(removable.is_present(Relaxed));
+        assert_eq!(removable.swap(6), Some(5));
+        assert_eq!(removable.take(AcqRel), Some(6));
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn take_gives_up_on_reserved_value() {
+        let removable = Arc::new(Removable::new(3));
+        let testing = Arc::new(AtomicBool::new(false));
+        let checked = Arc::new(AtomicBool::new(false));
+        let thread = {
+            let removable = removable.clone();
+            let testing = testing.clone();
+            let checked = checked.clone();
+            thread::spawn(move || {
+                removable.take_if(AcqRel, |_| {
+                    testing.store(true, Release);
+                    while !checked.load(Acquire) {
+                        thread::yield_now();
+                    }
+                    false
+                })
+            })
+        };
+
+        while !testing.load(Acquire) {
+            thread::yield_now();
+        }
+        // The predicate is still running, and taking must not wait for it.
+        assert!(!removable.is_present(Relaxed));
+        assert_eq!(removable.take(AcqRel), None);
+        assert_eq!(removable.take_if(AcqRel, |_| true), None);
+        checked.store(true, Release);
+        assert_eq!(thread.join().unwrap(), None);
+        assert_eq!(removable.take(AcqRel), Some(3));
+    }
+
+    #[test]
+    fn drops_present_value() {
+        let val = Arc::new(());
+        let removable = Removable::new(val.clone());
+        assert_eq!(Arc::strong_count(&val), 2);
+        drop(removable);
+        assert_eq!(Arc::strong_count(&val), 1);
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn take_if_two_takers() {
+        const NITER: usize = 1000;
+
+        for _ in 0..NITER {
+            let removable = Arc::new(Removable::new(7));
+            let barrier = Arc::new(Barrier::new(2));
+            let threads = (0..2)
+                .map(|_| {
+                    let removable = removable.clone();
+                    let barrier = barrier.clone();
+                    thread::spawn(move || {
+                        barrier.wait();
+                        removable.take_if(AcqRel, |&val| val == 7)
+                    })
+                })
+                .collect::<Vec<_>>();
+
+            let taken = threads
+                .into_iter()
+                .filter_map(|thread| thread.join().unwrap())
+                .collect::<Vec<_>>();
+            assert_eq!(taken, [7]);
+            assert!(!removable.is_present(Relaxed));
+        }
+    }
+}
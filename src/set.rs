@@ -1,20 +1,36 @@
-use crate::map::{
-    Insertion as MapInsertion, IntoIter as MapIntoIter, Iter as MapIter, Map, Preview,
-    ReadGuard as MapGuard, Removed as MapRemoved, SharedIncin as MapIncin,
+pub use crate::map::MappedGuard;
+use crate::{
+    backoff::RetryDecision,
+    footprint::MemoryFootprint,
+    hash::{DefaultState, SeededState},
+    incin::Reclaimer,
+    map::{
+        Drain as MapDrain, ExactIter as MapExactIter, Insertion as MapInsertion,
+        IntoIter as MapIntoIter, Iter as MapIter, Map, Preview, ReadGuard as MapGuard,
+        Removed as MapRemoved, SharedIncin as MapIncin,
+    },
+    traits::{ApproxLen, ConcurrentInsert, ConcurrentPop},
 };
+use alloc::vec::Vec;
 use core::{
     borrow::Borrow,
     cmp::Ordering,
     fmt,
-    hash::{BuildHasher, Hash},
-    iter::FromIterator,
+    hash::{BuildHasher, Hash, Hasher},
+    iter::{Chain, FromIterator, FusedIterator},
     ops::Deref,
 };
-use std::collections::hash_map::RandomState;
 
 /// A lock-free set. This is currently implemented on top of
 /// [`Map`](::map::Map). To check more details about it, please see `Map` docs.
-pub struct Set<T, H = RandomState> {
+/// The elements are the keys of entries whose values are `()`, which take no
+/// space at all, so every node is exactly as large as in a dedicated set.
+///
+/// Just like with the [`Map`](::map::Map), if a closure given to
+/// [`Set::insert_with`], [`Set::remove_with`] or [`Set::reinsert_with`]
+/// panics, the element it was called for is left exactly as it was, and the
+/// [`Set`] remains valid.
+pub struct Set<T, H = DefaultState> {
     inner: Map<T, (), H>,
 }
 
@@ -30,13 +46,79 @@ impl<T> Set<T> {
             inner: Map::with_incin(incin.inner),
         }
     }
+
+    /// Creates a [`Set`] whose removed elements and internal garbage are
+    /// handed to the given reclaimer. See [`Map::with_reclaimer`].
+    pub fn with_reclaimer<R>(reclaimer: R) -> Self
+    where
+        T: Send + 'static,
+        R: Reclaimer,
+    {
+        Self::with_incin(SharedIncin::with_reclaimer(reclaimer))
+    }
+}
+
+impl<T> Set<T, SeededState> {
+    /// Creates a [`Set`] whose hasher builder is a [`SeededState`] with the
+    /// given seed. See [`Map::with_seed`] for more details.
+    pub fn with_seed(seed: [u8; 16]) -> Self {
+        Self {
+            inner: Map::with_seed(seed),
+        }
+    }
 }
 
 impl<T, H> Set<T, H> {
+    /// Returns the number of elements in this [`Set`], without walking it. Just
+    /// like [`Map::len`], it is exact when no other thread modifies the
+    /// [`Set`], and a point-in-time estimate otherwise.
+    ///
+    /// # Example
+    /// ```rust
+    /// use tux_lockfree::set::Set;
+    ///
+    /// let set = Set::new();
+    /// assert!(set.is_empty());
+    /// set.insert("a").unwrap();
+    /// set.insert("b").unwrap();
+    /// set.insert("a").unwrap_err();
+    /// assert_eq!(set.len(), 2);
+    /// set.remove(&"b");
+    /// assert_eq!(set.len(), 1);
+    /// ```
+    pub fn len(&self) -> usize {
+        self.inner.len()
+    }
+
+    /// Returns whether this [`Set`] has no elements, with the same precision
+    /// as [`Set::len`].
+    pub fn is_empty(&self) -> bool {
+        self.inner.is_empty()
+    }
+
     /// Creates an iterator over guarded references to the elements.
     pub fn iter(&self) -> Iter<T> {
         self.into_iter()
     }
+
+    /// Creates an iterator over guarded references to the elements which knows
+    /// its exact length, unlike [`Set::iter`]. The exclusive access ensures no
+    /// element is inserted or removed while iterating.
+    pub fn iter_exact(&mut self) -> ExactIter<'_, T> {
+        ExactIter {
+            inner: self.inner.iter_exact(),
+        }
+    }
+
+    /// Clones the elements into a vector. Elements inserted or removed
+    /// concurrently may or may not be included, but every element was in the
+    /// [`Set`] at some point during the call.
+    pub fn elements_cloned(&self) -> Vec<T>
+    where
+        T: Clone,
+    {
+        self.inner.keys_cloned()
+    }
 }
 
 impl<T, H> Set<T, H>
@@ -76,6 +158,48 @@ where
         self.inner.optimize_space();
     }
 
+    /// Removes the elements for which the given predicate returns false,
+    /// visiting every element exactly once. Since this method cannot be
+    /// performed in a shared context, the removed elements are freed right
+    /// away, just like with [`Map::retain`].
+    ///
+    /// # Example
+    /// ```rust
+    /// use tux_lockfree::set::Set;
+    ///
+    /// let mut set = (0..10).collect::<Set<_>>();
+    /// set.retain(|elem| elem % 3 == 0);
+    /// assert_eq!(set.len(), 4);
+    /// assert!(!set.contains(&4));
+    /// ```
+    pub fn retain<F>(&mut self, mut pred: F)
+    where
+        F: FnMut(&T) -> bool,
+    {
+        self.inner.retain(|elem, _| pred(elem));
+    }
+
+    /// Removes all elements and returns an iterator over them as owned values.
+    /// Just like with [`Map::drain`], the [`Set`] is left empty even if the
+    /// iterator is dropped before being exhausted, in which case the remaining
+    /// elements are dropped.
+    ///
+    /// # Example
+    /// ```rust
+    /// use tux_lockfree::set::Set;
+    ///
+    /// let mut set = (0..3).collect::<Set<_>>();
+    /// let mut batch = set.drain().collect::<Vec<_>>();
+    /// batch.sort();
+    /// assert_eq!(batch, [0, 1, 2]);
+    /// assert!(set.is_empty());
+    /// ```
+    pub fn drain(&mut self) -> Drain<'_, T> {
+        Drain {
+            inner: self.inner.drain(),
+        }
+    }
+
     /// Removes all elements. This method cannot be performed in a shared
     /// context.
     pub fn clear(&mut self) {
@@ -84,11 +208,11 @@ where
 
     /// Tests if the given element is present on the [`Set`]. The method accepts
     /// a type resulted from borrowing the stored element. This method will
-    /// only work correctly if [`Hash`] and [`Ord`] are implemented in the same
+    /// only work correctly if [`Hash`] and [`Eq`] are implemented in the same
     /// way for the borrowed type and the stored type.
     pub fn contains<U>(&self, elem: &U) -> bool
     where
-        U: Hash + Ord,
+        U: Hash + Eq,
         T: Borrow<U>,
     {
         self.inner.get(elem).is_some()
@@ -97,12 +221,12 @@ where
     /// Returns a guarded reference to the given element in the [`Set`]. This
     /// may be useful for types with additional metadata. The method accepts
     /// a type resulted from borrowing the stored element. This method will
-    /// only work correctly if [`Hash`] and [`Ord`] are implemented in the same
+    /// only work correctly if [`Hash`] and [`Eq`] are implemented in the same
     /// way for the borrowed type and the stored type. If the element is not
     /// found, [`None`] is obviously returned.
     pub fn get<'set, U>(&'set self, elem: &U) -> Option<ReadGuard<'set, T>>
     where
-        U: Hash + Ord,
+        U: Hash + Eq,
         T: Borrow<U>,
     {
         self.inner.get(elem).map(ReadGuard::new)
@@ -112,7 +236,7 @@ where
     /// present, [`Err`]`(the_passed_value)` is returned.
     pub fn insert(&self, elem: T) -> Result<(), T>
     where
-        T: Hash + Ord,
+        T: Hash + Eq,
     {
         let result = self.inner.insert_with(elem, |_, _, stored| {
             if stored.is_some() {
@@ -128,6 +252,100 @@ where
         }
     }
 
+    /// Inserts the element unconditionally, just like `HashSet::replace`. If
+    /// an equal element was stored, it is replaced atomically and returned.
+    /// Unlike [`Set::insert`], this refreshes stored elements whose equality
+    /// ignores some of their data.
+    ///
+    /// # Example
+    /// ```rust
+    /// use std::hash::{Hash, Hasher};
+    /// use tux_lockfree::set::Set;
+    ///
+    /// #[derive(Debug)]
+    /// struct Session {
+    ///     id: u64,
+    ///     expiry: u64,
+    /// }
+    ///
+    /// impl PartialEq for Session {
+    ///     fn eq(&self, other: &Self) -> bool {
+    ///         self.id == other.id
+    ///     }
+    /// }
+    ///
+    /// impl Eq for Session {}
+    ///
+    /// impl Hash for Session {
+    ///     fn hash<H: Hasher>(&self, hasher: &mut H) {
+    ///         self.id.hash(hasher)
+    ///     }
+    /// }
+    ///
+    /// let sessions = Set::new();
+    /// assert!(sessions.replace(Session { id: 1, expiry: 10 }).is_none());
+    /// let old = sessions.replace(Session { id: 1, expiry: 20 }).unwrap();
+    /// assert_eq!(old.expiry, 10);
+    /// let key = Session { id: 1, expiry: 0 };
+    /// assert_eq!(sessions.get(&key).unwrap().expiry, 20);
+    /// ```
+    pub fn replace(&self, elem: T) -> Option<Removed<T>>
+    where
+        T: Hash + Eq,
+    {
+        self.inner.insert(elem, ()).map(Removed::new)
+    }
+
+    /// Inserts the element if it is absent, and returns a guard to whichever
+    /// element ended up stored, just like [`Map::get_or_insert`]. If an equal
+    /// element was already stored, the given one is dropped.
+    ///
+    /// # Example
+    /// ```rust
+    /// use tux_lockfree::set::Set;
+    ///
+    /// let names = Set::new();
+    /// let first = names.get_or_insert("alice".to_owned());
+    /// let second = names.get_or_insert("alice".to_owned());
+    /// assert!(std::ptr::eq(&*first, &*second));
+    /// assert_eq!(names.len(), 1);
+    /// ```
+    pub fn get_or_insert<'set>(&'set self, elem: T) -> ReadGuard<'set, T>
+    where
+        T: Hash + Eq,
+    {
+        ReadGuard::new(self.inner.get_or_insert(elem, ()))
+    }
+
+    /// Searches for the given borrowed element, and if it is not found,
+    /// inserts the element built by the given closure, just like
+    /// [`Set::get_or_insert`]. The closure is only called if the element was
+    /// found absent, so looking up an element already stored allocates
+    /// nothing. The built element must be equal to the borrowed one. If
+    /// another thread inserts it in the meantime, the built element is
+    /// dropped and the stored one is returned.
+    ///
+    /// # Example
+    /// ```rust
+    /// use tux_lockfree::set::Set;
+    ///
+    /// let interned = Set::<String>::new();
+    /// let first = interned.get_or_insert_with("alice", || "alice".to_owned());
+    /// let second = interned.get_or_insert_with("alice", || unreachable!());
+    /// assert!(std::ptr::eq(&*first, &*second));
+    /// ```
+    pub fn get_or_insert_with<'set, U, F>(&'set self, elem: &U, make: F) -> ReadGuard<'set, T>
+    where
+        U: ?Sized + Hash + Eq,
+        T: Borrow<U> + Hash + Eq,
+        F: FnOnce() -> T,
+    {
+        match self.inner.get(elem) {
+            Some(guard) => ReadGuard::new(guard),
+            None => self.get_or_insert(make()),
+        }
+    }
+
     /// Inserts _interactively_ the element into the [`Set`]. A passed closure
     /// tests if the insertion should proceed. The first argument of the
     /// closure is the element passed to `insert_with` and the second is the
@@ -136,7 +354,7 @@ where
     pub fn insert_with<F>(&self, elem: T, mut interactive: F) -> Insertion<T, T>
     where
         F: FnMut(&T, Option<&T>) -> bool,
-        T: Hash + Ord,
+        T: Hash + Eq,
     {
         let result = self.inner.insert_with(elem, |elem, _, stored| {
             if interactive(elem, stored.map(|(elem, _)| elem)) {
@@ -164,7 +382,7 @@ where
     /// fail. Otherwise, insertion cannot fail.
     pub fn reinsert(&self, elem: Removed<T>) -> Result<(), Removed<T>>
     where
-        T: Hash + Ord,
+        T: Hash + Eq,
     {
         let result = self
             .inner
@@ -192,7 +410,7 @@ where
     pub fn reinsert_with<F>(&self, elem: Removed<T>, mut interactive: F) -> Insertion<T, Removed<T>>
     where
         F: FnMut(&T, Option<&T>) -> bool,
-        T: Hash + Ord,
+        T: Hash + Eq,
     {
         let result = self.inner.reinsert_with(elem.inner, |(elem, _), stored| {
             interactive(elem, stored.map(|(elem, _)| elem))
@@ -207,11 +425,11 @@ where
 
     /// Removes the given element inconditionally. The method accepts a
     /// type resulted from borrowing the stored element. This method will only
-    /// work correctly if [`Hash`] and [`Ord`] are implemented in the same way
+    /// work correctly if [`Hash`] and [`Eq`] are implemented in the same way
     /// for the borrowed type and the stored type.
     pub fn remove<U>(&self, elem: &U) -> Option<Removed<T>>
     where
-        U: Hash + Ord,
+        U: Hash + Eq,
         T: Borrow<U>,
     {
         self.inner.remove(elem).map(Removed::new)
@@ -222,11 +440,11 @@ where
     /// reference to the found stored element. The return value is whether the
     /// removal should happen or not. The method accepts a type resulted from
     /// borrowing the stored element. This method will only work correctly
-    /// if [`Hash`] and [`Ord`] are implemented in the same way for the borrowed
+    /// if [`Hash`] and [`Eq`] are implemented in the same way for the borrowed
     /// type and the stored type.
     pub fn remove_with<U, F>(&self, elem: &U, mut interactive: F) -> Option<Removed<T>>
     where
-        U: Hash + Ord,
+        U: Hash + Eq,
         T: Borrow<U>,
         F: FnMut(&T) -> bool,
     {
@@ -235,12 +453,220 @@ where
             .map(Removed::new)
     }
 
+    /// Creates an iterator over guarded references to the elements of either
+    /// this [`Set`] or the other one, just like `HashSet::union`. The elements
+    /// of this [`Set`] are yielded first, and then the ones of the other which
+    /// are not in this [`Set`], so an element of both comes from this one.
+    /// Each element is yielded once unless the sets are modified concurrently.
+    ///
+    /// # Example
+    /// ```rust
+    /// use tux_lockfree::set::Set;
+    ///
+    /// let first = (0..4).collect::<Set<_>>();
+    /// let second = (2..6).collect::<Set<_>>();
+    /// let mut union = first.union(&second).map(|elem| *elem).collect::<Vec<_>>();
+    /// union.sort();
+    /// assert_eq!(union, [0, 1, 2, 3, 4, 5]);
+    /// ```
+    pub fn union<'set, H2>(&'set self, other: &'set Set<T, H2>) -> Union<'set, T, H>
+    where
+        T: Hash + Eq,
+    {
+        Union {
+            first: self.iter(),
+            second: Difference {
+                iter: other.iter(),
+                other: self,
+            },
+        }
+    }
+
+    /// Creates an iterator over guarded references to the elements in both
+    /// this [`Set`] and the other one, just like `HashSet::intersection`. The
+    /// smaller set, according to [`Set::len`], is iterated, and each of its
+    /// elements is looked up in the larger one, so the guards refer to the
+    /// elements of the smaller set. Elements inserted or removed concurrently
+    /// may or may not be yielded, but every element yielded was found in both
+    /// sets during the call.
+    ///
+    /// # Example
+    /// ```rust
+    /// use tux_lockfree::set::Set;
+    ///
+    /// let first = (0..4).collect::<Set<_>>();
+    /// let second = (2..6).collect::<Set<_>>();
+    /// let mut both = first.intersection(&second).map(|elem| *elem).collect::<Vec<_>>();
+    /// both.sort();
+    /// assert_eq!(both, [2, 3]);
+    /// ```
+    pub fn intersection<'set, H2>(
+        &'set self,
+        other: &'set Set<T, H2>,
+    ) -> Intersection<'set, T, H, H2>
+    where
+        H2: BuildHasher,
+        T: Hash + Eq,
+    {
+        let from_other = other.len() < self.len();
+        Intersection {
+            this: self,
+            other,
+            from_other,
+            iter: if from_other {
+                other.iter()
+            } else {
+                self.iter()
+            },
+        }
+    }
+
+    /// Creates an iterator over guarded references to the elements of this
+    /// [`Set`] which are not in the other one, just like
+    /// `HashSet::difference`. Elements inserted or removed concurrently may or
+    /// may not be yielded.
+    ///
+    /// # Example
+    /// ```rust
+    /// use tux_lockfree::set::Set;
+    ///
+    /// let seen = (0..4).collect::<Set<_>>();
+    /// let current = (2..6).collect::<Set<_>>();
+    /// let mut new = current.difference(&seen).map(|elem| *elem).collect::<Vec<_>>();
+    /// new.sort();
+    /// assert_eq!(new, [4, 5]);
+    /// ```
+    pub fn difference<'set, H2>(&'set self, other: &'set Set<T, H2>) -> Difference<'set, T, H2>
+    where
+        H2: BuildHasher,
+        T: Hash + Eq,
+    {
+        Difference {
+            iter: self.iter(),
+            other,
+        }
+    }
+
+    /// Creates an iterator over guarded references to the elements in exactly
+    /// one of this [`Set`] and the other one, just like
+    /// `HashSet::symmetric_difference`. It yields the
+    /// [difference](Set::difference) of this [`Set`] and the other, and then
+    /// the difference of the other and this one.
+    ///
+    /// # Example
+    /// ```rust
+    /// use tux_lockfree::set::Set;
+    ///
+    /// let first = (0..4).collect::<Set<_>>();
+    /// let second = (2..6).collect::<Set<_>>();
+    /// let mut either = first
+    ///     .symmetric_difference(&second)
+    ///     .map(|elem| *elem)
+    ///     .collect::<Vec<_>>();
+    /// either.sort();
+    /// assert_eq!(either, [0, 1, 4, 5]);
+    /// ```
+    pub fn symmetric_difference<'set, H2>(
+        &'set self,
+        other: &'set Set<T, H2>,
+    ) -> SymmetricDifference<'set, T, H, H2>
+    where
+        H2: BuildHasher,
+        T: Hash + Eq,
+    {
+        SymmetricDifference {
+            inner: self.difference(other).chain(other.difference(self)),
+        }
+    }
+
+    /// Tests whether every element of this [`Set`] is in the other one, just
+    /// like `HashSet::is_subset`. It returns `false` right away if this [`Set`]
+    /// is longer, according to [`Set::len`], and otherwise stops at the first
+    /// element missing from the other. The answer is exact when neither set is
+    /// modified concurrently.
+    ///
+    /// # Example
+    /// ```rust
+    /// use tux_lockfree::set::Set;
+    ///
+    /// let pending = (2..4).collect::<Set<_>>();
+    /// let all = (0..6).collect::<Set<_>>();
+    /// assert!(pending.is_subset(&all));
+    /// assert!(!all.is_subset(&pending));
+    /// pending.insert(9).unwrap();
+    /// assert!(!pending.is_subset(&all));
+    /// ```
+    pub fn is_subset<H2>(&self, other: &Set<T, H2>) -> bool
+    where
+        H2: BuildHasher,
+        T: Hash + Eq,
+    {
+        self.len() <= other.len() && self.iter().all(|elem| other.contains(&*elem))
+    }
+
+    /// Tests whether every element of the other [`Set`] is in this one, just
+    /// like `HashSet::is_superset`. See [`Set::is_subset`].
+    ///
+    /// # Example
+    /// ```rust
+    /// use tux_lockfree::set::Set;
+    ///
+    /// let all = (0..6).collect::<Set<_>>();
+    /// let pending = (2..4).collect::<Set<_>>();
+    /// assert!(all.is_superset(&pending));
+    /// assert!(!pending.is_superset(&all));
+    /// ```
+    pub fn is_superset<H2>(&self, other: &Set<T, H2>) -> bool
+    where
+        H2: BuildHasher,
+        T: Hash + Eq,
+    {
+        other.is_subset(self)
+    }
+
+    /// Tests whether this [`Set`] and the other one have no element in common,
+    /// just like `HashSet::is_disjoint`. Just like [`Set::intersection`], the
+    /// smaller set is iterated and its elements are looked up in the larger
+    /// one, stopping at the first common element.
+    ///
+    /// # Example
+    /// ```rust
+    /// use tux_lockfree::set::Set;
+    ///
+    /// let first = (0..4).collect::<Set<_>>();
+    /// let second = (4..8).collect::<Set<_>>();
+    /// assert!(first.is_disjoint(&second));
+    /// second.insert(3).unwrap();
+    /// assert!(!first.is_disjoint(&second));
+    /// ```
+    pub fn is_disjoint<H2>(&self, other: &Set<T, H2>) -> bool
+    where
+        H2: BuildHasher,
+        T: Hash + Eq,
+    {
+        self.intersection(other).next().is_none()
+    }
+
     /// Acts just like [`Extend::extend`] but does not require mutability.
+    /// Elements already present are left as they are. Since this method takes
+    /// precedence over the trait, references to copyable elements are inserted
+    /// through the trait instead, which is implemented by both `Set` and
+    /// `&Set`.
+    ///
+    /// # Example
+    /// ```rust
+    /// use tux_lockfree::set::Set;
+    ///
+    /// let set = Set::new();
+    /// set.extend(vec![1, 2, 2, 3]);
+    /// Extend::extend(&mut &set, &[3, 4]);
+    /// assert_eq!(set.len(), 4);
+    /// ```
     #[allow(unused_must_use)]
     pub fn extend<I>(&self, iterable: I)
     where
         I: IntoIterator<Item = T>,
-        T: Hash + Ord,
+        T: Hash + Eq,
     {
         for val in iterable {
             self.insert(val);
@@ -259,12 +685,90 @@ where
     }
 }
 
+impl<T, H> Clone for Set<T, H>
+where
+    T: Clone,
+    H: Clone,
+{
+    /// Creates an independent [`Set`] with clones of the elements and of the
+    /// hasher builder, and a new incinerator, just like the [`Clone`]
+    /// implementation of [`Map`]: the tables are rebuilt directly while the
+    /// incinerator of this [`Set`] is paused once.
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+impl<T, H, H2> PartialEq<Set<T, H2>> for Set<T, H>
+where
+    T: Hash + Eq,
+    H: BuildHasher,
+    H2: BuildHasher,
+{
+    /// Two sets are equal if they have the same length and every element of
+    /// this [`Set`] is in the other, regardless of the hasher builders and of
+    /// the order of insertion. The result is only meaningful if neither
+    /// [`Set`] is modified concurrently.
+    fn eq(&self, other: &Set<T, H2>) -> bool {
+        self.len() == other.len() && self.is_subset(other)
+    }
+}
+
+impl<T, H> Eq for Set<T, H>
+where
+    T: Hash + Eq,
+    H: BuildHasher,
+{
+}
+
 impl<T, H> fmt::Debug for Set<T, H>
 where
     H: fmt::Debug,
 {
     fn fmt(&self, fmtr: &mut fmt::Formatter) -> fmt::Result {
-        write!(fmtr, "Set {{ inner_map: {:?} }}", self.inner)
+        write!(
+            fmtr,
+            "Set {{ inner_map: {:?} }}",
+            self.inner.debug_structure()
+        )
+    }
+}
+
+impl<T, H> MemoryFootprint for Set<T, H> {
+    fn bytes_allocated(&self) -> usize {
+        self.inner.bytes_allocated()
+    }
+
+    fn garbage_bytes(&self) -> usize {
+        self.inner.garbage_bytes()
+    }
+}
+
+impl<T, H> ConcurrentInsert<T> for Set<T, H>
+where
+    H: BuildHasher,
+    T: Hash + Eq,
+{
+    fn try_insert(&self, elem: T) -> Result<(), T> {
+        self.insert(elem)
+    }
+}
+
+impl<T, H> ConcurrentPop<T> for Set<T, H>
+where
+    H: BuildHasher,
+    T: Hash + Eq + Clone,
+{
+    fn pop(&self) -> Option<T> {
+        self.inner.pop().map(|(elem, _)| elem)
+    }
+}
+
+impl<T, H> ApproxLen for Set<T, H> {
+    fn len(&self) -> usize {
+        Set::len(self)
     }
 }
 
@@ -295,7 +799,7 @@ impl<'set, T, H> IntoIterator for &'set Set<T, H> {
 impl<T, H> FromIterator<T> for Set<T, H>
 where
     H: BuildHasher + Default,
-    T: Hash + Ord,
+    T: Hash + Eq,
 {
     fn from_iter<I>(iterable: I) -> Self
     where
@@ -310,7 +814,7 @@ where
 impl<T, H> Extend<T> for Set<T, H>
 where
     H: BuildHasher,
-    T: Hash + Ord,
+    T: Hash + Eq,
 {
     fn extend<I>(&mut self, iterable: I)
     where
@@ -320,65 +824,66 @@ where
     }
 }
 
-/// An [`insert_with`](Set::insert_with) operation result.
-#[derive(Debug, PartialEq, Eq)]
-pub enum Insertion<T, E> {
-    /// The element was created.
-    Created,
-    /// The element was updated and this was the old element.
-    Updated(Removed<T>),
-    /// The insertion failed and no operation was performed. Failure of an
-    /// insertion might happen because the closure rejected the conditions.
-    /// Another reason is that method-specific contract was not respected (such
-    /// as the one of [`reinsert_with`](Set::reinsert_with)).
-    Failed(E),
-}
-
-impl<T, E> Insertion<T, E> {
-    /// Returns whether the insertion created an element.
-    pub fn created(&self) -> bool {
-        matches!(self, Insertion::Created)
-    }
-
-    /// Returns whether the insertion updated an element.
-    pub fn updated(&self) -> Option<&Removed<T>> {
-        match self {
-            Insertion::Updated(pair) => Some(pair),
-            _ => None,
-        }
-    }
-
-    /// Tries to take the updated element of this insertion and encodes it as a
-    /// [`Result`]. [`Ok`] is returned only if this insertion updated an
-    /// element.
-    pub fn take_updated(self) -> Result<Removed<T>, Self> {
-        match self {
-            Insertion::Updated(pair) => Ok(pair),
-            this => Err(this),
-        }
+impl<T, H> Extend<T> for &Set<T, H>
+where
+    H: BuildHasher,
+    T: Hash + Eq,
+{
+    fn extend<I>(&mut self, iterable: I)
+    where
+        I: IntoIterator<Item = T>,
+    {
+        (**self).extend(iterable)
     }
+}
 
-    /// Returns whether the insertion failed.
-    pub fn failed(&self) -> Option<&E> {
-        match self {
-            Insertion::Failed(err) => Some(err),
-            _ => None,
-        }
+impl<'a, T, H> Extend<&'a T> for Set<T, H>
+where
+    H: BuildHasher,
+    T: Hash + Eq + Copy,
+{
+    fn extend<I>(&mut self, iterable: I)
+    where
+        I: IntoIterator<Item = &'a T>,
+    {
+        (*self).extend(iterable.into_iter().copied())
     }
+}
 
-    /// Tries to take the failure of this insertion and encodes it as a
-    /// [`Result`]. [`Ok`] is returned only if this insertion has a failure.
-    pub fn take_failed(self) -> Result<E, Self> {
-        match self {
-            Insertion::Failed(e) => Ok(e),
-            this => Err(this),
-        }
+impl<'a, T, H> Extend<&'a T> for &Set<T, H>
+where
+    H: BuildHasher,
+    T: Hash + Eq + Copy,
+{
+    fn extend<I>(&mut self, iterable: I)
+    where
+        I: IntoIterator<Item = &'a T>,
+    {
+        (**self).extend(iterable.into_iter().copied())
     }
 }
 
+/// An [`insert_with`](Set::insert_with) operation result. The old element of
+/// an update is given back as a [`Removed`].
+pub type Insertion<T, E> = crate::insertion::Insertion<Removed<T>, E>;
+
 /// A read-operation guard. This ensures no element allocation is
 /// mutated or freed while potential reads are performed.
-#[derive(PartialEq, Eq, PartialOrd, Ord, Hash)]
+///
+/// Since the guard gives shared access to the element, it is only [`Send`] and
+/// [`Sync`] if `T` is [`Sync`]:
+///
+/// ```rust,compile_fail
+/// use std::rc::Rc;
+/// use tux_lockfree::set::Set;
+///
+/// fn assert_send<T: Send>(_: &T) {}
+///
+/// let set = Set::new();
+/// set.insert(Rc::new(0)).unwrap();
+/// let guard = set.iter().next().unwrap();
+/// assert_send(&guard);
+/// ```
 pub struct ReadGuard<'set, T>
 where
     T: 'set,
@@ -390,6 +895,51 @@ impl<'set, T> ReadGuard<'set, T> {
     fn new(inner: MapGuard<'set, T, ()>) -> Self {
         Self { inner }
     }
+
+    /// Clones the guarded element. This is the intended way of getting owned
+    /// data out of the guard, since the guard (and its pause) cannot escape
+    /// the lifetime of the [`Set`].
+    ///
+    /// # Example
+    /// ```rust
+    /// use tux_lockfree::set::Set;
+    ///
+    /// fn lookup(set: &Set<Vec<u8>>, elem: &Vec<u8>) -> Option<Vec<u8>> {
+    ///     set.get(elem).map(|guard| guard.cloned())
+    /// }
+    ///
+    /// let set = Set::new();
+    /// set.insert(vec![1, 2, 3]).unwrap();
+    /// assert_eq!(lookup(&set, &vec![1, 2, 3]), Some(vec![1, 2, 3]));
+    /// assert_eq!(lookup(&set, &vec![4]), None);
+    /// ```
+    pub fn cloned(&self) -> T
+    where
+        T: Clone,
+    {
+        (**self).clone()
+    }
+
+    /// Projects the guard into a part of the element. The returned guard keeps
+    /// the same protection as this one.
+    ///
+    /// # Example
+    /// ```rust
+    /// use tux_lockfree::set::{ReadGuard, Set};
+    ///
+    /// let set = Set::new();
+    /// set.insert((3, "three".to_owned())).unwrap();
+    /// let guard = set.iter().next().unwrap();
+    /// let name = ReadGuard::map(guard, |(_, name)| name.as_str());
+    /// assert_eq!(&*name, "three");
+    /// ```
+    pub fn map<U, F>(this: Self, proj: F) -> MappedGuard<'set, U>
+    where
+        U: ?Sized,
+        F: FnOnce(&T) -> &U,
+    {
+        MapGuard::map(this.inner, |(elem, _)| proj(elem))
+    }
 }
 
 impl<'set, T> Deref for ReadGuard<'set, T> {
@@ -418,6 +968,15 @@ where
     }
 }
 
+impl<'set, 'other, T> PartialEq<ReadGuard<'other, T>> for ReadGuard<'set, T>
+where
+    T: PartialEq,
+{
+    fn eq(&self, other: &ReadGuard<'other, T>) -> bool {
+        **self == **other
+    }
+}
+
 impl<'set, T> PartialEq<T> for ReadGuard<'set, T>
 where
     T: PartialEq,
@@ -427,6 +986,26 @@ where
     }
 }
 
+impl<'set, 'other, T> PartialEq<&'other T> for ReadGuard<'set, T>
+where
+    T: PartialEq,
+{
+    fn eq(&self, other: &&'other T) -> bool {
+        **self == **other
+    }
+}
+
+impl<'set, T> Eq for ReadGuard<'set, T> where T: Eq {}
+
+impl<'set, 'other, T> PartialOrd<ReadGuard<'other, T>> for ReadGuard<'set, T>
+where
+    T: PartialOrd,
+{
+    fn partial_cmp(&self, other: &ReadGuard<'other, T>) -> Option<Ordering> {
+        (**self).partial_cmp(&**other)
+    }
+}
+
 impl<'set, T> PartialOrd<T> for ReadGuard<'set, T>
 where
     T: PartialOrd,
@@ -436,6 +1015,36 @@ where
     }
 }
 
+impl<'set, 'other, T> PartialOrd<&'other T> for ReadGuard<'set, T>
+where
+    T: PartialOrd,
+{
+    fn partial_cmp(&self, other: &&'other T) -> Option<Ordering> {
+        (**self).partial_cmp(*other)
+    }
+}
+
+impl<'set, T> Ord for ReadGuard<'set, T>
+where
+    T: Ord,
+{
+    fn cmp(&self, other: &Self) -> Ordering {
+        (**self).cmp(&**other)
+    }
+}
+
+impl<'set, T> Hash for ReadGuard<'set, T>
+where
+    T: Hash,
+{
+    fn hash<H>(&self, hasher: &mut H)
+    where
+        H: Hasher,
+    {
+        (**self).hash(hasher)
+    }
+}
+
 impl<'set, T> Borrow<T> for ReadGuard<'set, T> {
     fn borrow(&self) -> &T {
         self.deref()
@@ -448,9 +1057,36 @@ impl<'set, T> AsRef<T> for ReadGuard<'set, T> {
     }
 }
 
+#[cfg(feature = "serde")]
+impl<'set, T> serde::Serialize for ReadGuard<'set, T>
+where
+    T: serde::Serialize,
+{
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        (**self).serialize(serializer)
+    }
+}
+
 /// A removed element. It can be reinserted at the same [`Set`] it was removed.
 /// It can also be inserted on another [`Set`], but only if either the [`Set`]
 /// is dropped or there are no sensitive reads running on that [`Set`].
+///
+/// It is [`Send`] if `T` is [`Send`], and [`Sync`] if `T` is [`Sync`]:
+///
+/// ```rust,compile_fail
+/// use std::rc::Rc;
+/// use tux_lockfree::set::Set;
+///
+/// fn assert_send<T: Send>(_: &T) {}
+///
+/// let set = Set::new();
+/// set.insert(Rc::new(0)).unwrap();
+/// let removed = set.remove(&Rc::new(0)).unwrap();
+/// assert_send(&removed);
+/// ```
 #[derive(PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct Removed<T> {
     inner: MapRemoved<T, ()>,
@@ -477,6 +1113,30 @@ impl<T> Removed<T> {
             Err(inner) => Err(Self::new(inner)),
         }
     }
+
+    /// Registers a callback to be called when the memory of this removed
+    /// element is actually reclaimed. See
+    /// [`map::Removed::on_reclaim`](MapRemoved::on_reclaim) for more details.
+    pub fn on_reclaim<F>(this: &mut Self, callback: F)
+    where
+        F: FnOnce(&T) + Send + 'static,
+    {
+        MapRemoved::on_reclaim(&mut this.inner, move |(elem, _)| callback(elem));
+    }
+
+    /// Tries to convert this wrapper into the element, retrying according to
+    /// the given policy while sensitive reads are being performed. See
+    /// [`map::Removed::try_into_with`](MapRemoved::try_into_with) for more
+    /// details.
+    pub fn try_into_with<F>(this: Self, backoff: &mut F) -> Result<T, Self>
+    where
+        F: FnMut(u32) -> RetryDecision,
+    {
+        match MapRemoved::try_into_with(this.inner, backoff) {
+            Ok((elem, _)) => Ok(elem),
+            Err(inner) => Err(Self::new(inner)),
+        }
+    }
 }
 
 impl<T> Deref for Removed<T> {
@@ -535,6 +1195,19 @@ impl<T> AsRef<T> for Removed<T> {
     }
 }
 
+#[cfg(feature = "serde")]
+impl<T> serde::Serialize for Removed<T>
+where
+    T: serde::Serialize,
+{
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        (**self).serialize(serializer)
+    }
+}
+
 /// An iterator over elements of a [`Set`]. The `Item` of this
 /// iterator is a [`ReadGuard`].
 #[derive(Debug)]
@@ -551,6 +1224,183 @@ impl<'set, T> Iterator for Iter<'set, T> {
     fn next(&mut self) -> Option<Self::Item> {
         self.inner.next().map(ReadGuard::new)
     }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl<'set, T> FusedIterator for Iter<'set, T> {}
+
+/// An iterator over elements of a [`Set`] under exclusive access, created by
+/// [`Set::iter_exact`]. It knows exactly how many elements it yields.
+#[derive(Debug)]
+pub struct ExactIter<'set, T>
+where
+    T: 'set,
+{
+    inner: MapExactIter<'set, T, ()>,
+}
+
+impl<'set, T> Iterator for ExactIter<'set, T> {
+    type Item = ReadGuard<'set, T>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next().map(ReadGuard::new)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl<'set, T> ExactSizeIterator for ExactIter<'set, T> {}
+
+impl<'set, T> FusedIterator for ExactIter<'set, T> {}
+
+/// An iterator over the elements of either of two [`Set`]s, created by
+/// [`Set::union`]. The `Item` of this iterator is a [`ReadGuard`] to the
+/// element in the set it was found in.
+#[derive(Debug)]
+pub struct Union<'set, T, H>
+where
+    T: 'set,
+    H: 'set,
+{
+    first: Iter<'set, T>,
+    second: Difference<'set, T, H>,
+}
+
+impl<'set, T, H> Iterator for Union<'set, T, H>
+where
+    H: BuildHasher,
+    T: Hash + Eq,
+{
+    type Item = ReadGuard<'set, T>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.first.next().or_else(|| self.second.next())
+    }
+}
+
+impl<'set, T, H> FusedIterator for Union<'set, T, H>
+where
+    H: BuildHasher,
+    T: Hash + Eq,
+{
+}
+
+/// An iterator over the elements of both of two [`Set`]s, created by
+/// [`Set::intersection`]. The `Item` of this iterator is a [`ReadGuard`] to
+/// the element in the smaller set.
+#[derive(Debug)]
+pub struct Intersection<'set, T, H, H2>
+where
+    T: 'set,
+    H: 'set,
+    H2: 'set,
+{
+    this: &'set Set<T, H>,
+    other: &'set Set<T, H2>,
+    // Whether `other` is iterated and `this` is looked up, instead of the
+    // opposite.
+    from_other: bool,
+    iter: Iter<'set, T>,
+}
+
+impl<'set, T, H, H2> Iterator for Intersection<'set, T, H, H2>
+where
+    H: BuildHasher,
+    H2: BuildHasher,
+    T: Hash + Eq,
+{
+    type Item = ReadGuard<'set, T>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (this, other, from_other) = (self.this, self.other, self.from_other);
+        self.iter.find(|elem| {
+            if from_other {
+                this.contains(&**elem)
+            } else {
+                other.contains(&**elem)
+            }
+        })
+    }
+}
+
+impl<'set, T, H, H2> FusedIterator for Intersection<'set, T, H, H2>
+where
+    H: BuildHasher,
+    H2: BuildHasher,
+    T: Hash + Eq,
+{
+}
+
+/// An iterator over the elements of a [`Set`] which are not in another one,
+/// created by [`Set::difference`]. The `Item` of this iterator is a
+/// [`ReadGuard`].
+#[derive(Debug)]
+pub struct Difference<'set, T, H>
+where
+    T: 'set,
+    H: 'set,
+{
+    iter: Iter<'set, T>,
+    other: &'set Set<T, H>,
+}
+
+impl<'set, T, H> Iterator for Difference<'set, T, H>
+where
+    H: BuildHasher,
+    T: Hash + Eq,
+{
+    type Item = ReadGuard<'set, T>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let other = self.other;
+        self.iter.find(|elem| !other.contains(&**elem))
+    }
+}
+
+impl<'set, T, H> FusedIterator for Difference<'set, T, H>
+where
+    H: BuildHasher,
+    T: Hash + Eq,
+{
+}
+
+/// An iterator over the elements in exactly one of two [`Set`]s, created by
+/// [`Set::symmetric_difference`]. The `Item` of this iterator is a
+/// [`ReadGuard`] to the element in the set it was found in.
+#[derive(Debug)]
+pub struct SymmetricDifference<'set, T, H, H2>
+where
+    T: 'set,
+    H: 'set,
+    H2: 'set,
+{
+    inner: Chain<Difference<'set, T, H2>, Difference<'set, T, H>>,
+}
+
+impl<'set, T, H, H2> Iterator for SymmetricDifference<'set, T, H, H2>
+where
+    H: BuildHasher,
+    H2: BuildHasher,
+    T: Hash + Eq,
+{
+    type Item = ReadGuard<'set, T>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next()
+    }
+}
+
+impl<'set, T, H, H2> FusedIterator for SymmetricDifference<'set, T, H, H2>
+where
+    H: BuildHasher,
+    H2: BuildHasher,
+    T: Hash + Eq,
+{
 }
 
 /// An iterator over owned elements of a [`Set`].
@@ -564,14 +1414,50 @@ impl<T> Iterator for IntoIter<T> {
     fn next(&mut self) -> Option<Self::Item> {
         self.inner.next().map(|(elem, _)| elem)
     }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
 }
 
+impl<T> ExactSizeIterator for IntoIter<T> {}
+
+impl<T> FusedIterator for IntoIter<T> {}
+
 impl<T> fmt::Debug for IntoIter<T> {
     fn fmt(&self, fmtr: &mut fmt::Formatter) -> fmt::Result {
         write!(fmtr, "IntoIter {{ inner: {:?} }}", self.inner)
     }
 }
 
+/// A draining iterator over owned elements of a [`Set`], created by
+/// [`Set::drain`]. Elements not yielded are dropped along with the iterator.
+pub struct Drain<'set, T> {
+    inner: MapDrain<'set, T, ()>,
+}
+
+impl<'set, T> Iterator for Drain<'set, T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next().map(|(elem, _)| elem)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl<'set, T> ExactSizeIterator for Drain<'set, T> {}
+
+impl<'set, T> FusedIterator for Drain<'set, T> {}
+
+impl<'set, T> fmt::Debug for Drain<'set, T> {
+    fn fmt(&self, fmtr: &mut fmt::Formatter) -> fmt::Result {
+        write!(fmtr, "Drain {{ inner: {:?} }}", self.inner)
+    }
+}
+
 /// The shared incinerator used by [`Set`]. You may want to use this type
 /// in order to reduce memory consumption of the minimal space required by the
 /// incinerator. However, garbage items may be hold for longer time than they
@@ -587,6 +1473,18 @@ impl<T> SharedIncin<T> {
             inner: MapIncin::new(),
         }
     }
+
+    /// Creates a new shared incinerator for [`Set`] which hands its garbage
+    /// to the given reclaimer.
+    pub fn with_reclaimer<R>(reclaimer: R) -> Self
+    where
+        T: Send + 'static,
+        R: Reclaimer,
+    {
+        Self {
+            inner: MapIncin::with_reclaimer(reclaimer),
+        }
+    }
 }
 
 impl<T> fmt::Debug for SharedIncin<T> {
@@ -609,15 +1507,135 @@ impl<T> Clone for SharedIncin<T> {
     }
 }
 
-#[cfg(test)]
+#[cfg(all(test, feature = "std"))]
 #[allow(dead_code)]
 mod test {
     use super::*;
+    use crate::test_util::with_writers;
+    use alloc::sync::Arc;
+    use core::{
+        cell::Cell,
+        sync::atomic::{AtomicBool, Ordering::*},
+    };
+    use static_assertions::{assert_impl_all, assert_not_impl_any};
     use std::{
         cmp::Ordering,
         hash::{Hash, Hasher},
+        panic::{catch_unwind, AssertUnwindSafe},
+        ptr,
+        rc::Rc,
+        sync::MutexGuard,
+        thread,
     };
 
+    assert_impl_all!(ReadGuard<'static, String>: Send, Sync);
+    assert_not_impl_any!(ReadGuard<'static, Cell<u8>>: Send, Sync);
+    assert_not_impl_any!(ReadGuard<'static, Rc<u8>>: Send, Sync);
+
+    assert_impl_all!(Removed<String>: Send, Sync);
+    assert_impl_all!(Removed<Cell<u8>>: Send);
+    assert_not_impl_any!(Removed<Cell<u8>>: Sync);
+    assert_impl_all!(Removed<MutexGuard<'static, u8>>: Sync);
+    assert_not_impl_any!(Removed<MutexGuard<'static, u8>>: Send);
+    assert_not_impl_any!(Removed<Rc<u8>>: Send, Sync);
+
+    #[test]
+    fn footprint_follows_elements() {
+        let mut set = Set::new();
+        let empty = set.bytes_allocated();
+        for i in 0..256u32 {
+            set.insert(i).unwrap();
+        }
+        let full = set.bytes_allocated();
+        assert!(full > empty);
+        for i in 0..256 {
+            set.remove(&i).unwrap();
+        }
+        set.optimize_space();
+        assert!(set.bytes_allocated() < full);
+        assert_eq!(set.memory_footprint(), set.bytes_allocated());
+    }
+
+    #[test]
+    fn elements_cloned_while_mutating() {
+        let set = Arc::new(Set::new());
+        let write = |set: &Set<u64>, id: usize| {
+            for round in 0..50 {
+                for i in 0..100 {
+                    if (i + round + id as u64) % 2 == 0 {
+                        set.insert(i).ok();
+                    } else {
+                        set.remove(&i);
+                    }
+                }
+            }
+        };
+        with_writers(&set, 2, write, |set| {
+            let elems = set.elements_cloned();
+            assert!(elems.iter().all(|&elem| elem < 100));
+        });
+        assert_eq!(set.elements_cloned().len(), set.iter().count());
+    }
+
+    #[test]
+    fn iter_exact_knows_its_length() {
+        let mut set = (0..100).collect::<Set<_>>();
+        set.remove(&7);
+        let iter = set.iter_exact();
+        assert_eq!(iter.len(), 99);
+        assert_eq!(iter.filter(|elem| **elem < 10).count(), 9);
+    }
+
+    #[test]
+    fn closures_unwind() {
+        let elem = Arc::new(5);
+        let mut set = Set::new();
+        set.insert(elem.clone()).unwrap();
+
+        let res = catch_unwind(AssertUnwindSafe(|| {
+            set.insert_with(Arc::new(6), |_, _| panic!("inserting"))
+        }));
+        assert!(res.is_err());
+        assert!(!set.contains(&6));
+
+        let res = catch_unwind(AssertUnwindSafe(|| {
+            set.insert_with(Arc::new(5), |_, _| panic!("updating"))
+        }));
+        assert!(res.is_err());
+        assert!(Arc::ptr_eq(&set.get(&5).unwrap(), &elem));
+
+        let res = catch_unwind(AssertUnwindSafe(|| {
+            set.remove_with(&5, |_| panic!("removing"))
+        }));
+        assert!(res.is_err());
+        assert!(set.contains(&5));
+
+        let removed = set.remove(&5).unwrap();
+        let res = catch_unwind(AssertUnwindSafe(|| {
+            set.reinsert_with(removed, |_, _| panic!("reinserting"))
+        }));
+        assert!(res.is_err());
+        assert!(!set.contains(&5));
+
+        set.optimize_space();
+        assert_eq!(Arc::strong_count(&elem), 1);
+    }
+
+    #[test]
+    fn same_seed_same_order() {
+        let seed = *b"0123456789abcdef";
+        let first = Set::with_seed(seed);
+        let second = Set::with_seed(seed);
+        for i in 0..512u32 {
+            first.insert(i).unwrap();
+            second.insert(i).unwrap();
+        }
+        assert!(first
+            .iter()
+            .map(|guard| *guard)
+            .eq(second.iter().map(|guard| *guard)));
+    }
+
     #[derive(Debug, Clone, Copy)]
     struct EqI {
         i: usize,
@@ -713,6 +1731,398 @@ mod test {
             .unwrap();
     }
 
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serializes_guards_and_removed() {
+        let set = Set::new();
+        set.insert("hello".to_owned()).unwrap();
+        let expected = serde_json::to_string("hello").unwrap();
+        let guard = set.get(&"hello".to_owned()).unwrap();
+        assert_eq!(serde_json::to_string(&guard).unwrap(), expected);
+        drop(guard);
+        let removed = set.remove(&"hello".to_owned()).unwrap();
+        assert_eq!(serde_json::to_string(&removed).unwrap(), expected);
+    }
+
+    #[test]
+    fn compares_guards_of_different_sets() {
+        let first = Set::new();
+        let second = Set::new();
+        first.extend(vec![1, 2, 3]);
+        second.extend(vec![2, 3, 4]);
+
+        let two = first.get(&2).unwrap();
+        assert_eq!(two, second.get(&2).unwrap());
+        assert_ne!(two, second.get(&3).unwrap());
+        assert_eq!(two, &2);
+        assert!(two < second.get(&4).unwrap());
+        assert!(two > 1);
+
+        let mut guards = first.iter().chain(second.iter()).collect::<Vec<_>>();
+        guards.sort();
+        let sorted = guards.iter().map(|guard| **guard).collect::<Vec<_>>();
+        assert_eq!(sorted, vec![1, 2, 2, 3, 3, 4]);
+    }
+
+    #[test]
+    fn projects_guard() {
+        let set = Set::new();
+        set.insert(EqI { i: 5, j: 7 }).unwrap();
+        let guard = ReadGuard::map(set.get(&EqI { i: 5, j: 0 }).unwrap(), |elem| &elem.j);
+        assert_eq!(*guard, 7);
+        let guard = MappedGuard::map(guard, |j| j);
+        assert_eq!(format!("{}", guard), "7");
+    }
+
+    #[test]
+    fn try_into_with_gives_up_intact() {
+        let set = Set::new();
+        set.insert(3).unwrap();
+        set.insert(5).unwrap();
+        let removed = set.remove(&3).unwrap();
+        let _active_read = set.get(&5).unwrap();
+        let removed = Removed::try_into_with(removed, &mut |_| RetryDecision::GiveUp).unwrap_err();
+        assert_eq!(removed, 3);
+    }
+
+    #[test]
+    fn len_follows_every_operation() {
+        let mut set = Set::new();
+        for i in 0..10 {
+            set.insert(EqI { i, j: 0 }).unwrap();
+        }
+        set.insert(EqI { i: 3, j: 1 }).unwrap_err();
+        assert!(set
+            .insert_with(EqI { i: 4, j: 1 }, |_, _| false)
+            .failed()
+            .is_some());
+        assert!(set
+            .insert_with(EqI { i: 4, j: 1 }, |_, _| true)
+            .updated()
+            .is_some());
+        assert_eq!(set.len(), 10);
+
+        assert!(set.remove_with(&EqI { i: 5, j: 0 }, |_| false).is_none());
+        let removed = set.remove(&EqI { i: 5, j: 0 }).unwrap();
+        assert_eq!(set.len(), 9);
+        set.insert(EqI { i: 5, j: 1 }).unwrap();
+        let removed = set
+            .reinsert_with(removed, |_, _| false)
+            .take_failed()
+            .unwrap();
+        assert!(set.reinsert(removed).is_err());
+        assert_eq!(set.len(), 10);
+
+        set.clear();
+        assert_eq!(set.len(), 0);
+        assert!(set.is_empty());
+    }
+
+    #[test]
+    fn union_prefers_own_elements() {
+        let first = Set::new();
+        let second = Set::with_seed([3; 16]);
+        for i in 0..6 {
+            first.insert(EqI { i, j: 1 }).unwrap();
+            second.insert(EqI { i: i + 3, j: 2 }).unwrap();
+        }
+
+        let mut union = first.union(&second).map(|elem| *elem).collect::<Vec<_>>();
+        union.sort();
+        let pairs = union
+            .iter()
+            .map(|elem| (elem.i, elem.j))
+            .collect::<Vec<_>>();
+        let expected = (0..9)
+            .map(|i| (i, if i < 6 { 1 } else { 2 }))
+            .collect::<Vec<_>>();
+        assert_eq!(pairs, expected);
+        assert_eq!(second.union(&first).count(), 9);
+    }
+
+    #[test]
+    fn intersection_ignores_concurrent_strangers() {
+        let first = (0..200u32).collect::<Set<_>>();
+        let second = Arc::new(Set::with_seed([5; 16]));
+        second.extend((0..200).step_by(2));
+        let done = Arc::new(AtomicBool::new(false));
+        let churner = {
+            let second = second.clone();
+            let done = done.clone();
+            thread::spawn(move || {
+                while !done.load(Acquire) {
+                    second.extend(1000..1300);
+                    for i in 1000..1300 {
+                        second.remove(&i);
+                    }
+                }
+            })
+        };
+
+        for _ in 0..20 {
+            let mut both = first
+                .intersection(&second)
+                .map(|elem| *elem)
+                .collect::<Vec<_>>();
+            both.sort();
+            assert!(both.iter().copied().eq((0..200).step_by(2)));
+            assert_eq!(second.intersection(&first).count(), 100);
+        }
+        done.store(true, Release);
+        churner.join().unwrap();
+    }
+
+    #[test]
+    fn difference_of_sets_filled_concurrently() {
+        let seen = Arc::new(Set::new());
+        let current = Arc::new(Set::with_seed([7; 16]));
+        let threads = (0..4u32)
+            .map(|t| {
+                let (seen, current) = (seen.clone(), current.clone());
+                thread::spawn(move || {
+                    for i in (t..300).step_by(4) {
+                        current.insert(i).unwrap();
+                        if i < 200 {
+                            seen.insert(i).unwrap();
+                        }
+                    }
+                })
+            })
+            .collect::<Vec<_>>();
+        for thread in threads {
+            thread.join().unwrap();
+        }
+
+        let mut new = current
+            .difference(&seen)
+            .map(|elem| *elem)
+            .collect::<Vec<_>>();
+        new.sort();
+        assert!(new.into_iter().eq(200..300));
+        assert_eq!(seen.difference(&*current).count(), 0);
+    }
+
+    #[test]
+    fn set_operations_compose() {
+        fn sorted<'set, I>(iter: I) -> Vec<u32>
+        where
+            I: Iterator<Item = ReadGuard<'set, u32>>,
+        {
+            let mut elems = iter.map(|elem| *elem).collect::<Vec<_>>();
+            elems.sort();
+            elems
+        }
+
+        let first = (0..10).collect::<Set<_>>();
+        let second = Set::with_seed([1; 16]);
+        second.extend(5..15);
+
+        let either = sorted(first.symmetric_difference(&second));
+        assert_eq!(either, sorted(second.symmetric_difference(&first)));
+        let mut rebuilt = sorted(first.difference(&second));
+        rebuilt.extend(sorted(second.difference(&first)));
+        assert_eq!(either, rebuilt);
+
+        let mut all = either;
+        all.extend(sorted(first.intersection(&second)));
+        all.sort();
+        assert_eq!(all, sorted(first.union(&second)));
+    }
+
+    #[test]
+    fn subsets_and_supersets() {
+        let empty = Set::<u32>::new();
+        let small = (0..50).collect::<Set<_>>();
+        let large = Set::with_seed([2; 16]);
+        large.extend(0..100);
+
+        assert!(empty.is_subset(&small) && small.is_superset(&empty));
+        assert!(small.is_subset(&large) && large.is_superset(&small));
+        assert!(!large.is_subset(&small) && !small.is_superset(&large));
+        assert!(small.is_subset(&small) && small.is_superset(&small));
+
+        // Same length, but one element differs.
+        let shifted = (1..51).collect::<Set<_>>();
+        assert!(!small.is_subset(&shifted) && !shifted.is_subset(&small));
+        large.remove(&49);
+        assert!(!small.is_subset(&large) && !large.is_superset(&small));
+    }
+
+    #[test]
+    fn disjoint_sets() {
+        let evens = (0..100).step_by(2).collect::<Set<u32>>();
+        let odds = Set::with_seed([4; 16]);
+        odds.extend((1..100).step_by(2));
+        let empty = Set::new();
+
+        assert!(evens.is_disjoint(&odds) && odds.is_disjoint(&evens));
+        assert!(evens.is_disjoint(&empty) && empty.is_disjoint(&evens));
+        odds.insert(42).unwrap();
+        assert!(!evens.is_disjoint(&odds) && !odds.is_disjoint(&evens));
+        assert!(!evens.is_disjoint(&evens));
+    }
+
+    #[test]
+    fn collects_and_extends_deduplicating() {
+        fn fill<E>(target: &mut E, elems: &[u32])
+        where
+            E: Extend<u32> + for<'a> Extend<&'a u32>,
+        {
+            target.extend(elems.iter().copied());
+            target.extend(elems);
+        }
+
+        let mut set = [5, 1, 5, 2, 1].iter().copied().collect::<Set<_>>();
+        assert_eq!(set.len(), 3);
+        fill(&mut set, &[2, 3]);
+        let shared = Arc::new(set);
+        let threads = (0..4)
+            .map(|t| {
+                let shared = shared.clone();
+                thread::spawn(move || fill(&mut &*shared, &[4, 6 + t, 4]))
+            })
+            .collect::<Vec<_>>();
+        for thread in threads {
+            thread.join().unwrap();
+        }
+
+        let mut elems = shared.iter().map(|elem| *elem).collect::<Vec<_>>();
+        elems.sort();
+        assert_eq!(elems, [1, 2, 3, 4, 5, 6, 7, 8, 9]);
+    }
+
+    #[test]
+    fn retain_frees_rejected_elements() {
+        let tokens = (0..100).map(Arc::new).collect::<Vec<_>>();
+        let mut set = tokens.iter().cloned().collect::<Set<_>>();
+        let mut visited = 0;
+        set.retain(|elem| {
+            visited += 1;
+            **elem % 10 == 0
+        });
+
+        assert_eq!(visited, 100);
+        assert_eq!(set.len(), 10);
+        for token in &tokens {
+            let kept = **token % 10 == 0;
+            assert_eq!(set.contains(token), kept);
+            assert_eq!(Arc::strong_count(token), if kept { 2 } else { 1 });
+        }
+    }
+
+    #[test]
+    fn drain_empties_even_if_abandoned() {
+        let tokens = (0..50).map(Arc::new).collect::<Vec<_>>();
+        let mut set = tokens.iter().cloned().collect::<Set<_>>();
+
+        let mut drain = set.drain();
+        assert_eq!(drain.len(), 50);
+        let first = drain.next().unwrap();
+        assert_eq!(drain.len(), 49);
+        drop(drain);
+        assert!(set.is_empty());
+        assert_eq!(set.iter().count(), 0);
+        assert!(tokens
+            .iter()
+            .all(|token| Arc::strong_count(token) == 1 + Arc::ptr_eq(token, &first) as usize));
+
+        set.extend(tokens.iter().cloned());
+        assert_eq!(set.drain().count(), 50);
+        assert!(set.insert(first).is_ok());
+        assert_eq!(set.len(), 1);
+    }
+
+    #[test]
+    fn interns_each_string_once() {
+        const WORDS: [&str; 4] = ["alpha", "beta", "gamma", "delta"];
+
+        let interned = Arc::new(Set::<String>::new());
+        let threads = (0..4)
+            .map(|_| {
+                let interned = interned.clone();
+                thread::spawn(move || {
+                    (0..100)
+                        .map(|i| {
+                            let word = WORDS[i % WORDS.len()];
+                            let guard = interned.get_or_insert_with(word, || word.to_owned());
+                            &*guard as *const String as usize
+                        })
+                        .collect::<Vec<_>>()
+                })
+            })
+            .collect::<Vec<_>>();
+        let addrs = threads
+            .into_iter()
+            .map(|thread| thread.join().unwrap())
+            .collect::<Vec<_>>();
+
+        assert_eq!(interned.len(), WORDS.len());
+        for (i, word) in WORDS.iter().enumerate() {
+            let stored = &*interned.get_or_insert_with(*word, || unreachable!()) as *const String;
+            assert!(addrs.iter().all(|addrs| addrs[i] == stored as usize));
+            let guard = interned.get_or_insert(word.to_string());
+            assert!(ptr::eq(&*guard, stored));
+        }
+        assert_eq!(interned.len(), WORDS.len());
+    }
+
+    #[test]
+    fn replace_refreshes_metadata() {
+        let set = Set::new();
+        assert!(set.replace(EqI { i: 1, j: 0 }).is_none());
+        set.insert(EqI { i: 2, j: 0 }).unwrap();
+
+        let old = set.replace(EqI { i: 1, j: 5 }).unwrap();
+        assert_eq!((old.i, old.j), (1, 0));
+        assert_eq!(set.get(&EqI { i: 1, j: 9 }).unwrap().j, 5);
+        assert_eq!(set.get(&EqI { i: 2, j: 9 }).unwrap().j, 0);
+        assert_eq!(set.len(), 2);
+    }
+
+    #[test]
+    fn clones_are_independent() {
+        let set = Set::with_seed([6; 16]);
+        set.extend((0..300).map(|i| EqI { i, j: i }));
+        let clone = set.clone();
+
+        assert_eq!(clone.len(), 300);
+        assert_eq!(clone.hasher(), set.hasher());
+        assert!(set
+            .iter()
+            .all(|elem| clone.get(&*elem).unwrap().j == elem.j));
+        set.remove(&EqI { i: 7, j: 0 });
+        clone.replace(EqI { i: 8, j: 0 });
+        assert!(clone.contains(&EqI { i: 7, j: 0 }));
+        assert_eq!(set.get(&EqI { i: 8, j: 0 }).unwrap().j, 8);
+    }
+
+    #[test]
+    fn equal_regardless_of_hasher_and_order() {
+        let parallel = Arc::new(Set::new());
+        let threads = (0..4u32)
+            .map(|t| {
+                let parallel = parallel.clone();
+                thread::spawn(move || parallel.extend((t..400).step_by(4)))
+            })
+            .collect::<Vec<_>>();
+        for thread in threads {
+            thread.join().unwrap();
+        }
+
+        let serial = Set::with_seed([8; 16]);
+        serial.extend((0..400).rev());
+        assert_eq!(*parallel, serial);
+        assert_eq!(serial, *parallel);
+
+        serial.remove(&17);
+        assert_ne!(*parallel, serial);
+        assert_ne!(serial, *parallel);
+        serial.insert(400).unwrap();
+        assert_ne!(*parallel, serial);
+        assert_ne!(serial, *parallel);
+        assert_eq!(Set::<u32>::new(), Set::with_seed([0; 16]));
+    }
+
     #[test]
     fn reinsert_with() {
         let set = Set::new();
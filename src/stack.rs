@@ -1,7 +1,12 @@
+use crate::{
+    footprint::MemoryFootprint,
+    incin::Reclaimer,
+    traits::{ApproxLen, ConcurrentInsert, ConcurrentPop},
+};
 use core::{
     fmt,
     iter::FromIterator,
-    mem::ManuallyDrop,
+    mem::{self, ManuallyDrop},
     ptr::{null_mut, NonNull},
     sync::atomic::{AtomicPtr, Ordering::*},
 };
@@ -19,6 +24,16 @@ impl<T> Stack<T> {
         Self::with_incin(SharedIncin::new())
     }
 
+    /// Creates an empty stack whose removed nodes are handed to the given
+    /// reclaimer. See [`Incinerator::with_reclaimer`](crate::incin::Incinerator::with_reclaimer).
+    pub fn with_reclaimer<R>(reclaimer: R) -> Self
+    where
+        T: Send + 'static,
+        R: Reclaimer,
+    {
+        Self::with_incin(SharedIncin::with_reclaimer(reclaimer))
+    }
+
     /// Creates an empty queue using the passed shared incinerator.
     pub fn with_incin(incin: SharedIncin<T>) -> Self {
         Self {
@@ -61,6 +76,43 @@ impl<T> Stack<T> {
         }
     }
 
+    /// Pushes the elements of the given iterable onto the top of the stack as
+    /// a single batch, as if they were pushed one by one in the order of the
+    /// iterable. The elements are contiguous in the stack: nothing pushed
+    /// concurrently, either alone or in another batch, lands among them.
+    /// Does nothing for an empty iterable.
+    pub fn push_many<I>(&self, iterable: I)
+    where
+        I: IntoIterator<Item = T>,
+    {
+        let mut iter = iterable.into_iter();
+        let bottom = match iter.next() {
+            Some(val) => OwnedAlloc::new(Node::new(val, null_mut())).into_raw(),
+            None => return,
+        };
+
+        // We link the nodes before publishing any of them. If the iterator
+        // panics, the nodes linked so far are leaked.
+        let mut top = bottom;
+        for val in iter {
+            top = OwnedAlloc::new(Node::new(val, top.as_ptr())).into_raw();
+        }
+
+        let mut next = self.top.load(Acquire);
+        loop {
+            // Safe because the chain is only shared once the compare and
+            // swap succeeds.
+            unsafe { (*bottom.as_ptr()).next = next };
+            match self
+                .top
+                .compare_exchange(next, top.as_ptr(), Release, Relaxed)
+            {
+                Ok(_) => break,
+                Err(ptr) => next = ptr,
+            }
+        }
+    }
+
     /// Pops a single element from the top of the stack.
     pub fn pop(&self) -> Option<T> {
         // We need this because of ABA problem and use-after-free.
@@ -102,7 +154,9 @@ impl<T> Stack<T> {
     }
 
     /// Pushes elements from the given iterable. Acts just like
-    /// [`Extend::extend`] but does not require mutability.
+    /// [`Extend::extend`] but does not require mutability. The elements are
+    /// pushed one by one, so concurrent pushes may land among them; see
+    /// [`push_many`](Stack::push_many) to keep them together.
     pub fn extend<I>(&self, iterable: I)
     where
         I: IntoIterator<Item = T>,
@@ -177,6 +231,65 @@ unsafe impl<T> Send for Stack<T> where T: Send {}
 
 unsafe impl<T> Sync for Stack<T> where T: Send {}
 
+impl<T> MemoryFootprint for Stack<T> {
+    /// Counts the nodes by walking the whole stack, so it takes time linear in
+    /// its length.
+    fn bytes_allocated(&self) -> usize {
+        // Pausing so no node is freed while we walk the stack.
+        let _pause = self.incin.get_unchecked().pause();
+        let mut count = 0;
+        let mut node = self.top.load(Acquire);
+
+        while let Some(nnptr) = NonNull::new(node) {
+            count += 1;
+            // Safe because we only delete nodes via incinerator and we have a
+            // pause now. The next field is never changed after the node is
+            // published.
+            node = unsafe { nnptr.as_ref().next };
+        }
+
+        count * mem::size_of::<Node<T>>()
+    }
+
+    fn garbage_bytes(&self) -> usize {
+        self.incin.get_unchecked().garbage_len() * mem::size_of::<Node<T>>()
+    }
+}
+
+impl<T> ConcurrentInsert<T> for Stack<T> {
+    fn try_insert(&self, elem: T) -> Result<(), T> {
+        self.push(elem);
+        Ok(())
+    }
+}
+
+impl<T> ConcurrentPop<T> for Stack<T> {
+    fn pop(&self) -> Option<T> {
+        Stack::pop(self)
+    }
+}
+
+impl<T> ApproxLen for Stack<T> {
+    /// Counts the elements by walking the whole stack, so it takes time linear
+    /// in its length. No counter is kept, which would make pushing and popping
+    /// contend on it.
+    fn len(&self) -> usize {
+        // Pausing so no node is freed while we walk the stack.
+        let _pause = self.incin.get_unchecked().pause();
+        let mut count = 0;
+        let mut node = self.top.load(Acquire);
+
+        while let Some(nnptr) = NonNull::new(node) {
+            count += 1;
+            // Safe because we only delete nodes via incinerator and we have a
+            // pause now.
+            node = unsafe { nnptr.as_ref().next };
+        }
+
+        count
+    }
+}
+
 /// An iterator based on [`pop`](Stack::pop) operation of the [`Stack`].
 pub struct PopIter<'stack, T>
 where
@@ -230,6 +343,28 @@ impl<T> Node<T> {
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::footprint::MemoryFootprint;
+    use alloc::string::{String, ToString};
+
+    #[test]
+    fn footprint_follows_elements() {
+        let stack = Stack::new();
+        let mut last = stack.bytes_allocated();
+        for i in 0..100 {
+            stack.push(i);
+            let now = stack.bytes_allocated();
+            assert!(now > last);
+            last = now;
+        }
+
+        let incin = stack.incin.get_unchecked().clone();
+        let pause = incin.pause();
+        while stack.pop().is_some() {}
+        assert!(stack.bytes_allocated() < last);
+        assert!(stack.garbage_bytes() > 0);
+        drop(pause);
+        assert_eq!(stack.garbage_bytes(), 0);
+    }
 
     #[test]
     fn on_empty_first_pop_is_none() {
@@ -259,8 +394,76 @@ mod test {
         assert_eq!(stack.pop(), Some(3));
     }
 
+    #[test]
+    fn push_many_keeps_order() {
+        let stack = Stack::new();
+        stack.push(1);
+        stack.push_many(2..5);
+        stack.push_many(None);
+        stack.push(5);
+        assert!(stack.pop_iter().eq((1..6).rev()));
+        stack.push_many(Some(6));
+        assert_eq!(stack.pop(), Some(6));
+        assert_eq!(stack.pop(), None);
+    }
+
+    #[test]
     #[cfg(feature = "std")]
+    fn push_many_batches_are_contiguous() {
+        use crate::test_util::{produce_batches, with_writers, BatchChecker, Produced};
+        use std::sync::Arc;
+
+        const NPRODUCER: usize = 4;
+
+        // Pops racing with a batch may take its top before the rest of it is
+        // covered by other pushes, so the stack is only drained at the end.
+        let stack = Arc::new(Stack::new());
+        with_writers(
+            &stack,
+            NPRODUCER,
+            |stack, id| {
+                for produced in produce_batches(id) {
+                    match produced {
+                        Produced::Single(item) => stack.push(item),
+                        Produced::Batch(batch) => stack.push_many(batch),
+                    }
+                }
+            },
+            |_| (),
+        );
+
+        let mut popped = stack.pop_iter().collect::<Vec<_>>();
+        popped.reverse();
+        let mut checker = BatchChecker::new();
+        popped.into_iter().for_each(|item| checker.consume(item));
+        checker.finish(NPRODUCER);
+    }
+
+    #[test]
+    fn removed_entries_hand_off_while_read() {
+        use crate::map::{Map, Removed};
+
+        let map = Map::new();
+        for i in 0..5 {
+            map.insert(i, i.to_string());
+        }
+        let stack = Stack::new();
+
+        let guard = map.get(&2).unwrap();
+        stack.extend((0..5).filter_map(|i| map.remove(&i)));
+        assert_eq!(guard.val(), "2");
+
+        let top = stack.pop().unwrap();
+        assert_eq!((*top.key(), top.val().as_str()), (4, "4"));
+        let top = Removed::try_into(top).unwrap_err();
+
+        drop(guard);
+        assert_eq!(Removed::try_into(top).ok(), Some((4, String::from("4"))));
+        assert_eq!(stack.pop_iter().count(), 4);
+    }
+
     #[test]
+    #[cfg(feature = "std")]
     fn no_data_corruption() {
         use std::{sync::Arc, thread};
 
@@ -0,0 +1,18 @@
+// Atomic types used by the internals which are model-checked with loom. When
+// compiled with `--cfg loom`, these are loom's instrumented types.
+
+#[cfg(loom)]
+pub(crate) use loom::sync::atomic::{
+    AtomicI16, AtomicI32, AtomicI8, AtomicIsize, AtomicPtr, AtomicU16, AtomicU32, AtomicU8,
+    AtomicUsize,
+};
+#[cfg(all(loom, target_has_atomic = "64"))]
+pub(crate) use loom::sync::atomic::{AtomicI64, AtomicU64};
+
+#[cfg(not(loom))]
+pub(crate) use core::sync::atomic::{
+    AtomicI16, AtomicI32, AtomicI8, AtomicIsize, AtomicPtr, AtomicU16, AtomicU32, AtomicU8,
+    AtomicUsize,
+};
+#[cfg(all(not(loom), target_has_atomic = "64"))]
+pub(crate) use core::sync::atomic::{AtomicI64, AtomicU64};
@@ -0,0 +1,102 @@
+// Pointers whose lowest bit is used as a tag. The tag tells tables apart from
+// buckets and entries in the map and in the TLS, marks removed entries in
+// buckets, and marks disconnected sides of channels.
+//
+// Every type whose pointers are tagged must be aligned to at least 2, which
+// `check_align` asserts when such values are created. Tagging offsets the
+// pointer instead of casting it from an integer, so tagged pointers keep the
+// provenance of their allocation.
+
+use core::mem::align_of;
+
+#[cfg(not(any(target_pointer_width = "32", target_pointer_width = "64")))]
+compile_error!("tux_lockfree only supports 32-bit and 64-bit targets");
+
+// The bit used as a tag.
+const TAG_BIT: usize = 1;
+
+// How many bits a `usize` key, such as a thread ID, has to index tables.
+#[cfg(target_pointer_width = "64")]
+const USIZE_BITS: usize = 64;
+#[cfg(target_pointer_width = "32")]
+const USIZE_BITS: usize = 32;
+
+// Asserts that pointers to `T` have a spare lower bit.
+#[inline(always)]
+pub fn check_align<T>() {
+    assert!(
+        align_of::<T>() > TAG_BIT,
+        "tagged pointers need an alignment of at least 2"
+    );
+}
+
+#[inline(always)]
+pub fn is_tagged<T>(ptr: *mut T) -> bool {
+    ptr as usize & TAG_BIT != 0
+}
+
+// Sets the tag of the pointer. Does nothing if it is already set.
+#[inline(always)]
+pub fn tag<T>(ptr: *mut T) -> *mut T {
+    let offset = !(ptr as usize) & TAG_BIT;
+    ptr.cast::<u8>().wrapping_add(offset).cast()
+}
+
+// Clears the tag of the pointer. Does nothing if it is not set.
+#[inline(always)]
+pub fn untag<T>(ptr: *mut T) -> *mut T {
+    let offset = ptr as usize & TAG_BIT;
+    ptr.cast::<u8>().wrapping_sub(offset).cast()
+}
+
+// Returns the index of a `usize` key in a table with `1 << bits` nodes at the
+// given depth, starting at 0. The key runs out of bits earlier on 32-bit
+// targets, and every index is 0 after that.
+#[inline(always)]
+pub fn key_index(key: usize, depth: usize, bits: usize) -> usize {
+    let shift = depth * bits;
+    let shifted = if shift < USIZE_BITS { key >> shift } else { 0 };
+    shifted & ((1 << bits) - 1)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use core::ptr::null_mut;
+
+    #[test]
+    fn tag_round_trip() {
+        let mut val = 5u16;
+        let ptr = &mut val as *mut u16;
+        assert!(!is_tagged(ptr));
+        let tagged = tag(ptr);
+        assert!(is_tagged(tagged));
+        assert_eq!(tag(tagged), tagged);
+        assert_eq!(untag(tagged), ptr);
+        assert_eq!(untag(ptr), ptr);
+        // Safe because the pointer points to `val` once the tag is cleared.
+        assert_eq!(unsafe { *untag(tagged) }, 5);
+    }
+
+    #[test]
+    fn tagged_null() {
+        let tagged = tag(null_mut::<u16>());
+        assert!(is_tagged(tagged));
+        assert!(!tagged.is_null());
+        assert!(untag(tagged).is_null());
+    }
+
+    #[test]
+    fn key_index_runs_out_of_bits() {
+        assert_eq!(key_index(0x1234, 0, 8), 0x34);
+        assert_eq!(key_index(0x1234, 1, 8), 0x12);
+        assert_eq!(key_index(usize::MAX, USIZE_BITS / 8 - 1, 8), 0xff);
+        assert_eq!(key_index(usize::MAX, USIZE_BITS / 8, 8), 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "alignment of at least 2")]
+    fn unaligned_type() {
+        check_align::<u8>();
+    }
+}
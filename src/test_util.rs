@@ -0,0 +1,162 @@
+use core::sync::atomic::{AtomicUsize, Ordering::*};
+use std::{
+    sync::{Arc, Barrier},
+    thread,
+};
+
+/// Runs `write` on `nwriter` threads, all released together by a barrier,
+/// and meanwhile calls `read` on the current thread until every writer has
+/// finished. `read` runs at least once. Panics of the writers are resumed
+/// once all of them are joined.
+pub fn with_writers<T, W, R>(shared: &Arc<T>, nwriter: usize, write: W, mut read: R)
+where
+    T: Send + Sync + 'static,
+    W: Fn(&T, usize) + Send + Sync + 'static,
+    R: FnMut(&T),
+{
+    // Counts a writer as finished even if it panics.
+    struct Finish(Arc<AtomicUsize>);
+
+    impl Drop for Finish {
+        fn drop(&mut self) {
+            self.0.fetch_sub(1, Release);
+        }
+    }
+
+    let write = Arc::new(write);
+    let running = Arc::new(AtomicUsize::new(nwriter));
+    let barrier = Arc::new(Barrier::new(nwriter + 1));
+    let writers = (0..nwriter)
+        .map(|id| {
+            let shared = shared.clone();
+            let write = write.clone();
+            let finish = Finish(running.clone());
+            let barrier = barrier.clone();
+            thread::spawn(move || {
+                let _finish = finish;
+                barrier.wait();
+                write(&shared, id);
+            })
+        })
+        .collect::<Vec<_>>();
+
+    barrier.wait();
+    loop {
+        let done = running.load(Acquire) == 0;
+        read(shared);
+        if done {
+            break;
+        }
+    }
+
+    for writer in writers {
+        if let Err(payload) = writer.join() {
+            std::panic::resume_unwind(payload);
+        }
+    }
+}
+
+/// How many items [`produce_batches`] makes for each producer.
+pub const BATCH_ITEMS: usize = 300;
+
+/// An item tagged with where it comes from, so [`BatchChecker`] can tell how
+/// the batches of concurrent producers were interleaved.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BatchItem {
+    pub producer: usize,
+    /// Position of the item among all items of its producer.
+    pub seq: usize,
+    pub batch: Option<Batch>,
+}
+
+/// Position of an item in the batch it was produced in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Batch {
+    pub id: usize,
+    pub index: usize,
+    pub len: usize,
+}
+
+/// Items made by [`produce_batches`], to be inserted alone or as a batch.
+#[derive(Debug)]
+pub enum Produced {
+    Single(BatchItem),
+    Batch(Vec<BatchItem>),
+}
+
+/// Makes the items of the given producer, some of them alone and the rest in
+/// batches of varying lengths.
+pub fn produce_batches(producer: usize) -> impl Iterator<Item = Produced> {
+    let mut seq = 0;
+    (0..).map_while(move |id| {
+        if seq == BATCH_ITEMS {
+            None
+        } else if id % 3 == 0 {
+            seq += 1;
+            Some(Produced::Single(BatchItem {
+                producer,
+                seq: seq - 1,
+                batch: None,
+            }))
+        } else {
+            let len = (id % 7 + 1).min(BATCH_ITEMS - seq);
+            let batch = (0..len)
+                .map(|index| BatchItem {
+                    producer,
+                    seq: seq + index,
+                    batch: Some(Batch { id, index, len }),
+                })
+                .collect();
+            seq += len;
+            Some(Produced::Batch(batch))
+        }
+    })
+}
+
+/// Checks, item by item in the order they were consumed, that batches are
+/// contiguous, that no item lands inside a batch it does not belong to, and
+/// that the items of each producer are consumed in the order they were made.
+#[derive(Debug, Default)]
+pub struct BatchChecker {
+    next_seq: Vec<usize>,
+    // The producer and the batch being consumed, if it is not over.
+    open: Option<(usize, Batch)>,
+}
+
+impl BatchChecker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn consume(&mut self, item: BatchItem) {
+        if self.next_seq.len() <= item.producer {
+            self.next_seq.resize(item.producer + 1, 0);
+        }
+        let next_seq = &mut self.next_seq[item.producer];
+        assert_eq!(item.seq, *next_seq, "{:?} consumed out of order", item);
+        *next_seq += 1;
+
+        match (self.open.take(), item.batch) {
+            (Some((producer, open)), Some(batch))
+                if producer == item.producer
+                    && open.id == batch.id
+                    && open.index + 1 == batch.index => {}
+            (Some((producer, open)), _) => {
+                panic!("{:?} landed inside batch {:?} of {}", item, open, producer)
+            }
+            (None, Some(batch)) => assert_eq!(batch.index, 0, "{:?} is not contiguous", item),
+            (None, None) => (),
+        }
+        if let Some(batch) = item.batch {
+            if batch.index + 1 < batch.len {
+                self.open = Some((item.producer, batch));
+            }
+        }
+    }
+
+    /// Checks that every item of the given number of producers was consumed.
+    pub fn finish(&self, nproducer: usize) {
+        assert!(self.open.is_none(), "batch {:?} was cut", self.open);
+        assert_eq!(self.next_seq, vec![BATCH_ITEMS; nproducer]);
+    }
+}
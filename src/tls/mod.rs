@@ -3,11 +3,14 @@ mod tid;
 pub use self::tid::ThreadId;
 use alloc::vec::Vec;
 
-use crate::ptr::check_null_align;
+use crate::{
+    footprint::MemoryFootprint,
+    tagged_ptr::{check_align, is_tagged, key_index, tag, untag},
+};
 use core::{
     fmt,
     marker::PhantomData,
-    mem::{forget, replace},
+    mem::{self, forget, replace},
     ptr::{null_mut, NonNull},
     sync::atomic::{AtomicPtr, Ordering::*},
 };
@@ -53,8 +56,8 @@ pub struct ThreadLocal<T> {
 impl<T> ThreadLocal<T> {
     /// Creates an empty thread local storage.
     pub fn new() -> Self {
-        check_null_align::<Table<T>>();
-        check_null_align::<Entry<T>>();
+        check_align::<Table<T>>();
+        check_align::<Entry<T>>();
         Self {
             top: Table::new_alloc(),
         }
@@ -130,7 +133,7 @@ impl<T> ThreadLocal<T> {
 
             // Having in_place's lower bit set to 0 means it is a
             // pointer to entry.
-            if in_place as usize & 1 == 0 {
+            if !is_tagged(in_place) {
                 // This is safe since:
                 //
                 // 1. We only store nodes with cleared lower bit if it is an
@@ -151,7 +154,7 @@ impl<T> ThreadLocal<T> {
             // The remaining case (non-null with lower bit set to 1) means
             // we have a child table.
             // Clear the pointer first lower bit so we can dereference it.
-            let table_ptr = (in_place as usize & !1) as *mut Table<T>;
+            let table_ptr = untag(in_place) as *mut Table<T>;
             // Set it as the table to be checked in the next iteration.
             // This is safe since:
             //
@@ -207,7 +210,7 @@ impl<T> ThreadLocal<T> {
                 // First lower bit set to 0 means this is a pointer to
                 // entry. This should be guaranteed by the alignment,
                 // however, always good to ensure it.
-                debug_assert!(nnptr.as_ptr() as usize & 1 == 0);
+                debug_assert!(!is_tagged(nnptr.as_ptr()));
 
                 // Trying to publish our freshly created entry.
                 match table.nodes[index].atomic.compare_exchange(
@@ -228,7 +231,7 @@ impl<T> ThreadLocal<T> {
 
                     Err(new) => in_place = new,
                 }
-            } else if in_place as usize & 1 == 0 {
+            } else if !is_tagged(in_place) {
                 // First lower bit set to 0 means we have an entry.
                 //
                 // This is safe since:
@@ -255,8 +258,8 @@ impl<T> ThreadLocal<T> {
                 let new_tbl = tbl_cache.take_or(Table::new_alloc);
 
                 // Calculate index for the collided entry.
-                let other_shifted = entry.id.bits() >> (depth * BITS);
-                let other_index = other_shifted & ((1 << BITS) - 1);
+                // IDs only have as many bits as a pointer.
+                let other_index = key_index(entry.id.bits(), depth, BITS);
 
                 // Pre-insert it in the table from the cache.
                 new_tbl.nodes[other_index].atomic.store(in_place, Relaxed);
@@ -270,7 +273,7 @@ impl<T> ThreadLocal<T> {
                     in_place,
                     // First lower bit set to 1 means it is a table
                     // pointer.
-                    (new_tbl_ptr.as_ptr() as usize | 1) as *mut (),
+                    tag(new_tbl_ptr.as_ptr()) as *mut (),
                     AcqRel,
                     Acquire,
                 ) {
@@ -310,7 +313,7 @@ impl<T> ThreadLocal<T> {
                 // The remaining case (non-null with first lower bit set to
                 // 1) is a table. Clear the pointer first lower bit so we
                 // can dereference it.
-                let table_ptr = (in_place as usize & !1) as *mut Table<T>;
+                let table_ptr = untag(in_place) as *mut Table<T>;
 
                 // Set it as table for the next iteration.
                 //
@@ -389,6 +392,18 @@ where
     }
 }
 
+impl<T> MemoryFootprint for ThreadLocal<T> {
+    fn bytes_allocated(&self) -> usize {
+        self.top.bytes_allocated()
+    }
+
+    // Entries are only freed through an exclusive reference, so there is never
+    // deferred garbage.
+    fn garbage_bytes(&self) -> usize {
+        0
+    }
+}
+
 impl<T> Default for ThreadLocal<T> {
     fn default() -> Self {
         Self::new()
@@ -461,7 +476,7 @@ impl<'tls, T> Iterator for Iter<'tls, T> {
             match table.nodes.get(index).map(|node| node.atomic.load(Acquire)) {
                 Some(ptr) if ptr.is_null() => self.curr_table = Some((table, index + 1)),
 
-                Some(ptr) if ptr as usize & 1 == 0 => {
+                Some(ptr) if !is_tagged(ptr) => {
                     let ptr = ptr as *mut Entry<T>;
                     self.curr_table = Some((table, index + 1));
                     // This is safe since:
@@ -476,7 +491,7 @@ impl<'tls, T> Iterator for Iter<'tls, T> {
                 }
 
                 Some(ptr) => {
-                    let ptr = (ptr as usize & !1) as *mut Table<T>;
+                    let ptr = untag(ptr) as *mut Table<T>;
                     // Set it as table for the next iteration.
                     //
                     // 1. We only store nodes with marked lower bit if it is an
@@ -520,7 +535,7 @@ impl<'tls, T> Iterator for IterMut<'tls, T> {
             {
                 Some(ptr) if ptr.is_null() => self.curr_table = Some((table, index + 1)),
 
-                Some(ptr) if ptr as usize & 1 == 0 => {
+                Some(ptr) if !is_tagged(ptr) => {
                     let ptr = ptr as *mut Entry<T>;
                     self.curr_table = Some((table, index + 1));
                     // This is safe since:
@@ -535,7 +550,7 @@ impl<'tls, T> Iterator for IterMut<'tls, T> {
                 }
 
                 Some(ptr) => {
-                    let ptr = (ptr as usize & !1) as *mut Table<T>;
+                    let ptr = untag(ptr) as *mut Table<T>;
                     // Set it as table for the next iteration.
                     //
                     // 1. We only store nodes with marked lower bit if it is an
@@ -586,7 +601,7 @@ impl<T> Iterator for IntoIter<T> {
             {
                 Some(ptr) if ptr.is_null() => self.curr_table = Some((table, index + 1)),
 
-                Some(ptr) if ptr as usize & 1 == 0 => {
+                Some(ptr) if !is_tagged(ptr) => {
                     let ptr = ptr as *mut Entry<T>;
                     // This is safe since:
                     //
@@ -602,7 +617,7 @@ impl<T> Iterator for IntoIter<T> {
                 }
 
                 Some(ptr) => {
-                    let ptr = (ptr as usize & !1) as *mut Table<T>;
+                    let ptr = untag(ptr) as *mut Table<T>;
                     // This is safe since:
                     //
                     // 1. We only store nodes with marked lower bit if it is an
@@ -645,10 +660,10 @@ impl<T> Node<T> {
             return;
         }
 
-        if ptr as usize & 1 == 0 {
+        if !is_tagged(ptr) {
             OwnedAlloc::from_raw(NonNull::new_unchecked(ptr as *mut Entry<T>));
         } else {
-            let table_ptr = (ptr as usize & !1) as *mut Table<T>;
+            let table_ptr = untag(ptr) as *mut Table<T>;
 
             debug_assert!(!table_ptr.is_null());
             tbl_stack.push(OwnedAlloc::from_raw(NonNull::new_unchecked(table_ptr)));
@@ -695,6 +710,30 @@ impl<T> Table<T> {
         }
     }
 
+    // Returns how many heap bytes this table and everything below it take.
+    fn bytes_allocated(&self) -> usize {
+        let mut bytes = mem::size_of::<Self>();
+
+        for node in &self.nodes as &[Node<T>] {
+            let loaded = node.atomic.load(Acquire);
+
+            if loaded.is_null() {
+                continue;
+            }
+
+            if !is_tagged(loaded) {
+                bytes += mem::size_of::<Entry<T>>();
+            } else {
+                let table_ptr = untag(loaded) as *mut Table<T>;
+                // Safe because tables and entries are only freed through an
+                // exclusive reference to the TLS.
+                bytes += unsafe { &*table_ptr }.bytes_allocated();
+            }
+        }
+
+        bytes
+    }
+
     // Unsafe because storing the wrong pointers in the table will lead to
     // undefined behavior.
     #[inline]
@@ -748,6 +787,7 @@ where
 #[cfg(all(test, feature = "std"))]
 mod test {
     use super::ThreadLocal;
+    use crate::footprint::MemoryFootprint;
     use alloc::sync::Arc;
     use alloc::vec::Vec;
     use std::sync::Barrier;
@@ -776,6 +816,36 @@ mod test {
         }
     }
 
+    #[test]
+    fn footprint_follows_entries() {
+        const THREADS: usize = 16;
+
+        let tls = Arc::new(ThreadLocal::new());
+        let empty = tls.bytes_allocated();
+        let mut threads = Vec::with_capacity(THREADS);
+        // prevent IDs from being reused.
+        let barrier = Arc::new(Barrier::new(THREADS));
+
+        for i in 0..THREADS {
+            let tls = tls.clone();
+            let barrier = barrier.clone();
+            threads.push(thread::spawn(move || {
+                tls.with_init(|| i);
+                barrier.wait();
+            }))
+        }
+
+        for thread in threads {
+            thread.join().unwrap();
+        }
+
+        let mut tls = Arc::try_unwrap(tls).unwrap();
+        assert!(tls.bytes_allocated() > empty);
+        assert_eq!(tls.garbage_bytes(), 0);
+        tls.clear();
+        assert_eq!(tls.bytes_allocated(), empty);
+    }
+
     #[test]
     fn iter() {
         const THREADS: usize = 32;
@@ -0,0 +1,135 @@
+// Emits a `tracing` event at the given level when the `tracing` feature is
+// enabled, and nothing otherwise. Only meant for structural events (tables,
+// reclamation batches, disconnections), never for per-element operations.
+macro_rules! trace_event {
+    ($level:ident, $($args:tt)*) => {
+        #[cfg(feature = "tracing")]
+        {
+            tracing::$level!($($args)*);
+        }
+    };
+}
+
+#[cfg(all(test, feature = "tracing", feature = "std"))]
+mod test {
+    use crate::{channel::spsc, incin::Incinerator, map::Map};
+    use alloc::{
+        format,
+        string::{String, ToString},
+        sync::Arc,
+        vec::Vec,
+    };
+    use core::fmt;
+    use std::sync::Mutex;
+    use tracing::{
+        field::{Field, Visit},
+        span, subscriber, Event, Metadata, Subscriber,
+    };
+
+    // Collects the messages of every event.
+    #[derive(Clone, Default)]
+    struct Collector {
+        messages: Arc<Mutex<Vec<String>>>,
+    }
+
+    impl Collector {
+        fn count(&self, message: &str) -> usize {
+            let messages = self.messages.lock().unwrap();
+            messages.iter().filter(|found| *found == message).count()
+        }
+    }
+
+    impl Subscriber for Collector {
+        fn enabled(&self, _metadata: &Metadata) -> bool {
+            true
+        }
+
+        fn new_span(&self, _span: &span::Attributes) -> span::Id {
+            span::Id::from_u64(1)
+        }
+
+        fn record(&self, _span: &span::Id, _values: &span::Record) {}
+
+        fn record_follows_from(&self, _span: &span::Id, _follows: &span::Id) {}
+
+        fn event(&self, event: &Event) {
+            let mut visitor = MessageVisitor(String::new());
+            event.record(&mut visitor);
+            self.messages.lock().unwrap().push(visitor.0);
+        }
+
+        fn enter(&self, _span: &span::Id) {}
+
+        fn exit(&self, _span: &span::Id) {}
+    }
+
+    struct MessageVisitor(String);
+
+    impl Visit for MessageVisitor {
+        fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+            if field.name() == "message" {
+                self.0 = format!("{:?}", value);
+            }
+        }
+    }
+
+    #[test]
+    fn map_tables() {
+        let collector = Collector::default();
+        subscriber::with_default(collector.clone(), || {
+            let mut map = Map::new();
+            for i in 0..1024u32 {
+                map.insert(i, i);
+            }
+            for i in 0..1024 {
+                map.remove(&i);
+            }
+            map.optimize_space();
+        });
+
+        assert!(collector.count("map created a nested table") > 0);
+        assert!(collector.count("map removed a nested table") > 0);
+        assert_eq!(collector.count("map optimized its space"), 1);
+    }
+
+    #[test]
+    fn per_element_operations_are_silent() {
+        let map = Map::new();
+        map.insert(0u32, 0u32);
+
+        let collector = Collector::default();
+        subscriber::with_default(collector.clone(), || {
+            map.insert(0, 1);
+            map.get(&0);
+        });
+
+        assert!(collector.messages.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn incinerator_batches() {
+        let collector = Collector::default();
+        subscriber::with_default(collector.clone(), || {
+            let incin = Incinerator::new();
+            let pause = incin.pause();
+            pause.add_to_incin("garbage".to_string());
+            incin.pause_with(|other| other.add_to_incin("garbage".to_string()));
+            drop(pause);
+        });
+
+        assert_eq!(collector.count("incinerator reclaimed garbage"), 1);
+    }
+
+    #[test]
+    fn channel_disconnections() {
+        let collector = Collector::default();
+        subscriber::with_default(collector.clone(), || {
+            let (sender, receiver) = spsc::create::<u8>();
+            drop(sender);
+            drop(receiver);
+        });
+
+        assert_eq!(collector.count("spsc sender disconnected"), 1);
+        assert_eq!(collector.count("spsc receiver disconnected"), 1);
+    }
+}
@@ -0,0 +1,93 @@
+/// A collection into which elements can be inserted concurrently. The map
+/// takes `(key, value)` pairs.
+pub trait ConcurrentInsert<T> {
+    /// Tries to insert the given element. If the collection rejects it, such
+    /// as when the key or element is already present in a map or set, the
+    /// element is given back as an error.
+    fn try_insert(&self, elem: T) -> Result<(), T>;
+}
+
+/// A collection from which elements can be taken concurrently. Queues and
+/// stacks follow their usual order, while maps and sets give an arbitrary
+/// element.
+pub trait ConcurrentPop<T> {
+    /// Takes an element out of the collection, if there is any.
+    fn pop(&self) -> Option<T>;
+}
+
+/// A collection which can count its elements. Since other threads may change
+/// the collection at the same time, the count is only an estimate. It also
+/// takes time proportional to the size of the collection.
+pub trait ApproxLen {
+    /// Returns an estimate of how many elements the collection has.
+    fn len(&self) -> usize;
+
+    /// Returns whether the estimate of the length is zero.
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{map::Map, queue::Queue, set::Set, stack::Stack};
+    use alloc::vec::Vec;
+
+    fn fill<C, T>(collection: &C, elems: Vec<T>)
+    where
+        C: ConcurrentInsert<T> + ApproxLen,
+    {
+        let len = elems.len();
+        for elem in elems {
+            assert!(collection.try_insert(elem).is_ok());
+        }
+        assert_eq!(collection.len(), len);
+    }
+
+    fn drain<C, T>(collection: &C) -> Vec<T>
+    where
+        C: ConcurrentPop<T> + ApproxLen,
+    {
+        let mut popped = Vec::new();
+        while let Some(elem) = collection.pop() {
+            popped.push(elem);
+        }
+        assert!(collection.is_empty());
+        popped
+    }
+
+    #[test]
+    fn queue_and_stack_order() {
+        let queue = Queue::new();
+        fill(&queue, (0..10).collect());
+        assert_eq!(drain(&queue), (0..10).collect::<Vec<_>>());
+
+        let stack = Stack::new();
+        fill(&stack, (0..10).collect());
+        assert_eq!(drain(&stack), (0..10).rev().collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn map_rejects_present_keys() {
+        let map = Map::new();
+        fill(&map, (0..10).map(|i| (i, i * 2)).collect());
+        assert_eq!(ConcurrentInsert::try_insert(&map, (3, 0)), Err((3, 0)));
+        assert_eq!(*map.get(&3).unwrap().val(), 6);
+
+        let mut popped = drain(&map);
+        popped.sort();
+        assert_eq!(popped, (0..10).map(|i| (i, i * 2)).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn set_rejects_present_elements() {
+        let set = Set::new();
+        fill(&set, (0..10).collect());
+        assert_eq!(set.try_insert(3), Err(3));
+
+        let mut popped = drain(&set);
+        popped.sort();
+        assert_eq!(popped, (0..10).collect::<Vec<_>>());
+    }
+}
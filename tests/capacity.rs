@@ -0,0 +1,51 @@
+// Counts the allocations made while bulk-inserting into a map, to check that
+// pre-sizing it moves the growth of its structure out of the insertions.
+
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    sync::atomic::{AtomicUsize, Ordering::*},
+};
+use tux_lockfree::map::Map;
+
+struct Counting;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: Counting = Counting;
+
+const KEYS: u64 = 1_000_000;
+
+fn allocations_inserting(map: &Map<u64, u64>) -> usize {
+    let before = ALLOCATIONS.load(Relaxed);
+    for i in 0..KEYS {
+        map.insert(i, i);
+    }
+    ALLOCATIONS.load(Relaxed) - before
+}
+
+#[test]
+fn presized_map_allocates_less_while_inserting() {
+    let grown = allocations_inserting(&Map::new());
+    let presized = allocations_inserting(&Map::with_capacity(KEYS as usize));
+
+    // Growing one million keys creates tens of thousands of nested tables,
+    // nearly all of which the pre-sized map already has.
+    assert!(
+        presized + 50_000 < grown,
+        "pre-sized: {} allocations, grown: {} allocations",
+        presized,
+        grown
+    );
+}
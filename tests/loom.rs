@@ -0,0 +1,141 @@
+// Model-checks the map's internals. Run with:
+// RUSTFLAGS="--cfg loom" cargo test --test loom --release
+#![cfg(loom)]
+
+use loom::{sync::Arc, thread};
+use std::hash::{BuildHasherDefault, Hasher};
+use tux_lockfree::map::{AtomicCell, Map};
+
+// Puts every key in the same bucket.
+#[derive(Default)]
+struct SameBucket;
+
+impl Hasher for SameBucket {
+    fn finish(&self) -> u64 {
+        0
+    }
+
+    fn write(&mut self, _: &[u8]) {}
+}
+
+// Uses an integer key as its own hash, so the table each key goes to is
+// predictable.
+#[derive(Default)]
+struct Identity(u64);
+
+impl Hasher for Identity {
+    fn finish(&self) -> u64 {
+        self.0
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.0 = self.0 << 8 | u64::from(byte);
+        }
+    }
+
+    fn write_u64(&mut self, val: u64) {
+        self.0 = val;
+    }
+}
+
+type SameBucketMap = Map<u64, u64, BuildHasherDefault<SameBucket>>;
+
+type IdentityMap = Map<u64, u64, BuildHasherDefault<Identity>>;
+
+fn value_of<H>(map: &Map<u64, u64, H>, key: u64) -> Option<u64>
+where
+    H: std::hash::BuildHasher,
+{
+    map.get(&key).map(|guard| *guard.val())
+}
+
+#[test]
+fn insert_racing_insert_on_same_bucket() {
+    loom::model(|| {
+        let map = Arc::new(SameBucketMap::default());
+
+        let other = map.clone();
+        let handle = thread::spawn(move || assert!(other.insert(1, 10).is_none()));
+        assert!(map.insert(2, 20).is_none());
+        handle.join().unwrap();
+
+        assert_eq!(value_of(&map, 1), Some(10));
+        assert_eq!(value_of(&map, 2), Some(20));
+    });
+}
+
+#[test]
+fn insert_racing_remove() {
+    loom::model(|| {
+        let map = Arc::new(SameBucketMap::default());
+        map.insert(1, 10);
+
+        let other = map.clone();
+        let handle = thread::spawn(move || assert!(other.insert(2, 20).is_none()));
+        let removed = map.remove(&1).unwrap();
+        assert_eq!(*removed.val(), 10);
+        handle.join().unwrap();
+
+        assert_eq!(value_of(&map, 1), None);
+        assert_eq!(value_of(&map, 2), Some(20));
+    });
+}
+
+#[test]
+fn get_racing_remove() {
+    loom::model(|| {
+        let map = Arc::new(SameBucketMap::default());
+        map.insert(1, 10);
+
+        let other = map.clone();
+        let handle = thread::spawn(move || {
+            if let Some(guard) = other.get(&1) {
+                // The pair must stay valid even if it is removed meanwhile.
+                assert_eq!(*guard.key(), 1);
+                assert_eq!(*guard.val(), 10);
+            }
+        });
+        let removed = map.remove(&1).unwrap();
+        drop(removed);
+        handle.join().unwrap();
+
+        assert_eq!(value_of(&map, 1), None);
+    });
+}
+
+#[test]
+fn nested_table_racing_reader() {
+    loom::model(|| {
+        let map = Arc::new(IdentityMap::default());
+        map.insert(1, 10);
+
+        let other = map.clone();
+        // The lower bits of both hashes are equal, so a nested table has to
+        // be created for the new key.
+        let handle = thread::spawn(move || assert!(other.insert(1 + (1 << 8), 20).is_none()));
+        assert_eq!(value_of(&map, 1), Some(10));
+        handle.join().unwrap();
+
+        assert_eq!(value_of(&map, 1), Some(10));
+        assert_eq!(value_of(&map, 1 + (1 << 8)), Some(20));
+    });
+}
+
+#[test]
+fn increment_racing_replacement() {
+    loom::model(|| {
+        let map = Arc::new(Map::<u64, AtomicCell<u64>, BuildHasherDefault<SameBucket>>::default());
+        map.insert(1, AtomicCell::new(0));
+
+        let other = map.clone();
+        let handle = thread::spawn(move || other.increment(1, 1));
+        let removed = map.insert(1, AtomicCell::new(0)).unwrap();
+        handle.join().unwrap();
+
+        // The increment is seen either by the replaced value or by the new
+        // one, never lost in between.
+        let left = map.get(&1).unwrap().val().load();
+        assert_eq!(removed.val().load() + left, 1);
+    });
+}